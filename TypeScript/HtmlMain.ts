@@ -3,6 +3,7 @@ class HtmlMain {
 
     public static layout(): string {
         return '<div id="main"></div>' +
+            TopicDetails.layout() +
             HtmlStatusBar.layout();
     }
 
@@ -0,0 +1,113 @@
+class TopicDetails {
+
+    private static loadedKey: string;
+
+    public static layout(): string {
+        return '<div id="topic-details"></div>';
+    }
+
+    public static show(namespace: string, topicId: string) {
+
+        let element = document.getElementById('topic-details');
+
+        this.loadedKey = namespace + '/' + topicId;
+        element.innerHTML = this.renderHeader(namespace, topicId) + '<div>Loading...</div>';
+        element.style.visibility = 'visible';
+
+        let key = this.loadedKey;
+
+        $.ajax({ url: '/api/Topic/' + encodeURIComponent(namespace) + '/' + encodeURIComponent(topicId), type: 'get' })
+            .then((result: ITopicDetails) => {
+                if (this.loadedKey != key)
+                    return;
+
+                element.innerHTML = this.renderHeader(namespace, topicId) + this.renderDetails(result);
+            }).fail((xhr) => {
+                if (this.loadedKey != key)
+                    return;
+
+                element.innerHTML = this.renderHeader(namespace, topicId) +
+                    '<div style="color:red">Can not load the topic: ' + xhr.status + ' ' + xhr.statusText + '</div>';
+            });
+    }
+
+    public static hide() {
+        this.loadedKey = undefined;
+        document.getElementById('topic-details').style.visibility = 'hidden';
+    }
+
+    private static renderHeader(namespace: string, topicId: string): string {
+        return '<div style="margin-bottom: 10px"><b>' + namespace + ' / ' + topicId + '</b>' +
+            '<button class="btn btn-sm btn-light" style="float:right" onclick="TopicDetails.hide()">Close</button></div>';
+    }
+
+    private static renderDetails(details: ITopicDetails): string {
+
+        let totalSize = 0;
+        for (let archive of details.archives) {
+            totalSize += archive.size;
+        }
+
+        let result = '<div>Current Id: ' + this.formatId(details.messageId) + '</div>' +
+            '<div>Stored Ids: ' + this.formatId(details.minStoredMessageId) + ' - ' + this.formatId(details.maxStoredMessageId) + '</div>' +
+            '<div>Archives: ' + details.archives.length + '; Size: ' + HtmlRenderer.formatMem(totalSize) + '</div>';
+
+        result += '<div style="margin-top: 10px">Year indexes:';
+        for (let yearIndex of details.yearIndexes) {
+            result += '<span class="badge ' + this.getLocationBadge(yearIndex.location) + '" style="margin-left: 5px">' +
+                yearIndex.year + ' ' + yearIndex.location + '</span>';
+        }
+        result += '</div>';
+
+        result += '<table class="table table-striped" style="font-size: 12px; margin-top: 10px">' +
+            '<tr><th>File</th><th>Location</th><th>Message Ids</th><th>Size</th><th>Sub pages</th></tr>';
+
+        for (let archive of details.archives) {
+            result += '<tr>' +
+                '<td>' + archive.fileNo + '</td>' +
+                '<td><span class="badge ' + this.getLocationBadge(archive.location) + '">' + archive.location + '</span></td>' +
+                '<td>' + archive.fromMessageId + ' - ' + archive.toMessageId + '</td>' +
+                '<td>' + HtmlRenderer.formatNumber(archive.size) + '</td>' +
+                '<td><div>' + archive.subPagesStored + ' of ' + archive.subPagesTotal + '</div>' +
+                this.renderOccupancy(archive) + '</td>' +
+                '</tr>';
+        }
+
+        return result + '</table>';
+    }
+
+    // The same 400px bar as the loaded pages widget, one filled span per range of stored sub pages.
+    private static renderOccupancy(archive: IArchiveFile): string {
+        let firstSubPageId = archive.fileNo * archive.subPagesTotal;
+        let scale = 400 / archive.subPagesTotal;
+
+        let result = '<svg width="400" height="12">' +
+            '<rect width="400" height="12" rx="3" ry="3" style="fill:white;stroke-width:1;stroke:black"/>';
+
+        for (let range of archive.storedSubPages) {
+            let x = (range.from - firstSubPageId) * scale;
+            let width = Math.max((range.to - range.from + 1) * scale, 1);
+            result += '<rect x="' + x + '" y="1" width="' + width + '" height="10" style="fill:blue"/>';
+        }
+
+        return result + '</svg>';
+    }
+
+    private static getLocationBadge(location: string): string {
+        switch (location) {
+            case 'local':
+                return 'badge-success';
+            case 'cold':
+                return 'badge-info';
+            default:
+                return 'badge-warning';
+        }
+    }
+
+    private static formatId(id: number): string {
+        if (id === undefined || id === null)
+            return '-';
+
+        return id.toString();
+    }
+}
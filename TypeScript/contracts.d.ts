@@ -1,5 +1,6 @@
 
 interface ITopicInfo {
+    namespace: string;
     topicId: string;
     messageId: number;
     lastSaveDur: string;
@@ -46,4 +47,35 @@ interface IStatus {
 interface ISystemStatus {
     usedmem: number,
     totalmem: number
-}
\ No newline at end of file
+}
+
+interface ITopicDetails {
+    namespace: string;
+    topicId: string;
+    messageId: number;
+    minStoredMessageId: number;
+    maxStoredMessageId: number;
+    archives: IArchiveFile[];
+    yearIndexes: IYearIndex[];
+}
+
+interface IArchiveFile {
+    fileNo: number;
+    location: string;
+    size: number;
+    fromMessageId: number;
+    toMessageId: number;
+    subPagesTotal: number;
+    subPagesStored: number;
+    storedSubPages: ISubPagesRange[];
+}
+
+interface ISubPagesRange {
+    from: number;
+    to: number;
+}
+
+interface IYearIndex {
+    year: number;
+    location: string;
+}
@@ -86,7 +86,8 @@ class HtmlRenderer {
             }
 
             result += '<tr style="font-size: 12px">' +
-                '<td>' + topic.topicId +
+                '<td><a href="#" onclick="TopicDetails.show(\'' + topic.namespace + '\', \'' + topic.topicId + '\'); return false;">' +
+                topic.namespace + ' / ' + topic.topicId + '</a>' +
                 '<div>Active:</div>' + activePagesBadges + '<hr/><div>Loaded:</div>' + this.renderCachedPages(topic.loadedPages) + '</td>' +
                 '<td>' + queuesContent + '</td>' +
                 '<td><div>Current Id:' + topic.messageId + '</div>' +
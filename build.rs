@@ -1,4 +1,7 @@
 fn main() {
     let url = "https://raw.githubusercontent.com/MyJetTools/my-sb-proto-files/main/proto/";
     ci_utils::sync_and_build_proto_file(url, "MyServicePersistenceGrpcService.proto");
+
+    // Owned by this repo rather than synced: only operators talk to it, never the bus node.
+    tonic_prost_build::compile_protos("proto/PersistenceAdminGrpcService.proto").unwrap();
 }
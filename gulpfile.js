@@ -8,6 +8,7 @@ gulp.task('default', function () {
         .src(['./JavaScript/HtmlMain.js',
             './JavaScript/HtmlStatusBar.js',
             './JavaScript/SubpagesWidget.js',
+            './JavaScript/TopicDetails.js',
             './JavaScript/html.js',
             './JavaScript/main.js'])
         .pipe(minifyjs())
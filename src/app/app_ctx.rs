@@ -4,12 +4,20 @@ use rust_extensions::AppStates;
 
 use crate::{
     archive_storage::{ArchiveFileNo, ArchiveFileOpener, ArchiveStorage, ArchiveStorageList},
+    auth::TokenAuth,
     cold_storage::ColdStorage,
+    consumer_lag::ConsumerLag,
+    disk_space::DiskSpace,
     file_storage::FileStorage,
     index_by_minute::{IndexByMinuteUtils, YearlyIndexByMinute},
+    namespace_deletions::NamespaceDeletions,
+    retention::TopicsRetention,
     settings::SettingsModel,
+    storage_usage::{ColdUsage, StorageUsage},
+    tls::TlsCertificates,
     topic_data::TopicsDataList,
     topic_key::{TopicKeyRef, DEFAULT_NAMESPACE},
+    topic_moves::TopicMoves,
     topics_snapshot::current_snapshot::CurrentTopicsSnapshot,
     typing::Year,
 };
@@ -36,39 +44,146 @@ pub struct AppContext {
     pub archive_locks: StorageLocks,
     /// The same, for the per-year minute index.
     pub index_locks: StorageLocks,
+    /// Held by a write from the check that its topic is not being moved until it is in; a
+    /// `MoveTopic` takes it exclusively once its journal entry is there, which waits out every
+    /// write that was checked before the entry.
+    pub topic_writes: StorageLocks,
+
+    /// Per-topic retention set over the admin API. Kept, not enforced yet.
+    pub topics_retention: TopicsRetention,
+
+    /// `MoveTopic` calls not finished yet - resumed on start, and both names refuse writes until
+    /// then.
+    pub topic_moves: TopicMoves,
+
+    /// `DeleteNamespace` jobs, with their progress. A namespace refuses writes while its deletion
+    /// runs - after a restart too, until the resumed job is done.
+    pub namespace_deletions: NamespaceDeletions,
+
+    /// Backlog of every queue as of the last snapshot; refreshed by its timer.
+    pub consumer_lag: ConsumerLag,
+
+    /// What each topic has in the cold tier, kept up by the cold storage itself.
+    pub cold_usage: Arc<ColdUsage>,
+    /// Bytes per topic and namespace as of the last accounting pass.
+    pub storage_usage: StorageUsage,
+
+    /// Free space of the data volume, as the disk watchdog last saw it.
+    pub disk_space: DiskSpace,
+
+    /// `None` when no `auth` section is configured - then every caller is let in, as before.
+    pub auth: Option<TokenAuth>,
+
+    /// `None` when no `tls` section is configured - both listeners are plaintext then.
+    pub tls: Option<Arc<TlsCertificates>>,
 
     /// `None` when no S3 section is configured - then nothing is ever uploaded and every file
-    /// stays local forever.
+    /// stays local forever. With only `s3_namespaces` it is there for those namespaces alone.
     cold_storage: Option<Arc<ColdStorage>>,
 }
 
 impl AppContext {
     pub async fn new(settings: SettingsModel) -> AppContext {
-        let cold_storage = settings
-            .get_s3_connection()
-            .map(|s3| Arc::new(ColdStorage::new(&s3)));
+        Self::create(settings, true).await
+    }
+
+    /// For `export-bundle` and `find-gaps`, which only read. Nothing here writes to the data
+    /// folder or to a bucket: the buckets are not checked or created - a command that reads the
+    /// cold tier finds out about a wrong key pair soon enough - and neither TLS nor auth is
+    /// loaded, since nothing is served.
+    pub async fn new_offline(settings: SettingsModel) -> AppContext {
+        Self::create(settings, false).await
+    }
+
+    async fn create(settings: SettingsModel, serving: bool) -> AppContext {
+        let cold_usage = Arc::new(ColdUsage::load(settings.data.clone()).await);
+
+        let metrics_keeper = PrometheusMetrics::new();
+
+        let namespace_connections = settings.get_s3_namespace_connections();
+
+        let cold_storage = match settings.get_s3_connection() {
+            Some(s3) => Some(ColdStorage::new(&s3)),
+            None if !namespace_connections.is_empty() => Some(ColdStorage::without_default()),
+            None => None,
+        };
+
+        let cold_storage = cold_storage.map(|cold_storage| {
+            let mut cold_storage = cold_storage
+                .with_usage(cold_usage.clone())
+                .with_metrics(metrics_keeper.get_cold_storage_metrics());
+
+            for (namespace, s3) in namespace_connections.iter() {
+                cold_storage = cold_storage.with_namespace(namespace.as_str(), s3);
+            }
+
+            Arc::new(cold_storage)
+        });
 
         // Touches the cold storage early so a wrong endpoint, region or key pair shows up in the
         // log now rather than only at the first upload hours later. It does not gate the start:
         // the cold tier holds sealed data, and refusing to serve the hot path over it would turn a
-        // storage problem into an outage. Other namespaces get their bucket on first touch.
-        if let Some(cold_storage) = cold_storage.as_ref() {
-            cold_storage.ensure_bucket(DEFAULT_NAMESPACE).await;
+        // storage problem into an outage. A namespace with a connection of its own is touched as
+        // well - its keys are as likely to be wrong. Other namespaces get their bucket on first
+        // touch.
+        if let Some(cold_storage) = cold_storage.as_ref().filter(|_| serving) {
+            if cold_storage.covers(DEFAULT_NAMESPACE) {
+                cold_storage.ensure_bucket(DEFAULT_NAMESPACE).await;
+            }
+
+            for namespace in cold_storage.get_namespaces_with_own_connection() {
+                cold_storage.ensure_bucket(namespace.as_str()).await;
+                check_nothing_left_under_default(cold_storage, namespace.as_str()).await;
+            }
+
+            // Before the uploader starts, which records into the same files.
+            cold_usage.rebuild_missing(cold_storage).await;
         }
 
-        let topics_snapshot = CurrentTopicsSnapshot::read_or_create(settings.data.clone()).await;
+        let topics_snapshot = CurrentTopicsSnapshot::read_or_create(
+            settings.data.clone(),
+            settings.snapshot_history.clone(),
+            settings.snapshot_guard.clone(),
+            cold_storage.clone(),
+        )
+        .await;
+
+        let topics_retention = TopicsRetention::load(settings.data.clone()).await;
+        let topic_moves = TopicMoves::load(settings.data.clone()).await;
+        let namespace_deletions = NamespaceDeletions::load(settings.data.clone()).await;
+
+        let auth = settings
+            .auth
+            .as_ref()
+            .filter(|_| serving)
+            .map(TokenAuth::new);
+        let tls = settings
+            .tls
+            .as_ref()
+            .filter(|_| serving)
+            .map(|tls| Arc::new(TlsCertificates::load(tls)));
 
         AppContext {
             topics_snapshot,
             topics_list: TopicsDataList::new(),
             settings,
 
-            metrics_keeper: PrometheusMetrics::new(),
+            metrics_keeper,
             index_by_minute_utils: IndexByMinuteUtils::new(),
             app_states: Arc::new(AppStates::create_un_initialized()),
             archive_storage_list: ArchiveStorageList::new(),
             archive_locks: StorageLocks::new(),
             index_locks: StorageLocks::new(),
+            topic_writes: StorageLocks::new(),
+            topics_retention,
+            topic_moves,
+            namespace_deletions,
+            consumer_lag: ConsumerLag::new(),
+            cold_usage,
+            storage_usage: StorageUsage::new(),
+            disk_space: DiskSpace::new(),
+            auth,
+            tls,
             cold_storage,
         }
     }
@@ -86,8 +201,17 @@ impl AppContext {
         self.settings.data.as_str()
     }
 
-    pub fn get_cold_storage(&self) -> Option<&Arc<ColdStorage>> {
-        self.cold_storage.as_ref()
+    /// Whether any namespace has a cold tier. Which one does is [`Self::get_cold_storage`].
+    pub fn has_cold_storage(&self) -> bool {
+        self.cold_storage.is_some()
+    }
+
+    /// `None` for a namespace that stays local - no S3 section at all, or only `s3_namespaces`
+    /// and this one is not in it.
+    pub fn get_cold_storage(&self, namespace: &str) -> Option<&Arc<ColdStorage>> {
+        self.cold_storage
+            .as_ref()
+            .filter(|itm| itm.covers(namespace))
     }
 
     pub async fn create_topic_folder(&self, topic_key: TopicKeyRef<'_>) {
@@ -132,7 +256,11 @@ impl AppContext {
         Some(Arc::new(result))
     }
 
-    fn get_year_index_path(&self, topic_key: TopicKeyRef<'_>, year: Year) -> std::path::PathBuf {
+    pub fn get_year_index_path(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        year: Year,
+    ) -> std::path::PathBuf {
         storage_layout::get_local_path(
             self.get_data_folder(),
             storage_layout::get_year_index_relative_path(topic_key, year).as_str(),
@@ -145,7 +273,7 @@ impl AppContext {
     /// bring the whole file back. That also gives the read-modify-write story for free: a late
     /// write for a closed year updates the local copy, and the uploader sends it back up.
     async fn restore_year_index_from_cold_storage(&self, topic_key: TopicKeyRef<'_>, year: Year) {
-        let Some(cold_storage) = self.get_cold_storage() else {
+        let Some(cold_storage) = self.get_cold_storage(topic_key.namespace) else {
             return;
         };
 
@@ -218,7 +346,7 @@ impl ArchiveFileOpener for AppContext {
         }
 
         // Not on disk - it may have been sealed and uploaded. Reads then go over ranged GETs.
-        let cold_storage = self.get_cold_storage()?;
+        let cold_storage = self.get_cold_storage(topic_key.namespace)?;
 
         let file_name = storage_layout::get_archive_file_name(archive_file_no);
 
@@ -244,3 +372,21 @@ impl ArchiveFileOpener for AppContext {
         ))
     }
 }
+
+/// A namespace of `s3_namespaces` is only ever read through its own connection. Objects it left
+/// under `s3_conn_string` would be quietly gone from it - every read a miss - so the start is
+/// refused until they are moved over. A listing that fails does not gate the start, the same as
+/// the bucket touch before it.
+async fn check_nothing_left_under_default(cold_storage: &ColdStorage, namespace: &str) {
+    match cold_storage.has_objects_under_default(namespace).await {
+        Ok(false) => {}
+        Ok(true) => panic!(
+            "s3_namespaces.{} is set, but the namespace still has objects under s3_conn_string. They would not be read any more - move them to the namespace's own connection, under the keys its layout gives, and start again",
+            namespace
+        ),
+        Err(err) => println!(
+            "Can not check whether namespace '{}' left objects under s3_conn_string: {}",
+            namespace, err
+        ),
+    }
+}
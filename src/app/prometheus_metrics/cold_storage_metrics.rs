@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use ahash::AHashSet;
+use parking_lot::Mutex;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry};
+
+const NAMESPACE_LABEL: &str = "namespace";
+const KIND_LABEL: &str = "kind";
+const ERROR_LABEL: &str = "error";
+
+/// An upload is an archive of hundreds of megabytes, so seconds to minutes.
+const UPLOAD_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// A ranged read sits on the path of a client's request.
+const RANGE_READ_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// What one pass of the uploader left behind in a namespace.
+#[derive(Default)]
+pub struct UploadBacklog {
+    pub files: usize,
+    pub bytes: u64,
+    /// Since the oldest of those files was last written - about when it sealed.
+    pub oldest_age: Option<Duration>,
+}
+
+/// Everything `ColdStorage` does, counted. Kept apart from the rest of the metrics because the cold
+/// storage is the one thing that reports into it, and it does so from inside every call.
+///
+/// An error is classed rather than spelled out, so a label never carries a message: `local` is a
+/// file that could not be read, `not_found` a key that is not there, `transient` what the client
+/// deems worth retrying - timeouts, 5xx, dropped connections - and `permanent` the rest, which is
+/// mostly permissions.
+pub struct ColdStorageMetrics {
+    uploads: IntCounterVec,
+    upload_bytes: IntCounterVec,
+    upload_duration_sec: HistogramVec,
+    upload_retries: IntCounterVec,
+    upload_failures: IntCounterVec,
+    range_reads: IntCounterVec,
+    range_read_bytes: IntCounterVec,
+    range_read_duration_sec: HistogramVec,
+    range_read_failures: IntCounterVec,
+    deletes: IntCounterVec,
+    delete_failures: IntCounterVec,
+    upload_backlog_files: IntGaugeVec,
+    upload_backlog_bytes: IntGaugeVec,
+    upload_oldest_pending_age_sec: IntGaugeVec,
+    backlog_namespaces: Mutex<AHashSet<String>>,
+}
+
+impl ColdStorageMetrics {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            uploads: register_counter(
+                registry,
+                "cold_uploads",
+                "Files uploaded to the cold tier",
+                &[NAMESPACE_LABEL],
+            ),
+            upload_bytes: register_counter(
+                registry,
+                "cold_upload_bytes",
+                "Bytes uploaded to the cold tier",
+                &[NAMESPACE_LABEL],
+            ),
+            upload_duration_sec: register_histogram(
+                registry,
+                "cold_upload_duration_sec",
+                "Seconds an upload took, retries included",
+                UPLOAD_BUCKETS,
+                &[NAMESPACE_LABEL],
+            ),
+            upload_retries: register_counter(
+                registry,
+                "cold_upload_retries",
+                "Upload attempts past the first one",
+                &[NAMESPACE_LABEL],
+            ),
+            upload_failures: register_counter(
+                registry,
+                "cold_upload_failures",
+                "Uploads that failed after their retries",
+                &[NAMESPACE_LABEL, ERROR_LABEL],
+            ),
+            range_reads: register_counter(
+                registry,
+                "cold_range_reads",
+                "Ranged reads of cold archives: the TOC, or a sub page",
+                &[NAMESPACE_LABEL, KIND_LABEL],
+            ),
+            range_read_bytes: register_counter(
+                registry,
+                "cold_range_read_bytes",
+                "Bytes read from cold archives",
+                &[NAMESPACE_LABEL, KIND_LABEL],
+            ),
+            range_read_duration_sec: register_histogram(
+                registry,
+                "cold_range_read_duration_sec",
+                "Seconds a ranged read of a cold archive took",
+                RANGE_READ_BUCKETS,
+                &[KIND_LABEL],
+            ),
+            range_read_failures: register_counter(
+                registry,
+                "cold_range_read_failures",
+                "Ranged reads of cold archives that failed",
+                &[NAMESPACE_LABEL, KIND_LABEL, ERROR_LABEL],
+            ),
+            deletes: register_counter(
+                registry,
+                "cold_deletes",
+                "Files deleted from the cold tier, or found gone already",
+                &[NAMESPACE_LABEL],
+            ),
+            delete_failures: register_counter(
+                registry,
+                "cold_delete_failures",
+                "Deletes from the cold tier that failed",
+                &[NAMESPACE_LABEL, ERROR_LABEL],
+            ),
+            upload_backlog_files: register_gauge(
+                registry,
+                "cold_upload_backlog_files",
+                "Sealed files still on the local disk, waiting to be uploaded",
+            ),
+            upload_backlog_bytes: register_gauge(
+                registry,
+                "cold_upload_backlog_bytes",
+                "Bytes of the sealed files still on the local disk",
+            ),
+            upload_oldest_pending_age_sec: register_gauge(
+                registry,
+                "cold_upload_oldest_pending_age_sec",
+                "Seconds since the oldest sealed file still on the local disk was last written",
+            ),
+            backlog_namespaces: Mutex::new(AHashSet::new()),
+        }
+    }
+
+    pub fn uploaded(&self, namespace: &str, bytes: u64, duration: Duration, retries: usize) {
+        self.uploads.with_label_values(&[namespace]).inc();
+        self.upload_bytes
+            .with_label_values(&[namespace])
+            .inc_by(bytes);
+        self.upload_duration_sec
+            .with_label_values(&[namespace])
+            .observe(duration.as_secs_f64());
+        self.upload_retries
+            .with_label_values(&[namespace])
+            .inc_by(retries as u64);
+    }
+
+    pub fn upload_failed(&self, namespace: &str, error_class: &str, retries: usize) {
+        self.upload_failures
+            .with_label_values(&[namespace, error_class])
+            .inc();
+        self.upload_retries
+            .with_label_values(&[namespace])
+            .inc_by(retries as u64);
+    }
+
+    pub fn range_read(&self, namespace: &str, kind: &str, bytes: usize, duration: Duration) {
+        self.range_reads.with_label_values(&[namespace, kind]).inc();
+        self.range_read_bytes
+            .with_label_values(&[namespace, kind])
+            .inc_by(bytes as u64);
+        self.range_read_duration_sec
+            .with_label_values(&[kind])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn range_read_failed(&self, namespace: &str, kind: &str, error_class: &str) {
+        self.range_read_failures
+            .with_label_values(&[namespace, kind, error_class])
+            .inc();
+    }
+
+    pub fn deleted(&self, namespace: &str) {
+        self.deletes.with_label_values(&[namespace]).inc();
+    }
+
+    pub fn delete_failed(&self, namespace: &str, error_class: &str) {
+        self.delete_failures
+            .with_label_values(&[namespace, error_class])
+            .inc();
+    }
+
+    /// Replaces the backlog of every namespace. One that has nothing pending reads `0` rather
+    /// than disappearing while it has topics; one that is gone from the disk is dropped.
+    pub fn update_upload_backlog(&self, backlog: Vec<(String, UploadBacklog)>) {
+        let mut backlog_namespaces = self.backlog_namespaces.lock();
+
+        let mut updated = AHashSet::new();
+
+        for (namespace, backlog) in backlog {
+            let labels = [namespace.as_str()];
+
+            self.upload_backlog_files
+                .with_label_values(&labels)
+                .set(backlog.files as i64);
+            self.upload_backlog_bytes
+                .with_label_values(&labels)
+                .set(backlog.bytes as i64);
+            self.upload_oldest_pending_age_sec
+                .with_label_values(&labels)
+                .set(backlog.oldest_age.map(|itm| itm.as_secs()).unwrap_or(0) as i64);
+
+            updated.insert(namespace);
+        }
+
+        for namespace in backlog_namespaces.iter() {
+            if updated.contains(namespace) {
+                continue;
+            }
+
+            let labels = [namespace.as_str()];
+            let _ = self.upload_backlog_files.remove_label_values(&labels);
+            let _ = self.upload_backlog_bytes.remove_label_values(&labels);
+            let _ = self
+                .upload_oldest_pending_age_sec
+                .remove_label_values(&labels);
+        }
+
+        *backlog_namespaces = updated;
+    }
+}
+
+fn register_counter(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+    registry.register(Box::new(counter.clone())).unwrap();
+    counter
+}
+
+fn register_gauge(registry: &Registry, name: &str, help: &str) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), &[NAMESPACE_LABEL]).unwrap();
+    registry.register(Box::new(gauge.clone())).unwrap();
+    gauge
+}
+
+fn register_histogram(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    buckets: &[f64],
+    labels: &[&str],
+) -> HistogramVec {
+    let histogram = HistogramVec::new(
+        HistogramOpts::new(name, help).buckets(buckets.to_vec()),
+        labels,
+    )
+    .unwrap();
+    registry.register(Box::new(histogram.clone())).unwrap();
+    histogram
+}
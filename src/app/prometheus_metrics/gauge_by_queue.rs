@@ -0,0 +1,36 @@
+use prometheus::{IntGaugeVec, Registry};
+
+use crate::topic_key::TopicKeyRef;
+
+const NAMESPACE_LABEL: &str = "namespace";
+const TOPIC_LABEL: &str = "topic";
+const QUEUE_LABEL: &str = "queue";
+
+pub struct GaugeByQueue(IntGaugeVec);
+
+impl GaugeByQueue {
+    pub fn new(registry: &Registry, name: &str, help: &str) -> Self {
+        let gauge = IntGaugeVec::new(
+            prometheus::Opts::new(name, help),
+            &[NAMESPACE_LABEL, TOPIC_LABEL, QUEUE_LABEL],
+        )
+        .unwrap();
+
+        registry.register(Box::new(gauge.clone())).unwrap();
+        Self(gauge)
+    }
+
+    pub fn update_value(&self, topic_key: TopicKeyRef<'_>, queue_id: &str, value: i64) {
+        self.0
+            .with_label_values(&[topic_key.namespace, topic_key.topic_id, queue_id])
+            .set(value);
+    }
+
+    /// A series that was never set is not an error here: the age and the oldest id are only set
+    /// while they are known.
+    pub fn remove_queue(&self, topic_key: TopicKeyRef<'_>, queue_id: &str) {
+        let _ = self
+            .0
+            .remove_label_values(&[topic_key.namespace, topic_key.topic_id, queue_id]);
+    }
+}
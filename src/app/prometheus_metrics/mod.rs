@@ -1,4 +1,8 @@
+mod cold_storage_metrics;
+mod gauge_by_queue;
 mod gauge_by_topic;
 mod prometheus_metrics;
+pub use cold_storage_metrics::*;
+pub use gauge_by_queue::*;
 pub use gauge_by_topic::*;
 pub use prometheus_metrics::*;
@@ -1,24 +1,69 @@
+use std::{sync::Arc, time::Duration};
+
 use ahash::{AHashMap, AHashSet};
 use parking_lot::Mutex;
-use prometheus::{Encoder, IntGauge, Registry, TextEncoder};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Registry,
+    TextEncoder,
+};
+use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use crate::topic_key::TopicKey;
+use crate::{
+    consumer_lag::QueueLag,
+    storage_usage::{sum_by_namespace, TopicStorageUsage},
+    topic_key::{TopicKey, TopicKeyRef},
+};
 
-use super::GaugeByTopic;
+use super::{ColdStorageMetrics, GaugeByQueue, GaugeByTopic};
 
 pub struct PrometheusMetricsToUpdate {
     pub not_persisted_size: usize,
     pub content_size: usize,
+    pub archive_write_failures: u32,
 }
 
 pub struct PrometheusMetrics {
     registry: Registry,
     topic_persist_queue_size: GaugeByTopic,
     cached_messages_size: GaugeByTopic,
+    topic_archive_write_failures: GaugeByTopic,
+    archive_write_failures: IntCounterVec,
     active_topics: Mutex<AHashSet<TopicKey>>,
     http_connections_amount: IntGauge,
+    streamed_reads_aborted: IntCounterVec,
+    queue_snapshots_rejected: IntCounterVec,
+    queue_unconsumed_messages: GaugeByQueue,
+    queue_oldest_unconsumed_message_id: GaugeByQueue,
+    queue_oldest_unconsumed_age_sec: GaugeByQueue,
+    active_queues: Mutex<AHashSet<(TopicKey, String)>>,
+    storage_archive_bytes: GaugeByTopic,
+    storage_year_index_bytes: GaugeByTopic,
+    storage_active_bytes: GaugeByTopic,
+    storage_cold_bytes: GaugeByTopic,
+    topic_missing_messages: GaugeByTopic,
+    namespace_local_bytes: IntGaugeVec,
+    namespace_cold_bytes: IntGaugeVec,
+    storage_topics: Mutex<AHashSet<TopicKey>>,
+    storage_namespaces: Mutex<AHashSet<String>>,
+    cold_storage: Arc<ColdStorageMetrics>,
+    sub_page_read_duration_sec: HistogramVec,
+    sub_page_decompress_duration_sec: HistogramVec,
+    grpc_requests: IntCounterVec,
+    grpc_request_duration_sec: HistogramVec,
+    grpc_stream_duration_sec: HistogramVec,
+    disk_free_bytes: IntGauge,
+    disk_total_bytes: IntGauge,
+    disk_space_level: IntGauge,
 }
 
+/// From a sub page already in memory - microseconds - to one fetched from the cold tier.
+const READ_PATH_BUCKETS: &[f64] = &[
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// A whole streamed read can run for minutes.
+const GRPC_STREAM_BUCKETS: &[f64] = &[0.01, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0, 900.0];
+
 impl PrometheusMetrics {
     pub fn new() -> Self {
         let registry = Registry::new();
@@ -31,20 +76,394 @@ impl PrometheusMetrics {
         let cached_messages_size =
             GaugeByTopic::new(&registry, "cached_messages_size", "Cached messages size");
 
+        let topic_archive_write_failures = GaugeByTopic::new(
+            &registry,
+            "topic_archive_write_failures",
+            "Archive writes of a topic failed in a row. Anything but 0 is a degraded topic",
+        );
+
+        let archive_write_failures = create_namespace_counter(
+            "archive_write_failures",
+            "Sub pages that could not be written to an archive and are retried",
+        );
+
+        registry
+            .register(Box::new(archive_write_failures.clone()))
+            .unwrap();
+
         let http_connections_amount = create_http_connections_amount();
 
         registry
             .register(Box::new(http_connections_amount.clone()))
             .unwrap();
 
+        let streamed_reads_aborted = create_streamed_reads_aborted();
+
+        registry
+            .register(Box::new(streamed_reads_aborted.clone()))
+            .unwrap();
+
+        let queue_snapshots_rejected = create_queue_snapshots_rejected();
+
+        registry
+            .register(Box::new(queue_snapshots_rejected.clone()))
+            .unwrap();
+
+        let queue_unconsumed_messages = GaugeByQueue::new(
+            &registry,
+            "queue_unconsumed_messages",
+            "Messages of a queue not consumed yet, as of the last saved queue snapshot",
+        );
+
+        let queue_oldest_unconsumed_message_id = GaugeByQueue::new(
+            &registry,
+            "queue_oldest_unconsumed_message_id",
+            "Id of the oldest message of a queue not consumed yet",
+        );
+
+        let queue_oldest_unconsumed_age_sec = GaugeByQueue::new(
+            &registry,
+            "queue_oldest_unconsumed_age_sec",
+            "Seconds since the oldest message of a queue not consumed yet was published",
+        );
+
+        let storage_archive_bytes = GaugeByTopic::new(
+            &registry,
+            "storage_archive_bytes",
+            "Bytes of a topic's archives on the local disk",
+        );
+
+        let storage_year_index_bytes = GaugeByTopic::new(
+            &registry,
+            "storage_year_index_bytes",
+            "Bytes of a topic's year indexes on the local disk",
+        );
+
+        let storage_active_bytes = GaugeByTopic::new(
+            &registry,
+            "storage_active_bytes",
+            "Bytes of a topic's active file on the local disk",
+        );
+
+        let storage_cold_bytes = GaugeByTopic::new(
+            &registry,
+            "storage_cold_bytes",
+            "Bytes of a topic uploaded to the cold tier, as recorded in the cold usage",
+        );
+
+        let topic_missing_messages = GaugeByTopic::new(
+            &registry,
+            "topic_missing_messages",
+            "Messages missing from a topic's stored range, as found by its last gap check",
+        );
+
+        let namespace_local_bytes = create_namespace_bytes(
+            "namespace_local_bytes",
+            "Bytes of a namespace's topics on the local disk",
+        );
+
+        registry
+            .register(Box::new(namespace_local_bytes.clone()))
+            .unwrap();
+
+        let namespace_cold_bytes = create_namespace_bytes(
+            "namespace_cold_bytes",
+            "Bytes of a namespace's topics in the cold tier",
+        );
+
+        registry
+            .register(Box::new(namespace_cold_bytes.clone()))
+            .unwrap();
+
+        let cold_storage = Arc::new(ColdStorageMetrics::new(&registry));
+
+        let sub_page_read_duration_sec = create_histogram(
+            "sub_page_read_duration_sec",
+            "Seconds to get a sub page to read, by the tier that had it, decompression included",
+            READ_PATH_BUCKETS,
+            &["namespace", "tier"],
+        );
+
+        registry
+            .register(Box::new(sub_page_read_duration_sec.clone()))
+            .unwrap();
+
+        let sub_page_decompress_duration_sec = create_histogram(
+            "sub_page_decompress_duration_sec",
+            "Seconds spent decompressing a sub page read from an archive",
+            READ_PATH_BUCKETS,
+            &["namespace", "tier"],
+        );
+
+        registry
+            .register(Box::new(sub_page_decompress_duration_sec.clone()))
+            .unwrap();
+
+        let grpc_requests = create_grpc_requests();
+
+        registry.register(Box::new(grpc_requests.clone())).unwrap();
+
+        let grpc_request_duration_sec = create_histogram(
+            "grpc_request_duration_sec",
+            "Seconds until a gRPC call answered - for a streamed one, until the stream was handed back",
+            READ_PATH_BUCKETS,
+            &["rpc"],
+        );
+
+        registry
+            .register(Box::new(grpc_request_duration_sec.clone()))
+            .unwrap();
+
+        let grpc_stream_duration_sec = create_histogram(
+            "grpc_stream_duration_sec",
+            "Seconds a streamed gRPC read took from the call to its last message",
+            GRPC_STREAM_BUCKETS,
+            &["rpc"],
+        );
+
+        registry
+            .register(Box::new(grpc_stream_duration_sec.clone()))
+            .unwrap();
+
+        let disk_free_bytes =
+            IntGauge::new("disk_free_bytes", "Free bytes of the data volume").unwrap();
+
+        registry
+            .register(Box::new(disk_free_bytes.clone()))
+            .unwrap();
+
+        let disk_total_bytes =
+            IntGauge::new("disk_total_bytes", "Size of the data volume in bytes").unwrap();
+
+        registry
+            .register(Box::new(disk_total_bytes.clone()))
+            .unwrap();
+
+        let disk_space_level = IntGauge::new(
+            "disk_space_level",
+            "0 - enough free space, 1 - below the soft threshold, 2 - below the hard one, writes refused",
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(disk_space_level.clone()))
+            .unwrap();
+
         return Self {
             registry,
             topic_persist_queue_size,
             cached_messages_size,
+            topic_archive_write_failures,
+            archive_write_failures,
             active_topics: Mutex::new(AHashSet::new()),
             http_connections_amount,
+            streamed_reads_aborted,
+            queue_snapshots_rejected,
+            queue_unconsumed_messages,
+            queue_oldest_unconsumed_message_id,
+            queue_oldest_unconsumed_age_sec,
+            active_queues: Mutex::new(AHashSet::new()),
+            storage_archive_bytes,
+            storage_year_index_bytes,
+            storage_active_bytes,
+            storage_cold_bytes,
+            topic_missing_messages,
+            namespace_local_bytes,
+            namespace_cold_bytes,
+            storage_topics: Mutex::new(AHashSet::new()),
+            storage_namespaces: Mutex::new(AHashSet::new()),
+            cold_storage,
+            sub_page_read_duration_sec,
+            sub_page_decompress_duration_sec,
+            grpc_requests,
+            grpc_request_duration_sec,
+            grpc_stream_duration_sec,
+            disk_free_bytes,
+            disk_total_bytes,
+            disk_space_level,
         };
     }
+
+    pub fn sub_page_read(&self, namespace: &str, tier: &str, duration: Duration) {
+        self.sub_page_read_duration_sec
+            .with_label_values(&[namespace, tier])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn sub_page_decompressed(&self, namespace: &str, tier: &str, duration: Duration) {
+        self.sub_page_decompress_duration_sec
+            .with_label_values(&[namespace, tier])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn grpc_request(&self, rpc: &str, code: &str, duration: Duration) {
+        self.grpc_requests.with_label_values(&[rpc, code]).inc();
+        self.grpc_request_duration_sec
+            .with_label_values(&[rpc])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn update_disk_space(&self, free_bytes: u64, total_bytes: u64, level: u8) {
+        self.disk_free_bytes.set(free_bytes as i64);
+        self.disk_total_bytes.set(total_bytes as i64);
+        self.disk_space_level.set(level as i64);
+    }
+
+    pub fn grpc_stream_finished(&self, rpc: &str, duration: Duration) {
+        self.grpc_stream_duration_sec
+            .with_label_values(&[rpc])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Handed to the cold storage, which reports into it from every call.
+    pub fn get_cold_storage_metrics(&self) -> Arc<ColdStorageMetrics> {
+        self.cold_storage.clone()
+    }
+
+    pub fn archive_write_failed(&self, namespace: &str) {
+        self.archive_write_failures
+            .with_label_values(&[namespace])
+            .inc();
+    }
+
+    pub fn streamed_read_aborted(&self, rpc: &str, reason: &str) {
+        self.streamed_reads_aborted
+            .with_label_values(&[rpc, reason])
+            .inc();
+    }
+
+    pub fn queue_snapshot_rejected(&self, reason: &str) {
+        self.queue_snapshots_rejected
+            .with_label_values(&[reason])
+            .inc();
+    }
+
+    /// An empty queue is `0` unconsumed and `0` seconds old, with no oldest id. An age that is not
+    /// known is left out rather than reported as `0`, which would read as "all caught up".
+    pub fn update_consumer_lag(&self, queues: &[QueueLag], now: DateTimeAsMicroseconds) {
+        let mut active_queues = self.active_queues.lock();
+
+        let mut updated = AHashSet::new();
+
+        for queue in queues {
+            let topic_key = queue.topic_key.to_ref();
+            let queue_id = queue.queue_id.as_str();
+
+            self.queue_unconsumed_messages
+                .update_value(topic_key, queue_id, queue.unconsumed);
+
+            match queue.oldest_unconsumed {
+                Some(message_id) => self.queue_oldest_unconsumed_message_id.update_value(
+                    topic_key,
+                    queue_id,
+                    message_id.get_value(),
+                ),
+                None => self
+                    .queue_oldest_unconsumed_message_id
+                    .remove_queue(topic_key, queue_id),
+            }
+
+            match queue.get_age_sec(now) {
+                Some(age_sec) => self
+                    .queue_oldest_unconsumed_age_sec
+                    .update_value(topic_key, queue_id, age_sec),
+                None => self
+                    .queue_oldest_unconsumed_age_sec
+                    .remove_queue(topic_key, queue_id),
+            }
+
+            updated.insert((queue.topic_key.clone(), queue.queue_id.clone()));
+        }
+
+        for (topic_key, queue_id) in active_queues.iter() {
+            if updated.contains(&(topic_key.clone(), queue_id.clone())) {
+                continue;
+            }
+
+            self.queue_unconsumed_messages
+                .remove_queue(topic_key.to_ref(), queue_id);
+            self.queue_oldest_unconsumed_message_id
+                .remove_queue(topic_key.to_ref(), queue_id);
+            self.queue_oldest_unconsumed_age_sec
+                .remove_queue(topic_key.to_ref(), queue_id);
+        }
+
+        *active_queues = updated;
+    }
+
+    /// A topic or a namespace that is gone from the accounting is gone from the gauges too, so a
+    /// sum over them is what is occupied now.
+    pub fn update_storage_usage(&self, topics: &[TopicStorageUsage]) {
+        let mut storage_topics = self.storage_topics.lock();
+
+        let mut updated = AHashSet::new();
+
+        for topic in topics {
+            let topic_key = topic.topic_key.to_ref();
+
+            self.storage_archive_bytes
+                .update_value(topic_key, topic.local.archive_bytes as i64);
+            self.storage_year_index_bytes
+                .update_value(topic_key, topic.local.year_index_bytes as i64);
+            self.storage_active_bytes
+                .update_value(topic_key, topic.local.active_bytes as i64);
+            self.storage_cold_bytes
+                .update_value(topic_key, topic.cold_bytes as i64);
+
+            updated.insert(topic.topic_key.clone());
+        }
+
+        for topic_key in storage_topics.iter() {
+            if updated.contains(topic_key) {
+                continue;
+            }
+
+            self.storage_archive_bytes.remove_topic(topic_key.to_ref());
+            self.storage_year_index_bytes
+                .remove_topic(topic_key.to_ref());
+            self.storage_active_bytes.remove_topic(topic_key.to_ref());
+            self.storage_cold_bytes.remove_topic(topic_key.to_ref());
+        }
+
+        *storage_topics = updated;
+
+        let mut storage_namespaces = self.storage_namespaces.lock();
+
+        let mut updated = AHashSet::new();
+
+        for namespace in sum_by_namespace(topics) {
+            self.namespace_local_bytes
+                .with_label_values(&[namespace.namespace.as_str()])
+                .set(namespace.local.get_total() as i64);
+            self.namespace_cold_bytes
+                .with_label_values(&[namespace.namespace.as_str()])
+                .set(namespace.cold_bytes as i64);
+
+            updated.insert(namespace.namespace);
+        }
+
+        for namespace in storage_namespaces.iter() {
+            if updated.contains(namespace) {
+                continue;
+            }
+
+            let _ = self
+                .namespace_local_bytes
+                .remove_label_values(&[namespace.as_str()]);
+            let _ = self
+                .namespace_cold_bytes
+                .remove_label_values(&[namespace.as_str()]);
+        }
+
+        *storage_namespaces = updated;
+    }
+
+    /// Set by a gap check only, so it holds the last result until the next check - there is no
+    /// timer behind it.
+    pub fn update_missing_messages(&self, topic_key: TopicKeyRef<'_>, value: i64) {
+        self.topic_missing_messages.update_value(topic_key, value);
+    }
+
     pub async fn update(
         &self,
         mut update_data: AHashMap<TopicKey, PrometheusMetricsToUpdate>,
@@ -63,6 +482,11 @@ impl PrometheusMetrics {
 
                     self.cached_messages_size
                         .update_value(active_topic_key.to_ref(), metrics.content_size as i64);
+
+                    self.topic_archive_write_failures.update_value(
+                        active_topic_key.to_ref(),
+                        metrics.archive_write_failures as i64,
+                    );
                 }
                 None => {
                     self.topic_persist_queue_size
@@ -71,6 +495,9 @@ impl PrometheusMetrics {
                     self.cached_messages_size
                         .remove_topic(active_topic_key.to_ref());
 
+                    self.topic_archive_write_failures
+                        .remove_topic(active_topic_key.to_ref());
+
                     topics_to_remove.push(active_topic_key.clone());
                 }
             }
@@ -87,6 +514,9 @@ impl PrometheusMetrics {
             self.cached_messages_size
                 .update_value(topic_key.to_ref(), metrics.content_size as i64);
 
+            self.topic_archive_write_failures
+                .update_value(topic_key.to_ref(), metrics.archive_write_failures as i64);
+
             active_topics.insert(topic_key);
         }
     }
@@ -104,3 +534,52 @@ impl PrometheusMetrics {
 fn create_http_connections_amount() -> IntGauge {
     IntGauge::new("http_connections_amount", "Amount of Http Connections").unwrap()
 }
+
+fn create_streamed_reads_aborted() -> IntCounterVec {
+    IntCounterVec::new(
+        prometheus::Opts::new(
+            "streamed_reads_aborted",
+            "Streamed reads stopped before the end of the requested range",
+        ),
+        &["rpc", "reason"],
+    )
+    .unwrap()
+}
+
+fn create_histogram(name: &str, help: &str, buckets: &[f64], labels: &[&str]) -> HistogramVec {
+    HistogramVec::new(
+        HistogramOpts::new(name, help).buckets(buckets.to_vec()),
+        labels,
+    )
+    .unwrap()
+}
+
+fn create_grpc_requests() -> IntCounterVec {
+    IntCounterVec::new(
+        prometheus::Opts::new(
+            "grpc_requests",
+            "gRPC calls, by the code they answered with",
+        ),
+        &["rpc", "code"],
+    )
+    .unwrap()
+}
+
+fn create_namespace_counter(name: &str, help: &str) -> IntCounterVec {
+    IntCounterVec::new(prometheus::Opts::new(name, help), &["namespace"]).unwrap()
+}
+
+fn create_namespace_bytes(name: &str, help: &str) -> IntGaugeVec {
+    IntGaugeVec::new(prometheus::Opts::new(name, help), &["namespace"]).unwrap()
+}
+
+fn create_queue_snapshots_rejected() -> IntCounterVec {
+    IntCounterVec::new(
+        prometheus::Opts::new(
+            "queue_snapshots_rejected",
+            "SaveQueueSnapshot calls refused because the node looked like it lost its state",
+        ),
+        &["reason"],
+    )
+    .unwrap()
+}
@@ -7,8 +7,13 @@ use crate::{archive_storage::ArchiveFileNo, topic_key::TopicKeyRef, typing::Year
 ///
 /// ```text
 /// {data_folder}/
+///     .lock                         held by the running instance: its pid, host and start time
+///     .topic-moves.yaml             MoveTopic calls not finished yet, with the step each got to
 ///     {namespace}/
 ///         topics-and-queue.yaml     that namespace's topics + queues, human readable
+///         retention.yaml            per topic: how long its data is meant to be kept
+///         .topics-and-queue.{:020}.yaml   past revisions of the snapshot, by when they were taken
+///         cold-usage.yaml       per topic: the files uploaded to the cold tier and their sizes
 ///         {topic}/
 ///             {:019}.archive        sealed sub pages: TOC + compressed blocks
 ///             .{year}.yearindex     527 040 minutes x 8 bytes, addressed at minute*8
@@ -25,12 +30,31 @@ use crate::{archive_storage::ArchiveFileNo, topic_key::TopicKeyRef, typing::Year
 /// into `default/` once, at startup - see `operations::migrate_legacy_topics`.
 /// One per namespace, next to that namespace's topic folders.
 pub const NAMESPACE_SNAPSHOT_FILE_NAME: &str = "topics-and-queue.yaml";
+/// One per namespace as well - the retention an operator set on its topics.
+pub const NAMESPACE_RETENTION_FILE_NAME: &str = "retention.yaml";
+/// And what its topics have in the cold tier, since the cold tier can not be listed.
+pub const NAMESPACE_COLD_USAGE_FILE_NAME: &str = "cold-usage.yaml";
+/// A past revision of the namespace snapshot is `.topics-and-queue.{revision_id:020}.yaml` next to
+/// it. A file, not a folder: every folder in a namespace is a topic.
+pub const SNAPSHOT_REVISION_FILE_PREFIX: &str = ".topics-and-queue.";
+pub const SNAPSHOT_REVISION_FILE_EXTENSION: &str = ".yaml";
+/// At the root, so it is never mistaken for a namespace - every folder there is one.
+pub const INSTANCE_LOCK_FILE_NAME: &str = ".lock";
+/// At the root as well, since a move can cross namespaces.
+pub const TOPIC_MOVES_FILE_NAME: &str = ".topic-moves.yaml";
+/// Not in the namespace's folder - that is the last thing the deletion removes.
+pub const NAMESPACE_DELETIONS_FILE_NAME: &str = ".namespace-deletions.yaml";
 /// The pre-YAML global protobuf blob - only the migration still knows about it.
 pub const LEGACY_TOPICS_SNAPSHOT_FILE_NAME: &str = "topicsdata";
 pub const ACTIVE_FILE_NAME: &str = "active";
 pub const ARCHIVE_FILE_EXTENSION: &str = ".archive";
 pub const YEAR_INDEX_FILE_EXTENSION: &str = ".yearindex";
 
+/// The cold tier can not be listed, so the years a topic spans have to be guessed whenever its
+/// year indexes are looked for there. Whoever probes goes from here up to the current year - a
+/// few dozen requests that mostly answer "not there", which is not an error.
+pub const OLDEST_POSSIBLE_YEAR: u32 = 2000;
+
 /// `{namespace}/{topic}` - the S3 key prefix and the local sub-folder alike.
 pub fn get_topic_relative_path(topic_key: TopicKeyRef<'_>) -> String {
     format!("{}/{}", topic_key.namespace, topic_key.topic_id)
@@ -72,6 +96,62 @@ pub fn get_namespace_snapshot_file(data_folder: &str, namespace: &str) -> PathBu
     result
 }
 
+pub fn get_namespace_retention_file(data_folder: &str, namespace: &str) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(namespace);
+    result.push(NAMESPACE_RETENTION_FILE_NAME);
+    result
+}
+
+pub fn get_namespace_cold_usage_file(data_folder: &str, namespace: &str) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(namespace);
+    result.push(NAMESPACE_COLD_USAGE_FILE_NAME);
+    result
+}
+
+/// The revision id is the moment it was taken, in unix microseconds - zero-padded, so the names
+/// sort in the order the revisions were taken.
+pub fn get_snapshot_revision_file_name(revision_id: i64) -> String {
+    format!(
+        "{}{:020}{}",
+        SNAPSHOT_REVISION_FILE_PREFIX, revision_id, SNAPSHOT_REVISION_FILE_EXTENSION
+    )
+}
+
+pub fn get_snapshot_revision_file(data_folder: &str, namespace: &str, revision_id: i64) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(namespace);
+    result.push(get_snapshot_revision_file_name(revision_id));
+    result
+}
+
+/// `.topics-and-queue.00000001700000000000.yaml` -> `1700000000000`. `None` for anything else,
+/// the current snapshot and a half-written `.tmp` included.
+pub fn parse_snapshot_revision_file_name(file_name: &str) -> Option<i64> {
+    let value = file_name.strip_prefix(SNAPSHOT_REVISION_FILE_PREFIX)?;
+    let value = value.strip_suffix(SNAPSHOT_REVISION_FILE_EXTENSION)?;
+    value.parse().ok()
+}
+
+pub fn get_instance_lock_file(data_folder: &str) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(INSTANCE_LOCK_FILE_NAME);
+    result
+}
+
+pub fn get_topic_moves_file(data_folder: &str) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(TOPIC_MOVES_FILE_NAME);
+    result
+}
+
+pub fn get_namespace_deletions_file(data_folder: &str) -> PathBuf {
+    let mut result = PathBuf::from(data_folder);
+    result.push(NAMESPACE_DELETIONS_FILE_NAME);
+    result
+}
+
 pub fn get_legacy_topics_snapshot_file(data_folder: &str) -> PathBuf {
     let mut result = PathBuf::from(data_folder);
     result.push(LEGACY_TOPICS_SNAPSHOT_FILE_NAME);
@@ -202,4 +282,21 @@ mod tests {
             names
         );
     }
+
+    #[test]
+    fn snapshot_revision_names_round_trip() {
+        let file_name = get_snapshot_revision_file_name(1_700_000_000_000_000);
+
+        assert_eq!(".topics-and-queue.00001700000000000000.yaml", file_name);
+        assert_eq!(
+            Some(1_700_000_000_000_000),
+            parse_snapshot_revision_file_name(&file_name)
+        );
+
+        assert!(parse_snapshot_revision_file_name(NAMESPACE_SNAPSHOT_FILE_NAME).is_none());
+        assert!(parse_snapshot_revision_file_name(
+            ".topics-and-queue.00001700000000000000.yaml.tmp"
+        )
+        .is_none());
+    }
 }
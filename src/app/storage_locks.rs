@@ -7,7 +7,8 @@ use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
 use crate::topic_key::{TopicKey, TopicKeyRef};
 
 /// One `RwLock` per topic, guarding that topic's files against being deleted out from under a
-/// reader.
+/// reader. `AppContext::topic_writes` uses the same map to let a `MoveTopic` wait out the writes
+/// already on their way into a topic.
 ///
 /// The pattern the background archiver follows:
 ///
@@ -25,6 +25,13 @@ impl ArchiveFileNo {
         SubPageId::new(result)
     }
 
+    pub fn get_last_sub_page_id(&self) -> SubPageId {
+        let result = self.get_first_sub_page_id().get_value()
+            + super::consts::ARCHIVE_SUB_PAGES_PER_FILE as i64
+            - 1;
+        SubPageId::new(result)
+    }
+
     pub fn get_toc_offset(&self, sub_page_id: SubPageId) -> usize {
         let result = (sub_page_id.get_value() - self.get_first_sub_page_id().get_value())
             * super::consts::TOC_STRUCTURE_SIZE as i64;
@@ -73,6 +80,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_file_covers_its_sub_pages() {
+        let file_no = ArchiveFileNo::new(2);
+
+        assert_eq!(20_000, file_no.get_first_sub_page_id().get_value());
+        assert_eq!(29_999, file_no.get_last_sub_page_id().get_value());
+    }
+
     #[test]
     fn test_offsets() {
         let file_no = ArchiveFileNo::from_sub_page_id(SubPageId::new(0));
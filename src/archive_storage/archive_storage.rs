@@ -10,10 +10,13 @@ use super::{
     toc::SubPagePosition,
     ArchiveFileNo,
 };
-use crate::{cold_storage::ColdStorage, topic_key::TopicKey};
+use crate::{
+    cold_storage::{ColdStorage, RangeReadKind},
+    topic_key::TopicKey,
+};
 
 #[derive(Debug)]
-// The payloads are read through `{:?}` in panics and logs, which dead-code analysis ignores.
+// The payloads are read through `{:?}` in logs, which dead-code analysis ignores.
 #[allow(dead_code)]
 pub enum ArchiveStorageError {
     FileStorageError(FileStorageError),
@@ -35,6 +38,13 @@ impl From<FileStorageError> for ArchiveStorageError {
 /// The head of the file is reserved as `TOC_SIZE` (page-rounded) rather than the `TOC_SIZE_IN_BITES`
 /// the entries actually occupy. That rounding comes from the page blob era, and it is kept so a
 /// file copied out of the old storage keeps its data offsets valid.
+/// The head of an archive as a copy of it starts, and where the copy ends.
+pub struct ArchiveHead {
+    /// The TOC, padded to the reserved size.
+    pub toc: Vec<u8>,
+    pub content_size: u64,
+}
+
 pub struct ArchiveStorage {
     pub archive_file_no: ArchiveFileNo,
     source: ArchiveSource,
@@ -109,6 +119,11 @@ impl ArchiveStorage {
         }
     }
 
+    /// Uploaded - every read is a request to the cold tier.
+    pub fn is_cold(&self) -> bool {
+        matches!(self.source, ArchiveSource::Cold(_))
+    }
+
     async fn get_sub_page_position(
         &self,
         sub_page_id: SubPageId,
@@ -129,6 +144,78 @@ impl ArchiveStorage {
         }
     }
 
+    /// Every slot of the TOC, in sub page order - what the inventory views show as occupancy.
+    /// The whole table is one read locally, and the cached copy for a cold file.
+    pub async fn read_toc(&self) -> Result<Vec<SubPagePosition>, ArchiveStorageError> {
+        let toc = match &self.source {
+            ArchiveSource::Local(file) => Arc::new(file.read(0, TOC_SIZE_IN_BITES).await?),
+            ArchiveSource::Cold(cold) => cold.get_toc().await?,
+        };
+
+        Ok(toc
+            .chunks_exact(TOC_STRUCTURE_SIZE)
+            .map(SubPagePosition::parse)
+            .collect())
+    }
+
+    /// Where the last payload ends - the size of the file, which the cold tier can not be asked
+    /// for. Payloads are appended, so it is the furthest end any slot points at.
+    pub async fn get_content_size(&self) -> Result<u64, ArchiveStorageError> {
+        let result = self
+            .read_toc()
+            .await?
+            .iter()
+            .map(|itm| itm.offset + itm.length as u64)
+            .max()
+            .unwrap_or(0);
+
+        Ok(result.max(TOC_SIZE as u64))
+    }
+
+    /// The TOC and the size it implies, from one read. A file still being written is copied
+    /// consistently this way: the head is the TOC as read rather than as it is by the time the
+    /// copy gets there, and a payload appended meanwhile lies past `content_size`.
+    pub async fn read_head(&self) -> Result<ArchiveHead, ArchiveStorageError> {
+        let toc = self.read_toc().await?;
+
+        let mut result = ArchiveHead {
+            toc: Vec::with_capacity(TOC_SIZE),
+            content_size: TOC_SIZE as u64,
+        };
+
+        for position in toc.iter() {
+            result
+                .toc
+                .extend_from_slice(position.serialize().as_slice());
+
+            if !position.is_empty() {
+                result.content_size = result
+                    .content_size
+                    .max(position.offset + position.length as u64);
+            }
+        }
+
+        result.toc.resize(TOC_SIZE, 0);
+
+        Ok(result)
+    }
+
+    /// The file as stored, a range at a time - everything past the TOC up to
+    /// [`ArchiveHead::content_size`].
+    pub async fn read_raw(
+        &self,
+        offset: u64,
+        length: usize,
+    ) -> Result<Vec<u8>, ArchiveStorageError> {
+        match &self.source {
+            ArchiveSource::Local(file) => Ok(file.read(offset as usize, length).await?),
+            ArchiveSource::Cold(cold) => {
+                cold.read_range(offset, offset + length as u64 - 1, RangeReadKind::Export)
+                    .await
+            }
+        }
+    }
+
     pub async fn read_sub_page_payload(
         &self,
         sub_page_id: SubPageId,
@@ -146,7 +233,11 @@ impl ArchiveStorage {
             }
             ArchiveSource::Cold(cold) => {
                 let payload = cold
-                    .read_range(pos.offset, pos.offset + pos.length as u64 - 1)
+                    .read_range(
+                        pos.offset,
+                        pos.offset + pos.length as u64 - 1,
+                        RangeReadKind::SubPage,
+                    )
                     .await?;
                 Ok(Some(payload))
             }
@@ -203,7 +294,9 @@ impl ColdArchive {
         }
 
         // The object is sealed, so this is fetched once and kept for the lifetime of the process.
-        let toc = self.read_range(0, TOC_SIZE_IN_BITES as u64 - 1).await?;
+        let toc = self
+            .read_range(0, TOC_SIZE_IN_BITES as u64 - 1, RangeReadKind::Toc)
+            .await?;
         let toc = Arc::new(toc);
 
         *self.toc.lock() = Some(toc.clone());
@@ -211,9 +304,20 @@ impl ColdArchive {
         Ok(toc)
     }
 
-    async fn read_range(&self, from: u64, to: u64) -> Result<Vec<u8>, ArchiveStorageError> {
+    async fn read_range(
+        &self,
+        from: u64,
+        to: u64,
+        kind: RangeReadKind,
+    ) -> Result<Vec<u8>, ArchiveStorageError> {
         self.cold_storage
-            .download_range(self.topic_key.to_ref(), self.file_name.as_str(), from, to)
+            .download_range(
+                self.topic_key.to_ref(),
+                self.file_name.as_str(),
+                from,
+                to,
+                kind,
+            )
             .await
             .map_err(ArchiveStorageError::ColdStorageError)
     }
@@ -282,6 +386,67 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    #[tokio::test]
+    async fn content_size_is_the_end_of_the_last_payload() {
+        let path = temp_path("content_size");
+
+        let storage = ArchiveStorage::open_or_create_local(ArchiveFileNo::new(0), &path)
+            .await
+            .unwrap();
+
+        assert_eq!(TOC_SIZE as u64, storage.get_content_size().await.unwrap());
+
+        // Written out of order: the last slot is not the last payload
+        storage
+            .write_payload(SubPageId::new(5), &[1u8; 10])
+            .await
+            .unwrap();
+        storage
+            .write_payload(SubPageId::new(2), &[2u8; 7])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            TOC_SIZE as u64 + 17,
+            storage.get_content_size().await.unwrap()
+        );
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            storage.get_content_size().await.unwrap()
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn head_and_raw_ranges_make_up_the_file() {
+        let path = temp_path("head_and_raw");
+
+        let storage = ArchiveStorage::open_or_create_local(ArchiveFileNo::new(0), &path)
+            .await
+            .unwrap();
+
+        storage
+            .write_payload(SubPageId::new(4), &[4u8; 20])
+            .await
+            .unwrap();
+        storage
+            .write_payload(SubPageId::new(1), &[1u8; 5])
+            .await
+            .unwrap();
+
+        let head = storage.read_head().await.unwrap();
+        assert_eq!(TOC_SIZE, head.toc.len());
+        assert_eq!(TOC_SIZE as u64 + 25, head.content_size);
+
+        let mut copy = head.toc.clone();
+        copy.extend(storage.read_raw(TOC_SIZE as u64, 25).await.unwrap());
+
+        assert_eq!(std::fs::read(&path).unwrap(), copy);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[tokio::test]
     async fn several_sub_pages_keep_their_own_slots() {
         let path = temp_path("several_sub_pages");
@@ -350,6 +515,29 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    #[tokio::test]
+    async fn toc_lists_every_slot_in_order() {
+        let path = temp_path("read_toc");
+
+        let storage = ArchiveStorage::open_or_create_local(ArchiveFileNo::new(0), &path)
+            .await
+            .unwrap();
+
+        storage
+            .write_payload(SubPageId::new(2), &[2u8; 10])
+            .await
+            .unwrap();
+
+        let toc = storage.read_toc().await.unwrap();
+
+        assert_eq!(TOC_SIZE_IN_BITES / TOC_STRUCTURE_SIZE, toc.len());
+        assert!(toc[0].is_empty());
+        assert_eq!(10, toc[2].length);
+        assert_eq!(1, toc.iter().filter(|itm| !itm.is_empty()).count());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[tokio::test]
     async fn reopening_sees_what_was_written() {
         let path = temp_path("reopen");
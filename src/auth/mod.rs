@@ -0,0 +1,2 @@
+mod token_auth;
+pub use token_auth::*;
@@ -0,0 +1,292 @@
+use std::sync::Arc;
+
+use crate::{
+    settings::{AuthScope, AuthSettingsModel},
+    topic_key::Namespace,
+};
+
+#[derive(Debug)]
+pub enum AuthError {
+    /// No token, a malformed header or a token nobody configured - 401 / `Unauthenticated`.
+    Unauthenticated(String),
+    /// A known token asking for a scope or a namespace it was not given - 403 /
+    /// `PermissionDenied`.
+    PermissionDenied(String),
+}
+
+/// A configured token, resolved once per request and then asked about every namespace the
+/// request touches - a `SaveMessages` stream or a snapshot spans several of them.
+#[derive(Debug)]
+pub struct AuthToken {
+    pub name: String,
+    token: String,
+    scopes: Vec<AuthScope>,
+    namespaces: Option<Vec<Namespace>>,
+}
+
+impl AuthToken {
+    pub fn has_scope(&self, scope: AuthScope) -> bool {
+        self.scopes
+            .iter()
+            .any(|itm| *itm == scope || *itm == AuthScope::Admin)
+    }
+
+    /// `false` for a token limited to a list of namespaces. Such a token can not take part in
+    /// calls that cover every namespace at once.
+    pub fn covers_every_namespace(&self) -> bool {
+        self.namespaces.is_none()
+    }
+
+    pub fn can_access_namespace(&self, namespace: &str) -> bool {
+        match &self.namespaces {
+            Some(namespaces) => namespaces.iter().any(|itm| itm.as_str() == namespace),
+            None => true,
+        }
+    }
+
+    pub fn check(&self, scope: AuthScope, namespace: Option<&str>) -> Result<(), AuthError> {
+        if !self.has_scope(scope) {
+            return Err(AuthError::PermissionDenied(format!(
+                "Token '{}' has no '{}' scope",
+                self.name,
+                scope.as_str()
+            )));
+        }
+
+        if let Some(namespace) = namespace {
+            if !self.can_access_namespace(namespace) {
+                return Err(AuthError::PermissionDenied(format!(
+                    "Token '{}' has no access to namespace '{}'",
+                    self.name, namespace
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// For the calls that read or replace the state of every namespace in one go.
+    pub fn check_every_namespace(&self, scope: AuthScope) -> Result<(), AuthError> {
+        self.check(scope, None)?;
+
+        if !self.covers_every_namespace() {
+            return Err(AuthError::PermissionDenied(format!(
+                "Token '{}' is limited to a list of namespaces and this call covers all of them",
+                self.name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct TokenAuth {
+    tokens: Vec<Arc<AuthToken>>,
+}
+
+impl TokenAuth {
+    /// Refuses to start on a configuration that can not mean what the operator intended: an
+    /// empty token would let in anybody sending `Bearer `, and a misspelled namespace would
+    /// silently lock its token out.
+    pub fn new(settings: &AuthSettingsModel) -> Self {
+        let mut tokens: Vec<Arc<AuthToken>> = Vec::with_capacity(settings.tokens.len());
+
+        for token in settings.tokens.iter() {
+            if token.token.trim().is_empty() {
+                panic!("Invalid auth settings: token '{}' is empty", token.name);
+            }
+
+            if tokens.iter().any(|itm| itm.token == token.token) {
+                panic!(
+                    "Invalid auth settings: token '{}' has the same value as another one",
+                    token.name
+                );
+            }
+
+            let namespaces = token.namespaces.as_ref().map(|namespaces| {
+                namespaces
+                    .iter()
+                    .map(|namespace| {
+                        Namespace::parse(Some(namespace.as_str())).unwrap_or_else(|err| {
+                            panic!(
+                                "Invalid auth settings: token '{}' lists namespace '{}'. {}",
+                                token.name, namespace, err
+                            )
+                        })
+                    })
+                    .collect()
+            });
+
+            tokens.push(Arc::new(AuthToken {
+                name: token.name.clone(),
+                token: token.token.clone(),
+                scopes: token.scopes.clone(),
+                namespaces,
+            }));
+        }
+
+        Self { tokens }
+    }
+
+    /// Takes the raw value of the `authorization` header - the same one for HTTP and for gRPC
+    /// metadata.
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<Arc<AuthToken>, AuthError> {
+        let Some(authorization) = authorization else {
+            return Err(AuthError::Unauthenticated(
+                "Authorization header is missing".to_string(),
+            ));
+        };
+
+        let Some(token) = parse_bearer(authorization) else {
+            return Err(AuthError::Unauthenticated(
+                "Authorization header is not a Bearer token".to_string(),
+            ));
+        };
+
+        for auth_token in self.tokens.iter() {
+            if constant_time_eq(auth_token.token.as_bytes(), token.as_bytes()) {
+                return Ok(auth_token.clone());
+            }
+        }
+
+        Err(AuthError::Unauthenticated("Unknown token".to_string()))
+    }
+}
+
+fn parse_bearer(authorization: &str) -> Option<&str> {
+    let authorization = authorization.trim();
+    let (scheme, token) = authorization.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let token = token.trim();
+
+    if token.is_empty() {
+        return None;
+    }
+
+    Some(token)
+}
+
+/// Compares without stopping at the first different byte, so the response time does not tell a
+/// caller how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::{AuthScope, AuthSettingsModel, AuthTokenSettingsModel};
+
+    use super::{AuthError, TokenAuth};
+
+    fn create_auth() -> TokenAuth {
+        TokenAuth::new(&AuthSettingsModel {
+            tokens: vec![
+                AuthTokenSettingsModel {
+                    name: "bus-node".to_string(),
+                    token: "node-token".to_string(),
+                    scopes: vec![AuthScope::Read, AuthScope::Write],
+                    namespaces: None,
+                },
+                AuthTokenSettingsModel {
+                    name: "alpha-reader".to_string(),
+                    token: "alpha-token".to_string(),
+                    scopes: vec![AuthScope::Read],
+                    namespaces: Some(vec!["alpha".to_string()]),
+                },
+                AuthTokenSettingsModel {
+                    name: "ops".to_string(),
+                    token: "ops-token".to_string(),
+                    scopes: vec![AuthScope::Admin],
+                    namespaces: None,
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn missing_or_unknown_token_is_unauthenticated() {
+        let auth = create_auth();
+
+        assert!(matches!(
+            auth.authenticate(None),
+            Err(AuthError::Unauthenticated(_))
+        ));
+        assert!(matches!(
+            auth.authenticate(Some("Basic node-token")),
+            Err(AuthError::Unauthenticated(_))
+        ));
+        assert!(matches!(
+            auth.authenticate(Some("Bearer wrong")),
+            Err(AuthError::Unauthenticated(_))
+        ));
+        assert!(matches!(
+            auth.authenticate(Some("Bearer ")),
+            Err(AuthError::Unauthenticated(_))
+        ));
+    }
+
+    #[test]
+    fn bearer_scheme_is_case_insensitive() {
+        let auth = create_auth();
+
+        let token = auth.authenticate(Some("bearer node-token")).unwrap();
+        assert_eq!(token.name, "bus-node");
+    }
+
+    #[test]
+    fn scopes_are_checked_and_admin_grants_everything() {
+        let auth = create_auth();
+
+        let node = auth.authenticate(Some("Bearer node-token")).unwrap();
+        assert!(node.check(AuthScope::Write, Some("alpha")).is_ok());
+        assert!(matches!(
+            node.check(AuthScope::Admin, None),
+            Err(AuthError::PermissionDenied(_))
+        ));
+
+        let ops = auth.authenticate(Some("Bearer ops-token")).unwrap();
+        assert!(ops.check(AuthScope::Read, Some("beta")).is_ok());
+        assert!(ops.check(AuthScope::Write, Some("beta")).is_ok());
+    }
+
+    #[test]
+    fn namespace_restriction_is_enforced() {
+        let auth = create_auth();
+
+        let reader = auth.authenticate(Some("Bearer alpha-token")).unwrap();
+
+        assert!(reader.check(AuthScope::Read, Some("alpha")).is_ok());
+        assert!(matches!(
+            reader.check(AuthScope::Read, Some("default")),
+            Err(AuthError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            reader.check_every_namespace(AuthScope::Read),
+            Err(AuthError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_token_refuses_to_start() {
+        TokenAuth::new(&AuthSettingsModel {
+            tokens: vec![AuthTokenSettingsModel {
+                name: "empty".to_string(),
+                token: " ".to_string(),
+                scopes: vec![AuthScope::Read],
+                namespaces: None,
+            }],
+        });
+    }
+}
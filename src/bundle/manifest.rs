@@ -0,0 +1,157 @@
+use my_service_bus::shared::sub_page::SubPageId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::storage_layout, archive_storage::ArchiveFileNo,
+    topics_snapshot::yaml_model::TopicYamlModel, typing::Year,
+};
+
+/// The first entry of every bundle.
+pub const BUNDLE_MANIFEST_FILE_NAME: &str = "manifest.yaml";
+
+/// Raised when a bundle written by this version could not be read by an older one.
+pub const BUNDLE_VERSION: u32 = 1;
+
+pub const SUB_PAGE_FILE_EXTENSION: &str = ".subpage";
+
+/// What a bundle holds, written ahead of the files so an importer knows what to expect before
+/// the first byte of data arrives.
+///
+/// A bundle is a tar stream:
+///
+/// ```text
+/// manifest.yaml
+/// {topic_id}/{archive_file_no:019}.archive       as stored, TOC and all
+/// {topic_id}/.{year}.yearindex                   as stored
+/// {topic_id}/{sub_page_id:019}.subpage           a sub page not archived yet, compressed
+/// ```
+///
+/// The namespace is in the manifest and not in the paths, so the same bundle imports under any
+/// name.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleManifestYamlModel {
+    pub version: u32,
+    pub created: String,
+    pub namespace: String,
+    #[serde(default)]
+    pub topics: Vec<BundleTopicYamlModel>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleTopicYamlModel {
+    /// The topic's section of `topics-and-queue.yaml` - its message id and its queues.
+    #[serde(flatten)]
+    pub snapshot: TopicYamlModel,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_sec: Option<u64>,
+
+    /// Names within the topic's folder in the bundle, in the order they follow.
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// A file a bundle may carry for a topic. Anything else is refused on import - these names are
+/// the only ones that ever reach the data folder.
+#[derive(Clone, Copy)]
+pub enum BundleFile {
+    Archive(ArchiveFileNo),
+    YearIndex(Year),
+    SubPage(SubPageId),
+}
+
+impl BundleFile {
+    pub fn get_file_name(&self) -> String {
+        match self {
+            BundleFile::Archive(archive_file_no) => {
+                storage_layout::get_archive_file_name(*archive_file_no)
+            }
+            BundleFile::YearIndex(year) => storage_layout::get_year_index_file_name(*year),
+            BundleFile::SubPage(sub_page_id) => {
+                format!("{:019}{}", sub_page_id.get_value(), SUB_PAGE_FILE_EXTENSION)
+            }
+        }
+    }
+
+    pub fn parse(file_name: &str) -> Option<Self> {
+        if let Some(archive_file_no) = storage_layout::parse_archive_file_name(file_name) {
+            return Some(Self::Archive(archive_file_no));
+        }
+
+        if let Some(year) = storage_layout::parse_year_index_file_name(file_name) {
+            return Some(Self::YearIndex(year));
+        }
+
+        let value = file_name.strip_suffix(SUB_PAGE_FILE_EXTENSION)?;
+        let value: i64 = value.parse().ok()?;
+        Some(Self::SubPage(SubPageId::new(value)))
+    }
+}
+
+pub fn get_entry_path(topic_id: &str, file_name: &str) -> String {
+    format!("{}/{}", topic_id, file_name)
+}
+
+/// `orders/.2024.yearindex` -> (`orders`, `.2024.yearindex`). A topic id never has a `/` in it.
+pub fn parse_entry_path(path: &str) -> Option<(&str, &str)> {
+    let (topic_id, file_name) = path.split_once('/')?;
+
+    if topic_id.is_empty() || file_name.is_empty() || file_name.contains('/') {
+        return None;
+    }
+
+    Some((topic_id, file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_files_are_parsed() {
+        for file in [
+            BundleFile::Archive(ArchiveFileNo::new(3)),
+            BundleFile::YearIndex(Year::new(2024)),
+            BundleFile::SubPage(SubPageId::new(42)),
+        ] {
+            let file_name = file.get_file_name();
+            let parsed = BundleFile::parse(file_name.as_str()).unwrap();
+            assert_eq!(file_name, parsed.get_file_name());
+        }
+
+        assert!(BundleFile::parse("active").is_none());
+        assert!(BundleFile::parse("../../etc.archive").is_none());
+
+        assert_eq!(
+            Some(("orders", ".2024.yearindex")),
+            parse_entry_path("orders/.2024.yearindex")
+        );
+        assert!(parse_entry_path("orders/../x.archive").is_none());
+        assert!(parse_entry_path("manifest.yaml").is_none());
+    }
+
+    #[test]
+    fn the_snapshot_section_is_flattened_into_the_topic() {
+        let yaml = "version: 1\n\
+                    created: 2026-01-01T00:00:00Z\n\
+                    namespace: tenant\n\
+                    topics:\n\
+                    - topic_id: orders\n  \
+                      message_id: 15\n  \
+                      queues:\n  \
+                      - queue_id: billing\n    \
+                        queue_type: 0\n  \
+                      retention_sec: 86400\n  \
+                      files:\n  \
+                      - 0000000000000000000.archive\n";
+
+        let manifest: BundleManifestYamlModel = serde_yaml::from_str(yaml).unwrap();
+
+        let topic = &manifest.topics[0];
+        assert_eq!("orders", topic.snapshot.topic_id);
+        assert_eq!(15, topic.snapshot.message_id);
+        assert_eq!("billing", topic.snapshot.queues[0].queue_id);
+        assert_eq!(Some(86400), topic.retention_sec);
+        assert_eq!(1, topic.files.len());
+    }
+}
@@ -0,0 +1,3 @@
+mod manifest;
+pub use manifest::*;
+pub mod tar;
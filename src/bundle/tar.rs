@@ -0,0 +1,517 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const BLOCK_SIZE: usize = 512;
+
+const NAME_LEN: usize = 100;
+
+/// The biggest size the octal field holds.
+const MAX_OCTAL_SIZE: u64 = 0o777_7777_7777;
+
+const TYPE_FILE: u8 = b'0';
+/// Pre-POSIX tars mark a regular file with a NUL.
+const TYPE_FILE_OLD: u8 = 0;
+const TYPE_PAX: u8 = b'x';
+
+/// A PAX header of ours is one record; a bigger one is not a name worth reading.
+const MAX_PAX_SIZE: u64 = 64 * 1024;
+
+const SKIP_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum TarError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl From<std::io::Error> for TarError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src)
+    }
+}
+
+impl std::fmt::Display for TarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TarError::Io(err) => write!(f, "{}", err),
+            TarError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TarHeader {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Just enough of the tar format for a bundle: regular files, written as ustar, with a PAX `path`
+/// record for a name that does not fit the 100 bytes of the header - a topic id alone can be 255.
+/// Sizes beyond what the 11 octal digits hold go in the GNU base-256 form. Whatever else a tar made
+/// by another tool may carry - directories, links, global headers - is skipped on reading, so a
+/// bundle unpacked and packed again by hand still imports.
+///
+/// The size of an entry goes into its header, so it has to be known up front; the content can
+/// then arrive in as many pieces as it likes.
+pub struct TarWriter<W> {
+    output: W,
+    entry_size: u64,
+    entry_left: u64,
+}
+
+impl<W: AsyncWrite + Unpin> TarWriter<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            entry_size: 0,
+            entry_left: 0,
+        }
+    }
+
+    pub async fn start_entry(&mut self, path: &str, size: u64, mtime: i64) -> Result<(), TarError> {
+        if self.entry_left > 0 {
+            return Err(TarError::Invalid(format!(
+                "The previous entry is {} bytes short",
+                self.entry_left
+            )));
+        }
+
+        if path.len() > NAME_LEN {
+            let record = encode_pax_record("path", path);
+            let header = encode_header(b"././@PaxHeader", record.len() as u64, mtime, TYPE_PAX);
+
+            self.output.write_all(header.as_slice()).await?;
+            self.output.write_all(record.as_slice()).await?;
+            self.write_padding(record.len() as u64).await?;
+        }
+
+        let header = encode_header(path.as_bytes(), size, mtime, TYPE_FILE);
+        self.output.write_all(header.as_slice()).await?;
+
+        self.entry_size = size;
+        self.entry_left = size;
+
+        Ok(())
+    }
+
+    pub async fn write(&mut self, content: &[u8]) -> Result<(), TarError> {
+        if content.len() as u64 > self.entry_left {
+            return Err(TarError::Invalid(format!(
+                "{} bytes more than the entry was declared with",
+                content.len() as u64 - self.entry_left
+            )));
+        }
+
+        self.output.write_all(content).await?;
+        self.entry_left -= content.len() as u64;
+
+        Ok(())
+    }
+
+    pub async fn finish_entry(&mut self) -> Result<(), TarError> {
+        if self.entry_left > 0 {
+            return Err(TarError::Invalid(format!(
+                "The entry is {} bytes short",
+                self.entry_left
+            )));
+        }
+
+        self.write_padding(self.entry_size).await
+    }
+
+    pub async fn append(&mut self, path: &str, content: &[u8], mtime: i64) -> Result<(), TarError> {
+        self.start_entry(path, content.len() as u64, mtime).await?;
+        self.write(content).await?;
+        self.finish_entry().await
+    }
+
+    /// The two empty blocks that end an archive - without them a reader takes the stream for cut
+    /// short.
+    pub async fn finish(mut self) -> Result<W, TarError> {
+        self.output.write_all(&[0u8; BLOCK_SIZE * 2]).await?;
+        self.output.flush().await?;
+        Ok(self.output)
+    }
+
+    async fn write_padding(&mut self, size: u64) -> Result<(), TarError> {
+        let padding = get_padding(size);
+
+        if padding > 0 {
+            self.output.write_all(&[0u8; BLOCK_SIZE][..padding]).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads entries in the order they come. Whatever of an entry was not read is skipped by the
+/// next `next_entry`.
+pub struct TarReader<R> {
+    input: R,
+    entry_left: u64,
+    padding_left: u64,
+}
+
+impl<R: AsyncRead + Unpin> TarReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            entry_left: 0,
+            padding_left: 0,
+        }
+    }
+
+    /// `None` at the end of the archive. A stream that ends without it is an error.
+    pub async fn next_entry(&mut self) -> Result<Option<TarHeader>, TarError> {
+        let mut pax_path = None;
+
+        loop {
+            self.skip_rest().await?;
+
+            let mut block = [0u8; BLOCK_SIZE];
+            self.input.read_exact(&mut block).await?;
+
+            if block.iter().all(|itm| *itm == 0) {
+                return Ok(None);
+            }
+
+            let (mut header, type_flag) = decode_header(&block)?;
+
+            self.entry_left = header.size;
+            self.padding_left = get_padding(header.size) as u64;
+
+            match type_flag {
+                TYPE_PAX => {
+                    let content = self.read_to_end(MAX_PAX_SIZE).await?;
+                    pax_path = decode_pax_path(content.as_slice())?.or(pax_path);
+                }
+                TYPE_FILE | TYPE_FILE_OLD => {
+                    if let Some(path) = pax_path {
+                        header.path = path;
+                    }
+
+                    return Ok(Some(header));
+                }
+                _ => {
+                    pax_path = None;
+                }
+            }
+        }
+    }
+
+    /// Reads the current entry. `0` once all of it is read.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, TarError> {
+        let len = (buffer.len() as u64).min(self.entry_left) as usize;
+
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.input.read_exact(&mut buffer[..len]).await?;
+        self.entry_left -= len as u64;
+
+        Ok(len)
+    }
+
+    /// The rest of the current entry in one piece - for the small ones.
+    pub async fn read_to_end(&mut self, max_size: u64) -> Result<Vec<u8>, TarError> {
+        if self.entry_left > max_size {
+            return Err(TarError::Invalid(format!(
+                "An entry of {} bytes where at most {} are expected",
+                self.entry_left, max_size
+            )));
+        }
+
+        let mut result = vec![0u8; self.entry_left as usize];
+        self.input.read_exact(result.as_mut_slice()).await?;
+        self.entry_left = 0;
+
+        Ok(result)
+    }
+
+    async fn skip_rest(&mut self) -> Result<(), TarError> {
+        let mut left = self.entry_left + self.padding_left;
+        let mut buffer = [0u8; SKIP_BUFFER_SIZE];
+
+        while left > 0 {
+            let len = (left as usize).min(SKIP_BUFFER_SIZE);
+            self.input.read_exact(&mut buffer[..len]).await?;
+            left -= len as u64;
+        }
+
+        self.entry_left = 0;
+        self.padding_left = 0;
+
+        Ok(())
+    }
+}
+
+fn get_padding(size: u64) -> usize {
+    (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE
+}
+
+fn encode_header(name: &[u8], size: u64, mtime: i64, type_flag: u8) -> [u8; BLOCK_SIZE] {
+    let mut header = [0u8; BLOCK_SIZE];
+
+    // A longer name is in the PAX record before this header; what fits here is only for tools
+    // that do not read PAX.
+    let name_len = name.len().min(NAME_LEN);
+    header[..name_len].copy_from_slice(&name[..name_len]);
+
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_size(&mut header[124..136], size);
+    write_octal(&mut header[136..148], mtime.max(0) as u64);
+    header[156] = type_flag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    let checksum = get_checksum(&header);
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+
+    header
+}
+
+fn decode_header(block: &[u8; BLOCK_SIZE]) -> Result<(TarHeader, u8), TarError> {
+    let stored_checksum = parse_octal(&block[148..156])?;
+
+    if stored_checksum != get_checksum(block) {
+        return Err(TarError::Invalid(
+            "A tar header does not match its checksum".to_string(),
+        ));
+    }
+
+    let name = read_c_string(&block[0..100]);
+
+    let path = if &block[257..262] == b"ustar" {
+        let prefix = read_c_string(&block[345..500]);
+
+        if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        }
+    } else {
+        name
+    };
+
+    let header = TarHeader {
+        path,
+        size: parse_size(&block[124..136])?,
+    };
+
+    Ok((header, block[156]))
+}
+
+/// The sum of the header bytes with the checksum field itself counted as spaces.
+fn get_checksum(header: &[u8; BLOCK_SIZE]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(index, itm)| {
+            if (148..156).contains(&index) {
+                b' ' as u64
+            } else {
+                *itm as u64
+            }
+        })
+        .sum()
+}
+
+/// Zero-padded octal, then a NUL - the field is one digit shorter than it is long.
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let value = format!("{:0width$o}", value, width = digits);
+    field[..digits].copy_from_slice(&value.as_bytes()[value.len() - digits..]);
+    field[digits] = 0;
+}
+
+fn write_size(field: &mut [u8], size: u64) {
+    if size <= MAX_OCTAL_SIZE {
+        write_octal(field, size);
+        return;
+    }
+
+    field.fill(0);
+    field[0] = 0x80;
+
+    let len = field.len();
+    field[len - 8..].copy_from_slice(size.to_be_bytes().as_slice());
+}
+
+fn parse_size(field: &[u8]) -> Result<u64, TarError> {
+    if field[0] & 0x80 == 0 {
+        return parse_octal(field);
+    }
+
+    let len = field.len();
+
+    if field[1..len - 8].iter().any(|itm| *itm != 0) {
+        return Err(TarError::Invalid("A tar entry is too big".to_string()));
+    }
+
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&field[len - 8..]);
+
+    Ok(u64::from_be_bytes(value))
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64, TarError> {
+    let value = read_c_string(field);
+    let value = value.trim_matches(' ');
+
+    if value.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(value, 8)
+        .map_err(|_| TarError::Invalid(format!("'{}' is not an octal number", value)))
+}
+
+fn read_c_string(field: &[u8]) -> String {
+    let len = field
+        .iter()
+        .position(|itm| *itm == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).to_string()
+}
+
+/// `{len} {key}={value}\n`, where `len` counts the whole record - its own digits included.
+fn encode_pax_record(key: &str, value: &str) -> Vec<u8> {
+    let body_len = key.len() + value.len() + 3;
+
+    let mut len = body_len + 1;
+
+    while len != body_len + len.to_string().len() {
+        len = body_len + len.to_string().len();
+    }
+
+    format!("{} {}={}\n", len, key, value).into_bytes()
+}
+
+fn decode_pax_path(content: &[u8]) -> Result<Option<String>, TarError> {
+    let mut result = None;
+    let mut position = 0;
+
+    while position < content.len() {
+        let invalid = || TarError::Invalid("A PAX header is malformed".to_string());
+
+        let space = content[position..]
+            .iter()
+            .position(|itm| *itm == b' ')
+            .ok_or_else(invalid)?;
+
+        let len: usize = std::str::from_utf8(&content[position..position + space])
+            .ok()
+            .and_then(|itm| itm.parse().ok())
+            .ok_or_else(invalid)?;
+
+        if len <= space + 1 || position + len > content.len() {
+            return Err(invalid());
+        }
+
+        let record = &content[position + space + 1..position + len - 1];
+
+        if let Some(value) = record.strip_prefix(b"path=") {
+            result = Some(String::from_utf8(value.to_vec()).map_err(|_| invalid())?);
+        }
+
+        position += len;
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn entries_read_back_as_written() {
+        let long_path = format!("{}/0000000000000000001.archive", "t".repeat(200));
+
+        let mut writer = TarWriter::new(Vec::new());
+        writer
+            .append("manifest.yaml", b"version: 1\n", 0)
+            .await
+            .unwrap();
+        writer.append("orders/empty", b"", 0).await.unwrap();
+        writer
+            .start_entry(long_path.as_str(), 600, 0)
+            .await
+            .unwrap();
+        writer.write(&[7u8; 100]).await.unwrap();
+        writer.write(&[8u8; 500]).await.unwrap();
+        writer.finish_entry().await.unwrap();
+        let content = writer.finish().await.unwrap();
+
+        assert_eq!(0, content.len() % BLOCK_SIZE);
+
+        let mut reader = TarReader::new(content.as_slice());
+
+        let entry = reader.next_entry().await.unwrap().unwrap();
+        assert_eq!("manifest.yaml", entry.path);
+        assert_eq!(
+            b"version: 1\n".to_vec(),
+            reader.read_to_end(1024).await.unwrap()
+        );
+
+        let entry = reader.next_entry().await.unwrap().unwrap();
+        assert_eq!("orders/empty", entry.path);
+        assert_eq!(0, entry.size);
+
+        // Not read - skipped by the next call.
+        let entry = reader.next_entry().await.unwrap().unwrap();
+        assert_eq!(long_path, entry.path);
+        assert_eq!(600, entry.size);
+
+        assert!(reader.next_entry().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_stream_cut_short_is_an_error() {
+        let mut writer = TarWriter::new(Vec::new());
+        writer.append("orders/a", &[1u8; 1000], 0).await.unwrap();
+        let content = writer.finish().await.unwrap();
+
+        let mut reader = TarReader::new(&content[..BLOCK_SIZE + 100]);
+        reader.next_entry().await.unwrap().unwrap();
+
+        assert!(reader.next_entry().await.is_err());
+    }
+
+    #[test]
+    fn a_size_too_big_for_octal_goes_base_256() {
+        for size in [
+            0,
+            10,
+            MAX_OCTAL_SIZE,
+            MAX_OCTAL_SIZE + 1,
+            20 * 1024 * 1024 * 1024,
+        ] {
+            let mut field = [0u8; 12];
+            write_size(&mut field, size);
+            assert_eq!(size, parse_size(&field).unwrap());
+        }
+    }
+
+    #[test]
+    fn a_corrupted_header_is_refused() {
+        let mut header = encode_header(b"orders/a", 10, 0, TYPE_FILE);
+        assert!(decode_header(&header).is_ok());
+
+        header[0] = b'x';
+        assert!(decode_header(&header).is_err());
+    }
+
+    #[test]
+    fn pax_record_length_counts_its_own_digits() {
+        for len in [1, 5, 90, 95, 100, 1000] {
+            let value = "a".repeat(len);
+            let record = encode_pax_record("path", value.as_str());
+
+            assert_eq!(Some(value), decode_pax_path(record.as_slice()).unwrap());
+        }
+    }
+}
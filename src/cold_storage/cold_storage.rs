@@ -1,15 +1,27 @@
-use std::{path::Path, time::Duration};
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use my_s3::S3Client;
 use parking_lot::Mutex;
 use tokio::io::AsyncReadExt;
 
 use crate::{
+    app::ColdStorageMetrics,
     settings::{S3BucketMode, S3ConnectionSettings},
+    storage_usage::ColdUsage,
     topic_key::TopicKeyRef,
 };
 
+use super::ObjectLister;
+
 /// Read from the file and handed to the request one chunk at a time, so peak memory is a chunk
 /// rather than the object. An archive is hundreds of megabytes; reading one whole was an OOM kill
 /// in a 512 MB container - and an OOM arrives as SIGKILL, so it left no panic and no log line.
@@ -29,6 +41,29 @@ const UPLOAD_TIMEOUT: Duration = Duration::from_secs(600);
 /// `PutObject` replaces the object atomically.
 const UPLOAD_RETRIES: usize = 3;
 
+/// What a ranged read of a cold archive is for - the label it is counted under.
+#[derive(Clone, Copy, Debug)]
+pub enum RangeReadKind {
+    /// The table of contents at the head of the archive, fetched once per archive.
+    Toc,
+    SubPage,
+    /// A whole object read back in chunks to go up again under another key - `MoveTopic`.
+    Copy,
+    /// A whole archive read back in chunks into a bundle.
+    Export,
+}
+
+impl RangeReadKind {
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            Self::Toc => "toc",
+            Self::SubPage => "sub_page",
+            Self::Copy => "copy",
+            Self::Export => "export",
+        }
+    }
+}
+
 /// The cold tier: sealed archives and closed year indexes, uploaded once and read back over ranged
 /// GETs. Nothing here is ever modified in place - S3 objects can only be replaced whole, which is
 /// exactly why only sealed files get here.
@@ -43,16 +78,45 @@ const UPLOAD_RETRIES: usize = 3;
 /// Both spell the same thing; they differ only in where the namespace sits - inside the key, or in
 /// the bucket name. Callers never see the difference: they hand over a topic key and a file name,
 /// and this is the only place that knows which layout is in force.
+///
+/// A namespace of `s3_namespaces` has a connection of its own - endpoint, keys and layout - and
+/// every other one goes through the `s3_conn_string` one. Which of them serves a call is decided
+/// by the namespace alone, so a topic moved between two namespaces is read through one and
+/// written through the other. Without an `s3_conn_string` only the namespaces with a connection of
+/// their own have a cold tier - ask [`Self::covers`] before anything else.
 pub struct ColdStorage {
+    /// The `s3_conn_string` one. `None` when only `s3_namespaces` is set.
+    connection: Option<ColdConnection>,
+    by_namespace: AHashMap<String, ColdConnection>,
+    /// Buckets this process has already created-or-confirmed, with the endpoint they are at - the
+    /// same name at two providers is two buckets. Keyed by the bucket rather than by the
+    /// namespace, so the shared layout naturally ensures once for everything.
+    ensured: Mutex<AHashSet<(String, String)>>,
+    /// Told about every topic file that goes up or is deleted, so the cold bytes of a topic can
+    /// be known without listing the bucket. `None` keeps no account.
+    usage: Option<Arc<ColdUsage>>,
+    /// `None` counts nothing - the tests run without it.
+    metrics: Option<Arc<ColdStorageMetrics>>,
+}
+
+/// One endpoint, one key pair, one layout.
+struct ColdConnection {
     client: S3Client,
+    lister: ObjectLister,
+    endpoint: String,
     bucket_mode: S3BucketMode,
-    /// Bucket names this process has already created-or-confirmed. Keyed by the bucket rather than
-    /// by the namespace, so the shared layout naturally ensures once for everything.
-    ensured: Mutex<AHashSet<String>>,
 }
 
-impl ColdStorage {
-    pub fn new(settings: &S3ConnectionSettings) -> Self {
+/// A topic's file as the cold tier lists it.
+#[derive(Debug, Clone)]
+pub struct ColdFile {
+    pub topic_id: String,
+    pub file_name: String,
+    pub size: u64,
+}
+
+impl ColdConnection {
+    fn new(settings: &S3ConnectionSettings) -> Self {
         // The region argument is `impl Into<S3Region>`: `S3Region` knows the AWS and Hetzner
         // regions by name and keeps anything else as `Other`, so an unfamiliar endpoint still
         // signs correctly.
@@ -68,7 +132,10 @@ impl ColdStorage {
         // `<Error><Code>` saying why. A successful answer is printed as a size, since it is the
         // archive that was just downloaded. The `Authorization` header is never printed.
         let client = if settings.debug {
-            println!("S3 request tracing is ON (Debug in s3_conn_string)");
+            println!(
+                "S3 request tracing is ON for {} (Debug in the connection string)",
+                settings.endpoint
+            );
             client.debug_to_console()
         } else {
             client
@@ -76,12 +143,13 @@ impl ColdStorage {
 
         Self {
             client,
+            lister: ObjectLister::new(settings),
+            endpoint: settings.endpoint.clone(),
             bucket_mode: settings.bucket_mode.clone(),
-            ensured: Mutex::new(AHashSet::new()),
         }
     }
 
-    pub fn get_bucket(&self, namespace: &str) -> String {
+    fn get_bucket(&self, namespace: &str) -> String {
         match &self.bucket_mode {
             S3BucketMode::Shared(bucket) => bucket.clone(),
             S3BucketMode::PerNamespace(prefix) => format!("{}-{}", prefix, namespace),
@@ -105,6 +173,127 @@ impl ColdStorage {
         }
     }
 
+    /// A file that belongs to the namespace rather than to one of its topics. It sits next to the
+    /// topic prefixes and carries no `/` of its own, so it can not be mistaken for a topic's file.
+    fn resolve_namespace_file(&self, namespace: &str, file_name: &str) -> (String, String) {
+        match &self.bucket_mode {
+            S3BucketMode::Shared(bucket) => {
+                (bucket.clone(), format!("{}/{}", namespace, file_name))
+            }
+            S3BucketMode::PerNamespace(prefix) => {
+                (format!("{}-{}", prefix, namespace), file_name.to_string())
+            }
+        }
+    }
+}
+
+impl ColdStorage {
+    pub fn new(settings: &S3ConnectionSettings) -> Self {
+        Self {
+            connection: Some(ColdConnection::new(settings)),
+            by_namespace: AHashMap::new(),
+            ensured: Mutex::new(AHashSet::new()),
+            usage: None,
+            metrics: None,
+        }
+    }
+
+    /// No `s3_conn_string` - only what [`Self::with_namespace`] adds has a cold tier.
+    pub fn without_default() -> Self {
+        Self {
+            connection: None,
+            by_namespace: AHashMap::new(),
+            ensured: Mutex::new(AHashSet::new()),
+            usage: None,
+            metrics: None,
+        }
+    }
+
+    /// Gives `namespace` a connection of its own - see `S3ConnectionSettings::parse_override`.
+    pub fn with_namespace(mut self, namespace: &str, settings: &S3ConnectionSettings) -> Self {
+        self.by_namespace
+            .insert(namespace.to_string(), ColdConnection::new(settings));
+        self
+    }
+
+    pub fn with_usage(mut self, usage: Arc<ColdUsage>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<ColdStorageMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn get_bucket(&self, namespace: &str) -> String {
+        self.get_connection(namespace).get_bucket(namespace)
+    }
+
+    /// The namespaces of `s3_namespaces`.
+    pub fn get_namespaces_with_own_connection(&self) -> Vec<String> {
+        self.by_namespace.keys().cloned().collect()
+    }
+
+    /// `false` for a namespace that stays local: no connection of its own, and no `s3_conn_string`.
+    pub fn covers(&self, namespace: &str) -> bool {
+        self.connection.is_some() || self.by_namespace.contains_key(namespace)
+    }
+
+    /// Whether the `s3_conn_string` connection still has objects of a namespace that has a
+    /// connection of its own now - they are never read through it again. One listing page.
+    pub async fn has_objects_under_default(&self, namespace: &str) -> Result<bool, String> {
+        let Some(connection) = self.connection.as_ref() else {
+            return Ok(false);
+        };
+
+        let Some(own_connection) = self.by_namespace.get(namespace) else {
+            return Ok(false);
+        };
+
+        let (bucket, prefix) = connection.resolve_namespace_file(namespace, "");
+
+        // Only the keys differ - the objects found there are the ones the namespace reads.
+        if connection.endpoint == own_connection.endpoint
+            && (bucket.clone(), prefix.clone())
+                == own_connection.resolve_namespace_file(namespace, "")
+        {
+            return Ok(false);
+        }
+
+        connection
+            .lister
+            .has_any(bucket.as_str(), prefix.as_str())
+            .await
+    }
+
+    fn get_connection(&self, namespace: &str) -> &ColdConnection {
+        match self.by_namespace.get(namespace) {
+            Some(connection) => connection,
+            None => match self.connection.as_ref() {
+                Some(connection) => connection,
+                None => panic!(
+                    "Namespace '{}' has no cold storage - it is not in s3_namespaces and there is no s3_conn_string",
+                    namespace
+                ),
+            },
+        }
+    }
+
+    fn get_client(&self, namespace: &str) -> &S3Client {
+        &self.get_connection(namespace).client
+    }
+
+    fn resolve(&self, topic_key: TopicKeyRef<'_>, file_name: &str) -> (String, String) {
+        self.get_connection(topic_key.namespace)
+            .resolve(topic_key, file_name)
+    }
+
+    fn resolve_namespace_file(&self, namespace: &str, file_name: &str) -> (String, String) {
+        self.get_connection(namespace)
+            .resolve_namespace_file(namespace, file_name)
+    }
+
     /// Creates the bucket unless this process already did. **Best effort - it never fails the
     /// caller.**
     ///
@@ -118,16 +307,22 @@ impl ColdStorage {
     /// deployment. So the operation goes ahead: if the bucket really is unusable, the upload or the
     /// read says so on its own terms, about the file it was actually working on.
     pub async fn ensure_bucket(&self, namespace: &str) {
-        let bucket = self.get_bucket(namespace);
-
-        if self.ensured.lock().contains(bucket.as_str()) {
+        let connection = self.get_connection(namespace);
+        let bucket = connection.get_bucket(namespace);
+        let endpoint = connection.endpoint.as_str();
+
+        if self
+            .ensured
+            .lock()
+            .contains(&(endpoint.to_string(), bucket.clone()))
+        {
             return;
         }
 
         if let Err(err) = validate_bucket_name(bucket.as_str()) {
             // Nothing will work with this name, but that is the operator's to fix, and shouting
             // about it on every upload would bury it.
-            self.report_bucket_problem(bucket, "use", err.as_str(), false);
+            self.report_bucket_problem(endpoint, bucket, "use", err.as_str(), false);
             return;
         }
 
@@ -140,11 +335,15 @@ impl ColdStorage {
         //
         // The per-namespace layout is the opposite case: a bucket genuinely appears at runtime,
         // when a namespace is first written to, so there creating it is the whole point.
-        if matches!(self.bucket_mode, S3BucketMode::Shared(_)) {
-            match self.client.check_if_bucket_exists(bucket.as_str()).await {
+        if matches!(connection.bucket_mode, S3BucketMode::Shared(_)) {
+            match connection
+                .client
+                .check_if_bucket_exists(bucket.as_str())
+                .await
+            {
                 Ok(true) => {
                     println!("Cold storage bucket '{}' is there", bucket);
-                    self.ensured.lock().insert(bucket);
+                    self.ensured.lock().insert((endpoint.to_string(), bucket));
                     return;
                 }
 
@@ -157,6 +356,7 @@ impl ColdStorage {
                 // fail differently.
                 Err(err) => {
                     self.report_bucket_problem(
+                        endpoint,
                         bucket,
                         "check",
                         format!("{:?}", err).as_str(),
@@ -169,14 +369,14 @@ impl ColdStorage {
 
         // `create_bucket_if_not_exists` absorbs `BucketAlreadyOwnedByYou` - the answer to every
         // restart after the first one, and to a bucket created by hand ahead of time.
-        let err = match self
+        let err = match connection
             .client
             .create_bucket_if_not_exists(bucket.as_str())
             .await
         {
             Ok(_) => {
                 println!("Cold storage bucket '{}' is ready", bucket);
-                self.ensured.lock().insert(bucket);
+                self.ensured.lock().insert((endpoint.to_string(), bucket));
                 return;
             }
             Err(err) => err,
@@ -184,10 +384,10 @@ impl ColdStorage {
 
         // `BucketAlreadyExists` wears similar words but means the opposite: the name is held by
         // *another account*. A bucket name is unique across every customer of the provider, so it
-        // points at the name in `s3_conn_string` rather than at anything transient.
+        // points at the name in the connection string rather than at anything transient.
         let message = if err.is_bucket_already_exists() {
             format!(
-                "the name belongs to another account - bucket names are unique across every customer of the provider, so check the one in s3_conn_string ({:?})",
+                "the name belongs to another account - bucket names are unique across every customer of the provider, so check the one in s3_conn_string or s3_namespaces ({:?})",
                 err
             )
         } else {
@@ -197,11 +397,18 @@ impl ColdStorage {
         // A transient failure is worth another go on the next operation; a deterministic one -
         // denied permission, a name that is somebody else's - would only repeat itself, and
         // retrying it on every single upload turns one problem into a flood of requests.
-        self.report_bucket_problem(bucket, "create", message.as_str(), err.is_retryable());
+        self.report_bucket_problem(
+            endpoint,
+            bucket,
+            "create",
+            message.as_str(),
+            err.is_retryable(),
+        );
     }
 
     fn report_bucket_problem(
         &self,
+        endpoint: &str,
         bucket: String,
         what_failed: &str,
         message: &str,
@@ -216,14 +423,14 @@ impl ColdStorage {
         my_logger::LOGGER.write_error(
             "ColdStorage::ensure_bucket",
             format!(
-                "Can not {} the cold storage bucket '{}': {}. {}",
-                what_failed, bucket, message, tail
+                "Can not {} the cold storage bucket '{}' at {}: {}. {}",
+                what_failed, bucket, endpoint, message, tail
             ),
             my_logger::LogEventCtx::new().add("bucket", bucket.as_str()),
         );
 
         if !retry_later {
-            self.ensured.lock().insert(bucket);
+            self.ensured.lock().insert((endpoint.to_string(), bucket));
         }
     }
 
@@ -243,21 +450,74 @@ impl ColdStorage {
 
         let (bucket, key) = self.resolve(topic_key, file_name);
 
-        let content_length = tokio::fs::metadata(path)
-            .await
-            .map_err(|err| format!("Can not size {:?}: {}", path, err))?
-            .len() as usize;
+        let size = self
+            .upload_object(topic_key.namespace, bucket.as_str(), key.as_str(), path)
+            .await?;
+
+        if let Some(usage) = self.usage.as_ref() {
+            if let Err(err) = usage.uploaded(topic_key, file_name, size).await {
+                report_usage_problem(topic_key, file_name, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// [`Self::upload_file`] for a namespace-level file - see `resolve_namespace_file`.
+    pub async fn upload_namespace_file(
+        &self,
+        namespace: &str,
+        file_name: &str,
+        path: &Path,
+    ) -> Result<(), String> {
+        self.ensure_bucket(namespace).await;
+
+        let (bucket, key) = self.resolve_namespace_file(namespace, file_name);
+
+        self.upload_object(namespace, bucket.as_str(), key.as_str(), path)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the size that went up.
+    async fn upload_object(
+        &self,
+        namespace: &str,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+    ) -> Result<u64, String> {
+        let size = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.upload_failed(namespace, ERROR_CLASS_LOCAL, 0);
+                }
+
+                return Err(format!("Can not size {:?}: {}", path, err));
+            }
+        };
+
+        let content_length = size as usize;
 
         let path = path.to_path_buf();
 
-        self.client
+        // The body is made once per attempt, so this is how the retries the client makes are seen.
+        let attempts = AtomicUsize::new(0);
+        let started = Instant::now();
+
+        let result = self
+            .get_client(namespace)
             .upload_streamed_with_retries(
-                bucket.as_str(),
-                key.as_str(),
+                bucket,
+                key,
                 content_length,
                 UPLOAD_TIMEOUT,
                 UPLOAD_RETRIES,
                 || {
+                    attempts.fetch_add(1, Ordering::Relaxed);
+
                     let (sender, receiver) = tokio::sync::mpsc::channel(UPLOAD_CHANNEL_SIZE);
                     let path = path.clone();
 
@@ -282,8 +542,34 @@ impl ColdStorage {
                     receiver
                 },
             )
-            .await
-            .map_err(|err| format!("{:?}", err))
+            .await;
+
+        let retries = attempts.load(Ordering::Relaxed).saturating_sub(1);
+
+        match result {
+            Ok(_) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.uploaded(namespace, size, started.elapsed(), retries);
+                }
+
+                Ok(size)
+            }
+            Err(err) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    let error_class = if err.is_key_not_found() {
+                        ERROR_CLASS_NOT_FOUND
+                    } else if err.is_retryable() {
+                        ERROR_CLASS_TRANSIENT
+                    } else {
+                        ERROR_CLASS_PERMANENT
+                    };
+
+                    metrics.upload_failed(namespace, error_class, retries);
+                }
+
+                Err(format!("{:?}", err))
+            }
+        }
     }
 
     /// `from`/`to` are inclusive byte offsets, as in the HTTP `Range` header.
@@ -293,15 +579,48 @@ impl ColdStorage {
         file_name: &str,
         from: u64,
         to: u64,
+        kind: RangeReadKind,
     ) -> Result<Vec<u8>, String> {
         self.ensure_bucket(topic_key.namespace).await;
 
         let (bucket, key) = self.resolve(topic_key, file_name);
 
-        self.client
+        let started = Instant::now();
+
+        let result = self
+            .get_client(topic_key.namespace)
             .download_file_range(bucket.as_str(), key.as_str(), from, Some(to))
-            .await
-            .map_err(|err| format!("{:?}", err))
+            .await;
+
+        match result {
+            Ok(content) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.range_read(
+                        topic_key.namespace,
+                        kind.as_label(),
+                        content.len(),
+                        started.elapsed(),
+                    );
+                }
+
+                Ok(content)
+            }
+            Err(err) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    let error_class = if err.is_key_not_found() {
+                        ERROR_CLASS_NOT_FOUND
+                    } else if err.is_retryable() {
+                        ERROR_CLASS_TRANSIENT
+                    } else {
+                        ERROR_CLASS_PERMANENT
+                    };
+
+                    metrics.range_read_failed(topic_key.namespace, kind.as_label(), error_class);
+                }
+
+                Err(format!("{:?}", err))
+            }
+        }
     }
 
     pub async fn download(
@@ -314,7 +633,7 @@ impl ColdStorage {
         let (bucket, key) = self.resolve(topic_key, file_name);
 
         match self
-            .client
+            .get_client(topic_key.namespace)
             .download_file(bucket.as_str(), key.as_str())
             .await
         {
@@ -341,7 +660,7 @@ impl ColdStorage {
         let (bucket, key) = self.resolve(topic_key, file_name);
 
         match self
-            .client
+            .get_client(topic_key.namespace)
             .download_file_range(bucket.as_str(), key.as_str(), 0, Some(0))
             .await
         {
@@ -361,19 +680,189 @@ impl ColdStorage {
 
         let (bucket, key) = self.resolve(topic_key, file_name);
 
-        match self.client.delete_file(bucket.as_str(), key.as_str()).await {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                if err.is_key_not_found() {
-                    return Ok(());
+        self.delete_object(topic_key.namespace, bucket.as_str(), key.as_str())
+            .await?;
+
+        if let Some(usage) = self.usage.as_ref() {
+            if let Err(err) = usage.deleted(topic_key, file_name).await {
+                report_usage_problem(topic_key, file_name, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// [`Self::delete`] for a namespace-level file - see `resolve_namespace_file`.
+    pub async fn delete_namespace_file(
+        &self,
+        namespace: &str,
+        file_name: &str,
+    ) -> Result<(), String> {
+        self.ensure_bucket(namespace).await;
+
+        let (bucket, key) = self.resolve_namespace_file(namespace, file_name);
+
+        self.delete_object(namespace, bucket.as_str(), key.as_str())
+            .await
+    }
+
+    /// The size of one object, from a listing of its own key - `None` when it is not there. For
+    /// telling a whole copy from a cut-off one, which `exists` can not.
+    pub async fn get_size(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        file_name: &str,
+    ) -> Result<Option<u64>, String> {
+        let (bucket, key) = self.resolve(topic_key, file_name);
+
+        let objects = self
+            .get_connection(topic_key.namespace)
+            .lister
+            .list(bucket.as_str(), key.as_str())
+            .await?;
+
+        // A prefix match is not the key: `1.archive` lists `1.archive.bak` too.
+        Ok(objects
+            .into_iter()
+            .find(|itm| itm.key == key)
+            .map(|itm| itm.size))
+    }
+
+    /// A topic's files and their sizes, in one request per thousand of them.
+    pub async fn list_topic_files(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+    ) -> Result<BTreeMap<String, u64>, String> {
+        let (bucket, prefix) = self.resolve(topic_key, "");
+
+        let objects = self
+            .get_connection(topic_key.namespace)
+            .lister
+            .list(bucket.as_str(), prefix.as_str())
+            .await?;
+
+        Ok(objects
+            .into_iter()
+            .filter_map(|itm| {
+                let file_name = itm.key.strip_prefix(prefix.as_str())?;
+
+                // Nothing of ours sits deeper, but a prefix match is not a folder.
+                if file_name.is_empty() || file_name.contains('/') {
+                    return None;
                 }
 
-                Err(format!("{:?}", err))
+                Some((file_name.to_string(), itm.size))
+            })
+            .collect())
+    }
+
+    /// Every topic file of a namespace - the namespace's own files, which sit next to the topic
+    /// prefixes, are left out.
+    pub async fn list_namespace_files(&self, namespace: &str) -> Result<Vec<ColdFile>, String> {
+        let (bucket, prefix) = self.resolve_namespace_file(namespace, "");
+
+        let objects = self
+            .get_connection(namespace)
+            .lister
+            .list(bucket.as_str(), prefix.as_str())
+            .await?;
+
+        Ok(objects
+            .into_iter()
+            .filter_map(|itm| {
+                let (topic_id, file_name) =
+                    itm.key.strip_prefix(prefix.as_str())?.split_once('/')?;
+
+                if topic_id.is_empty() || file_name.is_empty() || file_name.contains('/') {
+                    return None;
+                }
+
+                Some(ColdFile {
+                    topic_id: topic_id.to_string(),
+                    file_name: file_name.to_string(),
+                    size: itm.size,
+                })
+            })
+            .collect())
+    }
+
+    /// The namespace-level files whose name starts with `file_prefix`, and their sizes - see
+    /// `resolve_namespace_file`.
+    pub async fn list_namespace_level_files(
+        &self,
+        namespace: &str,
+        file_prefix: &str,
+    ) -> Result<BTreeMap<String, u64>, String> {
+        let (bucket, prefix) = self.resolve_namespace_file(namespace, file_prefix);
+
+        let objects = self
+            .get_connection(namespace)
+            .lister
+            .list(bucket.as_str(), prefix.as_str())
+            .await?;
+
+        // What the prefix of the file name was put on, to get the names back.
+        let key_prefix = &prefix[..prefix.len() - file_prefix.len()];
+
+        Ok(objects
+            .into_iter()
+            .filter_map(|itm| {
+                let file_name = itm.key.strip_prefix(key_prefix)?;
+
+                if file_name.contains('/') {
+                    return None;
+                }
+
+                Some((file_name.to_string(), itm.size))
+            })
+            .collect())
+    }
+
+    /// A 404 is a success: the object is gone, which is what was asked.
+    async fn delete_object(&self, namespace: &str, bucket: &str, key: &str) -> Result<(), String> {
+        if let Err(err) = self.get_client(namespace).delete_file(bucket, key).await {
+            if !err.is_key_not_found() {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    let error_class = if err.is_retryable() {
+                        ERROR_CLASS_TRANSIENT
+                    } else {
+                        ERROR_CLASS_PERMANENT
+                    };
+
+                    metrics.delete_failed(namespace, error_class);
+                }
+
+                return Err(format!("{:?}", err));
             }
         }
+
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.deleted(namespace);
+        }
+
+        Ok(())
     }
 }
 
+/// How a failure is counted - see [`ColdStorageMetrics`].
+const ERROR_CLASS_LOCAL: &str = "local";
+const ERROR_CLASS_NOT_FOUND: &str = "not_found";
+const ERROR_CLASS_TRANSIENT: &str = "transient";
+const ERROR_CLASS_PERMANENT: &str = "permanent";
+
+/// The object is where it should be either way - only the accounting is behind, and it is not
+/// worth failing an upload or a delete over.
+fn report_usage_problem(topic_key: TopicKeyRef<'_>, file_name: &str, err: String) {
+    my_logger::LOGGER.write_error(
+        "ColdStorage::usage",
+        format!(
+            "Can not record {}/{} in the cold usage: {}",
+            topic_key, file_name, err
+        ),
+        my_logger::LogEventCtx::new().add("topic", topic_key.to_string()),
+    );
+}
+
 /// S3 bucket naming, the subset we can produce: 3-63 chars, lowercase letters, digits and hyphens,
 /// starting and ending on a letter or a digit.
 ///
@@ -478,7 +967,7 @@ mod tests {
 
         // Inclusive offsets, the way the archive TOC and a sub page are fetched
         let chunk = cold_storage
-            .download_range(orders("default"), file_name, 10, 19)
+            .download_range(orders("default"), file_name, 10, 19, RangeReadKind::SubPage)
             .await
             .unwrap();
         assert_eq!(content[10..=19].to_vec(), chunk);
@@ -514,6 +1003,45 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    /// Topic files are accounted, namespace files are not - a snapshot revision is not a topic's.
+    #[tokio::test]
+    async fn uploads_and_deletes_are_accounted() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-upload-usage");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("default")).unwrap();
+
+        let usage = Arc::new(ColdUsage::load(root.to_str().unwrap().to_string()).await);
+
+        let (_fake, cold_storage) = connect().await;
+        let cold_storage = cold_storage.with_usage(usage.clone());
+
+        let path = temp_file("usage", &[0u8; 42]);
+
+        cold_storage
+            .upload_file(orders("default"), "0000000000000000000.archive", &path)
+            .await
+            .unwrap();
+        cold_storage
+            .upload_namespace_file("default", "topics-and-queue.yaml", &path)
+            .await
+            .unwrap();
+
+        let all = usage.get_all();
+        assert_eq!(1, all.len());
+        assert_eq!("default/orders", all[0].0.to_string());
+        assert_eq!(42, all[0].1);
+
+        cold_storage
+            .delete(orders("default"), "0000000000000000000.archive")
+            .await
+            .unwrap();
+        assert!(usage.get_all().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     /// The reason the streaming path exists: an object many chunks long has to arrive byte for
     /// byte, without the sender ever holding it whole.
     #[tokio::test]
@@ -616,6 +1144,54 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    /// A namespace-level file sits beside the topic prefixes in either layout, and lists back
+    /// under its own name.
+    #[tokio::test]
+    async fn a_namespace_file_has_no_topic_segment() {
+        let path = temp_file("namespace_file", &[1, 2, 3]);
+        let file_name = ".topics-and-queue.00000000000000000001.yaml";
+
+        let (fake, cold_storage) = connect_with(S3BucketMode::Shared("sb-data".to_string())).await;
+
+        cold_storage
+            .upload_namespace_file("alpha", file_name, path.as_path())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![format!("/sb-data/alpha/{}", file_name)],
+            fake.object_paths()
+        );
+
+        let listed = cold_storage
+            .list_namespace_level_files("alpha", ".topics-and-queue.")
+            .await
+            .unwrap();
+        assert_eq!(Some(&3), listed.get(file_name));
+        assert_eq!(1, listed.len());
+
+        let (fake, cold_storage) = connect().await;
+
+        cold_storage
+            .upload_namespace_file("alpha", file_name, path.as_path())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![format!("/sb-alpha/{}", file_name)],
+            fake.object_paths()
+        );
+
+        let listed = cold_storage
+            .list_namespace_level_files("alpha", ".topics-and-queue.")
+            .await
+            .unwrap();
+        assert_eq!(Some(&3), listed.get(file_name));
+        assert_eq!(1, listed.len());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     /// The bucket already exists because a previous run created it - or because an operator did,
     /// by hand, before the first start. A fresh process gets `BucketAlreadyOwnedByYou` on its very
     /// first call, which has to read as success.
@@ -794,6 +1370,174 @@ mod tests {
         assert!(fake.requests().iter().any(|itm| itm == "PUT /sb-alpha"));
     }
 
+    /// Over several pages, and in both layouts: the keys come back as topic files, the
+    /// namespace's own files and other namespaces left out.
+    #[tokio::test]
+    async fn lists_the_files_of_a_topic_and_of_a_namespace() {
+        for bucket_mode in [
+            S3BucketMode::PerNamespace("sb".to_string()),
+            S3BucketMode::Shared("sb-data".to_string()),
+        ] {
+            let (fake, cold_storage) = connect_with(bucket_mode.clone()).await;
+            fake.set_list_page_size(2);
+
+            let object_path = |namespace: &str, key: &str| match &bucket_mode {
+                S3BucketMode::PerNamespace(prefix) => format!("/{}-{}/{}", prefix, namespace, key),
+                S3BucketMode::Shared(bucket) => format!("/{}/{}/{}", bucket, namespace, key),
+            };
+
+            fake.create_bucket(cold_storage.get_bucket("default").as_str());
+            fake.create_bucket(cold_storage.get_bucket("alpha").as_str());
+
+            for (key, size) in [
+                ("orders/0000000000000000000.archive", 3),
+                ("orders/0000000000000000001.archive", 4),
+                ("orders/2024.yearindex", 5),
+                ("payments/0000000000000000000.archive", 6),
+                ("cold-usage.yaml", 7),
+            ] {
+                fake.put_object(object_path("default", key).as_str(), vec![0u8; size]);
+            }
+
+            fake.put_object(
+                object_path("alpha", "orders/0000000000000000000.archive").as_str(),
+                vec![0u8; 8],
+            );
+
+            let topic_files = cold_storage
+                .list_topic_files(orders("default"))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                vec![
+                    ("0000000000000000000.archive".to_string(), 3),
+                    ("0000000000000000001.archive".to_string(), 4),
+                    ("2024.yearindex".to_string(), 5),
+                ],
+                topic_files.into_iter().collect::<Vec<_>>()
+            );
+
+            let mut namespace_files: Vec<(String, String, u64)> = cold_storage
+                .list_namespace_files("default")
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|itm| (itm.topic_id, itm.file_name, itm.size))
+                .collect();
+            namespace_files.sort();
+
+            assert_eq!(4, namespace_files.len());
+            assert_eq!(
+                (
+                    "payments".to_string(),
+                    "0000000000000000000.archive".to_string(),
+                    6
+                ),
+                namespace_files[3]
+            );
+
+            assert!(cold_storage
+                .list_topic_files(orders("missing"))
+                .await
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn gets_the_size_of_exactly_the_key_asked_for() {
+        let (fake, cold_storage) = connect_with(S3BucketMode::PerNamespace("sb".to_string())).await;
+
+        fake.create_bucket("sb-default");
+        fake.put_object(
+            "/sb-default/orders/0000000000000000001.archive",
+            vec![0u8; 4],
+        );
+        fake.put_object(
+            "/sb-default/orders/0000000000000000001.archive.bak",
+            vec![0u8; 9],
+        );
+
+        assert_eq!(
+            Some(4),
+            cold_storage
+                .get_size(orders("default"), "0000000000000000001.archive")
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            None,
+            cold_storage
+                .get_size(orders("default"), "0000000000000000002.archive")
+                .await
+                .unwrap()
+        );
+    }
+
+    /// A namespace of `s3_namespaces` goes to its own endpoint, in its own layout; the rest stay on
+    /// the shared connection.
+    #[tokio::test]
+    async fn a_namespace_with_its_own_connection_goes_elsewhere() {
+        let (shared, cold_storage) =
+            connect_with(S3BucketMode::Shared("sb-data".to_string())).await;
+
+        let residency = FakeS3::start().await;
+
+        let cold_storage = cold_storage.with_namespace(
+            "tenant",
+            &S3ConnectionSettings {
+                endpoint: residency.endpoint.clone(),
+                region: "eu-north-1".to_string(),
+                access_key: "AKIATENANT".to_string(),
+                secret_key: "tenant-secret".to_string(),
+                bucket_mode: S3BucketMode::PerNamespace("eu".to_string()),
+                debug: false,
+            },
+        );
+
+        let path = temp_file("own_connection", &[1, 2, 3]);
+        let file_name = "0000000000000000000.archive";
+
+        cold_storage
+            .upload_file(orders("tenant"), file_name, path.as_path())
+            .await
+            .unwrap();
+        cold_storage
+            .upload_file(orders("default"), file_name, path.as_path())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![format!("/eu-tenant/orders/{}", file_name)],
+            residency.object_paths()
+        );
+        assert_eq!(
+            vec![format!("/sb-data/default/orders/{}", file_name)],
+            shared.object_paths()
+        );
+
+        assert_eq!("eu-tenant", cold_storage.get_bucket("tenant"));
+        assert_eq!(
+            vec![(file_name.to_string(), 3)],
+            cold_storage
+                .list_topic_files(orders("tenant"))
+                .await
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        cold_storage
+            .delete(orders("tenant"), file_name)
+            .await
+            .unwrap();
+        assert!(residency.object_paths().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     /// A namespace may end with a hyphen; a bucket may not.
     #[test]
     fn an_unusable_bucket_name_is_refused_before_it_is_created() {
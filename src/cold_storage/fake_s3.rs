@@ -25,6 +25,9 @@ pub struct FakeS3State {
     /// Bucket -> how many more creation attempts answer 503. Lets a test drive the difference
     /// between a transient failure and a deterministic one.
     pub flaky_buckets: HashMap<String, usize>,
+    /// Keys per `ListObjectsV2` page, below the `max-keys` asked for - a small one makes a test
+    /// walk the continuation tokens.
+    pub list_page_size: Option<usize>,
     pub requests: Vec<String>,
 }
 
@@ -90,6 +93,20 @@ impl FakeS3 {
             .insert(format!("/{}", bucket), times);
     }
 
+    pub fn set_list_page_size(&self, page_size: usize) {
+        self.state.lock().unwrap().list_page_size = Some(page_size);
+    }
+
+    /// Puts an object there behind the client's back - one nobody uploaded, as far as the
+    /// service knows.
+    pub fn put_object(&self, path: &str, content: Vec<u8>) {
+        self.state
+            .lock()
+            .unwrap()
+            .objects
+            .insert(path.to_string(), content);
+    }
+
     pub fn get_object(&self, path: &str) -> Option<Vec<u8>> {
         self.state.lock().unwrap().objects.get(path).cloned()
     }
@@ -237,6 +254,9 @@ async fn handle(
                 ok_response(404, "Not Found", Vec::new(), None)
             }
         }
+        "GET" if path.contains("?list-type=2") || path.contains("&list-type=2") => {
+            list_objects(path.as_str(), &state.lock().unwrap())
+        }
         "GET" => {
             let object = state.lock().unwrap().objects.get(&path).cloned();
 
@@ -289,6 +309,115 @@ fn bucket_already_owned_by_you() -> Vec<u8> {
     ok_response(409, "Conflict", body, None)
 }
 
+/// `ListObjectsV2` on a path-style bucket. The continuation token is simply the last key of the
+/// page - opaque to the client, as a real one is.
+fn list_objects(path: &str, state: &FakeS3State) -> Vec<u8> {
+    let (bucket_path, query) = path.split_once('?').unwrap_or((path, ""));
+    let bucket_path = bucket_path.trim_end_matches('/');
+
+    if !state.buckets.iter().any(|itm| itm == bucket_path) {
+        let body = b"<Error><Code>NoSuchBucket</Code><Message>The specified bucket does not exist.</Message></Error>".to_vec();
+        return ok_response(404, "Not Found", body, None);
+    }
+
+    let mut prefix = String::new();
+    let mut continuation_token = None;
+    let mut max_keys = 1000;
+
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+
+        match name {
+            "prefix" => prefix = value,
+            "continuation-token" => continuation_token = Some(value),
+            "max-keys" => max_keys = value.parse().unwrap_or(max_keys),
+            _ => {}
+        }
+    }
+
+    let page_size = state.list_page_size.unwrap_or(max_keys).min(max_keys);
+
+    let object_prefix = format!("{}/", bucket_path);
+
+    let mut keys: Vec<(String, usize)> = state
+        .objects
+        .iter()
+        .filter_map(|(path, content)| {
+            let key = path.strip_prefix(object_prefix.as_str())?;
+            Some((key.to_string(), content.len()))
+        })
+        .filter(|(key, _)| key.starts_with(prefix.as_str()))
+        .filter(|(key, _)| match continuation_token.as_ref() {
+            Some(token) => key.as_str() > token.as_str(),
+            None => true,
+        })
+        .collect();
+
+    keys.sort();
+
+    let is_truncated = keys.len() > page_size;
+    keys.truncate(page_size);
+
+    let mut body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult>");
+    body.push_str(format!("<MaxKeys>{}</MaxKeys>", max_keys).as_str());
+    body.push_str(format!("<IsTruncated>{}</IsTruncated>", is_truncated).as_str());
+
+    for (key, size) in keys.iter() {
+        body.push_str(
+            format!(
+                "<Contents><Key>{}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified><ETag>\"0\"</ETag><Size>{}</Size></Contents>",
+                xml_escape(key),
+                size
+            )
+            .as_str(),
+        );
+    }
+
+    if is_truncated {
+        if let Some((last, _)) = keys.last() {
+            body.push_str(
+                format!(
+                    "<NextContinuationToken>{}</NextContinuationToken>",
+                    xml_escape(last)
+                )
+                .as_str(),
+            );
+        }
+    }
+
+    body.push_str("</ListBucketResult>");
+
+    ok_response(200, "OK", body.into_bytes(), None)
+}
+
+fn percent_decode(src: &str) -> String {
+    let bytes = src.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            if let Ok(value) = u8::from_str_radix(&src[index + 1..index + 3], 16) {
+                result.push(value);
+                index += 3;
+                continue;
+            }
+        }
+
+        result.push(bytes[index]);
+        index += 1;
+    }
+
+    String::from_utf8_lossy(result.as_slice()).to_string()
+}
+
+fn xml_escape(src: &str) -> String {
+    src.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 fn find_header_end(buffer: &[u8]) -> Option<usize> {
     buffer
         .windows(4)
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use rusty_s3::{actions::ListObjectsV2, Bucket, Credentials, S3Action, UrlStyle};
+
+use my_logger::LogEventCtx;
+
+use crate::settings::S3ConnectionSettings;
+
+/// What S3 answers with unless asked for less; a page is one request.
+const MAX_KEYS: usize = 1000;
+
+/// A page is requested right after it is signed; the margin is for a clock a little off.
+const SIGNATURE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedObject {
+    pub key: String,
+    pub size: u64,
+}
+
+/// `ListObjectsV2`, which `my-s3` does not have. `rusty-s3` signs the request and parses the
+/// answer - it does no I/O of its own, so the signed URL is fetched here. Path-style, the way
+/// `my-s3` addresses a bucket, so it works against whatever endpoint the rest does.
+///
+/// An endpoint that is not a URL is refused when the settings are read; should one get here
+/// anyway, every listing fails with the reason rather than the process going down mid-request.
+pub struct ObjectLister {
+    http_client: reqwest::Client,
+    endpoint: Result<url::Url, String>,
+    region: String,
+    credentials: Credentials,
+}
+
+impl ObjectLister {
+    pub fn new(settings: &S3ConnectionSettings) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            endpoint: parse_endpoint(settings.endpoint.as_str()),
+            region: settings.region.clone(),
+            credentials: Credentials::new(settings.access_key.clone(), settings.secret_key.clone()),
+        }
+    }
+
+    /// Every object of `bucket` whose key starts with `prefix`, page after page. A bucket that is
+    /// not there lists as empty.
+    pub async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<ListedObject>, String> {
+        let bucket = self.address(bucket)?;
+
+        let mut result = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let page = self
+                .list_page(&bucket, prefix, continuation_token.as_deref())
+                .await?;
+
+            let Some(page) = page else {
+                return Ok(result);
+            };
+
+            result.extend(page.contents.into_iter().map(|itm| ListedObject {
+                key: itm.key,
+                size: itm.size,
+            }));
+
+            // Only a truncated page carries a token.
+            match page.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => return Ok(result),
+            }
+        }
+    }
+
+    /// Whether anything at all starts with `prefix` - the first page is enough to say.
+    pub async fn has_any(&self, bucket: &str, prefix: &str) -> Result<bool, String> {
+        let bucket = self.address(bucket)?;
+
+        let page = self.list_page(&bucket, prefix, None).await?;
+
+        Ok(page.map(|page| !page.contents.is_empty()).unwrap_or(false))
+    }
+
+    fn address(&self, bucket: &str) -> Result<Bucket, String> {
+        let endpoint = self.endpoint.clone()?;
+
+        Bucket::new(
+            endpoint,
+            UrlStyle::Path,
+            bucket.to_string(),
+            self.region.clone(),
+        )
+        .map_err(|err| format!("Can not address bucket {}: {}", bucket, err))
+    }
+
+    async fn list_page(
+        &self,
+        bucket: &Bucket,
+        prefix: &str,
+        continuation_token: Option<&str>,
+    ) -> Result<Option<rusty_s3::actions::ListObjectsV2Response>, String> {
+        let mut action = bucket.list_objects_v2(Some(&self.credentials));
+
+        action.with_prefix(prefix);
+        action.with_max_keys(MAX_KEYS);
+
+        if let Some(continuation_token) = continuation_token {
+            action.with_continuation_token(continuation_token);
+        }
+
+        let url = action.sign(SIGNATURE_TTL);
+
+        let response = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .map_err(|err| format!("{:?}", err))?;
+
+        let status = response.status().as_u16();
+
+        let body = response.text().await.map_err(|err| format!("{:?}", err))?;
+
+        if status == 404 && body.contains("<Code>NoSuchBucket</Code>") {
+            return Ok(None);
+        }
+
+        if status != 200 {
+            // The body is the `<Error><Code>` that says why - the caller only sees it as a failure.
+            my_logger::LOGGER.write_error(
+                "ListObjectsV2",
+                format!("Listing failed with status {}. {}", status, body),
+                LogEventCtx::new()
+                    .add("bucket", bucket.name().to_string())
+                    .add("prefix", prefix.to_string()),
+            );
+
+            return Err(format!("Status Code: {}. {}", status, body));
+        }
+
+        ListObjectsV2::parse_response(body.as_str())
+            .map(Some)
+            .map_err(|err| format!("Can not parse the listing: {}", err))
+    }
+}
+
+/// The bucket is joined onto the endpoint, and a join replaces the last path segment unless it
+/// ends with a slash - an endpoint behind a path prefix would lose the prefix, and with it the
+/// path the request is signed for.
+pub fn parse_endpoint(endpoint: &str) -> Result<url::Url, String> {
+    let endpoint = if endpoint.ends_with('/') {
+        endpoint.to_string()
+    } else {
+        format!("{}/", endpoint)
+    };
+
+    url::Url::parse(endpoint.as_str())
+        .map_err(|err| format!("Invalid S3 endpoint {}: {}", endpoint, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_s3::{actions::ListObjectsV2, Bucket, UrlStyle};
+
+    use super::parse_endpoint;
+
+    #[test]
+    fn reads_keys_sizes_and_the_continuation() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+  <Name>sb-default</Name>
+  <MaxKeys>1000</MaxKeys>
+  <IsTruncated>true</IsTruncated>
+  <Contents><Key>orders/0.archive</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified><ETag>"1"</ETag><Size>1024</Size></Contents>
+  <Contents><Key>a&amp;b/&lt;Key&gt;.yearindex</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified><ETag>"2"</ETag><Size>4216320</Size></Contents>
+  <NextContinuationToken>token&amp;1</NextContinuationToken>
+</ListBucketResult>"#;
+
+        let page = ListObjectsV2::parse_response(body).unwrap();
+
+        let objects: Vec<_> = page
+            .contents
+            .iter()
+            .map(|itm| (itm.key.as_str(), itm.size))
+            .collect();
+
+        assert_eq!(
+            vec![("orders/0.archive", 1024), ("a&b/<Key>.yearindex", 4216320)],
+            objects
+        );
+        assert_eq!(Some("token&1"), page.next_continuation_token.as_deref());
+    }
+
+    #[test]
+    fn a_path_prefix_of_the_endpoint_is_kept() {
+        let bucket = Bucket::new(
+            parse_endpoint("http://127.0.0.1:9000/storage").unwrap(),
+            UrlStyle::Path,
+            "sb-default".to_string(),
+            "us-east-1".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            "http://127.0.0.1:9000/storage/sb-default/",
+            bucket.base_url().as_str()
+        );
+    }
+
+    #[test]
+    fn an_endpoint_that_is_not_a_url_is_an_error() {
+        assert!(parse_endpoint("s3.example.com").is_err());
+    }
+
+    #[test]
+    fn an_endpoint_without_a_prefix_addresses_the_bucket_at_the_root() {
+        let bucket = Bucket::new(
+            parse_endpoint("https://s3.example.com/").unwrap(),
+            UrlStyle::Path,
+            "sb-default".to_string(),
+            "us-east-1".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            "https://s3.example.com/sb-default/",
+            bucket.base_url().as_str()
+        );
+    }
+}
@@ -1,4 +1,6 @@
 mod cold_storage;
 pub use cold_storage::*;
+mod list_objects;
+pub use list_objects::*;
 #[cfg(test)]
 pub mod fake_s3;
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use my_service_bus::abstractions::MessageId;
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{topic_key::TopicKey, topics_snapshot::QueueSnapshotProtobufModel};
+
+/// How far one queue is behind, as of the last snapshot the bus node saved.
+#[derive(Clone)]
+pub struct QueueLag {
+    pub topic_key: TopicKey,
+    pub queue_id: String,
+    /// Messages in the queue's ranges - not delivered yet, or delivered and not confirmed.
+    pub unconsumed: i64,
+    pub oldest_unconsumed: Option<MessageId>,
+    /// When the oldest unconsumed message was published. `None` when there is no such message,
+    /// or when its topic is not loaded and no year index on the local disk has it. For a topic
+    /// that is not loaded it comes from the year index, so it is the minute, not the moment.
+    pub oldest_unconsumed_created: Option<DateTimeAsMicroseconds>,
+}
+
+impl QueueLag {
+    /// `0` for a queue with nothing to consume; `None` when the moment is not known.
+    pub fn get_age_sec(&self, now: DateTimeAsMicroseconds) -> Option<i64> {
+        if self.oldest_unconsumed.is_none() {
+            return Some(0);
+        }
+
+        let created = self.oldest_unconsumed_created?;
+
+        Some((now.unix_microseconds - created.unix_microseconds).max(0) / 1_000_000)
+    }
+}
+
+/// The number of unconsumed messages and the oldest of them. A range of the bus node is
+/// `[from, to]` inclusive and an empty one has `from` past `to`.
+pub fn get_queue_backlog(queue: &QueueSnapshotProtobufModel) -> (i64, Option<MessageId>) {
+    let mut unconsumed = 0;
+    let mut oldest: Option<i64> = None;
+
+    for range in queue.ranges.iter() {
+        let from_id = range.get_from_id().get_value();
+        let to_id = range.get_to_id().get_value();
+
+        if to_id < from_id {
+            continue;
+        }
+
+        unconsumed += to_id - from_id + 1;
+
+        oldest = Some(match oldest {
+            Some(oldest) => oldest.min(from_id),
+            None => from_id,
+        });
+    }
+
+    (unconsumed, oldest.map(|itm| itm.into()))
+}
+
+/// The last computed lag of every queue, for `/api/Lag`, and the publish moments already looked
+/// up. The oldest message of a stuck queue stays the same for hours, so it is read once.
+pub struct ConsumerLag {
+    queues: Mutex<Vec<QueueLag>>,
+    /// `(topic, queue)` -> the oldest unconsumed message and when it was published - `None` if
+    /// that could not be found, which is not looked for again either.
+    created: Mutex<HashMap<(TopicKey, String), (i64, Option<DateTimeAsMicroseconds>)>>,
+}
+
+impl ConsumerLag {
+    pub fn new() -> Self {
+        Self {
+            queues: Mutex::new(Vec::new()),
+            created: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self) -> Vec<QueueLag> {
+        self.queues.lock().clone()
+    }
+
+    /// Replaces the view and forgets the publish moments of queues that are gone.
+    pub fn set(&self, queues: Vec<QueueLag>) {
+        self.created.lock().retain(|(topic_key, queue_id), _| {
+            queues
+                .iter()
+                .any(|itm| &itm.topic_key == topic_key && &itm.queue_id == queue_id)
+        });
+
+        *self.queues.lock() = queues;
+    }
+
+    /// `None` when it was not looked up yet.
+    pub fn get_created(
+        &self,
+        topic_key: &TopicKey,
+        queue_id: &str,
+        message_id: MessageId,
+    ) -> Option<Option<DateTimeAsMicroseconds>> {
+        let created = self.created.lock();
+        let (cached_id, created) = created.get(&(topic_key.clone(), queue_id.to_string()))?;
+
+        if *cached_id != message_id.get_value() {
+            return None;
+        }
+
+        Some(*created)
+    }
+
+    pub fn set_created(
+        &self,
+        topic_key: TopicKey,
+        queue_id: String,
+        message_id: MessageId,
+        created: Option<DateTimeAsMicroseconds>,
+    ) {
+        self.created
+            .lock()
+            .insert((topic_key, queue_id), (message_id.get_value(), created));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topics_snapshot::{QueueRangeProtobufModel, QueueSnapshotProtobufModel};
+
+    use super::get_queue_backlog;
+
+    fn queue(ranges: &[(i64, i64)]) -> QueueSnapshotProtobufModel {
+        QueueSnapshotProtobufModel {
+            queue_id: "q".to_string(),
+            ranges: ranges
+                .iter()
+                .map(|(from_id, to_id)| QueueRangeProtobufModel::new(*from_id, *to_id))
+                .collect(),
+            queue_type: 0,
+        }
+    }
+
+    #[test]
+    fn the_backlog_is_the_sum_of_the_ranges() {
+        let (unconsumed, oldest) = get_queue_backlog(&queue(&[(20, 29), (5, 5)]));
+        assert_eq!(11, unconsumed);
+        assert_eq!(Some(5), oldest.map(|itm| itm.get_value()));
+
+        // The bus node's empty range
+        let (unconsumed, oldest) = get_queue_backlog(&queue(&[(100, 99)]));
+        assert_eq!(0, unconsumed);
+        assert!(oldest.is_none());
+
+        assert_eq!(0, get_queue_backlog(&queue(&[])).0);
+    }
+}
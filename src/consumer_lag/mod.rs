@@ -0,0 +1,2 @@
+mod consumer_lag;
+pub use consumer_lag::*;
@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+
+use crate::settings::DiskWatchdogSettingsModel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskSpaceLevel {
+    Ok,
+    /// Below the soft threshold - room is being made.
+    Low,
+    /// Below the hard threshold - new messages are refused.
+    Critical,
+}
+
+impl DiskSpaceLevel {
+    pub fn get(free_bytes: u64, total_bytes: u64, settings: &DiskWatchdogSettingsModel) -> Self {
+        if total_bytes == 0 {
+            return Self::Ok;
+        }
+
+        let free_percent = free_bytes as f64 * 100.0 / total_bytes as f64;
+
+        if free_percent < settings.hard_min_free_percent {
+            return Self::Critical;
+        }
+
+        if free_percent < settings.soft_min_free_percent {
+            return Self::Low;
+        }
+
+        Self::Ok
+    }
+
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Low => "low",
+            Self::Critical => "critical",
+        }
+    }
+
+    pub fn as_value(&self) -> u8 {
+        match self {
+            Self::Ok => 0,
+            Self::Low => 1,
+            Self::Critical => 2,
+        }
+    }
+
+    fn from_value(value: u8) -> Self {
+        match value {
+            1 => Self::Low,
+            2 => Self::Critical,
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// Free space of the data volume as of the watchdog's last look. Read on every `SaveMessages`
+/// batch, so it is atomics rather than a lock.
+pub struct DiskSpace {
+    free_bytes: AtomicU64,
+    total_bytes: AtomicU64,
+    level: AtomicU8,
+    /// An offload is running - the watchdog does not start a second one over it.
+    offloading: AtomicBool,
+}
+
+impl DiskSpace {
+    pub fn new() -> Self {
+        Self {
+            free_bytes: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
+            level: AtomicU8::new(DiskSpaceLevel::Ok.as_value()),
+            offloading: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the level it replaced.
+    pub fn set(&self, free_bytes: u64, total_bytes: u64, level: DiskSpaceLevel) -> DiskSpaceLevel {
+        self.free_bytes.store(free_bytes, Ordering::Relaxed);
+        self.total_bytes.store(total_bytes, Ordering::Relaxed);
+        DiskSpaceLevel::from_value(self.level.swap(level.as_value(), Ordering::Relaxed))
+    }
+
+    pub fn get_level(&self) -> DiskSpaceLevel {
+        DiskSpaceLevel::from_value(self.level.load(Ordering::Relaxed))
+    }
+
+    pub fn get_free_bytes(&self) -> u64 {
+        self.free_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn get_total_bytes(&self) -> u64 {
+        self.total_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn is_write_refused(&self) -> bool {
+        self.get_level() == DiskSpaceLevel::Critical
+    }
+
+    /// `false` when one is running already; otherwise the caller owns it until
+    /// [`Self::offload_finished`].
+    pub fn try_start_offload(&self) -> bool {
+        !self.offloading.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn offload_finished(&self) {
+        self.offloading.store(false, Ordering::Release);
+    }
+}
+
+/// `(free, total)` bytes of the volume `data_folder` lives on: the mounted disk with the longest
+/// mount point the folder is under. `None` when no disk matches - a container whose volumes
+/// `sysinfo` does not list - and then the watchdog has nothing to go on.
+pub fn read_disk_space(data_folder: &str) -> Option<(u64, u64)> {
+    let data_folder = std::fs::canonicalize(data_folder).ok()?;
+
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+
+    disks
+        .list()
+        .iter()
+        .filter(|disk| data_folder.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| (disk.available_space(), disk.total_space()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::DiskWatchdogSettingsModel;
+
+    use super::DiskSpaceLevel;
+
+    #[test]
+    fn the_thresholds_are_a_share_of_the_volume() {
+        let settings = DiskWatchdogSettingsModel {
+            soft_min_free_percent: 10.0,
+            hard_min_free_percent: 3.0,
+        };
+
+        assert_eq!(DiskSpaceLevel::Ok, DiskSpaceLevel::get(50, 100, &settings));
+        assert_eq!(DiskSpaceLevel::Ok, DiskSpaceLevel::get(10, 100, &settings));
+        assert_eq!(DiskSpaceLevel::Low, DiskSpaceLevel::get(9, 100, &settings));
+        assert_eq!(
+            DiskSpaceLevel::Critical,
+            DiskSpaceLevel::get(2, 100, &settings)
+        );
+
+        // Nothing known about the volume
+        assert_eq!(DiskSpaceLevel::Ok, DiskSpaceLevel::get(0, 0, &settings));
+
+        // Off unless set
+        let off = DiskWatchdogSettingsModel::default();
+        assert_eq!(DiskSpaceLevel::Ok, DiskSpaceLevel::get(0, 100, &off));
+    }
+}
@@ -0,0 +1,2 @@
+mod disk_space;
+pub use disk_space::*;
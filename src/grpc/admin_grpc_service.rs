@@ -0,0 +1,972 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use my_logger::LogEventCtx;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::operations::{
+    MessagesImportFormat, MessagesImportRequest, OperationError, StorageLocation,
+    TopicCloneRequest, TopicInventory,
+};
+use crate::persistence_admin_grpc::persistence_admin_grpc_service_server::PersistenceAdminGrpcService;
+use crate::persistence_admin_grpc::*;
+use crate::settings::AuthScope;
+use crate::topic_key::{TopicKey, TopicKeyRef};
+
+use my_grpc_extensions::{server::*, StreamedResponseWriter};
+
+use super::{auth_interceptor::GrpcCaller, contracts};
+
+use super::server::PersistenceAdminGrpc;
+
+/// Under the 4 MiB a gRPC message is allowed by default.
+const BUNDLE_CHUNK_SIZE: usize = 1024 * 1024;
+
+#[tonic::async_trait]
+impl PersistenceAdminGrpcService for PersistenceAdminGrpc {
+    generate_server_stream!(stream_name:"ListNamespacesStream", item_name:"NamespaceInfoGrpcModel");
+    async fn list_namespaces(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<Self::ListNamespacesStream>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        caller.check_scope(AuthScope::Read)?;
+
+        // A namespace exists once it has a folder; one the node has just sent topics for may not
+        // have it yet.
+        let mut result: BTreeMap<String, NamespaceInfoGrpcModel> = BTreeMap::new();
+
+        for namespace in
+            crate::topics_snapshot::file_storage::scan_namespaces(self.app.get_data_folder()).await
+        {
+            get_namespace_info(&mut result, namespace.as_str());
+        }
+
+        let snapshot = self.app.topics_snapshot.get().await;
+
+        for topic in snapshot.snapshot.data.iter() {
+            let info = get_namespace_info(&mut result, topic.get_namespace());
+            info.topics_amount += 1;
+            info.queues_amount += topic.queues.len() as i32;
+        }
+
+        for topic_data in self.app.topics_list.get_all().iter() {
+            get_namespace_info(&mut result, topic_data.namespace.as_str()).loaded_topics_amount +=
+                1;
+        }
+
+        // Only for the namespaces found above: one whose folder is gone has nothing to show.
+        for usage in self.app.storage_usage.get_namespaces() {
+            if let Some(info) = result.get_mut(usage.namespace.as_str()) {
+                info.local_bytes = usage.local.get_total() as i64;
+                info.cold_bytes = usage.cold_bytes as i64;
+            }
+        }
+
+        for info in result.values_mut() {
+            info.deleting = self
+                .app
+                .namespace_deletions
+                .is_deleting(info.namespace.as_str());
+        }
+
+        let data = result
+            .into_values()
+            .filter(|itm| caller.can_access_namespace(itm.namespace.as_str()));
+
+        my_grpc_extensions::grpc_server_streams::send_from_iterator(data).await
+    }
+
+    async fn describe_namespace(
+        &self,
+        request: tonic::Request<NamespaceGrpcRequest>,
+    ) -> Result<tonic::Response<NamespaceDetailsGrpcModel>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        let topic_ids =
+            crate::operations::get_namespace_topics(self.app.as_ref(), namespace.as_str()).await;
+
+        let deletion = self.app.namespace_deletions.get(namespace.as_str());
+
+        if topic_ids.is_empty() && deletion.is_none() {
+            let folder = crate::app::storage_layout::get_local_path(
+                self.app.get_data_folder(),
+                namespace.as_str(),
+            );
+
+            if !folder.is_dir() {
+                return Err(tonic::Status::not_found(format!(
+                    "Namespace {} is not found",
+                    namespace.as_str()
+                )));
+            }
+        }
+
+        let snapshot = self.app.topics_snapshot.get().await;
+
+        let mut result = NamespaceDetailsGrpcModel {
+            namespace: namespace.as_str().to_string(),
+            topic_ids: topic_ids.into_iter().collect(),
+            snapshot_topics_amount: 0,
+            queues_amount: 0,
+            local_bytes: 0,
+            cold_bytes: 0,
+            bucket: self
+                .app
+                .get_cold_storage(namespace.as_str())
+                .map(|itm| itm.get_bucket(namespace.as_str())),
+            retentions: Vec::new(),
+            deletion: deletion.map(|itm| NamespaceDeletionGrpcModel {
+                started: itm.started.unix_microseconds,
+                finished: itm.finished.map(|itm| itm.unix_microseconds),
+                topics_total: itm.topics_total as i32,
+                topics_deleted: itm.topics_deleted as i32,
+                current_topic: itm.current_topic,
+                errors: itm.errors as i32,
+            }),
+        };
+
+        for topic in snapshot.snapshot.data.iter() {
+            if topic.get_namespace() == namespace.as_str() {
+                result.snapshot_topics_amount += 1;
+                result.queues_amount += topic.queues.len() as i32;
+            }
+        }
+
+        if let Some(usage) = self
+            .app
+            .storage_usage
+            .get_namespaces()
+            .into_iter()
+            .find(|itm| itm.namespace == namespace.as_str())
+        {
+            result.local_bytes = usage.local.get_total() as i64;
+            result.cold_bytes = usage.cold_bytes as i64;
+        }
+
+        for (topic_key, retention) in self.app.topics_retention.get_all() {
+            if topic_key.namespace == namespace.as_str() {
+                result.retentions.push(NamespaceRetentionGrpcModel {
+                    topic_id: topic_key.topic_id,
+                    max_age_sec: retention.max_age_sec as i64,
+                });
+            }
+        }
+
+        Ok(tonic::Response::new(result))
+    }
+
+    async fn export_namespace(
+        &self,
+        request: tonic::Request<NamespaceGrpcRequest>,
+    ) -> Result<tonic::Response<ExportNamespaceGrpcResponse>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        let export = crate::operations::export_namespace(self.app.as_ref(), namespace.as_str())
+            .await
+            .map_err(to_status)?;
+
+        Ok(tonic::Response::new(ExportNamespaceGrpcResponse {
+            snapshot_yaml: export.snapshot_yaml,
+            retention_yaml: export.retention_yaml,
+        }))
+    }
+
+    async fn delete_namespace(
+        &self,
+        request: tonic::Request<NamespaceGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        crate::operations::delete_namespace(&self.app, namespace.as_str())
+            .await
+            .map_err(to_status)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    generate_server_stream!(stream_name:"ListTopicsStream", item_name:"TopicInfoGrpcModel");
+    async fn list_topics(
+        &self,
+        request: tonic::Request<ListTopicsGrpcRequest>,
+    ) -> Result<tonic::Response<Self::ListTopicsStream>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        // No namespace here means every namespace the token can see, not `default`.
+        let namespace = match req.namespace {
+            Some(namespace) if !namespace.is_empty() => {
+                let namespace = contracts::get_namespace(Some(namespace))?;
+                caller.check(AuthScope::Read, namespace.as_str())?;
+                Some(namespace)
+            }
+            _ => {
+                caller.check_scope(AuthScope::Read)?;
+                None
+            }
+        };
+
+        let snapshot = self.app.topics_snapshot.get().await;
+
+        let mut result = Vec::new();
+
+        for topic in snapshot.snapshot.data.iter() {
+            if let Some(namespace) = namespace.as_ref() {
+                if topic.get_namespace() != namespace.as_str() {
+                    continue;
+                }
+            }
+
+            if !caller.can_access_namespace(topic.get_namespace()) {
+                continue;
+            }
+
+            let mut item = TopicInfoGrpcModel {
+                topic_id: topic.topic_id.clone(),
+                namespace: topic.get_namespace().to_string(),
+                message_id: topic.get_message_id().get_value(),
+                queues_amount: topic.queues.len() as i32,
+                persist: topic.persist,
+                loaded: false,
+                sub_pages_in_memory: 0,
+                messages_to_save: 0,
+                retention_sec: None,
+            };
+
+            let topic_key = topic.get_topic_key();
+
+            if let Some(topic_data) = self.app.topics_list.get(topic_key) {
+                item.loaded = true;
+                item.sub_pages_in_memory = topic_data.pages_list.get_all().await.len() as i32;
+                item.messages_to_save = topic_data
+                    .pages_list
+                    .get_messages_amount_to_save()
+                    .await
+                    .amount as i64;
+            }
+
+            item.retention_sec = get_retention_sec(self.app.as_ref(), topic_key);
+
+            result.push(item);
+        }
+
+        my_grpc_extensions::grpc_server_streams::send_from_iterator(result.into_iter()).await
+    }
+
+    async fn get_topic_details(
+        &self,
+        request: tonic::Request<AdminTopicGrpcRequest>,
+    ) -> Result<tonic::Response<TopicDetailsGrpcModel>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+
+        let inventory = crate::operations::get_topic_inventory(self.app.as_ref(), topic_key)
+            .await
+            .map_err(to_status)?;
+
+        let result = to_topic_details_grpc_model(
+            inventory,
+            topic_key,
+            get_retention_sec(self.app.as_ref(), topic_key),
+        );
+
+        Ok(tonic::Response::new(result))
+    }
+
+    async fn hard_delete_topic(
+        &self,
+        request: tonic::Request<AdminTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+        contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+        contracts::check_namespace_not_deleting(self.app.as_ref(), topic_key.namespace)?;
+
+        crate::operations::hard_delete_topic(&self.app, topic_key);
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn soft_delete_topic(
+        &self,
+        request: tonic::Request<SoftDeleteTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        if req.keep_sec < 0 {
+            return Err(tonic::Status::invalid_argument(
+                "KeepSec can not be negative",
+            ));
+        }
+
+        crate::operations::soft_delete_topic(
+            self.app.as_ref(),
+            TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
+            Duration::from_secs(req.keep_sec as u64),
+        )
+        .await
+        .map_err(to_status)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn restore_topic(
+        &self,
+        request: tonic::Request<AdminTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        crate::operations::restore_topic(
+            self.app.as_ref(),
+            TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
+        )
+        .await
+        .map_err(to_status)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn move_topic(
+        &self,
+        request: tonic::Request<MoveTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let from_namespace = contracts::get_namespace(req.from_namespace)?;
+        contracts::check_topic_id(req.from_topic_id.as_str())?;
+        caller.check(AuthScope::Admin, from_namespace.as_str())?;
+
+        let to_namespace = contracts::get_namespace(req.to_namespace)?;
+        contracts::check_topic_id(req.to_topic_id.as_str())?;
+        caller.check(AuthScope::Admin, to_namespace.as_str())?;
+
+        crate::operations::move_topic(
+            &self.app,
+            TopicKeyRef::new(from_namespace.as_str(), req.from_topic_id.as_str()),
+            TopicKeyRef::new(to_namespace.as_str(), req.to_topic_id.as_str()),
+        )
+        .await
+        .map_err(to_status)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn clone_topic(
+        &self,
+        request: tonic::Request<CloneTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let source_namespace = contracts::get_namespace(req.source_namespace)?;
+        contracts::check_topic_id(req.source_topic_id.as_str())?;
+        caller.check(AuthScope::Read, source_namespace.as_str())?;
+
+        let target_namespace = contracts::get_namespace(req.target_namespace)?;
+        contracts::check_topic_id(req.target_topic_id.as_str())?;
+        caller.check(AuthScope::Admin, target_namespace.as_str())?;
+
+        crate::operations::clone_topic(
+            &self.app,
+            TopicCloneRequest {
+                source: TopicKey::new(source_namespace, req.source_topic_id),
+                target: TopicKey::new(target_namespace, req.target_topic_id),
+                from_id: req.from_message_id.into(),
+                to_id: req.to_message_id.into(),
+            },
+        )
+        .await
+        .map_err(to_status)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    generate_server_stream!(stream_name:"ExportBundleStream", item_name:"BundleChunkGrpcModel");
+    async fn export_bundle(
+        &self,
+        request: tonic::Request<ExportBundleGrpcRequest>,
+    ) -> Result<tonic::Response<Self::ExportBundleStream>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+
+        if let Some(topic_id) = req.topic_id.as_ref() {
+            contracts::check_topic_id(topic_id.as_str())?;
+        }
+
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        // Everything that can be refused is refused here, while there is still a status to
+        // answer with - once the stream is handed back, a failure can only cut it short.
+        let export = crate::operations::prepare_bundle_export(
+            self.app.as_ref(),
+            namespace.as_str(),
+            req.topic_id.as_deref(),
+        )
+        .await
+        .map_err(to_status)?;
+
+        let streamed_response = StreamedResponseWriter::new(16);
+
+        let producer = streamed_response.get_stream_producer();
+
+        let (mut input, output) = tokio::io::duplex(BUNDLE_CHUNK_SIZE);
+
+        let app = self.app.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = crate::operations::write_bundle(app.as_ref(), export, output).await {
+                my_logger::LOGGER.write_error(
+                    "export_bundle",
+                    format!("The bundle is cut short. Err: {:?}", err),
+                    LogEventCtx::new().add("namespace", namespace.to_string()),
+                );
+            }
+        });
+
+        // A client that goes away drops the stream; the writer above then fails on its next write
+        // and stops.
+        tokio::spawn(async move {
+            let mut buffer = vec![0u8; BUNDLE_CHUNK_SIZE];
+
+            loop {
+                let read = match input.read(buffer.as_mut_slice()).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+
+                let chunk = BundleChunkGrpcModel {
+                    content: buffer[..read].to_vec(),
+                };
+
+                if producer.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        streamed_response.get_result()
+    }
+
+    async fn import_bundle(
+        &self,
+        request: tonic::Request<tonic::Streaming<ImportBundleGrpcRequest>>,
+    ) -> Result<tonic::Response<ImportBundleGrpcResponse>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+        contracts::check_disk_space(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let mut stream = request.into_inner();
+
+        let Some(first) = stream.message().await? else {
+            return Err(tonic::Status::invalid_argument("The bundle is empty"));
+        };
+
+        let target_namespace = match first.target_namespace {
+            Some(target_namespace) => Some(contracts::get_namespace(Some(target_namespace))?),
+            None => None,
+        };
+
+        // The bundle is read as it arrives. A client stream that breaks off closes the pipe early,
+        // which the tar reader sees as a bundle cut short - the import is then undone.
+        let (input, mut output) = tokio::io::duplex(BUNDLE_CHUNK_SIZE);
+
+        tokio::spawn(async move {
+            if output.write_all(first.content.as_slice()).await.is_err() {
+                return;
+            }
+
+            while let Ok(Some(message)) = stream.message().await {
+                if output.write_all(message.content.as_slice()).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        let bundle = crate::operations::BundleToImport::open(input)
+            .await
+            .map_err(to_status)?;
+
+        let namespace = match target_namespace {
+            Some(target_namespace) => target_namespace,
+            None => bundle.get_namespace().map_err(to_status)?,
+        };
+
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let topic_ids = crate::operations::import_bundle(self.app.as_ref(), bundle, &namespace)
+            .await
+            .map_err(to_status)?;
+
+        Ok(tonic::Response::new(ImportBundleGrpcResponse {
+            namespace: namespace.to_string(),
+            topic_ids,
+        }))
+    }
+
+    async fn import_messages(
+        &self,
+        request: tonic::Request<tonic::Streaming<ImportMessagesGrpcRequest>>,
+    ) -> Result<tonic::Response<ImportMessagesGrpcResponse>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+        contracts::check_disk_space(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let mut stream = request.into_inner();
+
+        let Some(first) = stream.message().await? else {
+            return Err(tonic::Status::invalid_argument("Nothing to import"));
+        };
+
+        let namespace = contracts::get_namespace(first.namespace)?;
+        contracts::check_topic_id(first.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let format = match MessagesImportFormatGrpcEnum::try_from(first.format) {
+            Ok(MessagesImportFormatGrpcEnum::NdJson) => MessagesImportFormat::NdJson,
+            Ok(MessagesImportFormatGrpcEnum::Binary) => MessagesImportFormat::Binary,
+            Err(_) => {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "Unknown Format {}",
+                    first.format
+                )))
+            }
+        };
+
+        let import_request = MessagesImportRequest {
+            format,
+            renumber_ids: first.renumber_ids,
+            dry_run: first.dry_run,
+        };
+
+        // Fed to the import as it arrives, the same way as `ImportBundle`. Unlike a bundle, an
+        // export file has no end marker: a stream that breaks off looks like a shorter file, so
+        // the pump says whether it saw the end.
+        let (input, mut output) = tokio::io::duplex(BUNDLE_CHUNK_SIZE);
+
+        let pump = tokio::spawn(async move {
+            if output.write_all(first.content.as_slice()).await.is_err() {
+                return false;
+            }
+
+            loop {
+                match stream.message().await {
+                    Ok(Some(message)) => {
+                        if output.write_all(message.content.as_slice()).await.is_err() {
+                            return false;
+                        }
+                    }
+                    Ok(None) => return true,
+                    Err(_) => return false,
+                }
+            }
+        });
+
+        let report = crate::operations::import_messages(
+            self.app.as_ref(),
+            TopicKeyRef::new(namespace.as_str(), first.topic_id.as_str()),
+            import_request,
+            input,
+        )
+        .await
+        .map_err(to_status)?;
+
+        if !pump.await.unwrap_or(false) {
+            return Err(tonic::Status::aborted(format!(
+                "The stream broke off. {} messages were imported before it did",
+                report.imported
+            )));
+        }
+
+        Ok(tonic::Response::new(ImportMessagesGrpcResponse {
+            read: report.read as i64,
+            imported: report.imported as i64,
+            collisions: report.collisions as i64,
+            collided_ids: report
+                .collided_ids
+                .iter()
+                .map(|itm| itm.get_value())
+                .collect(),
+            first_message_id: report.first_message_id.map(|itm| itm.get_value()),
+            last_message_id: report.last_message_id.map(|itm| itm.get_value()),
+        }))
+    }
+
+    async fn set_retention(
+        &self,
+        request: tonic::Request<SetRetentionGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let max_age = match req.max_age_sec {
+            0 => None,
+            max_age_sec if max_age_sec > 0 => Some(Duration::from_secs(max_age_sec as u64)),
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "MaxAgeSec can not be negative",
+                ))
+            }
+        };
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+        contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+
+        // Only kept for now: nothing expires data by it yet - see TODO.md.
+        self.app
+            .topics_retention
+            .set(topic_key, max_age)
+            .await
+            .map_err(tonic::Status::internal)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn rebuild_index(
+        &self,
+        request: tonic::Request<AdminTopicGrpcRequest>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+        contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+
+        // Reads the whole topic, so it runs in the background; the outcome goes to the log.
+        crate::operations::rebuild_index_by_minute(&self.app, topic_key);
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn force_upload(
+        &self,
+        request: tonic::Request<AdminTopicGrpcRequest>,
+    ) -> Result<tonic::Response<ForceUploadGrpcResponse>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        contracts::check_topic_id(req.topic_id.as_str())?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        if self.app.get_cold_storage(namespace.as_str()).is_none() {
+            return Err(tonic::Status::failed_precondition(format!(
+                "No cold storage is configured for namespace '{}'",
+                namespace
+            )));
+        }
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+        contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+
+        let files_uploaded = crate::timers::cold_storage_uploader::upload_sealed_files_of_topic(
+            self.app.as_ref(),
+            topic_key,
+        )
+        .await;
+
+        Ok(tonic::Response::new(ForceUploadGrpcResponse {
+            files_uploaded: files_uploaded as i32,
+        }))
+    }
+
+    generate_server_stream!(stream_name:"ListSnapshotRevisionsStream", item_name:"SnapshotRevisionGrpcModel");
+    async fn list_snapshot_revisions(
+        &self,
+        request: tonic::Request<ListSnapshotRevisionsGrpcRequest>,
+    ) -> Result<tonic::Response<Self::ListSnapshotRevisionsStream>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        let revisions = crate::operations::list_snapshot_revisions(self.app.as_ref(), &namespace)
+            .await
+            .map_err(to_status)?;
+
+        let data = revisions.into_iter().map(|itm| SnapshotRevisionGrpcModel {
+            revision_id: itm.revision_id,
+            created: itm.get_created().unix_microseconds,
+            size: itm.size as i64,
+        });
+
+        my_grpc_extensions::grpc_server_streams::send_from_iterator(data).await
+    }
+
+    async fn diff_snapshot_revisions(
+        &self,
+        request: tonic::Request<DiffSnapshotRevisionsGrpcRequest>,
+    ) -> Result<tonic::Response<SnapshotDiffGrpcModel>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Read, namespace.as_str())?;
+
+        let diff = crate::operations::diff_snapshot_revisions(
+            self.app.as_ref(),
+            &namespace,
+            req.from,
+            req.to,
+        )
+        .await
+        .map_err(to_status)?;
+
+        let result = SnapshotDiffGrpcModel {
+            topics_added: diff.topics_added,
+            topics_removed: diff.topics_removed,
+            topics_changed: diff
+                .topics_changed
+                .into_iter()
+                .map(|itm| TopicSnapshotDiffGrpcModel {
+                    topic_id: itm.topic_id,
+                    message_id_from: itm.message_id_from,
+                    message_id_to: itm.message_id_to,
+                    queues_added: itm.queues_added,
+                    queues_removed: itm.queues_removed,
+                    queues_changed: itm.queues_changed,
+                })
+                .collect(),
+        };
+
+        Ok(tonic::Response::new(result))
+    }
+
+    async fn restore_snapshot_revision(
+        &self,
+        request: tonic::Request<RestoreSnapshotRevisionGrpcRequest>,
+    ) -> Result<tonic::Response<RestoreSnapshotRevisionGrpcResponse>, tonic::Status> {
+        contracts::check_flags(self.app.as_ref())?;
+
+        let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+        contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+        let req = request.into_inner();
+
+        let namespace = contracts::get_namespace(req.namespace)?;
+        caller.check(AuthScope::Admin, namespace.as_str())?;
+
+        let kept_as = crate::operations::restore_snapshot_revision(
+            self.app.as_ref(),
+            &namespace,
+            req.revision_id,
+        )
+        .await
+        .map_err(to_status)?;
+
+        Ok(tonic::Response::new(RestoreSnapshotRevisionGrpcResponse {
+            kept_as,
+        }))
+    }
+}
+
+fn get_namespace_info<'s>(
+    result: &'s mut BTreeMap<String, NamespaceInfoGrpcModel>,
+    namespace: &str,
+) -> &'s mut NamespaceInfoGrpcModel {
+    result
+        .entry(namespace.to_string())
+        .or_insert_with(|| NamespaceInfoGrpcModel {
+            namespace: namespace.to_string(),
+            topics_amount: 0,
+            loaded_topics_amount: 0,
+            queues_amount: 0,
+            local_bytes: 0,
+            cold_bytes: 0,
+            deleting: false,
+        })
+}
+
+fn get_retention_sec(app: &crate::app::AppContext, topic_key: TopicKeyRef<'_>) -> Option<i64> {
+    app.topics_retention
+        .get(topic_key)
+        .map(|retention| retention.max_age_sec as i64)
+}
+
+fn to_status(err: OperationError) -> tonic::Status {
+    match err {
+        OperationError::TopicNotFound(topic_key) => {
+            tonic::Status::not_found(format!("Topic {} is not found", topic_key))
+        }
+        OperationError::SnapshotRevisionNotFound(revision_id) => {
+            tonic::Status::not_found(format!("Revision {} is not found", revision_id))
+        }
+        OperationError::InvalidTopicMove(message) => tonic::Status::invalid_argument(message),
+        OperationError::InvalidTopicClone(message) => tonic::Status::invalid_argument(message),
+        OperationError::TopicAlreadyExists(topic_key) => {
+            tonic::Status::already_exists(format!("Topic {} already exists", topic_key))
+        }
+        OperationError::TopicIsMoving(message) => tonic::Status::failed_precondition(message),
+        OperationError::TopicIsDeleted(topic_key) => tonic::Status::failed_precondition(format!(
+            "Topic {} is deleted; RestoreTopic brings it back until it is collected",
+            topic_key
+        )),
+        OperationError::NamespaceNotFound(namespace) => {
+            tonic::Status::not_found(format!("Namespace {} is not found", namespace))
+        }
+        OperationError::NamespaceIsBeingDeleted(namespace) => {
+            tonic::Status::failed_precondition(format!("Namespace {} is being deleted", namespace))
+        }
+        OperationError::InvalidBundle(message) => tonic::Status::invalid_argument(message),
+        OperationError::BundleStreamError(message) => tonic::Status::aborted(message),
+        OperationError::InvalidMessagesImport(message) => tonic::Status::invalid_argument(message),
+        OperationError::MessagesStreamError(message) => tonic::Status::aborted(message),
+        err => tonic::Status::internal(format!("{:?}", err)),
+    }
+}
+
+fn to_location_grpc_enum(src: StorageLocation) -> i32 {
+    let result = match src {
+        StorageLocation::Local => StorageLocationGrpcEnum::Local,
+        StorageLocation::Cold => StorageLocationGrpcEnum::Cold,
+        StorageLocation::LocalAndCold => StorageLocationGrpcEnum::LocalAndCold,
+    };
+
+    result as i32
+}
+
+fn to_topic_details_grpc_model(
+    inventory: TopicInventory,
+    topic_key: TopicKeyRef<'_>,
+    retention_sec: Option<i64>,
+) -> TopicDetailsGrpcModel {
+    TopicDetailsGrpcModel {
+        topic_id: topic_key.topic_id.to_string(),
+        namespace: topic_key.namespace.to_string(),
+        message_id: inventory
+            .message_id
+            .map(|itm| itm.get_value())
+            .unwrap_or(-1),
+        min_stored_message_id: inventory.min_stored_message_id.map(|itm| itm.get_value()),
+        max_stored_message_id: inventory.max_stored_message_id.map(|itm| itm.get_value()),
+        archives: inventory
+            .archives
+            .iter()
+            .map(|archive| ArchiveFileGrpcModel {
+                file_no: archive.archive_file_no.get_value(),
+                location: to_location_grpc_enum(archive.location),
+                size: archive.size as i64,
+                sub_pages_stored: archive.stored_sub_pages.len() as i32,
+                from_message_id: archive.get_first_message_id().get_value(),
+                to_message_id: archive.get_last_message_id().get_value(),
+            })
+            .collect(),
+        year_indexes: inventory
+            .year_indexes
+            .iter()
+            .map(|year_index| YearIndexGrpcModel {
+                year: year_index.year.get_value() as i32,
+                location: to_location_grpc_enum(year_index.location),
+            })
+            .collect(),
+        retention_sec,
+    }
+}
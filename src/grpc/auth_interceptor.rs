@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::{
+    app::AppContext,
+    auth::{AuthError, AuthToken},
+    settings::AuthScope,
+};
+
+const AUTHORIZATION_HEADER: &str = "authorization";
+
+/// Turns away a call without a known token before it reaches the service. What the token may do
+/// is checked later, in the RPC itself - the namespace travels in the request body, and the
+/// interceptor only ever sees the metadata.
+#[derive(Clone)]
+pub struct GrpcAuthInterceptor {
+    app: Arc<AppContext>,
+}
+
+impl GrpcAuthInterceptor {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+impl tonic::service::Interceptor for GrpcAuthInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let Some(auth) = self.app.auth.as_ref() else {
+            return Ok(request);
+        };
+
+        let authorization = request
+            .metadata()
+            .get(AUTHORIZATION_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        let token = auth.authenticate(authorization)?;
+
+        request.extensions_mut().insert(AuthenticatedToken(token));
+
+        Ok(request)
+    }
+}
+
+#[derive(Clone)]
+struct AuthenticatedToken(Arc<AuthToken>);
+
+/// Whoever made the call. Empty when authentication is switched off, and then every check
+/// passes.
+pub struct GrpcCaller(Option<Arc<AuthToken>>);
+
+impl GrpcCaller {
+    /// Has to be taken before `into_inner()` - the extensions go with it.
+    pub fn from_request<T>(
+        app: &AppContext,
+        request: &tonic::Request<T>,
+    ) -> Result<Self, tonic::Status> {
+        if app.auth.is_none() {
+            return Ok(Self(None));
+        }
+
+        match request.extensions().get::<AuthenticatedToken>() {
+            Some(token) => Ok(Self(Some(token.0.clone()))),
+            None => Err(tonic::Status::unauthenticated("Authorization is required")),
+        }
+    }
+
+    pub fn check(&self, scope: AuthScope, namespace: &str) -> Result<(), tonic::Status> {
+        match &self.0 {
+            Some(token) => Ok(token.check(scope, Some(namespace))?),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_every_namespace(&self, scope: AuthScope) -> Result<(), tonic::Status> {
+        match &self.0 {
+            Some(token) => Ok(token.check_every_namespace(scope)?),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_scope(&self, scope: AuthScope) -> Result<(), tonic::Status> {
+        match &self.0 {
+            Some(token) => Ok(token.check(scope, None)?),
+            None => Ok(()),
+        }
+    }
+
+    pub fn can_access_namespace(&self, namespace: &str) -> bool {
+        match &self.0 {
+            Some(token) => token.can_access_namespace(namespace),
+            None => true,
+        }
+    }
+}
+
+impl From<AuthError> for tonic::Status {
+    fn from(src: AuthError) -> Self {
+        match src {
+            AuthError::Unauthenticated(msg) => tonic::Status::unauthenticated(msg),
+            AuthError::PermissionDenied(msg) => tonic::Status::permission_denied(msg),
+        }
+    }
+}
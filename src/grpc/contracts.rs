@@ -16,7 +16,7 @@ impl NewMessagesProtobufContract {
     }
 }
  */
-use crate::topic_key::{validate_topic_id, Namespace};
+use crate::topic_key::{validate_topic_id, Namespace, TopicKeyRef};
 
 /// The topic id becomes a path component and an S3 key segment, so it is checked before it can
 /// reach storage - see `crate::topic_key::validate_topic_id`.
@@ -33,12 +33,121 @@ pub fn get_namespace(src: Option<String>) -> Result<Namespace, tonic::Status> {
     })
 }
 
+/// Below the hard threshold of the disk watchdog. `ResourceExhausted` rather than `Unavailable`:
+/// the node should hold on to the messages and try again later, not fail over to a replica that
+/// shares the volume.
+pub fn check_disk_space(app: &AppContext) -> Result<(), tonic::Status> {
+    if !app.disk_space.is_write_refused() {
+        return Ok(());
+    }
+
+    Err(tonic::Status::resource_exhausted(format!(
+        "The data volume is almost full: {} of {} bytes free. Messages are not accepted until there is room",
+        app.disk_space.get_free_bytes(),
+        app.disk_space.get_total_bytes()
+    )))
+}
+
+/// Either name of an unfinished `MoveTopic`. Writes would land in a folder that is about to be
+/// renamed, or come back to life under a name the data has just left. `Unavailable`: once the
+/// move is done the topic takes writes again under its new name.
+pub fn check_topic_not_moving(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(), tonic::Status> {
+    if !app.topic_moves.is_moving(topic_key) {
+        return Ok(());
+    }
+
+    Err(tonic::Status::unavailable(format!(
+        "Topic {} is being moved",
+        topic_key
+    )))
+}
+
+/// A topic taken out by `SoftDeleteTopic`. Its data is kept as it was when it was deleted, so that
+/// `RestoreTopic` brings back exactly that; a write now would start a new topic under the name
+/// and the restore would then have nowhere to go.
+pub fn check_topic_not_deleted(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(), tonic::Status> {
+    if !app.topics_snapshot.is_deleted(topic_key) {
+        return Ok(());
+    }
+
+    Err(tonic::Status::failed_precondition(format!(
+        "Topic {} is deleted; RestoreTopic brings it back until it is collected",
+        topic_key
+    )))
+}
+
+/// A namespace whose `DeleteNamespace` is running. Anything written now would be deleted with it,
+/// or - once the job is past the topic - left behind in a namespace that is meant to be gone.
+pub fn check_namespace_not_deleting(
+    app: &AppContext,
+    namespace: &str,
+) -> Result<(), tonic::Status> {
+    if !app.namespace_deletions.is_deleting(namespace) {
+        return Ok(());
+    }
+
+    Err(tonic::Status::unavailable(format!(
+        "Namespace {} is being deleted",
+        namespace
+    )))
+}
+
 /// Re-validates a namespace that already travelled through a mapper.
 pub fn check_namespace_is_supported(namespace: &str) -> Result<(), tonic::Status> {
     get_namespace(Some(namespace.to_string()))?;
     Ok(())
 }
 
+/// With a gRPC client CA configured, a call that changes data has to come over a connection that
+/// presented a certificate - the handshake has already verified it against the CA. Calls over
+/// the unix socket are local and carry no TLS at all, so they are not asked.
+pub fn check_client_certificate<T>(
+    app: &AppContext,
+    request: &tonic::Request<T>,
+) -> Result<(), tonic::Status> {
+    let Some(tls) = app.tls.as_ref() else {
+        return Ok(());
+    };
+
+    if !tls.has_grpc_client_ca() {
+        return Ok(());
+    }
+
+    if request
+        .extensions()
+        .get::<tonic::transport::server::UdsConnectInfo>()
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    match request.peer_certs() {
+        Some(certs) if !certs.is_empty() => Ok(()),
+        _ => Err(tonic::Status::unauthenticated(
+            "A client certificate is required for this call",
+        )),
+    }
+}
+
+/// Metadata of a `SaveQueueSnapshot` that replaces the snapshot even where it looks like the node
+/// lost its state - for a reset that is meant.
+const SNAPSHOT_OVERRIDE_HEADER: &str = "x-snapshot-override";
+
+/// Has to be read before `into_inner()`, like every other header.
+pub fn is_snapshot_override<T>(request: &tonic::Request<T>) -> bool {
+    let Some(value) = request.metadata().get(SNAPSHOT_OVERRIDE_HEADER) else {
+        return false;
+    };
+
+    matches!(value.to_str(), Ok("1") | Ok("true"))
+}
+
 pub fn check_flags(app: &AppContext) -> Result<(), tonic::Status> {
     if !app.app_states.is_initialized() {
         // `Unavailable`, not `Cancelled`: this is the standard "not ready, retry later" status,
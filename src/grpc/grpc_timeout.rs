@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// The moment a client stops waiting for the call, if it sent a deadline. Has to be read before
+/// `into_inner()` - the header is gone with the metadata afterwards.
+pub fn get_deadline<T>(request: &tonic::Request<T>) -> Option<tokio::time::Instant> {
+    let value = request.metadata().get(GRPC_TIMEOUT_HEADER)?;
+    let timeout = parse_grpc_timeout(value.to_str().ok()?)?;
+    Some(tokio::time::Instant::now() + timeout)
+}
+
+/// `grpc-timeout` is at most eight ASCII digits followed by a single unit letter:
+/// `H`ours, `M`inutes, `S`econds, `m`illiseconds, `u` microseconds or `n`anoseconds.
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+
+    let (amount, unit) = value.split_at(value.len() - 1);
+
+    if !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let amount: u64 = amount.parse().ok()?;
+
+    let result = match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_grpc_timeout;
+
+    #[test]
+    fn parses_every_unit() {
+        assert_eq!(parse_grpc_timeout("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_grpc_timeout("3M"), Some(Duration::from_secs(180)));
+        assert_eq!(parse_grpc_timeout("10S"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("99u"), Some(Duration::from_micros(99)));
+        assert_eq!(parse_grpc_timeout("5n"), Some(Duration::from_nanos(5)));
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert_eq!(parse_grpc_timeout(""), None);
+        assert_eq!(parse_grpc_timeout("S"), None);
+        assert_eq!(parse_grpc_timeout("10"), None);
+        assert_eq!(parse_grpc_timeout("10s"), None);
+        assert_eq!(parse_grpc_timeout("-1S"), None);
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+    }
+}
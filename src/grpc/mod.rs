@@ -1,4 +1,7 @@
+mod admin_grpc_service;
+mod auth_interceptor;
 mod contracts;
+mod grpc_timeout;
 mod mappers;
 //mod messages_mappers;
 mod persistence_grpc_service;
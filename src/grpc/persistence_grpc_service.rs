@@ -1,14 +1,21 @@
+use crate::operations::StreamedReadLimits;
 use crate::persistence_grpc::my_service_bus_messages_persistence_grpc_service_server::MyServiceBusMessagesPersistenceGrpcService;
 use crate::persistence_grpc::*;
+use crate::settings::AuthScope;
 use crate::topic_key::{NamespaceError, TopicKey, TopicKeyRef};
 use crate::topics_snapshot::TopicSnapshotProtobufModel;
 
 use my_grpc_extensions::{server::*, StreamedRequestReader, StreamedResponseWriter};
+use my_logger::LogEventCtx;
 use my_service_bus::abstractions::MessageId;
 use my_service_bus::shared::page_id::PageId;
 use my_service_bus::shared::sub_page::SubPageId;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use super::contracts;
+use std::future::Future;
+use std::time::Instant;
+
+use super::{auth_interceptor::GrpcCaller, contracts, grpc_timeout};
 
 use super::server::MyServicePersistenceGrpc;
 
@@ -19,15 +26,25 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
     generate_server_stream!(stream_name:"GetQueueSnapshotStream", item_name:"TopicAndQueuesSnapshotGrpcModel");
     async fn get_queue_snapshot(
         &self,
-        _: tonic::Request<()>,
+        request: tonic::Request<()>,
     ) -> Result<tonic::Response<Self::GetQueueSnapshotStream>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("GetQueueSnapshot", async move {
+            contracts::check_flags(self.app.as_ref())?;
+
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            caller.check_scope(AuthScope::Read)?;
+
+            let result = self.app.topics_snapshot.get().await;
 
-        let result = self.app.topics_snapshot.get().await;
+            // A token limited to some namespaces gets the snapshot of those only.
+            let data = result
+                .snapshot
+                .data
+                .into_iter()
+                .filter(|itm| caller.can_access_namespace(itm.get_namespace()));
 
-        my_grpc_extensions::grpc_server_streams::send_from_iterator(
-            result.snapshot.data.into_iter(),
-        )
+            my_grpc_extensions::grpc_server_streams::send_from_iterator(data).await
+        })
         .await
     }
 
@@ -35,90 +52,151 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
         &self,
         request: tonic::Request<tonic::Streaming<TopicAndQueuesSnapshotGrpcModel>>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("SaveQueueSnapshot", async move {
+            contracts::check_flags(self.app.as_ref())?;
+
+            // The call replaces the snapshot of every namespace, so a token limited to a few of
+            // them would wipe the rest.
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            caller.check_every_namespace(AuthScope::Write)?;
+            contracts::check_client_certificate(self.app.as_ref(), &request)?;
+
+            let force = contracts::is_snapshot_override(&request);
 
-        let stream = request.into_inner();
+            let stream = request.into_inner();
 
-        let values = StreamedRequestReader::new(stream);
+            let values = StreamedRequestReader::new(stream);
 
-        // One stream carries every namespace at once - that is how the node restores and saves
-        // everything in a single call.
-        let grpc_models: Vec<TopicAndQueuesSnapshotGrpcModel> = values.into_vec().await?;
+            // One stream carries every namespace at once - that is how the node restores and
+            // saves everything in a single call.
+            let grpc_models: Vec<TopicAndQueuesSnapshotGrpcModel> = values.into_vec().await?;
 
-        let mut snapshot: Vec<TopicSnapshotProtobufModel> = Vec::with_capacity(grpc_models.len());
+            let mut snapshot: Vec<TopicSnapshotProtobufModel> =
+                Vec::with_capacity(grpc_models.len());
+
+            for grpc_model in grpc_models {
+                let topic_snapshot: TopicSnapshotProtobufModel =
+                    grpc_model.try_into().map_err(|err: NamespaceError| {
+                        tonic::Status::invalid_argument(format!("Invalid Namespace. {}", err))
+                    })?;
+
+                contracts::check_namespace_is_supported(topic_snapshot.get_namespace())?;
+                contracts::check_topic_id(topic_snapshot.topic_id.as_str())?;
+
+                // Left out rather than refused: the stream carries every namespace, and the
+                // others must not stop being saved while one is deleted.
+                if self
+                    .app
+                    .namespace_deletions
+                    .is_deleting(topic_snapshot.get_namespace())
+                {
+                    continue;
+                }
+
+                snapshot.push(topic_snapshot);
+            }
+
+            contracts::check_flags(self.app.as_ref())?;
 
-        for grpc_model in grpc_models {
-            let topic_snapshot: TopicSnapshotProtobufModel =
-                grpc_model.try_into().map_err(|err: NamespaceError| {
-                    tonic::Status::invalid_argument(format!("Invalid Namespace. {}", err))
-                })?;
+            if force {
+                my_logger::LOGGER.write_info(
+                    "SaveQueueSnapshot".to_string(),
+                    format!(
+                        "Snapshot of {} topics saved with the override, unchecked",
+                        snapshot.len()
+                    ),
+                    LogEventCtx::new(),
+                );
+            }
 
-            contracts::check_namespace_is_supported(topic_snapshot.get_namespace())?;
-            contracts::check_topic_id(topic_snapshot.topic_id.as_str())?;
+            // `FailedPrecondition`: sending the same snapshot again gets the same answer, so it
+            // is not something to retry - unlike `Unavailable` while initializing.
+            let moving = self.app.topic_moves.get_all();
 
-            snapshot.push(topic_snapshot);
-        }
+            let result = self
+                .app
+                .topics_snapshot
+                .update(snapshot, force, moving.as_slice())
+                .await;
 
-        contracts::check_flags(self.app.as_ref())?;
+            if let Err(err) = result {
+                self.app
+                    .metrics_keeper
+                    .queue_snapshot_rejected(err.as_label());
 
-        self.app.topics_snapshot.update(snapshot).await;
+                return Err(tonic::Status::failed_precondition(format!(
+                    "Snapshot refused: {}. Send metadata x-snapshot-override: true to replace it anyway",
+                    err
+                )));
+            }
 
-        Ok(tonic::Response::new(()))
+            Ok(tonic::Response::new(()))
+        })
+        .await
     }
 
     async fn get_version(
         &self,
         _request: tonic::Request<()>,
     ) -> Result<tonic::Response<GetVersionGrpcResponse>, tonic::Status> {
-        let result = GetVersionGrpcResponse {
-            version: crate::app::APP_VERSION.to_string(),
-        };
+        self.observe("GetVersion", async move {
+            let result = GetVersionGrpcResponse {
+                version: crate::app::APP_VERSION.to_string(),
+            };
 
-        return Ok(tonic::Response::new(result));
+            return Ok(tonic::Response::new(result));
+        })
+        .await
     }
 
     async fn get_message(
         &self,
         request: tonic::Request<GetMessageGrpcRequest>,
     ) -> Result<tonic::Response<MessageContentGrpcModel>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
-
-        let req = request.into_inner();
-
-        let namespace = contracts::get_namespace(req.namespace)?;
-        contracts::check_topic_id(req.topic_id.as_str())?;
-
-        let message = crate::operations::get_message_by_id(
-            self.app.as_ref(),
-            TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
-            req.message_id.into(),
-        )
-        .await;
-
-        let message = match message {
-            Ok(message) => message,
-            // An unknown topic is a business answer, not a failure: it reads the same as
-            // "no such message". Panicking here would tear the stream down with RST_STREAM.
-            Err(crate::operations::OperationError::TopicNotFound(_)) => None,
-            Err(err) => {
-                return Err(tonic::Status::internal(format!(
-                    "get_message failed: {:?}",
-                    err
-                )))
-            }
-        };
+        self.observe("GetMessage", async move {
+            contracts::check_flags(self.app.as_ref())?;
 
-        let result = match message {
-            Some(msg) => msg.as_ref().into(),
-            None => MessageContentGrpcModel {
-                created: 0,
-                data: Vec::new(),
-                meta_data: Vec::new(),
-                message_id: -1,
-            },
-        };
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+
+            let req = request.into_inner();
+
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Read, namespace.as_str())?;
+
+            let message = crate::operations::get_message_by_id(
+                self.app.as_ref(),
+                TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
+                req.message_id.into(),
+            )
+            .await;
 
-        return Ok(tonic::Response::new(result));
+            let message = match message {
+                Ok(message) => message,
+                // An unknown topic is a business answer, not a failure: it reads the same as
+                // "no such message". Panicking here would tear the stream down with RST_STREAM.
+                Err(crate::operations::OperationError::TopicNotFound(_)) => None,
+                Err(err) => {
+                    return Err(tonic::Status::internal(format!(
+                        "get_message failed: {:?}",
+                        err
+                    )))
+                }
+            };
+
+            let result = match message {
+                Some(msg) => msg.as_ref().into(),
+                None => MessageContentGrpcModel {
+                    created: 0,
+                    data: Vec::new(),
+                    meta_data: Vec::new(),
+                    message_id: -1,
+                },
+            };
+
+            return Ok(tonic::Response::new(result));
+        })
+        .await
     }
 
     generate_server_stream!(stream_name:"GetPageCompressedStream", item_name:"CompressedMessageChunkModel");
@@ -126,36 +204,43 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
         &self,
         request: tonic::Request<crate::persistence_grpc::GetPageCompressedGrpcRequest>,
     ) -> Result<tonic::Response<Self::GetPageCompressedStream>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("GetPageCompressed", async move {
+            contracts::check_flags(self.app.as_ref())?;
 
-        let req = request.into_inner();
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
 
-        let namespace = contracts::get_namespace(req.namespace)?;
-        contracts::check_topic_id(req.topic_id.as_str())?;
+            let req = request.into_inner();
 
-        let app = self.app.clone();
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Read, namespace.as_str())?;
+
+            let app = self.app.clone();
 
-        let page_id = PageId::new(req.page_no);
+            let page_id = PageId::new(req.page_no);
 
-        let mut from_message_id = page_id.get_first_message_id();
-        let mut to_message_id = page_id.get_last_message_id();
+            let mut from_message_id = page_id.get_first_message_id();
+            let mut to_message_id = page_id.get_last_message_id();
 
-        if req.from_message_id > 0 && req.to_message_id > 0 {
-            from_message_id = MessageId::new(req.from_message_id);
-            to_message_id = MessageId::new(req.to_message_id);
-        }
+            if req.from_message_id > 0 && req.to_message_id > 0 {
+                from_message_id = MessageId::new(req.from_message_id);
+                to_message_id = MessageId::new(req.to_message_id);
+            }
 
-        let compressed = crate::operations::compressed_page_compiler::get_compressed_page(
-            app,
-            TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
-            from_message_id,
-            to_message_id,
-            req.version == 0,
-            MAX_PAYLOAD_SIZE,
-        )
-        .await;
+            let compressed = crate::operations::compressed_page_compiler::get_compressed_page(
+                app,
+                TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
+                from_message_id,
+                to_message_id,
+                req.version == 0,
+                MAX_PAYLOAD_SIZE,
+            )
+            .await;
 
-        my_grpc_extensions::grpc_server_streams::send_from_iterator(compressed.into_iter()).await
+            my_grpc_extensions::grpc_server_streams::send_from_iterator(compressed.into_iter())
+                .await
+        })
+        .await
     }
 
     generate_server_stream!(stream_name:"GetPageStream", item_name:"MessageContentGrpcModel");
@@ -163,40 +248,37 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
         &self,
         request: tonic::Request<crate::persistence_grpc::GetPageGrpcRequest>,
     ) -> Result<tonic::Response<Self::GetPageStream>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
-
-        let req = request.into_inner();
-
-        let namespace = contracts::get_namespace(req.namespace)?;
-        contracts::check_topic_id(req.topic_id.as_str())?;
-
-        let app = self.app.clone();
-
-        let page_id = PageId::new(req.page_no);
+        self.observe("GetPage", async move {
+            contracts::check_flags(self.app.as_ref())?;
 
-        let mut from_message_id = page_id.get_first_message_id();
-        let mut to_message_id = page_id.get_last_message_id();
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            let deadline = grpc_timeout::get_deadline(&request);
 
-        if req.from_message_id <= req.to_message_id {
-            from_message_id = MessageId::new(req.from_message_id);
-            to_message_id = MessageId::new(req.to_message_id);
-        }
+            let req = request.into_inner();
 
-        let topic_key = TopicKey::new(namespace, req.topic_id);
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Read, namespace.as_str())?;
 
-        let streamed_response = StreamedResponseWriter::new(1024);
+            let page_id = PageId::new(req.page_no);
 
-        let producer = streamed_response.get_stream_producer();
+            let mut from_message_id = page_id.get_first_message_id();
+            let mut to_message_id = page_id.get_last_message_id();
 
-        tokio::spawn(crate::operations::send_messages_to_channel(
-            app,
-            topic_key,
-            from_message_id,
-            to_message_id,
-            producer,
-        ));
+            if req.from_message_id <= req.to_message_id {
+                from_message_id = MessageId::new(req.from_message_id);
+                to_message_id = MessageId::new(req.to_message_id);
+            }
 
-        streamed_response.get_result()
+            self.spawn_streamed_read(
+                "GetPage",
+                TopicKey::new(namespace, req.topic_id),
+                from_message_id,
+                to_message_id,
+                deadline,
+            )
+        })
+        .await
     }
 
     generate_server_stream!(stream_name:"GetSubPageStream", item_name:"MessageContentGrpcModel");
@@ -204,82 +286,110 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
         &self,
         request: tonic::Request<GetSubPageGrpcRequest>,
     ) -> Result<tonic::Response<Self::GetSubPageStream>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("GetSubPage", async move {
+            contracts::check_flags(self.app.as_ref())?;
 
-        let req = request.into_inner();
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            let deadline = grpc_timeout::get_deadline(&request);
 
-        let namespace = contracts::get_namespace(req.namespace)?;
-        contracts::check_topic_id(req.topic_id.as_str())?;
+            let req = request.into_inner();
 
-        let sub_page_id = SubPageId::new(req.sub_page_no);
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Read, namespace.as_str())?;
 
-        let from_message_id = sub_page_id.get_first_message_id();
-        let to_message_id = sub_page_id.get_last_message_id();
+            let sub_page_id = SubPageId::new(req.sub_page_no);
 
-        let streamed_response = StreamedResponseWriter::new(1024);
+            let from_message_id = sub_page_id.get_first_message_id();
+            let to_message_id = sub_page_id.get_last_message_id();
 
-        let producer = streamed_response.get_stream_producer();
-
-        tokio::spawn(crate::operations::send_messages_to_channel(
-            self.app.clone(),
-            TopicKey::new(namespace, req.topic_id),
-            from_message_id,
-            to_message_id,
-            producer,
-        ));
-
-        streamed_response.get_result()
+            self.spawn_streamed_read(
+                "GetSubPage",
+                TopicKey::new(namespace, req.topic_id),
+                from_message_id,
+                to_message_id,
+                deadline,
+            )
+        })
+        .await
     }
 
     async fn save_messages(
         &self,
         request: tonic::Request<tonic::Streaming<SaveMessagesGrpcRequest>>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("SaveMessages", async move {
+            contracts::check_flags(self.app.as_ref())?;
+            contracts::check_disk_space(self.app.as_ref())?;
 
-        let request = request.into_inner();
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            contracts::check_client_certificate(self.app.as_ref(), &request)?;
 
-        let mut stream_reader = StreamedRequestReader::new(request);
+            let request = request.into_inner();
 
-        while let Some(message) = stream_reader.get_next().await {
-            contracts::check_flags(self.app.as_ref())?;
+            let mut stream_reader = StreamedRequestReader::new(request);
 
-            let message = message.unwrap();
+            while let Some(message) = stream_reader.get_next().await {
+                contracts::check_flags(self.app.as_ref())?;
+                contracts::check_disk_space(self.app.as_ref())?;
 
-            // Each message batch carries its own namespace - one stream may span several.
-            let namespace = contracts::get_namespace(message.namespace)?;
-            contracts::check_topic_id(message.topic_id.as_str())?;
+                let message = message.unwrap();
 
-            crate::operations::new_messages(
-                &self.app,
-                TopicKeyRef::new(namespace.as_str(), message.topic_id.as_str()),
-                message.messages.into_iter().map(|itm| itm.into()),
-            )
-            .await;
-        }
+                // Each message batch carries its own namespace - one stream may span several.
+                let namespace = contracts::get_namespace(message.namespace)?;
+                contracts::check_topic_id(message.topic_id.as_str())?;
+                caller.check(AuthScope::Write, namespace.as_str())?;
+
+                let topic_key = TopicKeyRef::new(namespace.as_str(), message.topic_id.as_str());
 
-        Ok(tonic::Response::new(()))
+                // Held from the checks until the batch is in: a move started in between waits
+                // for it rather than copying the topic without it.
+                let _write_guard = self.app.topic_writes.read(topic_key).await;
+
+                contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+                contracts::check_namespace_not_deleting(self.app.as_ref(), topic_key.namespace)?;
+                contracts::check_topic_not_deleted(self.app.as_ref(), topic_key)?;
+
+                crate::operations::new_messages(
+                    &self.app,
+                    topic_key,
+                    message.messages.into_iter().map(|itm| itm.into()),
+                )
+                .await;
+            }
+
+            Ok(tonic::Response::new(()))
+        })
+        .await
     }
 
     async fn hard_delete_topic(
         &self,
         request: tonic::Request<HardDeleteTopicGrpcRequest>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("HardDeleteTopic", async move {
+            contracts::check_flags(self.app.as_ref())?;
 
-        let req = request.into_inner();
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            contracts::check_client_certificate(self.app.as_ref(), &request)?;
 
-        let namespace = contracts::get_namespace(req.namespace)?;
-        contracts::check_topic_id(req.topic_id.as_str())?;
+            let req = request.into_inner();
 
-        // Deletes the topic within this namespace only. Returns as soon as the topic stops being
-        // served; wiping the data runs as a background job.
-        crate::operations::hard_delete_topic(
-            &self.app,
-            TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str()),
-        );
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Admin, namespace.as_str())?;
 
-        Ok(tonic::Response::new(()))
+            let topic_key = TopicKeyRef::new(namespace.as_str(), req.topic_id.as_str());
+            contracts::check_topic_not_moving(self.app.as_ref(), topic_key)?;
+            contracts::check_namespace_not_deleting(self.app.as_ref(), topic_key.namespace)?;
+
+            // Deletes the topic within this namespace only. Returns as soon as the topic stops
+            // being served; wiping the data runs as a background job.
+            crate::operations::hard_delete_topic(&self.app, topic_key);
+
+            Ok(tonic::Response::new(()))
+        })
+        .await
     }
 
     generate_server_stream!(stream_name:"GetHistoryByDateStream", item_name:"MessageContentGrpcModel");
@@ -287,16 +397,156 @@ impl MyServiceBusMessagesPersistenceGrpcService for MyServicePersistenceGrpc {
         &self,
         request: tonic::Request<GetHistoryByDateGrpcRequest>,
     ) -> Result<tonic::Response<Self::GetHistoryByDateStream>, tonic::Status> {
-        contracts::check_flags(self.app.as_ref())?;
+        self.observe("GetHistoryByDate", async move {
+            contracts::check_flags(self.app.as_ref())?;
+
+            let caller = GrpcCaller::from_request(self.app.as_ref(), &request)?;
+            let deadline = grpc_timeout::get_deadline(&request);
+
+            let req = request.into_inner();
+
+            let namespace = contracts::get_namespace(req.namespace)?;
+            contracts::check_topic_id(req.topic_id.as_str())?;
+            caller.check(AuthScope::Read, namespace.as_str())?;
 
-        // TODO: not implemented yet (see TODO.md). The namespace is still resolved so a caller
-        // gets the same validation as everywhere else once this is filled in.
-        let _namespace = contracts::get_namespace(request.into_inner().namespace)?;
+            let topic_key = TopicKey::new(namespace, req.topic_id);
 
-        my_grpc_extensions::grpc_server_streams::send_from_iterator([].into_iter()).await
+            let from_message_id = crate::operations::find_first_message_id_from_date(
+                self.app.as_ref(),
+                topic_key.to_ref(),
+                DateTimeAsMicroseconds::new(req.from_date_time),
+            )
+            .await;
+
+            let from_message_id = match from_message_id {
+                Ok(Some(message_id)) => message_id,
+                // Nothing indexed since that date - the topic is there, its history is empty.
+                Ok(None) => {
+                    return my_grpc_extensions::grpc_server_streams::send_from_iterator(
+                        [].into_iter(),
+                    )
+                    .await;
+                }
+                Err(crate::operations::OperationError::TopicNotFound(topic)) => {
+                    return Err(tonic::Status::not_found(format!(
+                        "Topic {} is not found",
+                        topic
+                    )))
+                }
+                Err(err) => {
+                    return Err(tonic::Status::internal(format!(
+                        "get_history_by_date failed: {:?}",
+                        err
+                    )))
+                }
+            };
+
+            // The history runs up to whatever the node has reported as the topic's current id.
+            let to_message_id = self
+                .app
+                .topics_snapshot
+                .get()
+                .await
+                .snapshot
+                .data
+                .iter()
+                .find(|itm| itm.get_topic_key() == topic_key.to_ref())
+                .map(|itm| itm.get_message_id())
+                .unwrap_or(from_message_id);
+
+            self.spawn_streamed_read(
+                "GetHistoryByDate",
+                topic_key,
+                from_message_id,
+                to_message_id,
+                deadline,
+            )
+        })
+        .await
     }
 
     async fn ping(&self, _: tonic::Request<()>) -> Result<tonic::Response<()>, tonic::Status> {
-        Ok(tonic::Response::new(()))
+        self.observe("Ping", async move { Ok(tonic::Response::new(())) })
+            .await
+    }
+}
+
+impl MyServicePersistenceGrpc {
+    /// Counts the call by the code it answered with, and times it. A streamed call is timed to the
+    /// moment its stream is handed back - the streaming itself is `grpc_stream_duration_sec`.
+    async fn observe<T>(
+        &self,
+        rpc: &'static str,
+        call: impl Future<Output = Result<T, tonic::Status>>,
+    ) -> Result<T, tonic::Status> {
+        let started = Instant::now();
+
+        let result = call.await;
+
+        let code = match &result {
+            Ok(_) => tonic::Code::Ok,
+            Err(status) => status.code(),
+        };
+
+        self.app.metrics_keeper.grpc_request(
+            rpc,
+            format!("{:?}", code).as_str(),
+            started.elapsed(),
+        );
+
+        result
+    }
+
+    /// Streams the range from a detached task. The task outlives the call on purpose - the
+    /// response is handed back before the first message is read - so how it ended is only
+    /// visible here: anything short of the full range is counted as an aborted stream.
+    ///
+    /// `GetPage`, `GetSubPage` and `GetHistoryByDate` streams are all the same boxed stream of
+    /// `MessageContentGrpcModel`, hence the one return type for the three of them.
+    fn spawn_streamed_read(
+        &self,
+        rpc: &'static str,
+        topic_key: TopicKey,
+        from_message_id: MessageId,
+        to_message_id: MessageId,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<
+        tonic::Response<<Self as MyServiceBusMessagesPersistenceGrpcService>::GetPageStream>,
+        tonic::Status,
+    > {
+        let streamed_response = StreamedResponseWriter::new(1024);
+
+        let producer = streamed_response.get_stream_producer();
+
+        let limits = StreamedReadLimits {
+            deadline,
+            max_empty_sub_pages: self.app.settings.streamed_reads.max_empty_sub_pages,
+        };
+
+        let app = self.app.clone();
+
+        let started = Instant::now();
+
+        tokio::spawn(async move {
+            let stop = crate::operations::send_messages_to_channel(
+                app.clone(),
+                topic_key,
+                from_message_id,
+                to_message_id,
+                limits,
+                producer,
+            )
+            .await;
+
+            if stop.is_aborted() {
+                app.metrics_keeper
+                    .streamed_read_aborted(rpc, stop.as_label());
+            }
+
+            app.metrics_keeper
+                .grpc_stream_finished(rpc, started.elapsed());
+        });
+
+        streamed_response.get_result()
     }
 }
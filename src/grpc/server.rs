@@ -1,4 +1,6 @@
 use crate::app::AppContext;
+use crate::grpc::auth_interceptor::GrpcAuthInterceptor;
+use crate::persistence_admin_grpc::persistence_admin_grpc_service_server::PersistenceAdminGrpcServiceServer;
 use crate::persistence_grpc::my_service_bus_messages_persistence_grpc_service_server::MyServiceBusMessagesPersistenceGrpcServiceServer;
 use anyhow::*;
 use std::net::SocketAddr;
@@ -16,16 +18,54 @@ impl MyServicePersistenceGrpc {
     }
 }
 
+/// Served next to the main service, on the same listeners and behind the same interceptor.
+#[derive(Clone)]
+pub struct PersistenceAdminGrpc {
+    pub app: Arc<AppContext>,
+}
+
+impl PersistenceAdminGrpc {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
 pub async fn start(app: Arc<AppContext>, port: u16) -> Result<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let service = MyServicePersistenceGrpc::new(app);
+    let interceptor = GrpcAuthInterceptor::new(app.clone());
+    let service = MyServicePersistenceGrpc::new(app.clone());
+    let admin_service = PersistenceAdminGrpc::new(app.clone());
 
-    println!("Listening to {:?} as grpc endpoint", addr);
-    Server::builder()
-        .add_service(MyServiceBusMessagesPersistenceGrpcServiceServer::new(
-            service,
-        ))
-        .serve(addr)
+    let server = Server::builder()
+        .add_service(
+            MyServiceBusMessagesPersistenceGrpcServiceServer::with_interceptor(
+                service,
+                interceptor.clone(),
+            ),
+        )
+        .add_service(PersistenceAdminGrpcServiceServer::with_interceptor(
+            admin_service,
+            interceptor,
+        ));
+
+    let Some(tls) = app.tls.clone() else {
+        println!("Listening to {:?} as grpc endpoint", addr);
+
+        return server.serve(addr).await.context("Server error");
+    };
+
+    // TLS is terminated here rather than by tonic's own `tls_config`: that one is fixed for the
+    // life of the server, and a rotated certificate has to be served without a restart.
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context("Can not bind grpc endpoint")?;
+
+    let incoming = crate::tls::tls_incoming("GrpcTls", listener, move || tls.get_grpc_config());
+
+    println!("Listening to {:?} as grpc endpoint with TLS", addr);
+
+    server
+        .serve_with_incoming(incoming)
         .await
         .context("Server error")
 }
@@ -37,15 +77,24 @@ pub async fn start_unix_socket(app: Arc<AppContext>, unix_socket_addr: String) -
     let uds = tokio::net::UnixListener::bind(unix_socket_addr.as_str()).unwrap();
     let uds_stream = tokio_stream::wrappers::UnixListenerStream::new(uds);
 
-    let service = MyServicePersistenceGrpc::new(app);
+    let interceptor = GrpcAuthInterceptor::new(app.clone());
+    let service = MyServicePersistenceGrpc::new(app.clone());
+    let admin_service = PersistenceAdminGrpc::new(app);
 
     println!(
         "Listening to {:?} as grpc unix_socket endpoint",
         unix_socket_addr.as_str()
     );
     Server::builder()
-        .add_service(MyServiceBusMessagesPersistenceGrpcServiceServer::new(
-            service,
+        .add_service(
+            MyServiceBusMessagesPersistenceGrpcServiceServer::with_interceptor(
+                service,
+                interceptor.clone(),
+            ),
+        )
+        .add_service(PersistenceAdminGrpcServiceServer::with_interceptor(
+            admin_service,
+            interceptor,
         ))
         .serve_with_incoming(uds_stream)
         .await
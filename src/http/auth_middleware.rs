@@ -0,0 +1,386 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpServerMiddleware};
+
+use crate::{
+    app::AppContext,
+    auth::AuthError,
+    settings::AuthScope,
+    topic_key::{Namespace, DEFAULT_NAMESPACE},
+};
+
+const AUTHORIZATION_HEADER: &str = "authorization";
+const NAMESPACE_QUERY_PARAM: &str = "namespace";
+
+/// Endpoints that carry the namespace as the path segment right after the prefix.
+const NAMESPACE_IN_PATH: &[&str] = &["/api/topic/", "/api/queues/"];
+
+/// Not `DELETE`s, but just as hard to take back - the cold repair deletes the objects of topics
+/// that are gone, and the scrub repair rewrites message ids and drops year indexes.
+const ADMIN_PATHS: &[&str] = &[
+    "/api/snapshothistory/restore",
+    "/api/scrub/repair",
+    "/api/cold/reconcile/repair",
+];
+
+/// Answer about every namespace unless one is given, so without one a token limited to a few
+/// namespaces is refused rather than shown the rest.
+const EVERY_NAMESPACE_UNLESS_GIVEN: &[&str] = &[
+    "/api/lag",
+    "/api/scrub",
+    "/api/scrub/repair",
+    "/api/cold/reconcile",
+    "/api/cold/reconcile/repair",
+];
+
+/// Reads that need `admin`: the status lists the topics, the lag and the usage of every namespace
+/// in one go, which a token limited to a few namespaces must not see and which is not worth
+/// filtering per token - it is an operator's page.
+const ADMIN_READ_PATHS: &[&str] = &["/api/status"];
+
+/// Every change under these is `admin`: moving a queue loses or replays messages.
+const ADMIN_PATH_PREFIXES: &[&str] = &["/api/queues/"];
+
+/// Sits in front of the controllers and the static files. Does nothing when no `auth` section
+/// is configured.
+pub struct AuthMiddleware {
+    app: Arc<AppContext>,
+}
+
+impl AuthMiddleware {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for AuthMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let auth = self.app.auth.as_ref()?;
+
+        let path = ctx.request.get_path().to_lowercase();
+
+        let scope = get_required_scope(ctx.request.get_method().as_str(), path.as_str())?;
+
+        let authorization = ctx
+            .request
+            .get_headers()
+            .get(AUTHORIZATION_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        let namespace = get_namespace_to_check(path.as_str(), ctx.request.get_uri().query());
+
+        let every_namespace = namespace.is_none();
+
+        let result = auth.authenticate(authorization).and_then(|token| {
+            if every_namespace {
+                token.check_every_namespace(scope)
+            } else {
+                token.check(scope, namespace.as_deref())
+            }
+        });
+
+        match result {
+            Ok(()) => None,
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+impl From<AuthError> for HttpFailResult {
+    fn from(src: AuthError) -> Self {
+        match src {
+            AuthError::Unauthenticated(msg) => HttpFailResult::as_unauthorized(Some(msg)),
+            AuthError::PermissionDenied(msg) => HttpFailResult::as_forbidden(Some(msg)),
+        }
+    }
+}
+
+/// `None` - open to anyone: the UI, swagger, the liveness probe and the Prometheus scrape. The
+/// probe and the scraper are not going to carry a token, and none of them expose messages.
+///
+/// Everything under `/read/` reads messages. Under `/api/` the verb decides: reading is `read`,
+/// changing is `write`, deleting is `admin`. A few changes are `admin` too - see
+/// `ADMIN_PATHS` and `ADMIN_PATH_PREFIXES` - and so are the `ADMIN_READ_PATHS`.
+fn get_required_scope(method: &str, path: &str) -> Option<AuthScope> {
+    if path == "/api/is_alive" || path == "/metrics" {
+        return None;
+    }
+
+    if path.starts_with("/read/") {
+        return Some(AuthScope::Read);
+    }
+
+    if !path.starts_with("/api/") {
+        return None;
+    }
+
+    if ADMIN_READ_PATHS.contains(&path) {
+        return Some(AuthScope::Admin);
+    }
+
+    let is_read = method == "GET" || method == "HEAD";
+
+    if !is_read
+        && (ADMIN_PATHS.contains(&path)
+            || ADMIN_PATH_PREFIXES
+                .iter()
+                .any(|prefix| path.starts_with(prefix)))
+    {
+        return Some(AuthScope::Admin);
+    }
+
+    let scope = match method {
+        "GET" | "HEAD" => AuthScope::Read,
+        "DELETE" => AuthScope::Admin,
+        _ => AuthScope::Write,
+    };
+
+    Some(scope)
+}
+
+/// The namespace a request is about, taken the same way the controllers take it. `None` - the
+/// request covers every namespace, which the `ADMIN_READ_PATHS` always do and the
+/// `EVERY_NAMESPACE_UNLESS_GIVEN` endpoints do without one. Every other controller reads an absent or empty parameter as `default`, so the
+/// check does too - checking nothing there would let a token limited to `alpha` export or read
+/// `default`.
+///
+/// A value that does not parse is passed through as is: it matches no namespace a token is
+/// limited to, so a limited token is refused, and an unlimited one gets the controller's
+/// validation error.
+fn get_namespace_to_check(path: &str, query: Option<&str>) -> Option<String> {
+    if ADMIN_READ_PATHS.contains(&path) {
+        return None;
+    }
+
+    for prefix in NAMESPACE_IN_PATH {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            let segment = rest.split('/').next().unwrap_or_default();
+
+            return match Namespace::parse(Some(segment)) {
+                Ok(namespace) => Some(namespace.as_str().to_string()),
+                Err(_) => Some(segment.to_string()),
+            };
+        }
+    }
+
+    let from_query = query.and_then(|query| {
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            if key.eq_ignore_ascii_case(NAMESPACE_QUERY_PARAM) {
+                Some(value.to_string())
+            } else {
+                None
+            }
+        })
+    });
+
+    match from_query {
+        // Empty is `default` everywhere else, but here it is what the controller takes as "every
+        // namespace".
+        Some(value) if value.is_empty() && EVERY_NAMESPACE_UNLESS_GIVEN.contains(&path) => None,
+        Some(value) => match Namespace::parse(Some(value.as_str())) {
+            Ok(namespace) => Some(namespace.as_str().to_string()),
+            Err(_) => Some(value),
+        },
+        None if EVERY_NAMESPACE_UNLESS_GIVEN.contains(&path) => None,
+        None => Some(DEFAULT_NAMESPACE.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        auth::TokenAuth,
+        settings::{AuthScope, AuthSettingsModel, AuthTokenSettingsModel},
+    };
+
+    use super::{get_namespace_to_check, get_required_scope};
+
+    #[test]
+    fn open_endpoints_need_no_token() {
+        assert_eq!(get_required_scope("GET", "/"), None);
+        assert_eq!(get_required_scope("GET", "/metrics"), None);
+        assert_eq!(get_required_scope("GET", "/api/is_alive"), None);
+        assert_eq!(get_required_scope("GET", "/js/app.js"), None);
+    }
+
+    #[test]
+    fn scope_follows_the_endpoint_and_the_verb() {
+        assert_eq!(
+            get_required_scope("GET", "/read/byid"),
+            Some(AuthScope::Read)
+        );
+        assert_eq!(
+            get_required_scope("GET", "/api/topic/alpha/orders"),
+            Some(AuthScope::Read)
+        );
+        assert_eq!(
+            get_required_scope("POST", "/api/topic/alpha/orders"),
+            Some(AuthScope::Write)
+        );
+        assert_eq!(
+            get_required_scope("DELETE", "/api/topic"),
+            Some(AuthScope::Admin)
+        );
+        assert_eq!(
+            get_required_scope("GET", "/api/snapshothistory/diff"),
+            Some(AuthScope::Read)
+        );
+        assert_eq!(
+            get_required_scope("POST", "/api/snapshothistory/restore"),
+            Some(AuthScope::Admin)
+        );
+        assert_eq!(
+            get_required_scope("POST", "/api/scrub/repair"),
+            Some(AuthScope::Admin)
+        );
+        assert_eq!(
+            get_required_scope("GET", "/api/queues/alpha/orders"),
+            Some(AuthScope::Read)
+        );
+        assert_eq!(
+            get_required_scope("POST", "/api/queues/alpha/orders/billing/position"),
+            Some(AuthScope::Admin)
+        );
+    }
+
+    #[test]
+    fn namespace_comes_from_the_query_or_defaults_on_reads() {
+        assert_eq!(
+            get_namespace_to_check("/read/byid", Some("topicId=orders&namespace=alpha")),
+            Some("alpha".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/read/byid", Some("topicId=orders&namespace=")),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/read/byid", Some("topicId=orders")),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/read/byid", Some("namespace=%61lpha")),
+            Some("%61lpha".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/api/topic/alpha/orders", None),
+            Some("alpha".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/api/queues/alpha/orders/billing", None),
+            Some("alpha".to_string())
+        );
+        assert_eq!(get_namespace_to_check("/api/lag", Some("namespace=")), None);
+        assert_eq!(get_namespace_to_check("/api/lag", None), None);
+        assert_eq!(
+            get_namespace_to_check("/api/lag", Some("namespace=alpha")),
+            Some("alpha".to_string())
+        );
+    }
+
+    /// The bundle export reads an absent namespace as `default`, so a token limited to another
+    /// one must not get past the check without it.
+    #[test]
+    fn a_namespace_less_bundle_export_is_about_default() {
+        assert_eq!(
+            get_namespace_to_check("/api/bundle/export", None),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            get_namespace_to_check("/api/bundle/export", Some("topicId=orders")),
+            Some("default".to_string())
+        );
+
+        let auth = limited_to_alpha();
+        let token = auth.authenticate(Some("Bearer alpha-token")).unwrap();
+
+        let namespace = get_namespace_to_check("/api/bundle/export", Some("topicId=orders"));
+        assert!(token.check(AuthScope::Read, namespace.as_deref()).is_err());
+
+        let namespace = get_namespace_to_check("/api/bundle/export", Some("namespace=alpha"));
+        assert!(token.check(AuthScope::Read, namespace.as_deref()).is_ok());
+    }
+
+    /// The same for the gap report: without `namespace=` it checks `default`.
+    #[test]
+    fn a_namespace_less_gap_report_is_about_default() {
+        let auth = limited_to_alpha();
+        let token = auth.authenticate(Some("Bearer alpha-token")).unwrap();
+
+        for query in [None, Some("namespace="), Some("topicId=orders")] {
+            let namespace = get_namespace_to_check("/api/gaps", query);
+
+            assert_eq!(Some("default".to_string()), namespace);
+            assert!(token.check(AuthScope::Read, namespace.as_deref()).is_err());
+        }
+
+        let namespace = get_namespace_to_check("/api/gaps", Some("namespace=alpha"));
+        assert!(token.check(AuthScope::Read, namespace.as_deref()).is_ok());
+    }
+
+    /// The snapshot history reads and restores `default` without `namespace=`, so that is what a
+    /// token is checked against - a restore most of all.
+    #[test]
+    fn a_namespace_less_snapshot_history_call_is_about_default() {
+        let auth = limited_to_alpha();
+        let token = auth.authenticate(Some("Bearer alpha-token")).unwrap();
+
+        for (method, path, query) in [
+            ("GET", "/api/snapshothistory", None),
+            ("GET", "/api/snapshothistory/diff", Some("from=1")),
+            ("POST", "/api/snapshothistory/restore", Some("revision=1")),
+            (
+                "POST",
+                "/api/snapshothistory/restore",
+                Some("namespace=&revision=1"),
+            ),
+        ] {
+            let scope = get_required_scope(method, path).unwrap();
+            let namespace = get_namespace_to_check(path, query);
+
+            assert_eq!(Some("default".to_string()), namespace);
+            assert!(token.check(scope, namespace.as_deref()).is_err());
+        }
+
+        let namespace =
+            get_namespace_to_check("/api/snapshothistory/restore", Some("namespace=alpha"));
+        assert!(token.check(AuthScope::Admin, namespace.as_deref()).is_ok());
+    }
+
+    /// The status shows every namespace at once, so even `namespace=alpha` does not narrow it
+    /// for a token limited to `alpha`.
+    #[test]
+    fn the_status_is_for_an_unlimited_admin() {
+        assert_eq!(
+            get_required_scope("GET", "/api/status"),
+            Some(AuthScope::Admin)
+        );
+        assert_eq!(get_namespace_to_check("/api/status", None), None);
+        assert_eq!(
+            get_namespace_to_check("/api/status", Some("namespace=alpha")),
+            None
+        );
+
+        let token = limited_to_alpha()
+            .authenticate(Some("Bearer alpha-token"))
+            .unwrap();
+
+        assert!(token.check_every_namespace(AuthScope::Admin).is_err());
+    }
+
+    fn limited_to_alpha() -> TokenAuth {
+        TokenAuth::new(&AuthSettingsModel {
+            tokens: vec![AuthTokenSettingsModel {
+                name: "alpha".to_string(),
+                token: "alpha-token".to_string(),
+                scopes: vec![AuthScope::Read, AuthScope::Write, AuthScope::Admin],
+                namespaces: Some(vec!["alpha".to_string()]),
+            }],
+        })
+    }
+}
@@ -13,6 +13,24 @@ pub fn build(app: &Arc<AppContext>) -> ControllersMiddleware {
     result.register_get_action(Arc::new(
         super::controllers::api_controller::GetStatusAction::new(app.clone()),
     ));
+    result.register_get_action(Arc::new(
+        super::controllers::api_controller::GetLagAction::new(app.clone()),
+    ));
+    result.register_get_action(Arc::new(
+        super::controllers::api_controller::GetGapsAction::new(app.clone()),
+    ));
+    result.register_get_action(Arc::new(
+        super::controllers::api_controller::ScrubAction::new(app.clone()),
+    ));
+    result.register_post_action(Arc::new(
+        super::controllers::api_controller::ScrubRepairAction::new(app.clone()),
+    ));
+    result.register_get_action(Arc::new(
+        super::controllers::api_controller::ReconcileColdAction::new(app.clone()),
+    ));
+    result.register_post_action(Arc::new(
+        super::controllers::api_controller::ReconcileColdRepairAction::new(app.clone()),
+    ));
 
     /*
        result.register_get_action(Arc::new(
@@ -24,14 +42,55 @@ pub fn build(app: &Arc<AppContext>) -> ControllersMiddleware {
        ));
     */
     //Controller Topic
-    // TODO: re-enable with soft-delete + GC (see TODO.md)
-    // result.register_delete_action(Arc::new(
-    //     super::controllers::topic_controller::DeleteTopicAction::new(app.clone()),
-    // ));
-    //
-    // result.register_get_action(Arc::new(
-    //     super::controllers::topic_controller::GetDeletedTopicsAction::new(app.clone()),
-    // ));
+    result.register_delete_action(Arc::new(
+        super::controllers::topic_controller::DeleteTopicAction::new(app.clone()),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::topic_controller::GetDeletedTopicsAction::new(app.clone()),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::topic_controller::GetTopicDetailsAction::new(app.clone()),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::queue_controller::GetQueuesAction::new(app.clone()),
+    ));
+
+    result.register_post_action(Arc::new(
+        super::controllers::queue_controller::SetQueuePositionAction::new(app.clone()),
+    ));
+
+    result.register_post_action(Arc::new(
+        super::controllers::queue_controller::SetQueueTypeAction::new(app.clone()),
+    ));
+
+    result.register_delete_action(Arc::new(
+        super::controllers::queue_controller::DeleteQueueAction::new(app.clone()),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::snapshot_history_controller::ListSnapshotRevisionsAction::new(
+            app.clone(),
+        ),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::snapshot_history_controller::DiffSnapshotRevisionsAction::new(
+            app.clone(),
+        ),
+    ));
+
+    result.register_post_action(Arc::new(
+        super::controllers::snapshot_history_controller::RestoreSnapshotRevisionAction::new(
+            app.clone(),
+        ),
+    ));
+
+    result.register_get_action(Arc::new(
+        super::controllers::bundle_controller::ExportBundleAction::new(app.clone()),
+    ));
 
     result.register_get_action(Arc::new(
         super::controllers::prometheus_controller::MetricsAction::new(app.clone()),
@@ -49,5 +108,9 @@ pub fn build(app: &Arc<AppContext>) -> ControllersMiddleware {
         super::controllers::read_controller::ListFromDateAction::new(app.clone()),
     ));
 
+    result.register_get_action(Arc::new(
+        super::controllers::read_controller::ExportAction::new(app.clone()),
+    ));
+
     result
 }
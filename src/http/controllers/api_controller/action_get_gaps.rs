@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+
+use super::contracts::{GetGapsHttpContract, TopicGapsHttpModel};
+
+/// Reads every sub page of the range it checks - one topic after an incident, not something to
+/// poll. The `topic_missing_messages` gauge keeps the count of the last check.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/gaps",
+    input_data: "GetGapsHttpContract",
+    description: "Sub pages and message ids missing from a topic's stored range",
+    summary: "Gap report",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Gaps of every topic checked", model:"Vec<TopicGapsHttpModel>"},
+        {status_code: 404, description: "Topic or namespace not found"},
+    ]
+)]
+pub struct GetGapsAction {
+    app: Arc<AppContext>,
+}
+
+impl GetGapsAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &GetGapsAction,
+    input_data: GetGapsHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+
+    let topic_id = if input_data.topic_id.is_empty() {
+        None
+    } else {
+        check_topic_id(input_data.topic_id.as_str())?;
+        Some(input_data.topic_id.as_str())
+    };
+
+    let reports =
+        crate::operations::find_namespace_gaps(action.app.as_ref(), namespace.as_str(), topic_id)
+            .await?;
+
+    let model: Vec<TopicGapsHttpModel> = reports.iter().map(TopicGapsHttpModel::new).collect();
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
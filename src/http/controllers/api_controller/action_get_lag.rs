@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::{GetLagHttpContract, QueueLagHttpModel};
+
+/// The same numbers as the `queue_*` gauges, as of the last snapshot the bus node saved.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/lag",
+    input_data: "GetLagHttpContract",
+    description: "Unconsumed messages of every queue, the oldest of them and its age",
+    summary: "Consumer lag",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Lag of every queue", model:"Vec<QueueLagHttpModel>"},
+    ]
+)]
+pub struct GetLagAction {
+    app: Arc<AppContext>,
+}
+
+impl GetLagAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &GetLagAction,
+    input_data: GetLagHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = if input_data.namespace.is_empty() {
+        None
+    } else {
+        Some(parse_namespace(input_data.namespace.as_str())?)
+    };
+
+    let now = DateTimeAsMicroseconds::now();
+
+    let model: Vec<QueueLagHttpModel> = action
+        .app
+        .consumer_lag
+        .get()
+        .iter()
+        .filter(|itm| match namespace.as_ref() {
+            Some(namespace) => itm.topic_key.namespace == namespace.as_str(),
+            None => true,
+        })
+        .map(|itm| QueueLagHttpModel::new(itm, now))
+        .collect();
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
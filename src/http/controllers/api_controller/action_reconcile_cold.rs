@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::{ColdReconcileHttpContract, NamespaceReconcileHttpModel};
+
+/// Every namespace listed is in the answer; one with no findings matches the cold tier. Empty
+/// when there is no cold tier.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/cold/reconcile",
+    input_data: "ColdReconcileHttpContract",
+    description: "Lists the cold tier and holds it against the snapshot and the local disk",
+    summary: "Cold tier reconciliation",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Findings of every namespace listed", model:"Vec<NamespaceReconcileHttpModel>"},
+    ]
+)]
+pub struct ReconcileColdAction {
+    app: Arc<AppContext>,
+}
+
+impl ReconcileColdAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ReconcileColdAction,
+    input_data: ColdReconcileHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    run_reconcile(&action.app, input_data, false).await
+}
+
+pub(super) async fn run_reconcile(
+    app: &Arc<AppContext>,
+    input_data: ColdReconcileHttpContract,
+    repair: bool,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = if input_data.namespace.is_empty() {
+        None
+    } else {
+        Some(parse_namespace(input_data.namespace.as_str())?)
+    };
+
+    let reports = crate::operations::reconcile_cold_storage(
+        app.as_ref(),
+        namespace.as_ref().map(|itm| itm.as_str()),
+        repair,
+    )
+    .await;
+
+    let model: Vec<NamespaceReconcileHttpModel> = reports
+        .iter()
+        .map(NamespaceReconcileHttpModel::new)
+        .collect();
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
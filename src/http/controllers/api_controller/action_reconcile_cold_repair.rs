@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult};
+
+use crate::app::AppContext;
+
+use super::action_reconcile_cold::run_reconcile;
+use super::contracts::{ColdReconcileHttpContract, NamespaceReconcileHttpModel};
+
+/// The same reconciliation, with exact deletions and uploads for what it finds - see
+/// `operations::reconcile_cold_storage`.
+#[my_http_server::macros::http_route(
+    method: "POST",
+    route: "/api/cold/reconcile/repair",
+    input_data: "ColdReconcileHttpContract",
+    description: "Reconciles like /api/cold/reconcile and repairs what it finds",
+    summary: "Cold tier reconciliation and repair",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Findings of every namespace listed", model:"Vec<NamespaceReconcileHttpModel>"},
+    ]
+)]
+pub struct ReconcileColdRepairAction {
+    app: Arc<AppContext>,
+}
+
+impl ReconcileColdRepairAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ReconcileColdRepairAction,
+    input_data: ColdReconcileHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    run_reconcile(&action.app, input_data, true).await
+}
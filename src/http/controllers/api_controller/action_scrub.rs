@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::{ScrubHttpContract, TopicScrubHttpModel};
+
+/// Every topic checked is in the answer; one with no findings is consistent.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/scrub",
+    input_data: "ScrubHttpContract",
+    description: "Cross-checks the snapshot, the topic folders, the year indexes and the cold tier",
+    summary: "Consistency check",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Findings of every topic checked", model:"Vec<TopicScrubHttpModel>"},
+    ]
+)]
+pub struct ScrubAction {
+    app: Arc<AppContext>,
+}
+
+impl ScrubAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ScrubAction,
+    input_data: ScrubHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    run_scrub(&action.app, input_data, false).await
+}
+
+pub(super) async fn run_scrub(
+    app: &Arc<AppContext>,
+    input_data: ScrubHttpContract,
+    repair: bool,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = if input_data.namespace.is_empty() {
+        None
+    } else {
+        Some(parse_namespace(input_data.namespace.as_str())?)
+    };
+
+    let reports =
+        crate::operations::scrub(app, namespace.as_ref().map(|itm| itm.as_str()), repair).await;
+
+    let model: Vec<TopicScrubHttpModel> = reports.iter().map(TopicScrubHttpModel::new).collect();
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
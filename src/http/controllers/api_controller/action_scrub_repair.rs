@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult};
+
+use crate::app::AppContext;
+
+use super::action_scrub::run_scrub;
+use super::contracts::{ScrubHttpContract, TopicScrubHttpModel};
+
+/// The same check, fixing what is safe to fix on the way - see `operations::scrub`.
+#[my_http_server::macros::http_route(
+    method: "POST",
+    route: "/api/scrub/repair",
+    input_data: "ScrubHttpContract",
+    description: "Cross-checks like /api/scrub and repairs the safe cases",
+    summary: "Consistency check and repair",
+    controller: "Api",
+    result:[
+        {status_code: 200, description: "Findings of every topic checked", model:"Vec<TopicScrubHttpModel>"},
+    ]
+)]
+pub struct ScrubRepairAction {
+    app: Arc<AppContext>,
+}
+
+impl ScrubRepairAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ScrubRepairAction,
+    input_data: ScrubHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    run_scrub(&action.app, input_data, true).await
+}
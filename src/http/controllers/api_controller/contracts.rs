@@ -2,10 +2,15 @@ use std::{collections::BTreeMap, sync::Arc, usize};
 
 use crate::{
     app::AppContext,
+    consumer_lag::QueueLag,
+    disk_space::DiskSpace,
+    operations::{MessageIdRange, NamespaceReconcileReport, TopicGapReport, TopicScrubReport},
+    storage_usage::{LocalUsage, StorageUsage},
     topic_data::TopicData,
     topics_snapshot::{QueueSnapshotProtobufModel, TopicSnapshotProtobufModel},
     utils::duration_to_string,
 };
+use my_http_server::macros::{MyHttpInput, MyHttpObjectStructure};
 use my_service_bus::shared::{page_id::PageId, sub_page::SubPageId};
 use rust_extensions::date_time::{DateTimeAsMicroseconds, DateTimeDuration};
 use serde::{Deserialize, Serialize};
@@ -38,7 +43,7 @@ fn get_queues(queue_snapshot: &Vec<QueueSnapshotProtobufModel>) -> Vec<QueueStat
     for q in queue_snapshot {
         let model = QueueStatusModel {
             queue_id: q.queue_id.to_string(),
-            queue_type: 0,
+            queue_type: q.queue_type,
             ranges: q
                 .ranges
                 .iter()
@@ -79,6 +84,13 @@ struct TopicInfo {
     active_pages: Vec<i64>,
 
     queues: Vec<QueueStatusModel>,
+
+    /// Archive writes failed in a row - the topic is degraded while it is not `0`.
+    #[serde(rename = "archiveWriteFailures")]
+    archive_write_failures: u32,
+
+    #[serde(rename = "archiveWriteError", skip_serializing_if = "Option::is_none")]
+    archive_write_error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -138,6 +150,106 @@ impl LoadedPageModel {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct LocalUsageModel {
+    archives: u64,
+    #[serde(rename = "yearIndexes")]
+    year_indexes: u64,
+    active: u64,
+    total: u64,
+}
+
+impl LocalUsageModel {
+    fn new(src: &LocalUsage) -> Self {
+        Self {
+            archives: src.archive_bytes,
+            year_indexes: src.year_index_bytes,
+            active: src.active_bytes,
+            total: src.get_total(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct NamespaceStorageModel {
+    namespace: String,
+    topics: usize,
+    #[serde(rename = "localBytes")]
+    local_bytes: LocalUsageModel,
+    #[serde(rename = "coldBytes")]
+    cold_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TopicStorageModel {
+    namespace: String,
+    #[serde(rename = "topicId")]
+    topic_id: String,
+    #[serde(rename = "localBytes")]
+    local_bytes: LocalUsageModel,
+    #[serde(rename = "coldBytes")]
+    cold_bytes: u64,
+}
+
+/// As of the last accounting pass - `calculated` is empty until the first one is done.
+#[derive(Serialize, Deserialize, Debug)]
+struct StorageStatusModel {
+    calculated: Option<String>,
+    namespaces: Vec<NamespaceStorageModel>,
+    topics: Vec<TopicStorageModel>,
+}
+
+impl StorageStatusModel {
+    fn new(src: &StorageUsage) -> Self {
+        Self {
+            calculated: src.get_calculated().map(|itm| itm.to_rfc3339()),
+            namespaces: src
+                .get_namespaces()
+                .iter()
+                .map(|itm| NamespaceStorageModel {
+                    namespace: itm.namespace.clone(),
+                    topics: itm.topics,
+                    local_bytes: LocalUsageModel::new(&itm.local),
+                    cold_bytes: itm.cold_bytes,
+                })
+                .collect(),
+            topics: src
+                .get_topics()
+                .iter()
+                .map(|itm| TopicStorageModel {
+                    namespace: itm.topic_key.namespace.clone(),
+                    topic_id: itm.topic_key.topic_id.clone(),
+                    local_bytes: LocalUsageModel::new(&itm.local),
+                    cold_bytes: itm.cold_bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// As of the last disk watchdog tick.
+#[derive(Serialize, Deserialize, Debug)]
+struct DiskStatusModel {
+    #[serde(rename = "freeBytes")]
+    free_bytes: u64,
+    #[serde(rename = "totalBytes")]
+    total_bytes: u64,
+    level: String,
+    #[serde(rename = "writesRefused")]
+    writes_refused: bool,
+}
+
+impl DiskStatusModel {
+    fn new(src: &DiskSpace) -> Self {
+        Self {
+            free_bytes: src.get_free_bytes(),
+            total_bytes: src.get_total_bytes(),
+            level: src.get_level().as_label().to_string(),
+            writes_refused: src.is_write_refused(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct StatusModel {
     #[serde(rename = "queuesSnapshotId")]
@@ -149,6 +261,8 @@ pub struct StatusModel {
     #[serde(rename = "topics")]
     topics: Vec<TopicInfo>,
     system: SystemStatusModel,
+    storage: StorageStatusModel,
+    disk: DiskStatusModel,
     #[serde(skip_serializing_if = "Option::is_none")]
     initialing: Option<bool>,
 }
@@ -186,6 +300,8 @@ impl StatusModel {
                 totalmem: sys_info.total_memory(),
                 usedmem: sys_info.used_memory(),
             },
+            storage: StorageStatusModel::new(&app.storage_usage),
+            disk: DiskStatusModel::new(&app.disk_space),
         };
 
         return model;
@@ -217,5 +333,195 @@ async fn get_topics_model(
         queues: get_queues(&snapshot.queues),
         last_save_duration,
         last_save_moment: duration_to_string(last_save_moment_since.as_positive_or_zero()),
+        archive_write_failures: topic_data
+            .map(|itm| itm.metrics.get_archive_write_failures())
+            .unwrap_or(0),
+        archive_write_error: topic_data.and_then(|itm| itm.metrics.get_last_archive_error()),
+    }
+}
+
+#[derive(MyHttpInput)]
+pub struct GetLagHttpContract {
+    #[http_query(name = "namespace"; description="Only this namespace. Empty means every namespace"; default: "")]
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct QueueLagHttpModel {
+    pub namespace: String,
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    #[serde(rename = "queueId")]
+    pub queue_id: String,
+    pub unconsumed: i64,
+    #[serde(rename = "oldestUnconsumedId")]
+    pub oldest_unconsumed_id: Option<i64>,
+    /// `None` when it is not known - the topic is not loaded.
+    #[serde(rename = "oldestUnconsumedAgeSec")]
+    pub oldest_unconsumed_age_sec: Option<i64>,
+}
+
+impl QueueLagHttpModel {
+    pub fn new(src: &QueueLag, now: DateTimeAsMicroseconds) -> Self {
+        Self {
+            namespace: src.topic_key.namespace.clone(),
+            topic_id: src.topic_key.topic_id.clone(),
+            queue_id: src.queue_id.clone(),
+            unconsumed: src.unconsumed,
+            oldest_unconsumed_id: src.oldest_unconsumed.map(|itm| itm.get_value()),
+            oldest_unconsumed_age_sec: src.get_age_sec(now),
+        }
+    }
+}
+
+#[derive(MyHttpInput)]
+pub struct GetGapsHttpContract {
+    #[http_query(name = "namespace"; description="Namespace. Empty means 'default'"; default: "")]
+    pub namespace: String,
+
+    #[http_query(name = "topicId"; description="Topic to check. Empty means every topic of the namespace"; default: "")]
+    pub topic_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct MessageIdRangeHttpModel {
+    #[serde(rename = "fromId")]
+    pub from_id: i64,
+    #[serde(rename = "toId")]
+    pub to_id: i64,
+}
+
+impl MessageIdRangeHttpModel {
+    pub fn new(src: &MessageIdRange) -> Self {
+        Self {
+            from_id: src.from_id,
+            to_id: src.to_id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct TopicGapsHttpModel {
+    pub namespace: String,
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    #[serde(rename = "messageId")]
+    pub message_id: Option<i64>,
+    pub checked: Option<MessageIdRangeHttpModel>,
+    #[serde(rename = "missingSubPages")]
+    pub missing_sub_pages: Vec<MessageIdRangeHttpModel>,
+    #[serde(rename = "missingRanges")]
+    pub missing_ranges: Vec<MessageIdRangeHttpModel>,
+    #[serde(rename = "corruptedSubPages")]
+    pub corrupted_sub_pages: Vec<i64>,
+    #[serde(rename = "missingMessages")]
+    pub missing_messages: i64,
+}
+
+impl TopicGapsHttpModel {
+    pub fn new(src: &TopicGapReport) -> Self {
+        Self {
+            namespace: src.namespace.clone(),
+            topic_id: src.topic_id.clone(),
+            message_id: src.message_id,
+            checked: src.checked.as_ref().map(MessageIdRangeHttpModel::new),
+            missing_sub_pages: src
+                .missing_sub_pages
+                .iter()
+                .map(MessageIdRangeHttpModel::new)
+                .collect(),
+            missing_ranges: src
+                .missing_ranges
+                .iter()
+                .map(MessageIdRangeHttpModel::new)
+                .collect(),
+            corrupted_sub_pages: src.corrupted_sub_pages.clone(),
+            missing_messages: src.missing_messages,
+        }
+    }
+}
+
+#[derive(MyHttpInput)]
+pub struct ScrubHttpContract {
+    #[http_query(name = "namespace"; description="Only this namespace. Empty means every namespace"; default: "")]
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct ScrubFindingHttpModel {
+    pub kind: String,
+    pub description: String,
+    pub repaired: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct TopicScrubHttpModel {
+    pub namespace: String,
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    pub findings: Vec<ScrubFindingHttpModel>,
+}
+
+impl TopicScrubHttpModel {
+    pub fn new(src: &TopicScrubReport) -> Self {
+        Self {
+            namespace: src.topic_key.namespace.clone(),
+            topic_id: src.topic_key.topic_id.clone(),
+            findings: src
+                .findings
+                .iter()
+                .map(|itm| ScrubFindingHttpModel {
+                    kind: itm.get_kind().to_string(),
+                    description: itm.get_description(),
+                    repaired: itm.is_repaired(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(MyHttpInput)]
+pub struct ColdReconcileHttpContract {
+    #[http_query(name = "namespace"; description="Only this namespace. Empty means every namespace"; default: "")]
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct ColdReconcileFindingHttpModel {
+    pub kind: String,
+    /// Empty for a finding about the whole namespace.
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    pub description: String,
+    pub repaired: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct NamespaceReconcileHttpModel {
+    pub namespace: String,
+    #[serde(rename = "listedObjects")]
+    pub listed_objects: usize,
+    #[serde(rename = "listedBytes")]
+    pub listed_bytes: u64,
+    pub findings: Vec<ColdReconcileFindingHttpModel>,
+}
+
+impl NamespaceReconcileHttpModel {
+    pub fn new(src: &NamespaceReconcileReport) -> Self {
+        Self {
+            namespace: src.namespace.clone(),
+            listed_objects: src.listed_objects,
+            listed_bytes: src.listed_bytes,
+            findings: src
+                .findings
+                .iter()
+                .map(|itm| ColdReconcileFindingHttpModel {
+                    kind: itm.get_kind().to_string(),
+                    topic_id: itm.get_topic_id().unwrap_or_default().to_string(),
+                    description: itm.get_description(),
+                    repaired: itm.is_repaired(),
+                })
+                .collect(),
+        }
     }
 }
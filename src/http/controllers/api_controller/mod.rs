@@ -1,6 +1,18 @@
+mod action_get_gaps;
+mod action_get_lag;
 mod action_get_status;
 mod action_is_alive;
+mod action_reconcile_cold;
+mod action_reconcile_cold_repair;
+mod action_scrub;
+mod action_scrub_repair;
 
 mod contracts;
+pub use action_get_gaps::GetGapsAction;
+pub use action_get_lag::GetLagAction;
 pub use action_get_status::GetStatusAction;
 pub use action_is_alive::IsAliveAction;
+pub use action_reconcile_cold::ReconcileColdAction;
+pub use action_reconcile_cold_repair::ReconcileColdRepairAction;
+pub use action_scrub::ScrubAction;
+pub use action_scrub_repair::ScrubRepairAction;
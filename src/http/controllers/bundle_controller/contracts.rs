@@ -0,0 +1,10 @@
+use my_http_server::macros::MyHttpInput;
+
+#[derive(MyHttpInput)]
+pub struct ExportBundleHttpContract {
+    #[http_query(name = "namespace"; description="Namespace. Empty means 'default'"; default: "")]
+    pub namespace: String,
+
+    #[http_query(name = "topicId"; description="Topic to export. Empty means every topic of the namespace"; default: "")]
+    pub topic_id: String,
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult};
+use my_logger::LogEventCtx;
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::http::StreamedBodyWriter;
+
+use super::contracts::*;
+
+/// The bundle is sent while it is written, the same way `ExportBundle` streams it over gRPC - a
+/// whole namespace goes out without ever being in memory.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/Bundle/Export",
+    input_data: "ExportBundleHttpContract",
+    description: "Exports a topic, or every topic of a namespace, as a tar bundle",
+    summary: "Export bundle",
+    controller: "Bundle",
+    result:[
+        {status_code: 200, description: "The bundle"},
+        {status_code: 404, description: "Topic or namespace not found"},
+    ]
+)]
+pub struct ExportBundleAction {
+    app: Arc<AppContext>,
+}
+
+impl ExportBundleAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ExportBundleAction,
+    input_data: ExportBundleHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+
+    let topic_id = if input_data.topic_id.is_empty() {
+        None
+    } else {
+        check_topic_id(input_data.topic_id.as_str())?;
+        Some(input_data.topic_id.as_str())
+    };
+
+    // Everything that can be refused is refused here, while there is still a status to answer
+    // with - once the body is streaming, a failure can only cut it short.
+    let export =
+        crate::operations::prepare_bundle_export(action.app.as_ref(), namespace.as_str(), topic_id)
+            .await?;
+
+    let (mut output, response) = StreamedBodyWriter::new("application/x-tar");
+
+    let app = action.app.clone();
+
+    tokio::spawn(async move {
+        let result = crate::operations::write_bundle(app.as_ref(), export, &mut output).await;
+
+        if let Err(err) = result {
+            my_logger::LOGGER.write_error(
+                "export_bundle",
+                format!("The bundle is cut short. Err: {:?}", err),
+                LogEventCtx::new().add("namespace", namespace.to_string()),
+            );
+
+            output.fail(format!("{:?}", err)).await;
+        }
+    });
+
+    response.into_ok_result(false)
+}
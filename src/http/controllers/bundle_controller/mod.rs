@@ -0,0 +1,3 @@
+mod contracts;
+mod export_action;
+pub use export_action::*;
@@ -6,6 +6,24 @@ impl From<crate::operations::OperationError> for HttpFailResult {
             crate::operations::OperationError::TopicNotFound(msg) => {
                 HttpFailResult::as_not_found(format!("Topic {} not found", msg), false)
             }
+            crate::operations::OperationError::SnapshotRevisionNotFound(revision_id) => {
+                HttpFailResult::as_not_found(format!("Revision {} not found", revision_id), false)
+            }
+            crate::operations::OperationError::NamespaceNotFound(namespace) => {
+                HttpFailResult::as_not_found(format!("Namespace {} not found", namespace), false)
+            }
+            crate::operations::OperationError::QueueNotFound(queue_id) => {
+                HttpFailResult::as_not_found(format!("Queue {} not found", queue_id), false)
+            }
+            crate::operations::OperationError::InvalidQueueEdit(msg) => {
+                HttpFailResult::as_validation_error(msg.to_string())
+            }
+            crate::operations::OperationError::TopicIsDeleted(topic) => {
+                HttpFailResult::as_validation_error(format!("Topic {} is deleted", topic))
+            }
+            crate::operations::OperationError::InvalidMessagesExport(msg) => {
+                HttpFailResult::as_validation_error(msg.to_string())
+            }
             _ => HttpFailResult::as_fatal_error(format!("{:?}", src)),
         }
     }
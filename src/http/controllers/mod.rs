@@ -1,7 +1,10 @@
 pub mod api_controller;
+pub mod bundle_controller;
 mod error_converters;
 pub mod home_controller;
 //pub mod logs_controller;
 pub mod prometheus_controller;
+pub mod queue_controller;
 pub mod read_controller;
+pub mod snapshot_history_controller;
 pub mod topic_controller;
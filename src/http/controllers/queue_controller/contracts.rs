@@ -0,0 +1,111 @@
+use my_http_server::macros::{MyHttpInput, MyHttpObjectStructure};
+use my_service_bus::abstractions::MessageId;
+use serde::{Deserialize, Serialize};
+
+use crate::topics_snapshot::QueueSnapshotProtobufModel;
+
+#[derive(MyHttpInput)]
+pub struct GetQueuesHttpContract {
+    #[http_path(name = "namespace"; description="Namespace of the topic")]
+    pub namespace: String,
+
+    #[http_path(name = "topic"; description="Id of topic")]
+    pub topic_id: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct QueueHttpContract {
+    #[http_path(name = "namespace"; description="Namespace of the topic")]
+    pub namespace: String,
+
+    #[http_path(name = "topic"; description="Id of topic")]
+    pub topic_id: String,
+
+    #[http_path(name = "queue"; description="Id of queue")]
+    pub queue_id: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct SetQueuePositionHttpContract {
+    #[http_path(name = "namespace"; description="Namespace of the topic")]
+    pub namespace: String,
+
+    #[http_path(name = "topic"; description="Id of topic")]
+    pub topic_id: String,
+
+    #[http_path(name = "queue"; description="Id of queue")]
+    pub queue_id: String,
+
+    #[http_query(name = "messageId"; description="The next message the queue delivers"; default: "")]
+    pub message_id: String,
+
+    #[http_query(name = "fromDate"; description="Or: the first message stored at or after this moment, RFC3339"; default: "")]
+    pub from_date: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct SetQueueTypeHttpContract {
+    #[http_path(name = "namespace"; description="Namespace of the topic")]
+    pub namespace: String,
+
+    #[http_path(name = "topic"; description="Id of topic")]
+    pub topic_id: String,
+
+    #[http_path(name = "queue"; description="Id of queue")]
+    pub queue_id: String,
+
+    #[http_query(name = "queueType"; description="0 - Permanent, 1 - AutoDelete, 2 - PermanentWithSingleConnection")]
+    pub queue_type: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct TopicQueuesHttpModel {
+    /// The next id the topic assigns; a queue that delivered everything starts there.
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+    pub queues: Vec<QueueHttpModel>,
+}
+
+impl TopicQueuesHttpModel {
+    pub fn new(message_id: MessageId, queues: &[QueueSnapshotProtobufModel]) -> Self {
+        Self {
+            message_id: message_id.get_value(),
+            queues: queues.iter().map(QueueHttpModel::new).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct QueueHttpModel {
+    #[serde(rename = "queueId")]
+    pub queue_id: String,
+    #[serde(rename = "queueType")]
+    pub queue_type: i32,
+    /// The messages still to deliver, `[fromId, toId]` inclusive.
+    pub ranges: Vec<QueueRangeHttpModel>,
+}
+
+impl QueueHttpModel {
+    pub fn new(src: &QueueSnapshotProtobufModel) -> Self {
+        Self {
+            queue_id: src.queue_id.clone(),
+            queue_type: src.queue_type,
+            ranges: src
+                .ranges
+                .iter()
+                .map(|itm| QueueRangeHttpModel {
+                    from_id: itm.get_from_id().get_value(),
+                    to_id: itm.get_to_id().get_value(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct QueueRangeHttpModel {
+    #[serde(rename = "fromId")]
+    pub from_id: i64,
+    #[serde(rename = "toId")]
+    pub to_id: i64,
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+#[my_http_server::macros::http_route(
+    method: "DELETE",
+    route: "/api/Queues/{namespace}/{topic}/{queue}",
+    input_data: "QueueHttpContract",
+    description: "Deletes a queue from the snapshot. Do it with the bus node stopped",
+    summary: "Delete queue",
+    controller: "Queues",
+    result:[
+        {status_code: 202, description: "Deleted"},
+        {status_code: 404, description: "Topic or queue not found"},
+    ]
+)]
+pub struct DeleteQueueAction {
+    app: Arc<AppContext>,
+}
+
+impl DeleteQueueAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &DeleteQueueAction,
+    input_data: QueueHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    crate::operations::delete_queue(
+        action.app.as_ref(),
+        TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str()),
+        input_data.queue_id.as_str(),
+    )
+    .await?;
+
+    HttpOutput::Empty.into_ok_result(true).into()
+}
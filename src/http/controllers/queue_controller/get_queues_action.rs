@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/Queues/{namespace}/{topic}",
+    input_data: "GetQueuesHttpContract",
+    description: "The queues of a topic with the ranges still to deliver, as the snapshot has them",
+    summary: "Queues of a topic",
+    controller: "Queues",
+    result:[
+        {status_code: 200, description: "Queues", model:"TopicQueuesHttpModel"},
+        {status_code: 404, description: "Topic not found"},
+    ]
+)]
+pub struct GetQueuesAction {
+    app: Arc<AppContext>,
+}
+
+impl GetQueuesAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &GetQueuesAction,
+    input_data: GetQueuesHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let topic_key = TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str());
+
+    let (message_id, queues) =
+        crate::operations::get_queues(action.app.as_ref(), topic_key).await?;
+
+    HttpOutput::as_json(TopicQueuesHttpModel::new(message_id, queues.as_slice()))
+        .into_ok_result(true)
+        .into()
+}
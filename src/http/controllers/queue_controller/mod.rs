@@ -0,0 +1,9 @@
+mod contracts;
+mod delete_queue_action;
+pub use delete_queue_action::*;
+mod get_queues_action;
+pub use get_queues_action::*;
+mod set_queue_position_action;
+pub use set_queue_position_action::*;
+mod set_queue_type_action;
+pub use set_queue_type_action::*;
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+use my_service_bus::abstractions::AsMessageId;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+/// Rewinds or skips a consumer. The running bus node saves its own queues over the change - see
+/// `crate::operations::get_queues`.
+#[my_http_server::macros::http_route(
+    method: "POST",
+    route: "/api/Queues/{namespace}/{topic}/{queue}/Position",
+    input_data: "SetQueuePositionHttpContract",
+    description: "Makes the queue deliver from a message id, or from the first message stored at or after a date. Do it with the bus node stopped",
+    summary: "Move queue position",
+    controller: "Queues",
+    result:[
+        {status_code: 200, description: "The queue as it is now", model:"QueueHttpModel"},
+        {status_code: 404, description: "Topic or queue not found"},
+    ]
+)]
+pub struct SetQueuePositionAction {
+    app: Arc<AppContext>,
+}
+
+impl SetQueuePositionAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &SetQueuePositionAction,
+    input_data: SetQueuePositionHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let topic_key = TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str());
+    let queue_id = input_data.queue_id.as_str();
+
+    let queue = match (
+        input_data.message_id.is_empty(),
+        input_data.from_date.is_empty(),
+    ) {
+        (false, true) => {
+            let message_id = input_data.message_id.parse::<i64>().map_err(|_| {
+                HttpFailResult::as_validation_error("Invalid messageId".to_string())
+            })?;
+
+            crate::operations::set_queue_position(
+                action.app.as_ref(),
+                topic_key,
+                queue_id,
+                message_id.as_message_id(),
+            )
+            .await?
+        }
+        (true, false) => {
+            let from = DateTimeAsMicroseconds::parse_iso_string(input_data.from_date.as_str())
+                .ok_or_else(|| {
+                    HttpFailResult::as_validation_error("Invalid fromDate".to_string())
+                })?;
+
+            crate::operations::set_queue_position_from_date(
+                action.app.as_ref(),
+                topic_key,
+                queue_id,
+                from,
+            )
+            .await?
+        }
+        _ => {
+            return Err(HttpFailResult::as_validation_error(
+                "Exactly one of messageId and fromDate is required".to_string(),
+            ))
+        }
+    };
+
+    HttpOutput::as_json(QueueHttpModel::new(&queue))
+        .into_ok_result(true)
+        .into()
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+#[my_http_server::macros::http_route(
+    method: "POST",
+    route: "/api/Queues/{namespace}/{topic}/{queue}/Type",
+    input_data: "SetQueueTypeHttpContract",
+    description: "Changes the type of a queue. Do it with the bus node stopped",
+    summary: "Change queue type",
+    controller: "Queues",
+    result:[
+        {status_code: 200, description: "The queue as it is now", model:"QueueHttpModel"},
+        {status_code: 404, description: "Topic or queue not found"},
+    ]
+)]
+pub struct SetQueueTypeAction {
+    app: Arc<AppContext>,
+}
+
+impl SetQueueTypeAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &SetQueueTypeAction,
+    input_data: SetQueueTypeHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let queue = crate::operations::set_queue_type(
+        action.app.as_ref(),
+        TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str()),
+        input_data.queue_id.as_str(),
+        input_data.queue_type,
+    )
+    .await?;
+
+    HttpOutput::as_json(QueueHttpModel::new(&queue))
+        .into_ok_result(true)
+        .into()
+}
@@ -32,6 +32,33 @@ pub struct GetMessagesByIdInputContract {
     pub namespace: String,
 }
 
+#[derive(MyHttpInput)]
+pub struct ExportMessagesInputContract {
+    #[http_query(name = "topicId"; description="Id of topic")]
+    pub topic_id: String,
+
+    #[http_query(name = "namespace"; description="Namespace of the topic. Empty means 'default'"; default: "")]
+    pub namespace: String,
+
+    #[http_query(name = "format"; description="ndjson, csv or binary. Empty means ndjson"; default: "")]
+    pub format: String,
+
+    #[http_query(name = "content"; description="base64 or utf8 - how the content goes into ndjson and csv. Empty means base64"; default: "")]
+    pub content: String,
+
+    #[http_query(name = "fromId"; description="First message id. Either this or fromDate"; default: "")]
+    pub from_id: String,
+
+    #[http_query(name = "fromDate"; description="First moment, RFC3339. Either this or fromId"; default: "")]
+    pub from_date: String,
+
+    #[http_query(name = "toId"; description="Last message id, inclusive. Empty means the topic's current one"; default: "")]
+    pub to_id: String,
+
+    #[http_query(name = "toDate"; description="Last moment, RFC3339. Instead of toId"; default: "")]
+    pub to_date: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
 pub struct GetMessagesResponseModel {
     result: i32,
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult};
+use my_logger::LogEventCtx;
+use my_service_bus::abstractions::MessageId;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::app::AppContext;
+use crate::http::StreamedBodyWriter;
+use crate::operations::{
+    ContentEncoding, MessagesExportEnd, MessagesExportFormat, MessagesExportRequest,
+    MessagesExportStart,
+};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+use super::{check_topic_id, parse_namespace};
+
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/Read/Export",
+    controller: "Read",
+    description: "Exports a message range as NDJSON, CSV or length-prefixed protobuf",
+    summary: "Export messages",
+    input_data: "ExportMessagesInputContract",
+    result:[
+        {status_code: 200, description: "The messages"},
+        {status_code: 404, description: "Topic not found"},
+    ]
+)]
+pub struct ExportAction {
+    app: Arc<AppContext>,
+}
+
+impl ExportAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ExportAction,
+    input_data: ExportMessagesInputContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let request = parse_request(&input_data)?;
+
+    let topic_key = TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str());
+
+    // Everything that can be refused is refused here, while there is still a status to answer
+    // with - once the body is streaming, a failure can only cut it short.
+    let export =
+        crate::operations::prepare_messages_export(action.app.as_ref(), topic_key, &request)
+            .await?;
+
+    let (mut output, response) = StreamedBodyWriter::new(export.get_format().get_content_type());
+
+    let app = action.app.clone();
+    let topic_key = topic_key.to_string();
+
+    tokio::spawn(async move {
+        let result = crate::operations::export_messages(app.as_ref(), &export, &mut output).await;
+
+        if let Err(err) = result {
+            my_logger::LOGGER.write_error(
+                "export_messages",
+                format!("The export is cut short. Err: {:?}", err),
+                LogEventCtx::new().add("topic", topic_key),
+            );
+
+            output.fail(format!("{:?}", err)).await;
+        }
+    });
+
+    response.into_ok_result(false)
+}
+
+fn parse_request(
+    input_data: &ExportMessagesInputContract,
+) -> Result<MessagesExportRequest, HttpFailResult> {
+    let format = MessagesExportFormat::parse(input_data.format.as_str()).ok_or_else(|| {
+        HttpFailResult::as_validation_error(format!("Unknown format {}", input_data.format))
+    })?;
+
+    let content_encoding =
+        ContentEncoding::parse(input_data.content.as_str()).ok_or_else(|| {
+            HttpFailResult::as_validation_error(format!("Unknown content {}", input_data.content))
+        })?;
+
+    let from = match (input_data.from_id.as_str(), input_data.from_date.as_str()) {
+        ("", "") => {
+            return Err(HttpFailResult::as_validation_error(
+                "Either fromId or fromDate is required".to_string(),
+            ))
+        }
+        (from_id, "") => MessagesExportStart::MessageId(parse_message_id("fromId", from_id)?),
+        ("", from_date) => MessagesExportStart::Date(parse_date("fromDate", from_date)?),
+        _ => {
+            return Err(HttpFailResult::as_validation_error(
+                "fromId and fromDate can not go together".to_string(),
+            ))
+        }
+    };
+
+    let to = match (input_data.to_id.as_str(), input_data.to_date.as_str()) {
+        ("", "") => MessagesExportEnd::Latest,
+        (to_id, "") => MessagesExportEnd::MessageId(parse_message_id("toId", to_id)?),
+        ("", to_date) => MessagesExportEnd::Date(parse_date("toDate", to_date)?),
+        _ => {
+            return Err(HttpFailResult::as_validation_error(
+                "toId and toDate can not go together".to_string(),
+            ))
+        }
+    };
+
+    Ok(MessagesExportRequest {
+        from,
+        to,
+        format,
+        content_encoding,
+    })
+}
+
+fn parse_message_id(name: &str, src: &str) -> Result<MessageId, HttpFailResult> {
+    src.parse::<i64>()
+        .map(MessageId::new)
+        .map_err(|_| HttpFailResult::as_validation_error(format!("Invalid {}: {}", name, src)))
+}
+
+fn parse_date(name: &str, src: &str) -> Result<DateTimeAsMicroseconds, HttpFailResult> {
+    DateTimeAsMicroseconds::parse_iso_string(src)
+        .ok_or_else(|| HttpFailResult::as_validation_error(format!("Invalid {}: {}", name, src)))
+}
@@ -1,7 +1,9 @@
 mod by_id_action;
 mod contracts;
+mod export_action;
 mod list_from_date_action;
 mod parse_namespace;
 pub use by_id_action::*;
+pub use export_action::*;
 pub use list_from_date_action::ListFromDateAction;
 pub use parse_namespace::*;
@@ -0,0 +1,112 @@
+use my_http_server::macros::{MyHttpInput, MyHttpObjectStructure};
+use serde::{Deserialize, Serialize};
+
+use crate::topics_snapshot::{
+    snapshot_diff::{SnapshotDiff, TopicSnapshotDiff},
+    snapshot_history::SnapshotRevision,
+};
+
+#[derive(MyHttpInput)]
+pub struct ListSnapshotRevisionsHttpContract {
+    #[http_query(name = "namespace"; description="Namespace. Empty means 'default'"; default: "")]
+    pub namespace: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct DiffSnapshotRevisionsHttpContract {
+    #[http_query(name = "namespace"; description="Namespace. Empty means 'default'"; default: "")]
+    pub namespace: String,
+
+    #[http_query(name = "from"; description="Revision id to diff from")]
+    pub from: i64,
+
+    #[http_query(name = "to"; description="Revision id to diff to. Empty means the current snapshot"; default: "")]
+    pub to: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct RestoreSnapshotRevisionHttpContract {
+    #[http_query(name = "namespace"; description="Namespace. Required - 'default' has to be named as well"; default: "")]
+    pub namespace: String,
+
+    #[http_query(name = "revision"; description="Revision id to make the current snapshot")]
+    pub revision: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct SnapshotRevisionHttpModel {
+    #[serde(rename = "revisionId")]
+    pub revision_id: i64,
+    pub created: String,
+    pub size: u64,
+}
+
+impl SnapshotRevisionHttpModel {
+    pub fn new(src: &SnapshotRevision) -> Self {
+        Self {
+            revision_id: src.revision_id,
+            created: src.get_created().to_rfc3339(),
+            size: src.size,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct SnapshotDiffHttpModel {
+    #[serde(rename = "topicsAdded")]
+    pub topics_added: Vec<String>,
+    #[serde(rename = "topicsRemoved")]
+    pub topics_removed: Vec<String>,
+    #[serde(rename = "topicsChanged")]
+    pub topics_changed: Vec<TopicSnapshotDiffHttpModel>,
+}
+
+impl SnapshotDiffHttpModel {
+    pub fn new(src: SnapshotDiff) -> Self {
+        Self {
+            topics_added: src.topics_added,
+            topics_removed: src.topics_removed,
+            topics_changed: src
+                .topics_changed
+                .into_iter()
+                .map(TopicSnapshotDiffHttpModel::new)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct TopicSnapshotDiffHttpModel {
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    #[serde(rename = "messageIdFrom")]
+    pub message_id_from: i64,
+    #[serde(rename = "messageIdTo")]
+    pub message_id_to: i64,
+    #[serde(rename = "queuesAdded")]
+    pub queues_added: Vec<String>,
+    #[serde(rename = "queuesRemoved")]
+    pub queues_removed: Vec<String>,
+    #[serde(rename = "queuesChanged")]
+    pub queues_changed: Vec<String>,
+}
+
+impl TopicSnapshotDiffHttpModel {
+    fn new(src: TopicSnapshotDiff) -> Self {
+        Self {
+            topic_id: src.topic_id,
+            message_id_from: src.message_id_from,
+            message_id_to: src.message_id_to,
+            queues_added: src.queues_added,
+            queues_removed: src.queues_removed,
+            queues_changed: src.queues_changed,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct RestoreSnapshotRevisionHttpModel {
+    /// The revision the replaced snapshot was kept as; `None` if there was nothing to keep.
+    #[serde(rename = "keptAs")]
+    pub kept_as: Option<i64>,
+}
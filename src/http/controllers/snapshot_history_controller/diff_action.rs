@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::*;
+
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/SnapshotHistory/Diff",
+    input_data: "DiffSnapshotRevisionsHttpContract",
+    description: "Topics and queues that differ between two revisions, or a revision and the current snapshot",
+    summary: "Diff snapshot revisions",
+    controller: "SnapshotHistory",
+    result:[
+        {status_code: 200, description: "Diff", model:"SnapshotDiffHttpModel"},
+        {status_code: 404, description: "Revision not found"},
+    ]
+)]
+pub struct DiffSnapshotRevisionsAction {
+    app: Arc<AppContext>,
+}
+
+impl DiffSnapshotRevisionsAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &DiffSnapshotRevisionsAction,
+    input_data: DiffSnapshotRevisionsHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+
+    let to = if input_data.to.is_empty() {
+        None
+    } else {
+        let to = input_data.to.parse::<i64>().map_err(|_| {
+            HttpFailResult::as_validation_error("Invalid 'to' revision id".to_string())
+        })?;
+        Some(to)
+    };
+
+    let diff = crate::operations::diff_snapshot_revisions(
+        action.app.as_ref(),
+        &namespace,
+        input_data.from,
+        to,
+    )
+    .await?;
+
+    HttpOutput::as_json(SnapshotDiffHttpModel::new(diff))
+        .into_ok_result(true)
+        .into()
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::*;
+
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/SnapshotHistory",
+    input_data: "ListSnapshotRevisionsHttpContract",
+    description: "Kept revisions of the topics and queues snapshot of a namespace, oldest first",
+    summary: "Snapshot revisions",
+    controller: "SnapshotHistory",
+    result:[
+        {status_code: 200, description: "Revisions", model:"Vec<SnapshotRevisionHttpModel>"},
+    ]
+)]
+pub struct ListSnapshotRevisionsAction {
+    app: Arc<AppContext>,
+}
+
+impl ListSnapshotRevisionsAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &ListSnapshotRevisionsAction,
+    input_data: ListSnapshotRevisionsHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+
+    let revisions =
+        crate::operations::list_snapshot_revisions(action.app.as_ref(), &namespace).await?;
+
+    let model: Vec<SnapshotRevisionHttpModel> = revisions
+        .iter()
+        .map(SnapshotRevisionHttpModel::new)
+        .collect();
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
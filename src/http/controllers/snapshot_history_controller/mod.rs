@@ -0,0 +1,7 @@
+mod contracts;
+mod diff_action;
+pub use diff_action::*;
+mod list_action;
+pub use list_action::*;
+mod restore_action;
+pub use restore_action::*;
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::parse_namespace;
+
+use super::contracts::*;
+
+/// The running bus node overwrites a restored snapshot with its next save - see
+/// `restore_snapshot_revision`.
+#[my_http_server::macros::http_route(
+    method: "POST",
+    route: "/api/SnapshotHistory/Restore",
+    input_data: "RestoreSnapshotRevisionHttpContract",
+    description: "Makes a revision the current snapshot of its namespace. Do it with the bus node stopped",
+    summary: "Restore snapshot revision",
+    controller: "SnapshotHistory",
+    result:[
+        {status_code: 200, description: "Restored", model:"RestoreSnapshotRevisionHttpModel"},
+        {status_code: 404, description: "Revision not found"},
+    ]
+)]
+pub struct RestoreSnapshotRevisionAction {
+    app: Arc<AppContext>,
+}
+
+impl RestoreSnapshotRevisionAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &RestoreSnapshotRevisionAction,
+    input_data: RestoreSnapshotRevisionHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    // Unlike a read, a forgotten parameter here would roll `default` back.
+    if input_data.namespace.is_empty() {
+        return Err(HttpFailResult::as_validation_error(
+            "namespace is required - name 'default' if it is the one to restore".to_string(),
+        ));
+    }
+
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+
+    let kept_as = crate::operations::restore_snapshot_revision(
+        action.app.as_ref(),
+        &namespace,
+        input_data.revision,
+    )
+    .await?;
+
+    HttpOutput::as_json(RestoreSnapshotRevisionHttpModel { kept_as })
+        .into_ok_result(true)
+        .into()
+}
@@ -1,9 +1,16 @@
-use my_http_server::macros::MyHttpInput;
+use my_http_server::macros::{MyHttpInput, MyHttpObjectStructure};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    operations::{ArchiveFileInventory, TopicInventory},
+    topic_key::TopicKeyRef,
+};
 
-// TODO: used by HTTP DELETE /api/Topic when soft-delete + GC flow is reimplemented (see TODO.md)
-#[allow(dead_code)]
 #[derive(MyHttpInput)]
 pub struct DeleteTopicHttpContract {
+    #[http_query(name = "namespace"; description="Namespace of the topic. Empty means 'default'"; default: "")]
+    pub namespace: String,
+
     #[http_query(name = "topicId"; description="Id of topic")]
     pub topic_id: String,
 
@@ -13,3 +20,160 @@ pub struct DeleteTopicHttpContract {
     #[http_query(name = "deleteAfter"; description="GC moment in RFC3339 (optional)"; default: "")]
     pub delete_after: Option<String>,
 }
+
+#[derive(MyHttpInput)]
+pub struct GetDeletedTopicsHttpContract {
+    #[http_query(name = "namespace"; description="Namespace of the topics. Empty means 'default'"; default: "")]
+    pub namespace: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct DeletedTopicHttpModel {
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    /// Where the topic stood when it was deleted - and where a restore puts it back.
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+    /// From this moment on the GC may delete the data.
+    #[serde(rename = "gcAfter")]
+    pub gc_after: String,
+}
+
+#[derive(MyHttpInput)]
+pub struct GetTopicDetailsHttpContract {
+    #[http_path(name = "namespace"; description="Namespace of the topic")]
+    pub namespace: String,
+
+    #[http_path(name = "topic"; description="Id of topic")]
+    pub topic_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct TopicDetailsHttpModel {
+    pub namespace: String,
+    #[serde(rename = "topicId")]
+    pub topic_id: String,
+    /// What the bus node last reported; `None` for a topic that has files but is not in the
+    /// snapshot.
+    #[serde(rename = "messageId")]
+    pub message_id: Option<i64>,
+    #[serde(rename = "minStoredMessageId")]
+    pub min_stored_message_id: Option<i64>,
+    #[serde(rename = "maxStoredMessageId")]
+    pub max_stored_message_id: Option<i64>,
+    pub archives: Vec<ArchiveFileHttpModel>,
+    #[serde(rename = "yearIndexes")]
+    pub year_indexes: Vec<YearIndexHttpModel>,
+}
+
+impl TopicDetailsHttpModel {
+    pub fn new(topic_key: TopicKeyRef<'_>, inventory: &TopicInventory) -> Self {
+        Self {
+            namespace: topic_key.namespace.to_string(),
+            topic_id: topic_key.topic_id.to_string(),
+            message_id: inventory.message_id.map(|itm| itm.get_value()),
+            min_stored_message_id: inventory.min_stored_message_id.map(|itm| itm.get_value()),
+            max_stored_message_id: inventory.max_stored_message_id.map(|itm| itm.get_value()),
+            archives: inventory
+                .archives
+                .iter()
+                .map(ArchiveFileHttpModel::new)
+                .collect(),
+            year_indexes: inventory
+                .year_indexes
+                .iter()
+                .map(|itm| YearIndexHttpModel {
+                    year: itm.year.get_value(),
+                    location: itm.location.as_str().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct ArchiveFileHttpModel {
+    #[serde(rename = "fileNo")]
+    pub file_no: i64,
+    /// `local`, `cold` or `local+cold`.
+    pub location: String,
+    pub size: u64,
+    #[serde(rename = "fromMessageId")]
+    pub from_message_id: i64,
+    #[serde(rename = "toMessageId")]
+    pub to_message_id: i64,
+    #[serde(rename = "subPagesTotal")]
+    pub sub_pages_total: i64,
+    #[serde(rename = "subPagesStored")]
+    pub sub_pages_stored: usize,
+    /// The occupied TOC slots as `[from, to]` sub page id ranges - a full file is one range, a
+    /// hole shows up as a break between two.
+    #[serde(rename = "storedSubPages")]
+    pub stored_sub_pages: Vec<SubPagesRangeHttpModel>,
+}
+
+impl ArchiveFileHttpModel {
+    fn new(src: &ArchiveFileInventory) -> Self {
+        let first_sub_page_id = src.archive_file_no.get_first_sub_page_id().get_value();
+        let last_sub_page_id = src.archive_file_no.get_last_sub_page_id().get_value();
+
+        Self {
+            file_no: src.archive_file_no.get_value(),
+            location: src.location.as_str().to_string(),
+            size: src.size,
+            from_message_id: src.get_first_message_id().get_value(),
+            to_message_id: src.get_last_message_id().get_value(),
+            sub_pages_total: last_sub_page_id - first_sub_page_id + 1,
+            sub_pages_stored: src.stored_sub_pages.len(),
+            stored_sub_pages: to_ranges(src.stored_sub_pages.iter().map(|itm| itm.get_value())),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct SubPagesRangeHttpModel {
+    pub from: i64,
+    pub to: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, MyHttpObjectStructure)]
+pub struct YearIndexHttpModel {
+    pub year: u32,
+    pub location: String,
+}
+
+/// Expects the ids in ascending order, as the TOC yields them.
+fn to_ranges(sub_page_ids: impl Iterator<Item = i64>) -> Vec<SubPagesRangeHttpModel> {
+    let mut result: Vec<SubPagesRangeHttpModel> = Vec::new();
+
+    for sub_page_id in sub_page_ids {
+        if let Some(last) = result.last_mut() {
+            if last.to + 1 == sub_page_id {
+                last.to = sub_page_id;
+                continue;
+            }
+        }
+
+        result.push(SubPagesRangeHttpModel {
+            from: sub_page_id,
+            to: sub_page_id,
+        });
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_ranges;
+
+    #[test]
+    fn consecutive_sub_pages_collapse_into_one_range() {
+        let ranges = to_ranges(vec![10, 11, 12, 15, 17, 18].into_iter());
+
+        let ranges: Vec<(i64, i64)> = ranges.iter().map(|itm| (itm.from, itm.to)).collect();
+
+        assert_eq!(vec![(10, 12), (15, 15), (17, 18)], ranges);
+        assert!(to_ranges(Vec::new().into_iter()).is_empty());
+    }
+}
@@ -1,93 +1,77 @@
-// TODO: HTTP DELETE /api/Topic. See `TODO.md`.
-// Should: validate the secret key, parse `delete_after`, and call the
-// soft-delete operation that schedules the topic for GC.
-//
-// use std::sync::Arc;
-//
-// use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
-//
-// use crate::app::AppContext;
-//
-// use super::contracts::*;
-//
-// #[my_http_server::macros::http_route(
-//     method: "DELETE",
-//     route: "/api/Topic",
-//     input_data: "DeleteTopicHttpContract",
-//     description: "Deletes Topic",
-//     summary: "Delete Topic",
-//     controller: "Topic",
-//     result:[
-//         {status_code: 202, description: "Topic is deleted"},
-//         {status_code: 404, description: "Topic not found"},
-//     ]
-// )]
-// pub struct DeleteTopicAction {
-//     app: Arc<AppContext>,
-// }
-//
-// impl DeleteTopicAction {
-//     pub fn new(app: Arc<AppContext>) -> Self {
-//         Self { app }
-//     }
-// }
-//
-// async fn handle_request(
-//     action: &DeleteTopicAction,
-//     input_data: DeleteTopicHttpContract,
-//     _ctx: &HttpContext,
-// ) -> Result<HttpOkResult, HttpFailResult> {
-//     if action.app.settings.delete_topic_secret_key.as_str() != input_data.api_key {
-//         return HttpOutput::as_unauthorized(Some("Invalid Secret Key")).into_err(false, false);
-//     }
-//
-//     // Parse delete_after (RFC3339). Default: now + 24h to give grace period.
-//     let delete_after = {
-//         let parsed: Result<rust_extensions::date_time::DateTimeAsMicroseconds, HttpFailResult> =
-//             match input_data.delete_after {
-//                 None => {
-//                     let mut v = rust_extensions::date_time::DateTimeAsMicroseconds::now();
-//                     v.add_days(1);
-//                     Ok(v)
-//                 }
-//                 Some(ref delete_after) if delete_after.is_empty() => {
-//                     let mut v = rust_extensions::date_time::DateTimeAsMicroseconds::now();
-//                     v.add_days(1);
-//                     Ok(v)
-//                 }
-//                 Some(delete_after) => {
-//                     if let Some(parsed) =
-//                         rust_extensions::date_time::DateTimeAsMicroseconds::parse_iso_string(
-//                             delete_after.as_str(),
-//                         )
-//                     {
-//                         Ok(parsed)
-//                     } else {
-//                         Err(HttpFailResult::as_validation_error(format!(
-//                             "Invalid deleteAfter: expected RFC3339, got '{}'",
-//                             delete_after
-//                         )))
-//                     }
-//                 }
-//             };
-//
-//         match parsed {
-//             Ok(val) => val,
-//             Err(err) => return Err(err),
-//         }
-//     };
-//
-//     match crate::operations::delete_topic(
-//         action.app.as_ref(),
-//         input_data.topic_id.as_str(),
-//         delete_after,
-//     )
-//     .await
-//     {
-//         Ok(_) => HttpOutput::Empty.into_ok_result(true).into(),
-//         Err(crate::operations::OperationError::TopicNotFound(topic)) => Err(
-//             HttpFailResult::as_not_found(format!("Topic {} not found", topic), true),
-//         ),
-//         Err(err) => Err(HttpFailResult::as_fatal_error(format!("{:?}", err))),
-//     }
-// }
+use std::{sync::Arc, time::Duration};
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+/// Without `deleteAfter` the data is kept for a day, time enough to notice the wrong topic went.
+const DEFAULT_KEEP_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[my_http_server::macros::http_route(
+    method: "DELETE",
+    route: "/api/Topic",
+    input_data: "DeleteTopicHttpContract",
+    description: "Soft deletes a topic: its data is kept until deleteAfter, RestoreTopic brings it back until then",
+    summary: "Delete Topic",
+    controller: "Topic",
+    result:[
+        {status_code: 202, description: "Topic is deleted"},
+        {status_code: 404, description: "Topic not found"},
+    ]
+)]
+pub struct DeleteTopicAction {
+    app: Arc<AppContext>,
+}
+
+impl DeleteTopicAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &DeleteTopicAction,
+    input_data: DeleteTopicHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    if action.app.settings.delete_topic_secret_key.as_str() != input_data.api_key {
+        return Err(HttpFailResult::as_unauthorized(Some(
+            "Invalid Secret Key".to_string(),
+        )));
+    }
+
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let keep_for = match input_data.delete_after.as_deref() {
+        None | Some("") => DEFAULT_KEEP_FOR,
+        Some(delete_after) => {
+            let Some(delete_after) = DateTimeAsMicroseconds::parse_iso_string(delete_after) else {
+                return Err(HttpFailResult::as_validation_error(format!(
+                    "Invalid deleteAfter: expected RFC3339, got '{}'",
+                    delete_after
+                )));
+            };
+
+            // A moment in the past is "as soon as the GC gets to it".
+            let keep_for_micros =
+                delete_after.unix_microseconds - DateTimeAsMicroseconds::now().unix_microseconds;
+
+            Duration::from_micros(keep_for_micros.max(0) as u64)
+        }
+    };
+
+    crate::operations::soft_delete_topic(
+        action.app.as_ref(),
+        TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str()),
+        keep_for,
+    )
+    .await?;
+
+    HttpOutput::Empty.into_ok_result(true).into()
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpOutput};
+
+use crate::app::AppContext;
+use crate::http::controllers::read_controller::{check_topic_id, parse_namespace};
+use crate::topic_key::TopicKeyRef;
+
+use super::contracts::*;
+
+/// What is stored for one topic, wherever it sits. The cold tier is probed key by key, so this
+/// is for looking at a topic, not for polling.
+#[my_http_server::macros::http_route(
+    method: "GET",
+    route: "/api/Topic/{namespace}/{topic}",
+    input_data: "GetTopicDetailsHttpContract",
+    description: "Archive files, year indexes and the stored message id range of a topic",
+    summary: "Topic details",
+    controller: "Topic",
+    result:[
+        {status_code: 200, description: "Topic details", model:"TopicDetailsHttpModel"},
+        {status_code: 404, description: "Topic not found"},
+    ]
+)]
+pub struct GetTopicDetailsAction {
+    app: Arc<AppContext>,
+}
+
+impl GetTopicDetailsAction {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+async fn handle_request(
+    action: &GetTopicDetailsAction,
+    input_data: GetTopicDetailsHttpContract,
+    _ctx: &mut HttpContext,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let namespace = parse_namespace(input_data.namespace.as_str())?;
+    check_topic_id(input_data.topic_id.as_str())?;
+
+    let topic_key = TopicKeyRef::new(namespace.as_str(), input_data.topic_id.as_str());
+
+    let inventory = crate::operations::get_topic_inventory(action.app.as_ref(), topic_key).await?;
+
+    let model = TopicDetailsHttpModel::new(topic_key, &inventory);
+
+    HttpOutput::as_json(model).into_ok_result(true).into()
+}
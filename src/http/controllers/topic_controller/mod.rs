@@ -1,6 +1,7 @@
 mod contracts;
-// TODO: re-enable with soft-delete + GC (see TODO.md)
-// mod delete_topic_action;
-// pub use delete_topic_action::*;
-// mod get_deleted_action;
-// pub use get_deleted_action::*;
+mod get_topic_details_action;
+pub use get_topic_details_action::*;
+mod delete_topic_action;
+pub use delete_topic_action::*;
+mod get_deleted_action;
+pub use get_deleted_action::*;
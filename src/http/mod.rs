@@ -1,3 +1,7 @@
+mod auth_middleware;
 mod builder;
 pub mod controllers;
 pub mod start_up;
+mod streamed_body;
+pub use streamed_body::*;
+mod tls_front;
@@ -7,8 +7,26 @@ use my_http_server::{
 
 use crate::app::AppContext;
 
+use super::tls_front::{TlsFront, TlsFrontMiddleware};
+
 pub fn setup_server(app: &Arc<AppContext>, port: u16) -> HttpConnectionsCounter {
-    let mut http_server = MyHttpServer::new(SocketAddr::from(([0, 0, 0, 0], port)));
+    let tls_front = app.tls.as_ref().map(|tls| {
+        let backend_port = app
+            .settings
+            .tls
+            .as_ref()
+            .and_then(|settings| settings.http_backend_port);
+
+        match TlsFront::start(port, backend_port, tls.clone()) {
+            Ok(tls_front) => tls_front,
+            Err(err) => panic!("Can not start the http TLS front. {}", err),
+        }
+    });
+
+    let mut http_server = match tls_front.as_ref() {
+        Some(tls_front) => MyHttpServer::new(tls_front.get_backend_addr()),
+        None => MyHttpServer::new(SocketAddr::from(([0, 0, 0, 0], port))),
+    };
 
     let controllers = Arc::new(super::builder::build(app));
 
@@ -20,6 +38,15 @@ pub fn setup_server(app: &Arc<AppContext>, port: u16) -> HttpConnectionsCounter
 
     let swagger_middleware = Arc::new(swagger_middleware);
 
+    // Before the token check: a connection that went round the TLS is not asked for a token.
+    if let Some(tls_front) = tls_front {
+        http_server.add_middleware(Arc::new(TlsFrontMiddleware::new(tls_front)));
+    }
+
+    // Then the token check, so no controller runs for a caller it has turned away.
+    http_server.add_middleware(Arc::new(super::auth_middleware::AuthMiddleware::new(
+        app.clone(),
+    )));
     http_server.add_middleware(swagger_middleware);
     http_server.add_middleware(controllers);
 
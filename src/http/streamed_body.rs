@@ -0,0 +1,179 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Frame;
+use my_http_server::HttpOutput;
+use tokio::{io::AsyncWrite, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::PollSender;
+
+/// Chunks of up to this size go out one at a time; the body holds a few of them at most, so an
+/// export of any size takes the same memory.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// How many chunks may wait for a slow client before the writer waits too.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+type BodyFrame = Result<Frame<Bytes>, String>;
+
+/// The writing end of a response body that is sent chunked while it is being written - an export
+/// of several GB goes out without the whole of it ever being in memory. The HTTP server only takes
+/// a body as one buffer, so this hands it a raw response over a channel instead.
+///
+/// A client that goes away drops the receiving end; the next write then fails and the export
+/// stops. [`Self::fail`] cuts the response short so the client sees an incomplete transfer rather
+/// than a body that ends as if it were whole.
+pub struct StreamedBodyWriter {
+    sender: PollSender<BodyFrame>,
+    buffer: Vec<u8>,
+}
+
+impl StreamedBodyWriter {
+    /// The writer, and the response to answer the request with right away - the status and the
+    /// headers go out at once, the body as it is written.
+    pub fn new(content_type: &str) -> (Self, HttpOutput) {
+        let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+
+        let body = StreamBody::new(ReceiverStream::new(receiver)).boxed();
+
+        let response = hyper::Response::builder()
+            .status(200)
+            .header("Content-Type", content_type)
+            .body(body)
+            .unwrap();
+
+        let writer = Self {
+            sender: PollSender::new(sender),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        };
+
+        (writer, HttpOutput::Raw(response))
+    }
+
+    /// Aborts the response with `err` - whatever was written and not sent yet is dropped.
+    pub async fn fail(self, err: String) {
+        if let Some(sender) = self.sender.get_ref().cloned() {
+            let _ = sender.send(Err(err)).await;
+        }
+    }
+
+    fn poll_send_buffer(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if self.buffer.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        ready!(self.sender.poll_reserve(cx)).map_err(|_| client_is_gone())?;
+
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+
+        self.sender
+            .send_item(Ok(Frame::data(Bytes::from(chunk))))
+            .map_err(|_| client_is_gone())?;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for StreamedBodyWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let writer = self.get_mut();
+
+        if writer.buffer.len() >= CHUNK_SIZE {
+            ready!(writer.poll_send_buffer(cx))?;
+        }
+
+        // Never past a chunk: a sub page encoded in one piece is taken a chunk at a time.
+        let taken = buf.len().min(CHUNK_SIZE - writer.buffer.len());
+        writer.buffer.extend_from_slice(&buf[..taken]);
+
+        Poll::Ready(Ok(taken))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().poll_send_buffer(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let writer = self.get_mut();
+        ready!(writer.poll_send_buffer(cx))?;
+        writer.sender.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn client_is_gone() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "The client is gone".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+    use my_http_server::HttpOutput;
+    use tokio::io::AsyncWriteExt;
+
+    use super::{StreamedBodyWriter, CHUNK_SIZE};
+
+    #[tokio::test]
+    async fn sends_what_is_written_in_chunks() {
+        let (mut writer, output) = StreamedBodyWriter::new("application/octet-stream");
+
+        let HttpOutput::Raw(response) = output else {
+            panic!("Expected a raw response");
+        };
+
+        let content: Vec<u8> = (0..CHUNK_SIZE * 3 + 10).map(|i| i as u8).collect();
+
+        let written = content.clone();
+        tokio::spawn(async move {
+            writer.write_all(written.as_slice()).await.unwrap();
+            writer.shutdown().await.unwrap();
+        });
+
+        let mut body = response.into_body();
+        let mut received = Vec::new();
+        let mut chunks = 0;
+
+        while let Some(frame) = body.frame().await {
+            let chunk = frame.unwrap().into_data().unwrap();
+            assert!(chunk.len() <= CHUNK_SIZE);
+            received.extend_from_slice(&chunk);
+            chunks += 1;
+        }
+
+        assert_eq!(content, received);
+        assert_eq!(4, chunks);
+    }
+
+    #[tokio::test]
+    async fn a_failed_writer_cuts_the_body_short() {
+        let (mut writer, output) = StreamedBodyWriter::new("application/octet-stream");
+
+        let HttpOutput::Raw(response) = output else {
+            panic!("Expected a raw response");
+        };
+
+        tokio::spawn(async move {
+            writer.write_all(b"first").await.unwrap();
+            writer.flush().await.unwrap();
+            writer.fail("Disk is gone".to_string()).await;
+        });
+
+        let mut body = response.into_body();
+
+        let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        assert_eq!(b"first".as_slice(), first.as_ref());
+
+        assert_eq!("Disk is gone", body.frame().await.unwrap().unwrap_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use my_http_server::{HttpContext, HttpFailResult, HttpOkResult, HttpServerMiddleware};
+use my_logger::LogEventCtx;
+use parking_lot::Mutex;
+
+use crate::tls::TlsCertificates;
+
+/// Decrypts on the public port and passes the bytes on to the HTTP server, which listens on a
+/// loopback port. The HTTP server binds its listener itself and has no TLS of its own; doing it
+/// here keeps the certificate hot reload in one place for both listeners.
+///
+/// Loopback is still reachable by anything else on the host, so the front keeps track of the
+/// connections it opened to the backend - `TlsFrontMiddleware` refuses every other one, and gives
+/// the ones it lets through the address of the client behind them.
+pub struct TlsFront {
+    backend_addr: SocketAddr,
+    /// Keyed by the front's own end of each backend connection - which is what the HTTP server
+    /// sees as the peer - with the client the connection carries.
+    connections: Mutex<HashMap<SocketAddr, SocketAddr>>,
+}
+
+impl TlsFront {
+    /// Binds the public port right away, so a port that is taken fails the start instead of a
+    /// background task. Has to be called within the runtime.
+    pub fn start(
+        port: u16,
+        backend_port: Option<u16>,
+        tls: Arc<TlsCertificates>,
+    ) -> Result<Arc<Self>, String> {
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+        let listener = std::net::TcpListener::bind(addr)
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                tokio::net::TcpListener::from_std(listener)
+            })
+            .map_err(|err| format!("Can not bind http endpoint {}. {}", addr, err))?;
+
+        let backend_port = match backend_port {
+            Some(backend_port) => backend_port,
+            None => pick_free_port()?,
+        };
+
+        let front = Arc::new(Self {
+            backend_addr: SocketAddr::from(([127, 0, 0, 1], backend_port)),
+            connections: Mutex::new(HashMap::new()),
+        });
+
+        println!(
+            "Listening to {:?} as http endpoint with TLS, served from {:?}",
+            addr, front.backend_addr
+        );
+
+        tokio::spawn(serve(front.clone(), listener, tls));
+
+        Ok(front)
+    }
+
+    pub fn get_backend_addr(&self) -> SocketAddr {
+        self.backend_addr
+    }
+
+    /// `None` - the connection was not opened by the front.
+    fn get_client_addr(&self, backend_peer: &SocketAddr) -> Option<SocketAddr> {
+        self.connections.lock().get(backend_peer).copied()
+    }
+}
+
+async fn serve(front: Arc<TlsFront>, listener: tokio::net::TcpListener, tls: Arc<TlsCertificates>) {
+    let mut incoming = crate::tls::tls_incoming("HttpTls", listener, move || tls.get_http_config());
+
+    while let Some(tls_stream) = incoming.as_mut().recv().await {
+        let Ok(tls_stream) = tls_stream else {
+            continue;
+        };
+
+        tokio::spawn(pass_on(front.clone(), tls_stream));
+    }
+}
+
+async fn pass_on(
+    front: Arc<TlsFront>,
+    mut tls_stream: tokio_rustls::server::TlsStream<tokio::net::TcpStream>,
+) {
+    let Ok(client_addr) = tls_stream.get_ref().0.peer_addr() else {
+        return;
+    };
+
+    let mut backend = match tokio::net::TcpStream::connect(front.backend_addr).await {
+        Ok(backend) => backend,
+        Err(err) => {
+            my_logger::LOGGER.write_error(
+                "HttpTls",
+                format!("Can not connect to the http backend. {}", err),
+                LogEventCtx::new().add("addr", client_addr.to_string()),
+            );
+            return;
+        }
+    };
+
+    let Ok(local_addr) = backend.local_addr() else {
+        return;
+    };
+
+    // Before a single byte is passed on, so the backend never sees a request on a connection it
+    // can not tell apart from a stranger's.
+    front.connections.lock().insert(local_addr, client_addr);
+
+    let _ = tokio::io::copy_bidirectional(&mut tls_stream, &mut backend).await;
+
+    front.connections.lock().remove(&local_addr);
+}
+
+/// The HTTP server binds the address it is given itself, so the OS is asked for a free port here
+/// and it is handed over - free a moment ago, and on loopback next to never taken in between.
+fn pick_free_port() -> Result<u16, String> {
+    std::net::TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|err| format!("Can not find a free port for the http backend. {}", err))
+}
+
+/// Sits in front of everything else when TLS is on. A connection the front did not open came to
+/// the loopback port around it - without TLS, and past whatever the network allows - so it is
+/// refused. One it did open gets the client's address in place of the front's own.
+pub struct TlsFrontMiddleware {
+    front: Arc<TlsFront>,
+}
+
+impl TlsFrontMiddleware {
+    pub fn new(front: Arc<TlsFront>) -> Self {
+        Self { front }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for TlsFrontMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        match self.front.get_client_addr(&ctx.request.addr) {
+            Some(client_addr) => {
+                ctx.request.addr = client_addr;
+                None
+            }
+            None => Some(Err(HttpFailResult::as_forbidden(Some(
+                "The http endpoint is only served over TLS".to_string(),
+            )))),
+        }
+    }
+}
@@ -1,10 +1,13 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use my_service_bus::abstractions::MessageId;
 
-use crate::file_storage::FileStorage;
+use crate::file_storage::{FileStorage, FileStorageError};
 
-use super::{utils::MINUTE_INDEX_FILE_SIZE, MinuteWithinYear};
+use super::{
+    utils::{INDEX_STEP, MINUTE_INDEX_FILE_SIZE},
+    MinuteWithinYear,
+};
 
 /// One file per topic per year: a flat array of 527 040 slots, 8 bytes each, addressed at
 /// `minute * 8`. A slot holds the id of the first message of that minute, or zero when the
@@ -79,6 +82,114 @@ impl IndexByMinuteFile {
         Some(MessageId::new(result))
     }
 
+    /// The first minute at or after `minute` that saw traffic. The rest of the year is read in one
+    /// go rather than slot by slot - it is 4 MB at most, and a quiet topic can have months of empty
+    /// slots in a row.
+    ///
+    /// A read that fails is returned rather than taken for an empty index: this serves client
+    /// reads and the retention, and "nothing since then" is an answer both act on.
+    pub async fn find_first_message_id_from(
+        &self,
+        minute: MinuteWithinYear,
+    ) -> Result<Option<(MinuteWithinYear, MessageId)>, FileStorageError> {
+        let from = minute.get_position_in_file();
+
+        if from >= MINUTE_INDEX_FILE_SIZE {
+            return Ok(None);
+        }
+
+        let payload = self.file.read(from, MINUTE_INDEX_FILE_SIZE - from).await?;
+
+        for (no, slot) in payload.chunks_exact(INDEX_STEP).enumerate() {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(slot);
+
+            let result = i64::from_le_bytes(value);
+
+            if result != 0 {
+                return Ok(Some((
+                    MinuteWithinYear::new(minute.get_value() + no as u32),
+                    MessageId::new(result),
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The minute `message_id` was published in: the last one whose first message is not past
+    /// it. Ids only grow, so the minutes after it start past it as well. `None` when even the
+    /// first minute of the year starts past it - the message is from an earlier year.
+    pub async fn find_minute_of_message_id(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Option<MinuteWithinYear>, FileStorageError> {
+        let payload = self.file.read(0, MINUTE_INDEX_FILE_SIZE).await?;
+
+        let mut result = None;
+
+        for (no, slot) in payload.chunks_exact(INDEX_STEP).enumerate() {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(slot);
+
+            let first_id = i64::from_le_bytes(value);
+
+            if first_id == 0 {
+                continue;
+            }
+
+            if first_id > message_id.get_value() {
+                break;
+            }
+
+            result = Some(MinuteWithinYear::new(no as u32));
+        }
+
+        Ok(result)
+    }
+
+    /// Replaces every slot at once - one write of the whole file, so a slot that is not in
+    /// `message_ids` ends up empty.
+    pub async fn rewrite(&self, message_ids: &BTreeMap<MinuteWithinYear, MessageId>) {
+        let mut payload = vec![0u8; MINUTE_INDEX_FILE_SIZE];
+
+        for (minute, message_id) in message_ids {
+            let position = minute.get_position_in_file();
+            payload[position..position + INDEX_STEP]
+                .copy_from_slice(message_id.get_value().to_le_bytes().as_slice());
+        }
+
+        self.file
+            .write(0, payload.as_slice())
+            .await
+            .expect("Can not rewrite the year index");
+    }
+
+    /// Every slot that is not empty - the whole file in one read, the other way round from
+    /// [`Self::rewrite`].
+    pub async fn read_all(&self) -> BTreeMap<MinuteWithinYear, MessageId> {
+        let payload = self
+            .file
+            .read(0, MINUTE_INDEX_FILE_SIZE)
+            .await
+            .expect("Can not read the year index");
+
+        let mut result = BTreeMap::new();
+
+        for (no, slot) in payload.chunks_exact(INDEX_STEP).enumerate() {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(slot);
+
+            let message_id = i64::from_le_bytes(value);
+
+            if message_id != 0 {
+                result.insert(MinuteWithinYear::new(no as u32), MessageId::new(message_id));
+            }
+        }
+
+        result
+    }
+
     #[cfg(test)]
     pub fn get_file(&self) -> &FileStorage {
         &self.file
@@ -153,6 +264,97 @@ mod tests {
         let _ = std::fs::remove_file(&path);
     }
 
+    #[tokio::test]
+    async fn rewrite_replaces_every_slot() {
+        let path = temp_path("rewrite");
+        let storage = IndexByMinuteFile::open_or_create(&path).await;
+
+        storage
+            .write_message_id_to_minute_index(MinuteWithinYear::new(3), MessageId::new(999))
+            .await;
+
+        let mut message_ids = BTreeMap::new();
+        message_ids.insert(MinuteWithinYear::new(7), MessageId::new(70));
+
+        storage.rewrite(&message_ids).await;
+
+        assert_eq!(message_ids, storage.read_all().await);
+
+        assert!(storage
+            .read_message_id_from_minute_index(MinuteWithinYear::new(3))
+            .await
+            .is_none());
+        assert_eq!(
+            Some(MessageId::new(70)),
+            storage
+                .read_message_id_from_minute_index(MinuteWithinYear::new(7))
+                .await
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn find_first_message_id_from_skips_empty_minutes() {
+        let path = temp_path("find_first");
+        let storage = IndexByMinuteFile::open_or_create(&path).await;
+
+        storage
+            .write_message_id_to_minute_index(MinuteWithinYear::new(10), MessageId::new(100))
+            .await;
+        storage
+            .write_message_id_to_minute_index(MinuteWithinYear::new(500), MessageId::new(200))
+            .await;
+
+        let (minute, message_id) = storage
+            .find_first_message_id_from(MinuteWithinYear::new(11))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(minute.get_value(), 500);
+        assert_eq!(message_id, MessageId::new(200));
+
+        assert!(storage
+            .find_first_message_id_from(MinuteWithinYear::new(501))
+            .await
+            .unwrap()
+            .is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn finds_the_minute_a_message_was_published_in() {
+        let path = temp_path("minute_of");
+        let storage = IndexByMinuteFile::open_or_create(&path).await;
+
+        storage
+            .write_message_id_to_minute_index(MinuteWithinYear::new(10), MessageId::new(100))
+            .await;
+        storage
+            .write_message_id_to_minute_index(MinuteWithinYear::new(500), MessageId::new(200))
+            .await;
+
+        async fn minute_of(storage: &IndexByMinuteFile, message_id: i64) -> Option<u32> {
+            storage
+                .find_minute_of_message_id(MessageId::new(message_id))
+                .await
+                .unwrap()
+                .map(|itm| itm.get_value())
+        }
+
+        assert_eq!(Some(10), minute_of(&storage, 100).await);
+        assert_eq!(Some(10), minute_of(&storage, 199).await);
+        assert_eq!(Some(500), minute_of(&storage, 200).await);
+        assert_eq!(Some(500), minute_of(&storage, 1000).await);
+
+        // From the year before
+        assert_eq!(None, minute_of(&storage, 99).await);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[tokio::test]
     async fn open_if_exists_returns_none_for_a_missing_file() {
         let path = temp_path("missing");
@@ -47,6 +47,19 @@ impl UpdateQueue {
         Some(result.message_id)
     }
 
+    /// The earliest queued minute at or after `minute`.
+    pub async fn get_first_from(
+        &self,
+        minute_within_year: MinuteWithinYear,
+    ) -> Option<UpdateQueueItem> {
+        let read_access = self.data.lock();
+
+        read_access
+            .iter()
+            .find(|itm| itm.minute_within_year >= minute_within_year)
+            .cloned()
+    }
+
     pub async fn get_items_ready_to_be_gc(&self) -> Option<Vec<MinuteWithinYear>> {
         let read_access = self.data.lock();
         if read_access.len() <= 1 {
@@ -100,6 +100,34 @@ impl IndexByMinuteUtils {
 
         (MinuteWithinYear::new(minute), (d.year() as u32).into())
     }
+
+    /// The other way round: when `minute` of `year` starts. The index gives February 29 days in
+    /// every year, so its last day has no moment in a year that has 28 - `None` for it.
+    pub fn get_minute_start(
+        &self,
+        year: Year,
+        minute: MinuteWithinYear,
+    ) -> Option<DateTimeAsMicroseconds> {
+        let minute = minute.get_value();
+
+        let month = (1..=12)
+            .rev()
+            .find(|month| self.day_no_in_year[*month] - 1 <= minute)?;
+
+        let minute_in_month = minute - (self.day_no_in_year[month] - 1);
+        let minute_in_day = minute_in_month % MINUTES_PER_DAY;
+
+        let date_time = chrono::NaiveDate::from_ymd_opt(
+            year.get_value() as i32,
+            month as u32,
+            minute_in_month / MINUTES_PER_DAY + 1,
+        )?
+        .and_hms_opt(minute_in_day / 60, minute_in_day % 60, 0)?;
+
+        Some(DateTimeAsMicroseconds::new(
+            date_time.and_utc().timestamp_micros(),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -149,4 +177,33 @@ mod tests {
 
         assert_eq!(215090, minute.get_value());
     }
+
+    #[test]
+    fn the_minute_start_is_the_other_way_round() {
+        let utils = IndexByMinuteUtils::new();
+
+        for date_time in [
+            "2021-01-01T00:00:00",
+            "2021-05-29T08:50:00",
+            "2024-02-29T23:59:00",
+            "2023-12-31T23:59:00",
+        ] {
+            let date_time = DateTimeAsMicroseconds::parse_iso_string(date_time).unwrap();
+
+            let (minute, year) = utils.get_minute_within_the_year(date_time);
+
+            assert_eq!(
+                Some(date_time.unix_microseconds),
+                utils
+                    .get_minute_start(year, minute)
+                    .map(|itm| itm.unix_microseconds)
+            );
+        }
+
+        // February 29 of a year that has no such day
+        let (minute, _) = utils.get_minute_within_the_year(
+            DateTimeAsMicroseconds::parse_iso_string("2024-02-29T00:00:00").unwrap(),
+        );
+        assert!(utils.get_minute_start(2023u32.into(), minute).is_none());
+    }
 }
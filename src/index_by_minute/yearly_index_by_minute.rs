@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use my_service_bus::abstractions::MessageId;
 use rust_extensions::date_time::AtomicDateTimeAsMicroseconds;
 
+use crate::file_storage::FileStorageError;
+
 use super::{IndexByMinuteFile, MinuteWithinYear, UpdateQueue};
 
 pub struct YearlyIndexByMinute {
@@ -56,6 +58,55 @@ impl YearlyIndexByMinute {
             .await
     }
 
+    /// The first message at or after `minute_within_year`, together with the minute it was
+    /// indexed under. Looks at what is still queued as well as at the file: the queue holds the
+    /// most recent minutes, which are exactly the ones a "since" query usually lands on.
+    pub async fn find_first_message_id_from(
+        &self,
+        minute_within_year: MinuteWithinYear,
+    ) -> Result<Option<(MinuteWithinYear, MessageId)>, FileStorageError> {
+        let queued = self.update_queue.get_first_from(minute_within_year).await;
+
+        let stored = self
+            .file
+            .find_first_message_id_from(minute_within_year)
+            .await?;
+
+        let result = match (queued, stored) {
+            (Some(queued), Some(stored)) => {
+                if queued.minute_within_year < stored.0 {
+                    Some((queued.minute_within_year, queued.message_id))
+                } else {
+                    Some(stored)
+                }
+            }
+            (Some(queued), None) => Some((queued.minute_within_year, queued.message_id)),
+            (None, stored) => stored,
+        };
+
+        Ok(result)
+    }
+
+    /// See [`IndexByMinuteFile::find_minute_of_message_id`]. Only the file: this is for the year
+    /// indexes of a topic that is not loaded, which have nothing queued.
+    pub async fn find_minute_of_message_id(
+        &self,
+        message_id: MessageId,
+    ) -> Result<Option<MinuteWithinYear>, FileStorageError> {
+        self.file.find_minute_of_message_id(message_id).await
+    }
+
+    /// Replaces what is stored with `message_ids`. Whatever is still queued stays queued: it came
+    /// in after the messages were read, and a queued id only ever fills a slot that is empty.
+    pub async fn rewrite(&self, message_ids: &BTreeMap<MinuteWithinYear, MessageId>) {
+        self.file.rewrite(message_ids).await;
+    }
+
+    /// What is in the file - minutes still queued are not, they are the newest ones.
+    pub async fn get_stored(&self) -> BTreeMap<MinuteWithinYear, MessageId> {
+        self.file.read_all().await
+    }
+
     pub async fn flush_to_storage(&self) {
         let items_to_write = self.update_queue.get_items_ready_to_be_gc().await;
 
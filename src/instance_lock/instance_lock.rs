@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    time::{Duration, Instant},
+};
+
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+use serde::{Deserialize, Serialize};
+
+use crate::app::storage_layout;
+
+/// How often a waiting instance looks at the lock again.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Who holds the data folder - written into the lock file once it is taken, so the instance that
+/// is turned away can say who by.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InstanceLockHolderModel {
+    pub pid: u32,
+    pub host: String,
+    pub started: String,
+}
+
+#[derive(Debug)]
+pub enum InstanceLockError {
+    /// Another process holds it. `None` when what it wrote could not be read back.
+    Held(Option<InstanceLockHolderModel>),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for InstanceLockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Held(Some(holder)) => write!(
+                f,
+                "it is held by pid {} on {}, started at {}",
+                holder.pid, holder.host, holder.started
+            ),
+            Self::Held(None) => write!(f, "it is held by another process"),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// An exclusive advisory lock (`flock` on Unix) on `{data}/.lock`, held for as long as the
+/// process lives. Two processes appending to the same archives and `active` files corrupt both -
+/// a rolling deploy that overlaps, or a replica pointed at the wrong volume, is all it takes.
+///
+/// The lock goes with the file descriptor, so a process that dies for any reason - `kill -9`
+/// included - releases it, and a lock file left behind means nothing on its own. It is advisory:
+/// it stops another instance of this service, not a person with `cp`.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Keeps trying for up to `wait` - the old instance of a handover may still be flushing its
+    /// tails - and gives up with the holder of the lock as of the last attempt.
+    pub async fn acquire(data_folder: &str, wait: Duration) -> Result<Self, InstanceLockError> {
+        let started = Instant::now();
+        let mut reported = false;
+
+        loop {
+            match Self::try_acquire(data_folder) {
+                Err(InstanceLockError::Held(holder)) if started.elapsed() < wait => {
+                    if !reported {
+                        println!(
+                            "The data folder {} is locked - {}. Waiting up to {:?} for it",
+                            data_folder,
+                            InstanceLockError::Held(holder),
+                            wait
+                        );
+                        reported = true;
+                    }
+
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn try_acquire(data_folder: &str) -> Result<Self, InstanceLockError> {
+        std::fs::create_dir_all(data_folder).map_err(InstanceLockError::Io)?;
+
+        let path = storage_layout::get_instance_lock_file(data_folder);
+
+        // Not truncated on open: until the lock is ours, what is in there belongs to the holder.
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_path())
+            .map_err(InstanceLockError::Io)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(std::fs::TryLockError::WouldBlock) => {
+                return Err(InstanceLockError::Held(read_holder(&path)));
+            }
+            Err(std::fs::TryLockError::Error(err)) => return Err(InstanceLockError::Io(err)),
+        }
+
+        let holder = InstanceLockHolderModel {
+            pid: std::process::id(),
+            host: sysinfo::System::host_name().unwrap_or_default(),
+            started: DateTimeAsMicroseconds::now().to_rfc3339(),
+        };
+
+        let content = serde_yaml::to_string(&holder).unwrap();
+
+        file.set_len(0).map_err(InstanceLockError::Io)?;
+        file.seek(SeekFrom::Start(0))
+            .map_err(InstanceLockError::Io)?;
+        file.write_all(content.as_bytes())
+            .map_err(InstanceLockError::Io)?;
+        file.sync_all().map_err(InstanceLockError::Io)?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+fn read_holder(path: &std::path::Path) -> Option<InstanceLockHolderModel> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_yaml::from_str(content.as_str()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{InstanceLock, InstanceLockError};
+
+    #[tokio::test]
+    async fn a_second_instance_is_turned_away_until_the_first_one_is_gone() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-instance-lock");
+        let _ = std::fs::remove_dir_all(&root);
+        let data_folder = root.to_str().unwrap();
+
+        let first = InstanceLock::try_acquire(data_folder).unwrap();
+
+        // A lock belongs to the open file, not to the process, so this is what a second process
+        // would see.
+        match InstanceLock::acquire(data_folder, Duration::from_millis(600)).await {
+            Err(InstanceLockError::Held(Some(holder))) => {
+                assert_eq!(std::process::id(), holder.pid);
+            }
+            Err(err) => panic!("Expected the holder, got {}", err),
+            Ok(_) => panic!("The data folder was locked twice"),
+        }
+
+        drop(first);
+
+        InstanceLock::try_acquire(data_folder).unwrap();
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,2 @@
+mod instance_lock;
+pub use instance_lock::*;
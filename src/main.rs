@@ -1,20 +1,31 @@
 use std::{sync::Arc, time::Duration};
 mod app;
 
+mod auth;
+
 mod archive_storage;
+mod bundle;
 mod cold_storage;
+mod consumer_lag;
+mod disk_space;
 
 mod file_storage;
 mod grpc;
 mod http;
 mod index_by_minute;
+mod instance_lock;
 mod message_pages;
+mod namespace_deletions;
 mod operations;
+mod retention;
 
 mod settings;
+mod storage_usage;
 mod timers;
+mod tls;
 mod topic_data;
 mod topic_key;
+mod topic_moves;
 mod topics_snapshot;
 mod utils;
 
@@ -23,25 +34,66 @@ mod typing;
 
 use crate::{
     app::AppContext,
+    instance_lock::InstanceLock,
     settings::SettingsModel,
     timers::{
-        cold_storage_uploader::ColdStorageUploaderTimer, metrics_updater::MetricsUpdater,
-        pages_gc::PagesGcTimer, save_min_index::SaveMinIndexTimer,
-        topics_snapshot_saver::TopicsSnapshotSaverTimer,
+        cold_storage_uploader::ColdStorageUploaderTimer,
+        consumer_lag_updater::ConsumerLagUpdaterTimer, deleted_topics_gc::DeletedTopicsGcTimer,
+        disk_watchdog::DiskWatchdogTimer, metrics_updater::MetricsUpdater, pages_gc::PagesGcTimer,
+        save_min_index::SaveMinIndexTimer, storage_usage::StorageUsageTimer,
+        tls_reload::TlsReloadTimer, topics_snapshot_saver::TopicsSnapshotSaverTimer,
     },
+    topic_key::DEFAULT_NAMESPACE,
 };
 #[allow(non_snake_case)]
 pub mod persistence_grpc {
     tonic::include_proto!("persistence");
 }
 
+#[allow(non_snake_case)]
+pub mod persistence_admin_grpc {
+    tonic::include_proto!("persistence_admin");
+}
+
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 #[tokio::main]
 async fn main() {
+    let offline_command = get_offline_command();
+
     let settings = SettingsModel::read().await;
 
+    // Before anything touches the data folder - the legacy migration included. Held to the end of
+    // `main`, past the tails written on shutdown.
+    let _instance_lock = InstanceLock::acquire(
+        settings.data.as_str(),
+        Duration::from_secs(settings.instance_lock.wait_sec),
+    )
+    .await
+    .unwrap_or_else(|err| {
+        panic!(
+            "Can not start: the data folder {} can not be locked - {}",
+            settings.data, err
+        )
+    });
+
+    // Only reads, so it gets a context of its own: no migration, no bucket checks. A folder the
+    // service never brought up to the current layout is refused rather than read half-way.
+    if let Some(offline_command) = offline_command {
+        if settings.legacy.is_some() || !operations::is_layout_current(settings.data.as_str()) {
+            panic!(
+                "Can not run {} on {}: the data folder is not fully migrated. Start the service on it, let the legacy migration finish and drop the legacy section first.",
+                offline_command.get_name(),
+                settings.data
+            );
+        }
+
+        let app = AppContext::new_offline(settings).await;
+        run_offline_command(&app, offline_command).await;
+        return;
+    }
+
     // Two phases, both before anything reads the snapshot or opens a topic. The first only brings
     // over what is needed to serve; the rest follows in the background once the service is up.
     let legacy_migration = settings
@@ -71,6 +123,20 @@ async fn main() {
         Arc::new(SaveMinIndexTimer::new(app.clone())),
     );
 
+    timer_3s.register_timer(
+        "ConsumerLagUpdater",
+        Arc::new(ConsumerLagUpdaterTimer::new(app.clone())),
+    );
+
+    timer_3s.register_timer(
+        "DiskWatchdog",
+        Arc::new(DiskWatchdogTimer::new(app.clone())),
+    );
+
+    if app.tls.is_some() {
+        timer_3s.register_timer("TlsReload", Arc::new(TlsReloadTimer::new(app.clone())));
+    }
+
     timer_3s.start(app.app_states.clone(), my_logger::LOGGER.clone());
 
     let mut timer_persist_queues = MyTimer::new(Duration::from_secs(1));
@@ -79,13 +145,12 @@ async fn main() {
 
     timer_persist_queues.start(app.app_states.clone(), my_logger::LOGGER.clone());
 
-    // TODO: re-enable DeletedTopicsGc timer once soft-delete + GC flow is reimplemented (see TODO.md)
-    // let mut timer_30s = MyTimer::new(Duration::from_secs(30));
-    // timer_30s.register_timer(
-    //     "DeletedTopicsGc",
-    //     Arc::new(DeletedTopicsGcTimer::new(app.clone())),
-    // );
-    // timer_30s.start(app.app_states.clone(), my_logger::LOGGER.clone());
+    let mut timer_30s = MyTimer::new(Duration::from_secs(30));
+    timer_30s.register_timer(
+        "DeletedTopicsGc",
+        Arc::new(DeletedTopicsGcTimer::new(app.clone())),
+    );
+    timer_30s.start(app.app_states.clone(), my_logger::LOGGER.clone());
 
     let http_connections_counter = crate::http::start_up::setup_server(&app, 7123);
 
@@ -101,13 +166,21 @@ async fn main() {
         "ColdStorageUploader",
         Arc::new(ColdStorageUploaderTimer::new(app.clone())),
     );
+    timer_60s.register_timer(
+        "StorageUsage",
+        Arc::new(StorageUsageTimer::new(app.clone())),
+    );
     timer_60s.start(app.app_states.clone(), my_logger::LOGGER.clone());
 
     if let Some(migration) = legacy_migration {
         let app = app.clone();
         tokio::spawn(async move {
+            // Everything legacy belongs to `default`.
             migration
-                .move_the_rest(app.get_cold_storage().map(|itm| itm.as_ref()))
+                .move_the_rest(
+                    app.get_cold_storage(DEFAULT_NAMESPACE)
+                        .map(|itm| itm.as_ref()),
+                )
                 .await;
         });
     }
@@ -127,3 +200,103 @@ async fn main() {
 
     crate::operations::before_shut_down::execute_before_shutdown(app).await;
 }
+
+/// `export-bundle <file> <namespace> [topic_id]` writes a bundle, `find-gaps <file> <namespace>
+/// [topic_id]` a JSON gap report - instead of starting the service. Meant for a stopped service:
+/// the data folder is locked for as long as it runs, the same as for the service itself, so it
+/// refuses to start next to a running one.
+struct OfflineCommand {
+    kind: OfflineCommandKind,
+    file: String,
+    namespace: String,
+    topic_id: Option<String>,
+}
+
+impl OfflineCommand {
+    fn get_name(&self) -> &'static str {
+        match self.kind {
+            OfflineCommandKind::ExportBundle => "export-bundle",
+            OfflineCommandKind::FindGaps => "find-gaps",
+        }
+    }
+}
+
+enum OfflineCommandKind {
+    ExportBundle,
+    FindGaps,
+}
+
+fn get_offline_command() -> Option<OfflineCommand> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let kind = match args.first().map(|itm| itm.as_str()) {
+        Some("export-bundle") => OfflineCommandKind::ExportBundle,
+        Some("find-gaps") => OfflineCommandKind::FindGaps,
+        _ => return None,
+    };
+
+    let command = OfflineCommand {
+        kind,
+        file: args.get(1).cloned().unwrap_or_default(),
+        namespace: args.get(2).cloned().unwrap_or_default(),
+        topic_id: args.get(3).cloned(),
+    };
+
+    if args.len() < 3 || args.len() > 4 {
+        panic!(
+            "Usage: {} <file> <namespace> [topic_id]",
+            command.get_name()
+        );
+    }
+
+    Some(command)
+}
+
+async fn run_offline_command(app: &AppContext, command: OfflineCommand) {
+    let namespace = topic_key::Namespace::parse(Some(command.namespace.as_str()))
+        .unwrap_or_else(|err| panic!("Invalid namespace. {}", err.as_string()));
+
+    if let Some(topic_id) = command.topic_id.as_ref() {
+        topic_key::validate_topic_id(topic_id)
+            .unwrap_or_else(|err| panic!("Invalid topic id. {}", err.as_string()));
+    }
+
+    match command.kind {
+        OfflineCommandKind::ExportBundle => {
+            run_offline_export(app, namespace.as_str(), &command).await
+        }
+        OfflineCommandKind::FindGaps => {
+            run_offline_find_gaps(app, namespace.as_str(), &command).await
+        }
+    }
+}
+
+async fn run_offline_export(app: &AppContext, namespace: &str, command: &OfflineCommand) {
+    let export = operations::prepare_bundle_export(app, namespace, command.topic_id.as_deref())
+        .await
+        .unwrap_or_else(|err| panic!("Can not export the bundle: {:?}", err));
+
+    let file = tokio::fs::File::create(command.file.as_str())
+        .await
+        .unwrap_or_else(|err| panic!("Can not create {}: {}", command.file, err));
+
+    let mut file = operations::write_bundle(app, export, file)
+        .await
+        .unwrap_or_else(|err| panic!("Can not export the bundle: {:?}", err));
+
+    tokio::io::AsyncWriteExt::flush(&mut file)
+        .await
+        .unwrap_or_else(|err| panic!("Can not write {}: {}", command.file, err));
+}
+
+async fn run_offline_find_gaps(app: &AppContext, namespace: &str, command: &OfflineCommand) {
+    let reports = operations::find_namespace_gaps(app, namespace, command.topic_id.as_deref())
+        .await
+        .unwrap_or_else(|err| panic!("Can not check for gaps: {:?}", err));
+
+    let content = serde_json::to_vec_pretty(&reports).unwrap();
+
+    tokio::fs::write(command.file.as_str(), content)
+        .await
+        .unwrap_or_else(|err| panic!("Can not write {}: {}", command.file, err));
+}
@@ -74,15 +74,21 @@ impl PagesList {
         result
     }
 
-    pub async fn gc(&self) -> Option<Arc<SubPage>> {
-        let mut pages_access = self.sub_pages.lock();
+    /// The oldest sub page, unless it is the open tail. It stays in the list - and keeps serving
+    /// reads - until [`Self::remove`] is called once it is archived, so a write that fails loses
+    /// nothing.
+    pub async fn get_sub_page_to_gc(&self) -> Option<Arc<SubPage>> {
+        let pages_access = self.sub_pages.lock();
 
         if pages_access.len() <= 1 {
             return None;
         }
 
-        let first_key = pages_access.first().unwrap().get_id().clone();
-        pages_access.remove(first_key.as_ref())
+        pages_access.first().cloned()
+    }
+
+    pub async fn remove(&self, sub_page_id: SubPageId) -> Option<Arc<SubPage>> {
+        self.sub_pages.lock().remove(sub_page_id.as_ref())
     }
 
     /// The newest sub page - the open tail. `None` when the topic holds nothing, which happens
@@ -23,4 +23,16 @@ impl SubPageReadCopy {
     pub fn get(&self, message_id: MessageId) -> Option<&Arc<MessageProtobufModel>> {
         self.messages.get(message_id.as_ref())
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<MessageProtobufModel>> {
+        self.messages.iter()
+    }
+
+    pub fn get_first_message_id(&self) -> Option<MessageId> {
+        self.messages.first().map(|itm| itm.get_message_id())
+    }
+
+    pub fn get_last_message_id(&self) -> Option<MessageId> {
+        self.messages.last().map(|itm| itm.get_message_id())
+    }
 }
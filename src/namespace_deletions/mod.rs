@@ -0,0 +1,2 @@
+mod namespace_deletions;
+pub use namespace_deletions::*;
@@ -0,0 +1,260 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::storage_layout, topics_snapshot::file_storage::read_to_string_if_exists,
+    utils::PersistedYaml,
+};
+
+/// Where the deletion of one namespace has got. Kept after it finishes, so the outcome can still
+/// be looked at.
+#[derive(Clone, Debug)]
+pub struct NamespaceDeletionProgress {
+    pub started: DateTimeAsMicroseconds,
+    /// `None` while it runs.
+    pub finished: Option<DateTimeAsMicroseconds>,
+    pub topics_total: usize,
+    pub topics_deleted: usize,
+    /// The topic being wiped right now.
+    pub current_topic: Option<String>,
+    /// Files that could not be deleted and are left orphaned - the details are in the log.
+    pub errors: usize,
+}
+
+impl NamespaceDeletionProgress {
+    fn new(started: DateTimeAsMicroseconds, topics_total: usize) -> Self {
+        Self {
+            started,
+            finished: None,
+            topics_total,
+            topics_deleted: 0,
+            current_topic: None,
+            errors: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct NamespaceDeletionsYamlModel {
+    #[serde(default)]
+    namespaces: Vec<String>,
+}
+
+/// The namespace deletions of this process, with the progress of each in memory. Which ones are
+/// running is also kept in `{data_folder}/.namespace-deletions.yaml`: a deletion cut short by a
+/// restart is found there on the next start, refuses writes from the first request on, and is
+/// resumed by `resume_namespace_deletions` - its progress starts over, counting what is left.
+pub struct NamespaceDeletions {
+    data_folder: String,
+    data: Mutex<BTreeMap<String, NamespaceDeletionProgress>>,
+    running: PersistedYaml<Vec<String>>,
+}
+
+impl NamespaceDeletions {
+    pub async fn load(data_folder: String) -> Self {
+        let path = storage_layout::get_namespace_deletions_file(data_folder.as_str());
+
+        let running = match read_to_string_if_exists(&path).await {
+            // Forgotten, a namespace half deleted would take writes again - into what is left of it.
+            Some(content) => match serde_yaml::from_str::<NamespaceDeletionsYamlModel>(&content) {
+                Ok(model) => model.namespaces,
+                Err(err) => panic!("Can not parse {:?}: {}", path, err),
+            },
+            None => Vec::new(),
+        };
+
+        let now = DateTimeAsMicroseconds::now();
+
+        let data = running
+            .iter()
+            .map(|namespace| (namespace.clone(), NamespaceDeletionProgress::new(now, 0)))
+            .collect();
+
+        Self {
+            data_folder,
+            data: Mutex::new(data),
+            running: PersistedYaml::new(running),
+        }
+    }
+
+    /// `Ok(false)` - this namespace is being deleted already. Recorded on disk before it returns.
+    pub async fn start(
+        &self,
+        namespace: &str,
+        topics_total: usize,
+        now: DateTimeAsMicroseconds,
+    ) -> Result<bool, String> {
+        {
+            let mut write_access = self.data.lock();
+
+            if let Some(existing) = write_access.get(namespace) {
+                if existing.is_running() {
+                    return Ok(false);
+                }
+            }
+
+            write_access.insert(
+                namespace.to_string(),
+                NamespaceDeletionProgress::new(now, topics_total),
+            );
+        }
+
+        let saved = self
+            .running
+            .modify(self.get_path(), |data| {
+                data.push(namespace.to_string());
+                Some(to_yaml_model(data))
+            })
+            .await;
+
+        if let Err(err) = saved {
+            // Not started after all: one the next start would not know about must not run.
+            self.data.lock().remove(namespace);
+            return Err(err);
+        }
+
+        Ok(true)
+    }
+
+    /// The deletions a previous run did not finish - running again from the start of this one.
+    pub fn get_interrupted(&self) -> Vec<String> {
+        self.running.read(|data| data.clone())
+    }
+
+    /// What is left of an interrupted deletion, once it is known.
+    pub fn resumed(&self, namespace: &str, topics_total: usize) {
+        if let Some(progress) = self.data.lock().get_mut(namespace) {
+            progress.topics_total = topics_total;
+        }
+    }
+
+    pub fn is_deleting(&self, namespace: &str) -> bool {
+        self.data
+            .lock()
+            .get(namespace)
+            .map(|itm| itm.is_running())
+            .unwrap_or(false)
+    }
+
+    pub fn get(&self, namespace: &str) -> Option<NamespaceDeletionProgress> {
+        self.data.lock().get(namespace).cloned()
+    }
+
+    pub fn topic_started(&self, namespace: &str, topic_id: &str) {
+        if let Some(progress) = self.data.lock().get_mut(namespace) {
+            progress.current_topic = Some(topic_id.to_string());
+        }
+    }
+
+    pub fn topic_deleted(&self, namespace: &str, errors: usize) {
+        if let Some(progress) = self.data.lock().get_mut(namespace) {
+            progress.current_topic = None;
+            progress.topics_deleted += 1;
+            progress.errors += errors;
+        }
+    }
+
+    /// `Err` - the record stays on disk, and the next start runs the deletion again. That finds
+    /// nothing left to delete, so it only costs the time to look.
+    pub async fn finished(
+        &self,
+        namespace: &str,
+        errors: usize,
+        now: DateTimeAsMicroseconds,
+    ) -> Result<(), String> {
+        if let Some(progress) = self.data.lock().get_mut(namespace) {
+            progress.current_topic = None;
+            progress.errors += errors;
+            progress.finished = Some(now);
+        }
+
+        self.running
+            .modify(self.get_path(), |data| {
+                data.retain(|itm| itm != namespace);
+                Some(to_yaml_model(data))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    fn get_path(&self) -> PathBuf {
+        storage_layout::get_namespace_deletions_file(self.data_folder.as_str())
+    }
+}
+
+fn to_yaml_model(data: &[String]) -> NamespaceDeletionsYamlModel {
+    NamespaceDeletionsYamlModel {
+        namespaces: data.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_data_folder(name: &str) -> String {
+        let mut root = std::env::temp_dir();
+        root.push(name);
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn a_running_deletion_is_not_started_twice() {
+        let data_folder = create_data_folder("my-sb-persistence-namespace-deletions");
+        let deletions = NamespaceDeletions::load(data_folder.clone()).await;
+        let now = DateTimeAsMicroseconds::now();
+
+        assert!(deletions.start("tenant", 2, now).await.unwrap());
+        assert!(!deletions.start("tenant", 2, now).await.unwrap());
+        assert!(deletions.is_deleting("tenant"));
+        assert!(!deletions.is_deleting("other"));
+
+        deletions.topic_started("tenant", "orders");
+        deletions.topic_deleted("tenant", 1);
+        deletions.finished("tenant", 0, now).await.unwrap();
+
+        let progress = deletions.get("tenant").unwrap();
+        assert_eq!(1, progress.topics_deleted);
+        assert_eq!(1, progress.errors);
+        assert!(!deletions.is_deleting("tenant"));
+
+        // Finished - it can be run again, for whatever the bus node has written since.
+        assert!(deletions.start("tenant", 0, now).await.unwrap());
+
+        let _ = std::fs::remove_dir_all(data_folder);
+    }
+
+    #[tokio::test]
+    async fn a_deletion_cut_short_refuses_writes_after_a_restart() {
+        let data_folder = create_data_folder("my-sb-persistence-namespace-deletions-restart");
+        let now = DateTimeAsMicroseconds::now();
+
+        let deletions = NamespaceDeletions::load(data_folder.clone()).await;
+        deletions.start("tenant", 2, now).await.unwrap();
+        deletions.start("other", 1, now).await.unwrap();
+        deletions.finished("other", 0, now).await.unwrap();
+
+        let restarted = NamespaceDeletions::load(data_folder.clone()).await;
+
+        assert!(restarted.is_deleting("tenant"));
+        assert!(!restarted.is_deleting("other"));
+        assert_eq!(vec!["tenant".to_string()], restarted.get_interrupted());
+
+        restarted.finished("tenant", 0, now).await.unwrap();
+
+        let restarted = NamespaceDeletions::load(data_folder.clone()).await;
+        assert!(!restarted.is_deleting("tenant"));
+
+        let _ = std::fs::remove_dir_all(data_folder);
+    }
+}
@@ -1,12 +1,44 @@
+use std::time::Instant;
+
 use my_service_bus::shared::{page_compressor::CompressedPageReaderError, sub_page::SubPageId};
 use rust_extensions::{date_time::DateTimeAsMicroseconds, StopWatch};
 
 use crate::{
     app::AppContext,
+    archive_storage::{ArchiveStorage, ArchiveStorageError},
     message_pages::{SubPage, SubPageInner},
     topic_data::TopicData,
 };
 
+/// Where a sub page to read was found - the label its read is timed under. `Missing` is a sub page
+/// nothing has, which still costs the lookups to find that out.
+#[derive(Clone, Copy, Debug)]
+pub enum ReadTier {
+    Memory,
+    Local,
+    Cold,
+    Missing,
+}
+
+impl ReadTier {
+    pub fn of_archive(archive_storage: &ArchiveStorage) -> Self {
+        if archive_storage.is_cold() {
+            Self::Cold
+        } else {
+            Self::Local
+        }
+    }
+
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::Local => "local",
+            Self::Cold => "cold",
+            Self::Missing => "missing",
+        }
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum RestoreSubPageError {
@@ -32,6 +64,9 @@ pub async fn restore_sub_page(
     topic_data: &TopicData,
     sub_page_id: SubPageId,
 ) -> Result<SubPage, RestoreSubPageError> {
+    let started = Instant::now();
+    let namespace = topic_data.get_topic_key().namespace;
+
     // Held across the open AND the read: the background archiver takes it exclusively before it
     // deletes a local file, so nothing can vanish between the two.
     let _guard = app.archive_locks.read(topic_data.get_topic_key()).await;
@@ -44,27 +79,50 @@ pub async fn restore_sub_page(
         .try_get_or_open(sub_page_id.into(), topic_data.get_topic_key(), app)
         .await;
 
-    if page_blob_storage.is_none() {
+    let Some(page_blob_storage) = page_blob_storage else {
+        app.metrics_keeper.sub_page_read(
+            namespace,
+            ReadTier::Missing.as_label(),
+            started.elapsed(),
+        );
         return Err(RestoreSubPageError::NotFound);
-    }
+    };
+
+    let tier = ReadTier::of_archive(page_blob_storage.as_ref());
 
-    let compressed_payload = page_blob_storage
-        .unwrap()
-        .read_sub_page_payload(sub_page_id)
-        .await?;
+    let compressed_payload = page_blob_storage.read_sub_page_payload(sub_page_id).await?;
 
-    if compressed_payload.is_none() {
+    let Some(compressed_payload) = compressed_payload else {
+        app.metrics_keeper.sub_page_read(
+            namespace,
+            ReadTier::Missing.as_label(),
+            started.elapsed(),
+        );
         return Err(RestoreSubPageError::NotFound);
-    }
+    };
 
-    let compressed_payload = compressed_payload.unwrap();
+    let decompress_started = Instant::now();
 
     let result = SubPageInner::from_compressed_payload(sub_page_id, compressed_payload.as_slice())?;
 
+    app.metrics_keeper.sub_page_decompressed(
+        namespace,
+        tier.as_label(),
+        decompress_started.elapsed(),
+    );
+    app.metrics_keeper
+        .sub_page_read(namespace, tier.as_label(), started.elapsed());
+
     Ok(SubPage::restore_from_archive(result))
 }
 
-pub async fn save_sub_page(app: &AppContext, topic_data: &TopicData, sub_page: &SubPage) {
+/// Safe to call again after a failure: the table of contents is written last, so a payload that
+/// did not make it is appended again and the half-written one is never pointed at.
+pub async fn save_sub_page(
+    app: &AppContext,
+    topic_data: &TopicData,
+    sub_page: &SubPage,
+) -> Result<(), ArchiveStorageError> {
     let sub_page_id = sub_page.get_id();
     if let Some(zip_payload) = sub_page.to_compressed_payload().await {
         let _guard = app.archive_locks.read(topic_data.get_topic_key()).await;
@@ -76,17 +134,9 @@ pub async fn save_sub_page(app: &AppContext, topic_data: &TopicData, sub_page: &
 
         let sw = StopWatch::new();
 
-        if let Err(err) = storage
+        storage
             .write_payload(sub_page_id, zip_payload.as_slice())
-            .await
-        {
-            panic!(
-                "Can not archive sub page {} of topic {}: {:?}",
-                sub_page_id.get_value(),
-                topic_data.get_topic_key(),
-                err
-            );
-        }
+            .await?;
 
         topic_data.metrics.update_last_saved_duration(sw.duration());
 
@@ -94,4 +144,6 @@ pub async fn save_sub_page(app: &AppContext, topic_data: &TopicData, sub_page: &
             .metrics
             .update_last_saved_moment(DateTimeAsMicroseconds::now());
     }
+
+    Ok(())
 }
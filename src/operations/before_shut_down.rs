@@ -1,6 +1,9 @@
 use std::{sync::Arc, time::Duration};
 
-use crate::{app::AppContext, topic_data::TopicData};
+use crate::{app::AppContext, message_pages::SubPage, topic_data::TopicData};
+
+const SHUTDOWN_ARCHIVE_ATTEMPTS: usize = 3;
+const SHUTDOWN_ARCHIVE_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 pub async fn execute_before_shutdown(app: Arc<AppContext>) {
     let duration = Duration::from_secs(1);
@@ -20,16 +23,57 @@ pub async fn execute_before_shutdown(app: Arc<AppContext>) {
             .save_before_shutdown()
             .await;
 
-        save_topic_messages_to_be_archived(app.as_ref(), topic_data.as_ref()).await;
-    }
+        let unarchived =
+            save_topic_messages_to_be_archived(app.as_ref(), topic_data.as_ref()).await;
 
-    super::current_sub_pages_io::write(app.as_ref()).await;
+        super::current_sub_pages_io::write_topic(
+            app.as_ref(),
+            topic_data.as_ref(),
+            unarchived.as_slice(),
+        )
+        .await;
+    }
 
     println!("Application can be closed now safely");
 }
 
-pub async fn save_topic_messages_to_be_archived(app: &AppContext, topic_data: &TopicData) {
-    while let Some(sub_page) = topic_data.pages_list.gc().await {
-        crate::operations::archive_io::save_sub_page(app, &topic_data, &sub_page).await;
+/// A shutdown does not wait out the backoff of a degraded topic: each sub page gets a few quick
+/// attempts, so the open tails of this and every other topic still get written. One that still
+/// fails is returned, to be written along with the open tail - and archived after the next start.
+pub async fn save_topic_messages_to_be_archived(
+    app: &AppContext,
+    topic_data: &TopicData,
+) -> Vec<Arc<SubPage>> {
+    let mut unarchived = Vec::new();
+
+    while let Some(sub_page) = topic_data.pages_list.get_sub_page_to_gc().await {
+        let mut attempt = 1;
+
+        loop {
+            let result =
+                crate::operations::archive_io::save_sub_page(app, topic_data, &sub_page).await;
+
+            let Err(err) = result else {
+                break;
+            };
+
+            if attempt >= SHUTDOWN_ARCHIVE_ATTEMPTS {
+                println!(
+                    "Sub page {} of topic {} can not be archived, it goes to the active file: {:?}",
+                    sub_page.get_id().get_value(),
+                    topic_data.get_topic_key(),
+                    err
+                );
+                unarchived.push(sub_page.clone());
+                break;
+            }
+
+            attempt += 1;
+            tokio::time::sleep(SHUTDOWN_ARCHIVE_RETRY_DELAY).await;
+        }
+
+        topic_data.pages_list.remove(sub_page.get_id()).await;
     }
+
+    unarchived
 }
@@ -0,0 +1,770 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::Duration,
+};
+
+use my_logger::LogEventCtx;
+use my_service_bus::shared::sub_page::SubPageId;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    app::{storage_layout, AppContext},
+    archive_storage::ArchiveFileNo,
+    bundle::{
+        get_entry_path, parse_entry_path,
+        tar::{TarError, TarReader, TarWriter},
+        BundleFile, BundleManifestYamlModel, BundleTopicYamlModel, BUNDLE_MANIFEST_FILE_NAME,
+        BUNDLE_VERSION,
+    },
+    file_storage::delete_folder_if_exists,
+    message_pages::SubPageInner,
+    topic_key::{validate_topic_id, Namespace, TopicKeyRef},
+    topics_snapshot::yaml_model::TopicYamlModel,
+    typing::Year,
+};
+
+use super::{OperationError, StorageLocation};
+
+/// An archive goes into a bundle this much per read - it can be far bigger than is sensible to
+/// hold in memory.
+const EXPORT_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+const IMPORT_BUFFER_SIZE: usize = 1024 * 1024;
+
+const MAX_MANIFEST_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A sub page is a thousand messages; this is far past anything the bus node accepts.
+const MAX_SUB_PAGE_SIZE: u64 = 1024 * 1024 * 1024;
+
+const TEMP_FILE_SUFFIX: &str = ".importing";
+
+impl From<TarError> for OperationError {
+    fn from(src: TarError) -> Self {
+        match src {
+            TarError::Invalid(message) => Self::InvalidBundle(message),
+            TarError::Io(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Self::InvalidBundle("The bundle ends in the middle of an entry".to_string())
+            }
+            TarError::Io(err) => Self::BundleStreamError(format!("{}", err)),
+        }
+    }
+}
+
+/// Writes a topic, or every topic of a namespace, as a bundle - see `BundleManifestYamlModel` for
+/// the layout. For moving a tenant to another cluster or handing its data over.
+///
+/// Archives go in as stored, each from a TOC read once: sub pages archived while the export runs
+/// are past the size it took and are left out, so a file is always a consistent copy of itself.
+/// Sub pages not archived yet go in as they are at that moment. What arrives at the topic after
+/// its files are written is not in the bundle - stop the bus node first for an exact copy.
+///
+/// Split in two so the checks and the inventory - where almost every error comes from - are done
+/// before anything is streamed back: `prepare_bundle_export` decides what goes in, and
+/// `write_bundle` writes it.
+pub async fn prepare_bundle_export(
+    app: &AppContext,
+    namespace: &str,
+    topic_id: Option<&str>,
+) -> Result<BundleExport, OperationError> {
+    if app.namespace_deletions.is_deleting(namespace) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            namespace.to_string(),
+        ));
+    }
+
+    let topic_ids = match topic_id {
+        Some(topic_id) => {
+            let topic_key = TopicKeyRef::new(namespace, topic_id);
+
+            if !super::move_topic::topic_exists(app, topic_key).await {
+                return Err(OperationError::TopicNotFound(topic_key.to_string()));
+            }
+
+            vec![topic_id.to_string()]
+        }
+        None => {
+            if !super::namespaces::namespace_exists(app, namespace).await {
+                return Err(OperationError::NamespaceNotFound(namespace.to_string()));
+            }
+
+            super::get_namespace_topics(app, namespace)
+                .await
+                .into_iter()
+                .collect()
+        }
+    };
+
+    let created = DateTimeAsMicroseconds::now();
+    let mtime = created.unix_microseconds / 1_000_000;
+
+    let mut manifest = BundleManifestYamlModel {
+        version: BUNDLE_VERSION,
+        created: created.to_rfc3339(),
+        namespace: namespace.to_string(),
+        topics: Vec::with_capacity(topic_ids.len()),
+    };
+
+    let mut topics = Vec::with_capacity(topic_ids.len());
+
+    for topic_id in topic_ids {
+        let (manifest_topic, topic) =
+            plan_topic(app, TopicKeyRef::new(namespace, topic_id.as_str())).await?;
+
+        manifest.topics.push(manifest_topic);
+        topics.push(topic);
+    }
+
+    let manifest = serde_yaml::to_string(&manifest).map_err(|err| {
+        OperationError::FileStorageError(format!("Can not serialize the manifest: {}", err))
+    })?;
+
+    Ok(BundleExport {
+        namespace: namespace.to_string(),
+        mtime,
+        manifest,
+        topics,
+    })
+}
+
+/// The output is only finished once everything is in it; on an error it stops where it was, and
+/// a reader sees a bundle cut short.
+pub async fn write_bundle<W: AsyncWrite + Unpin>(
+    app: &AppContext,
+    export: BundleExport,
+    output: W,
+) -> Result<W, OperationError> {
+    let BundleExport {
+        namespace,
+        mtime,
+        manifest,
+        topics,
+    } = export;
+
+    let namespace = namespace.as_str();
+
+    let mut writer = TarWriter::new(output);
+
+    writer
+        .append(BUNDLE_MANIFEST_FILE_NAME, manifest.as_bytes(), mtime)
+        .await?;
+
+    for topic in topics.iter() {
+        let topic_key = TopicKeyRef::new(namespace, topic.topic_id.as_str());
+
+        for archive_file_no in topic.archives.iter() {
+            write_archive(app, &mut writer, topic_key, *archive_file_no, mtime).await?;
+        }
+
+        for (year, location) in topic.year_indexes.iter() {
+            let content = read_year_index(app, topic_key, *year, *location).await?;
+            let path = get_entry_path(
+                topic_key.topic_id,
+                BundleFile::YearIndex(*year).get_file_name().as_str(),
+            );
+
+            writer
+                .append(path.as_str(), content.as_slice(), mtime)
+                .await?;
+        }
+
+        for (sub_page_id, payload) in topic.sub_pages.iter() {
+            let path = get_entry_path(
+                topic_key.topic_id,
+                BundleFile::SubPage(*sub_page_id).get_file_name().as_str(),
+            );
+
+            writer
+                .append(path.as_str(), payload.as_slice(), mtime)
+                .await?;
+        }
+    }
+
+    let output = writer.finish().await?;
+
+    my_logger::LOGGER.write_info(
+        "export_bundle",
+        format!("Exported {} topics", topics.len()),
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+
+    Ok(output)
+}
+
+/// A bundle decided on, not written yet.
+pub struct BundleExport {
+    namespace: String,
+    mtime: i64,
+    manifest: String,
+    topics: Vec<ExportedTopic>,
+}
+
+/// What goes into the bundle for one topic, decided before the first byte is written - the
+/// manifest lists it.
+struct ExportedTopic {
+    topic_id: String,
+    archives: Vec<ArchiveFileNo>,
+    year_indexes: Vec<(Year, StorageLocation)>,
+    sub_pages: Vec<(SubPageId, Vec<u8>)>,
+}
+
+impl ExportedTopic {
+    fn get_file_names(&self) -> Vec<String> {
+        self.archives
+            .iter()
+            .map(|itm| BundleFile::Archive(*itm))
+            .chain(
+                self.year_indexes
+                    .iter()
+                    .map(|(year, _)| BundleFile::YearIndex(*year)),
+            )
+            .chain(
+                self.sub_pages
+                    .iter()
+                    .map(|(sub_page_id, _)| BundleFile::SubPage(*sub_page_id)),
+            )
+            .map(|itm| itm.get_file_name())
+            .collect()
+    }
+}
+
+async fn plan_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(BundleTopicYamlModel, ExportedTopic), OperationError> {
+    if app.topic_moves.is_moving(topic_key) {
+        return Err(OperationError::TopicIsMoving(format!(
+            "{} is being moved",
+            topic_key
+        )));
+    }
+
+    let topic_data = app.topics_list.get(topic_key);
+
+    // The minute index of a loaded topic is a few seconds behind its queue; written now, the
+    // files are as complete as they get.
+    if let Some(topic_data) = topic_data.as_ref() {
+        for index in topic_data.yearly_index_by_minute.get_all().await {
+            index.flush_to_storage().await;
+        }
+    }
+
+    let inventory = super::get_topic_inventory(app, topic_key).await?;
+
+    let mut sub_pages = Vec::new();
+
+    match topic_data {
+        Some(topic_data) => {
+            for sub_page in topic_data.pages_list.get_all().await {
+                if let Some(payload) = sub_page.to_compressed_payload().await {
+                    sub_pages.push((sub_page.get_id(), payload));
+                }
+            }
+        }
+        None => {
+            if let Some(active) = super::current_sub_pages_io::read_active(app, topic_key).await {
+                for unarchived in active.unarchived {
+                    sub_pages.push((SubPageId::new(unarchived.sub_page_id), unarchived.payload));
+                }
+
+                if let Some(payload) = active.payload {
+                    sub_pages.push((SubPageId::new(active.sub_page_id), payload));
+                }
+            }
+        }
+    }
+
+    let snapshot = app
+        .topics_snapshot
+        .get()
+        .await
+        .snapshot
+        .data
+        .iter()
+        .find(|itm| itm.get_topic_key() == topic_key)
+        .map(TopicYamlModel::from_domain);
+
+    // A topic with files and no snapshot entry goes on from past what it stores.
+    let snapshot = snapshot.unwrap_or_else(|| TopicYamlModel {
+        topic_id: topic_key.topic_id.to_string(),
+        message_id: inventory
+            .max_stored_message_id
+            .map(|itm| itm.get_value() + 1)
+            .unwrap_or(0),
+        persist: None,
+        deleted: 0,
+        queues: vec![],
+    });
+
+    let topic = ExportedTopic {
+        topic_id: topic_key.topic_id.to_string(),
+        archives: inventory
+            .archives
+            .iter()
+            .map(|itm| itm.archive_file_no)
+            .collect(),
+        year_indexes: inventory
+            .year_indexes
+            .iter()
+            .map(|itm| (itm.year, itm.location))
+            .collect(),
+        sub_pages,
+    };
+
+    let manifest_topic = BundleTopicYamlModel {
+        snapshot,
+        retention_sec: app
+            .topics_retention
+            .get(topic_key)
+            .map(|itm| itm.max_age_sec),
+        files: topic.get_file_names(),
+    };
+
+    Ok((manifest_topic, topic))
+}
+
+/// Each range is read under its own short read lock, opening the file again - the uploader may
+/// have moved it to the cold tier in between, which changes where it is read from but not a
+/// byte of what is read. Holding the lock for the whole file would stall the uploader, and the
+/// readers queued behind it, for as long as the client takes to read it.
+async fn write_archive<W: AsyncWrite + Unpin>(
+    app: &AppContext,
+    writer: &mut TarWriter<W>,
+    topic_key: TopicKeyRef<'_>,
+    archive_file_no: ArchiveFileNo,
+    mtime: i64,
+) -> Result<(), OperationError> {
+    let file_name = BundleFile::Archive(archive_file_no).get_file_name();
+
+    let head = {
+        let _guard = app.archive_locks.read(topic_key).await;
+
+        let storage = app
+            .archive_storage_list
+            .try_get_or_open(archive_file_no, topic_key, app)
+            .await
+            .ok_or_else(|| archive_is_gone(topic_key, file_name.as_str()))?;
+
+        storage.read_head().await?
+    };
+
+    let path = get_entry_path(topic_key.topic_id, file_name.as_str());
+
+    writer
+        .start_entry(path.as_str(), head.content_size, mtime)
+        .await?;
+    writer.write(head.toc.as_slice()).await?;
+
+    let mut position = head.toc.len() as u64;
+
+    while position < head.content_size {
+        let len = (head.content_size - position).min(EXPORT_CHUNK_SIZE as u64) as usize;
+
+        let chunk = {
+            let _guard = app.archive_locks.read(topic_key).await;
+
+            let storage = app
+                .archive_storage_list
+                .try_get_or_open(archive_file_no, topic_key, app)
+                .await
+                .ok_or_else(|| archive_is_gone(topic_key, file_name.as_str()))?;
+
+            storage.read_raw(position, len).await?
+        };
+
+        writer.write(chunk.as_slice()).await?;
+        position += len as u64;
+    }
+
+    writer.finish_entry().await?;
+
+    Ok(())
+}
+
+fn archive_is_gone(topic_key: TopicKeyRef<'_>, file_name: &str) -> OperationError {
+    OperationError::FileStorageError(format!(
+        "{}/{} is gone - deleted by the retention while it was being exported",
+        topic_key, file_name
+    ))
+}
+
+/// A year index is of a fixed, modest size and is read whole. A local copy the uploader drops
+/// meanwhile is read from the cold tier instead.
+async fn read_year_index(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    year: Year,
+    location: StorageLocation,
+) -> Result<Vec<u8>, OperationError> {
+    if location != StorageLocation::Cold {
+        let _guard = app.index_locks.read(topic_key).await;
+
+        if let Ok(content) = tokio::fs::read(app.get_year_index_path(topic_key, year)).await {
+            return Ok(content);
+        }
+    }
+
+    let file_name = storage_layout::get_year_index_file_name(year);
+
+    let cold_storage = app.get_cold_storage(topic_key.namespace).ok_or_else(|| {
+        OperationError::FileStorageError(format!("{}/{} is gone", topic_key, file_name))
+    })?;
+
+    cold_storage
+        .download(topic_key, file_name.as_str())
+        .await
+        .map_err(OperationError::ColdStorageError)?
+        .ok_or_else(|| {
+            OperationError::ColdStorageError(format!("{}/{} is gone", topic_key, file_name))
+        })
+}
+
+/// A bundle whose manifest has been read - enough to know what it holds and where it came from
+/// before anything of it is written.
+pub struct BundleToImport<R> {
+    manifest: BundleManifestYamlModel,
+    reader: TarReader<R>,
+}
+
+impl<R: AsyncRead + Unpin> BundleToImport<R> {
+    pub async fn open(input: R) -> Result<Self, OperationError> {
+        let mut reader = TarReader::new(input);
+
+        let Some(entry) = reader.next_entry().await? else {
+            return Err(OperationError::InvalidBundle(
+                "The bundle is empty".to_string(),
+            ));
+        };
+
+        if entry.path != BUNDLE_MANIFEST_FILE_NAME {
+            return Err(OperationError::InvalidBundle(format!(
+                "The bundle starts with {} instead of {}",
+                entry.path, BUNDLE_MANIFEST_FILE_NAME
+            )));
+        }
+
+        let content = reader.read_to_end(MAX_MANIFEST_SIZE).await?;
+
+        let manifest: BundleManifestYamlModel = serde_yaml::from_slice(content.as_slice())
+            .map_err(|err| {
+                OperationError::InvalidBundle(format!("The manifest does not parse: {}", err))
+            })?;
+
+        if manifest.version > BUNDLE_VERSION {
+            return Err(OperationError::InvalidBundle(format!(
+                "The bundle is of version {}; this service reads up to {}",
+                manifest.version, BUNDLE_VERSION
+            )));
+        }
+
+        Ok(Self { manifest, reader })
+    }
+
+    /// The namespace the bundle was exported from.
+    pub fn get_namespace(&self) -> Result<Namespace, OperationError> {
+        Namespace::parse(Some(self.manifest.namespace.as_str())).map_err(|err| {
+            OperationError::InvalidBundle(format!("The manifest's namespace: {}", err))
+        })
+    }
+}
+
+/// Creates the topics of a bundle in `namespace` - the one it was exported from, or any other.
+///
+/// None of them may exist in any form; the whole bundle is refused otherwise, before anything is
+/// written. Archives and year indexes are written as they come; the sub pages that were not
+/// archived go into the archives, all but the newest of each topic, which becomes its open sub
+/// page as it was at the source. Each topic is added to the snapshot and gets its retention last,
+/// so a bundle that fails half way leaves nothing a reader could see: what it wrote is removed.
+///
+/// The topics are not known to the bus node - it has to be told about them, the same as after a
+/// `CloneTopic`.
+pub async fn import_bundle<R: AsyncRead + Unpin>(
+    app: &AppContext,
+    bundle: BundleToImport<R>,
+    namespace: &Namespace,
+) -> Result<Vec<String>, OperationError> {
+    let BundleToImport {
+        manifest,
+        mut reader,
+    } = bundle;
+
+    if app.namespace_deletions.is_deleting(namespace.as_str()) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            namespace.to_string(),
+        ));
+    }
+
+    let mut expected_files = BTreeSet::new();
+
+    for topic in manifest.topics.iter() {
+        let topic_id = topic.snapshot.topic_id.as_str();
+
+        validate_topic_id(topic_id).map_err(|err| {
+            OperationError::InvalidBundle(format!("Topic '{}' in the manifest: {}", topic_id, err))
+        })?;
+
+        for file_name in topic.files.iter() {
+            if BundleFile::parse(file_name).is_none() {
+                return Err(OperationError::InvalidBundle(format!(
+                    "{} is not a file a bundle can carry",
+                    file_name
+                )));
+            }
+
+            expected_files.insert(get_entry_path(topic_id, file_name));
+        }
+    }
+
+    let topic_ids: BTreeSet<&str> = manifest
+        .topics
+        .iter()
+        .map(|itm| itm.snapshot.topic_id.as_str())
+        .collect();
+
+    if topic_ids.len() != manifest.topics.len() {
+        return Err(OperationError::InvalidBundle(
+            "The manifest lists a topic twice".to_string(),
+        ));
+    }
+
+    for topic_id in topic_ids.iter() {
+        let topic_key = TopicKeyRef::new(namespace.as_str(), topic_id);
+
+        if app.topic_moves.is_moving(topic_key) {
+            return Err(OperationError::TopicIsMoving(format!(
+                "{} is being moved",
+                topic_key
+            )));
+        }
+
+        if super::move_topic::topic_exists(app, topic_key).await {
+            return Err(OperationError::TopicAlreadyExists(topic_key.to_string()));
+        }
+    }
+
+    // Taken now, so a second import of the same topics is refused instead of writing into these.
+    for topic_id in topic_ids.iter() {
+        app.create_topic_folder(TopicKeyRef::new(namespace.as_str(), topic_id))
+            .await;
+    }
+
+    let result = receive_files(app, &mut reader, namespace, expected_files).await;
+
+    let mut open_sub_pages = match result {
+        Ok(open_sub_pages) => open_sub_pages,
+        Err(err) => {
+            for topic_id in topic_ids.iter() {
+                discard_topic(app, TopicKeyRef::new(namespace.as_str(), topic_id)).await;
+            }
+
+            return Err(err);
+        }
+    };
+
+    let mut imported = Vec::with_capacity(manifest.topics.len());
+
+    for topic in manifest.topics {
+        let topic_id = topic.snapshot.topic_id.clone();
+        let topic_key = TopicKeyRef::new(namespace.as_str(), topic_id.as_str());
+
+        // `false` - the bus node has sent a snapshot with the topic in it meanwhile, and that
+        // entry is the one to keep.
+        app.topics_snapshot
+            .insert_topic(topic.snapshot.into_domain(namespace))
+            .await;
+
+        if let Some(open_sub_page) = open_sub_pages.remove(&topic_id) {
+            let topic_data = app.topics_list.init_topic_data(topic_key);
+            topic_data.pages_list.insert(open_sub_page).await;
+        }
+
+        if let Some(retention_sec) = topic.retention_sec {
+            if let Err(err) = app
+                .topics_retention
+                .set(topic_key, Some(Duration::from_secs(retention_sec)))
+                .await
+            {
+                my_logger::LOGGER.write_error(
+                    "import_bundle",
+                    format!(
+                        "Can not set the retention. Set it again by hand. Err: {}",
+                        err
+                    ),
+                    LogEventCtx::new().add("topic", topic_key.to_string()),
+                );
+            }
+        }
+
+        imported.push(topic_id);
+    }
+
+    my_logger::LOGGER.write_info(
+        "import_bundle",
+        format!(
+            "Imported {} topics exported from {} at {}",
+            imported.len(),
+            manifest.namespace,
+            manifest.created
+        ),
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+
+    Ok(imported)
+}
+
+/// Writes every file of the bundle and archives the sub pages that were not archived at the
+/// source - all but the newest of each topic, which is handed back to become its open sub page.
+async fn receive_files<R: AsyncRead + Unpin>(
+    app: &AppContext,
+    reader: &mut TarReader<R>,
+    namespace: &Namespace,
+    mut expected_files: BTreeSet<String>,
+) -> Result<BTreeMap<String, SubPageInner>, OperationError> {
+    // topic_id -> sub pages, in id order
+    let mut sub_pages: BTreeMap<String, Vec<SubPageInner>> = BTreeMap::new();
+
+    while let Some(entry) = reader.next_entry().await? {
+        if !expected_files.remove(&entry.path) {
+            return Err(OperationError::InvalidBundle(format!(
+                "{} is not in the manifest, or is in the bundle twice",
+                entry.path
+            )));
+        }
+
+        // Both parse - every expected path was built from a checked topic id and file name.
+        let (topic_id, file_name) = parse_entry_path(entry.path.as_str()).unwrap();
+        let bundle_file = BundleFile::parse(file_name).unwrap();
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), topic_id);
+
+        match bundle_file {
+            BundleFile::Archive(_) | BundleFile::YearIndex(_) => {
+                let path = storage_layout::get_topic_folder(app.get_data_folder(), topic_key)
+                    .join(file_name);
+
+                receive_file(reader, path.as_path()).await?;
+            }
+            BundleFile::SubPage(sub_page_id) => {
+                let payload = reader.read_to_end(MAX_SUB_PAGE_SIZE).await?;
+
+                let sub_page =
+                    SubPageInner::from_compressed_payload(sub_page_id, payload.as_slice())
+                        .map_err(|err| {
+                            OperationError::CorruptedSubPage(format!(
+                                "{}, sub page {}: {:?}",
+                                topic_key,
+                                sub_page_id.get_value(),
+                                err
+                            ))
+                        })?;
+
+                let topic_sub_pages = sub_pages.entry(topic_id.to_string()).or_default();
+                topic_sub_pages.push(sub_page);
+                topic_sub_pages.sort_by_key(|itm| itm.sub_page_id.get_value());
+            }
+        }
+    }
+
+    if let Some(missing) = expected_files.first() {
+        return Err(OperationError::InvalidBundle(format!(
+            "The bundle ended without {} and {} more files the manifest lists",
+            missing,
+            expected_files.len() - 1
+        )));
+    }
+
+    let mut result = BTreeMap::new();
+
+    for (topic_id, mut topic_sub_pages) in sub_pages {
+        let Some(open_sub_page) = topic_sub_pages.pop() else {
+            continue;
+        };
+
+        let topic_key = TopicKeyRef::new(namespace.as_str(), topic_id.as_str());
+        archive_sub_pages(app, topic_key, topic_sub_pages).await?;
+
+        result.insert(topic_id, open_sub_page);
+    }
+
+    Ok(result)
+}
+
+/// Through a temporary name, so a file is there whole or not at all.
+async fn receive_file<R: AsyncRead + Unpin>(
+    reader: &mut TarReader<R>,
+    path: &Path,
+) -> Result<(), OperationError> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(TEMP_FILE_SUFFIX);
+    let temp_path = std::path::PathBuf::from(temp_path);
+
+    let to_error = |err: std::io::Error| {
+        OperationError::FileStorageError(format!("Can not write {:?}: {}", path, err))
+    };
+
+    let mut file = tokio::fs::File::create(&temp_path)
+        .await
+        .map_err(to_error)?;
+
+    let mut buffer = vec![0u8; IMPORT_BUFFER_SIZE];
+
+    loop {
+        let len = reader.read(buffer.as_mut_slice()).await?;
+
+        if len == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..len]).await.map_err(to_error)?;
+    }
+
+    file.sync_all().await.map_err(to_error)?;
+    drop(file);
+
+    tokio::fs::rename(&temp_path, path).await.map_err(to_error)
+}
+
+async fn archive_sub_pages(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_pages: Vec<SubPageInner>,
+) -> Result<(), OperationError> {
+    for sub_page in sub_pages {
+        let sub_page_id = sub_page.sub_page_id;
+        let payload = super::clone_topic::compress(sub_page.messages.as_slice());
+
+        let _guard = app.archive_locks.read(topic_key).await;
+
+        let storage = app
+            .archive_storage_list
+            .get_or_create(sub_page_id.into(), topic_key, app)
+            .await;
+
+        // A sub page archived at the source while it was being exported is in the archive
+        // already - the slot is taken and this is a no-op.
+        storage
+            .write_payload(sub_page_id, payload.as_slice())
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn discard_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) {
+    app.topics_list.remove(topic_key);
+    app.archive_storage_list.forget_topic(topic_key);
+
+    let folder = storage_layout::get_topic_folder(app.get_data_folder(), topic_key);
+
+    if let Err(err) = delete_folder_if_exists(folder.as_path()).await {
+        my_logger::LOGGER.write_error(
+            "import_bundle",
+            format!(
+                "Can not remove {:?} of an import that failed. Remove it with HardDeleteTopic. Err: {}",
+                folder, err
+            ),
+            LogEventCtx::new().add("topic", topic_key.to_string()),
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::sync::Arc;
+
+use my_logger::LogEventCtx;
+use my_service_bus::{
+    abstractions::MessageId,
+    shared::{protobuf_models::MessageProtobufModel, sub_page::SubPageId},
+};
+use rust_extensions::sorted_vec::SortedVecOfArc;
+
+use crate::{
+    app::AppContext,
+    message_pages::SubPageInner,
+    topic_key::{Namespace, TopicKey, TopicKeyRef},
+    topics_snapshot::protobuf_model::TopicSnapshotProtobufModel,
+};
+
+use super::{rebuild_index_by_minute::MinutesOfYears, OperationError};
+
+/// What a clone is asked to copy - the source, the target and an inclusive message id range.
+pub struct TopicCloneRequest {
+    pub source: TopicKey,
+    pub target: TopicKey,
+    pub from_id: MessageId,
+    pub to_id: MessageId,
+}
+
+/// Copies a message id range of one topic into a new topic, in any namespace - for reproducing an
+/// incident against data that stays put.
+///
+/// A sub page that lies wholly inside the range is copied as the compressed block it is stored
+/// as; only the two at the edges are decoded and cut. The last one is not archived but becomes
+/// the target's open sub page, exactly as the bus node's own writes would have left it: an
+/// archived sub page can not be added to, so a message sent to the target later would otherwise
+/// replace it.
+///
+/// The checks happen before this returns; the copy is a background job and its outcome goes to
+/// the log. The target appears in the snapshot only once it is complete - one that a crash cut
+/// short has a folder and nothing else, and is removed with `HardDeleteTopic`.
+pub async fn clone_topic(
+    app: &Arc<AppContext>,
+    request: TopicCloneRequest,
+) -> Result<(), OperationError> {
+    let source = request.source.to_ref();
+    let target = request.target.to_ref();
+
+    if source == target {
+        return Err(OperationError::InvalidTopicClone(
+            "The source and the target are the same topic".to_string(),
+        ));
+    }
+
+    if request.from_id.get_value() > request.to_id.get_value() {
+        return Err(OperationError::InvalidTopicClone(format!(
+            "The range {}..{} is empty",
+            request.from_id.get_value(),
+            request.to_id.get_value()
+        )));
+    }
+
+    for topic_key in [source, target] {
+        if app.topic_moves.is_moving(topic_key) {
+            return Err(OperationError::TopicIsMoving(format!(
+                "{} is being moved",
+                topic_key
+            )));
+        }
+    }
+
+    if app.namespace_deletions.is_deleting(target.namespace) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            target.namespace.to_string(),
+        ));
+    }
+
+    if !super::move_topic::topic_exists(app.as_ref(), source).await {
+        return Err(OperationError::TopicNotFound(source.to_string()));
+    }
+
+    if super::move_topic::topic_exists(app.as_ref(), target).await {
+        return Err(OperationError::TopicAlreadyExists(target.to_string()));
+    }
+
+    // Taken now, so a second clone to the same target is refused instead of writing into this one.
+    app.create_topic_folder(target).await;
+
+    let app = app.clone();
+
+    tokio::spawn(async move {
+        let source = request.source.to_ref();
+        let target = request.target.to_ref();
+
+        match copy_range(app.as_ref(), &request).await {
+            Ok(messages) => {
+                my_logger::LOGGER.write_info(
+                    "clone_topic",
+                    format!("Cloned {} messages into {}", messages, target),
+                    LogEventCtx::new().add("topic", source.to_string()),
+                );
+            }
+            Err(err) => {
+                my_logger::LOGGER.write_error(
+                    "clone_topic",
+                    format!(
+                        "Can not clone into {}. It is left incomplete. Err: {:?}",
+                        target, err
+                    ),
+                    LogEventCtx::new().add("topic", source.to_string()),
+                );
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn copy_range(
+    app: &AppContext,
+    request: &TopicCloneRequest,
+) -> Result<usize, OperationError> {
+    let source = request.source.to_ref();
+    let target = request.target.to_ref();
+
+    let first_sub_page_id: SubPageId = request.from_id.into();
+    let last_sub_page_id: SubPageId = request.to_id.into();
+
+    let mut minutes: MinutesOfYears = MinutesOfYears::new();
+    let mut open_sub_page = None;
+    let mut messages_copied = 0;
+
+    for sub_page_no in first_sub_page_id.get_value()..=last_sub_page_id.get_value() {
+        let sub_page_id = SubPageId::new(sub_page_no);
+
+        let Some(stored) = read_sub_page(app, source, sub_page_id).await? else {
+            continue;
+        };
+
+        let whole = sub_page_id.get_first_message_id().get_value() >= request.from_id.get_value()
+            && sub_page_id.get_last_message_id().get_value() <= request.to_id.get_value();
+
+        let messages: Vec<Arc<MessageProtobufModel>> = stored
+            .messages
+            .iter()
+            .filter(|itm| is_in_range(request, itm.get_message_id()))
+            .cloned()
+            .collect();
+
+        if messages.is_empty() {
+            continue;
+        }
+
+        messages_copied += messages.len();
+
+        for message in messages.iter() {
+            super::rebuild_index_by_minute::add_message(app, &mut minutes, message);
+        }
+
+        if sub_page_no == last_sub_page_id.get_value() {
+            open_sub_page = Some(SubPageInner::restore(sub_page_id, to_sorted(messages)));
+            continue;
+        }
+
+        let payload = match stored.payload {
+            Some(payload) if whole => payload,
+            _ => compress(messages.as_slice()),
+        };
+
+        let _guard = app.archive_locks.read(target).await;
+
+        let storage = app
+            .archive_storage_list
+            .get_or_create(sub_page_id.into(), target, app)
+            .await;
+
+        storage
+            .write_payload(sub_page_id, payload.as_slice())
+            .await?;
+    }
+
+    super::rebuild_index_by_minute::write_minutes(app, target, minutes).await;
+
+    let persist = app
+        .topics_snapshot
+        .get()
+        .await
+        .snapshot
+        .data
+        .iter()
+        .find(|itm| itm.get_topic_key() == source)
+        .and_then(|itm| itm.persist);
+
+    let mut topic = TopicSnapshotProtobufModel::new(
+        &Namespace::default_namespace(),
+        String::new(),
+        MessageId::new(request.to_id.get_value() + 1),
+        vec![],
+        persist,
+        0,
+    );
+    topic.set_topic_key(target);
+
+    app.topics_snapshot.insert_topic(topic).await;
+
+    if let Some(open_sub_page) = open_sub_page {
+        let topic_data = app.topics_list.init_topic_data(target);
+        topic_data.pages_list.insert(open_sub_page).await;
+    }
+
+    Ok(messages_copied)
+}
+
+struct StoredSubPage {
+    messages: SortedVecOfArc<i64, MessageProtobufModel>,
+    /// As stored in the archive - `None` for a sub page still in memory.
+    payload: Option<Vec<u8>>,
+}
+
+/// Memory first: a sub page still being filled there is newer than anything archived.
+async fn read_sub_page(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_page_id: SubPageId,
+) -> Result<Option<StoredSubPage>, OperationError> {
+    if let Some(topic_data) = app.topics_list.get(topic_key) {
+        if let Some(sub_page) = topic_data.pages_list.get(sub_page_id).await {
+            if sub_page.is_active() {
+                let messages = sub_page.get_all_messages().await;
+
+                return Ok(Some(StoredSubPage {
+                    messages: to_sorted(messages.iter().cloned().collect()),
+                    payload: None,
+                }));
+            }
+        }
+    }
+
+    let Some(payload) = super::read_stored_payload(app, topic_key, sub_page_id).await? else {
+        return Ok(None);
+    };
+
+    let sub_page =
+        SubPageInner::from_compressed_payload(sub_page_id, payload.as_slice()).map_err(|err| {
+            OperationError::CorruptedSubPage(format!(
+                "{}, sub page {}: {:?}",
+                topic_key,
+                sub_page_id.get_value(),
+                err
+            ))
+        })?;
+
+    Ok(Some(StoredSubPage {
+        messages: sub_page.messages,
+        payload: Some(payload),
+    }))
+}
+
+fn is_in_range(request: &TopicCloneRequest, message_id: MessageId) -> bool {
+    let message_id = message_id.get_value();
+    message_id >= request.from_id.get_value() && message_id <= request.to_id.get_value()
+}
+
+fn to_sorted(
+    messages: Vec<Arc<MessageProtobufModel>>,
+) -> SortedVecOfArc<i64, MessageProtobufModel> {
+    let mut result = SortedVecOfArc::new();
+
+    for message in messages {
+        result.insert_or_replace(message);
+    }
+
+    result
+}
+
+pub(super) fn compress(messages: &[Arc<MessageProtobufModel>]) -> Vec<u8> {
+    let mut page_compressor =
+        my_service_bus::shared::page_compressor::CompressedPageBuilder::new_as_single_file();
+
+    for message in messages {
+        page_compressor.add_message(message).unwrap();
+    }
+
+    page_compressor.get_payload().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(from_id: i64, to_id: i64) -> TopicCloneRequest {
+        TopicCloneRequest {
+            source: TopicKey::new(Namespace::default_namespace(), "source".to_string()),
+            target: TopicKey::new(Namespace::default_namespace(), "target".to_string()),
+            from_id: MessageId::new(from_id),
+            to_id: MessageId::new(to_id),
+        }
+    }
+
+    #[test]
+    fn range_is_inclusive_at_both_ends() {
+        let request = request(10, 20);
+
+        assert!(!is_in_range(&request, MessageId::new(9)));
+        assert!(is_in_range(&request, MessageId::new(10)));
+        assert!(is_in_range(&request, MessageId::new(20)));
+        assert!(!is_in_range(&request, MessageId::new(21)));
+    }
+}
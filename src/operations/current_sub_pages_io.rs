@@ -1,10 +1,13 @@
+use std::sync::Arc;
+
 use my_logger::LogEventCtx;
 use my_service_bus::shared::sub_page::SubPageId;
 
 use crate::{
     app::{storage_layout, AppContext},
     file_storage::{delete_file_if_exists, FileStorage},
-    message_pages::SubPageInner,
+    message_pages::{SubPage, SubPageInner},
+    topic_data::TopicData,
     topic_key::{namespace_from_persisted, TopicKey, TopicKeyRef},
 };
 
@@ -15,6 +18,22 @@ use crate::{
 /// neither the namespace nor the topic id.
 #[derive(Clone, prost::Message)]
 pub struct ActiveSubPageModel {
+    /// Meaningless without a `payload`.
+    #[prost(int64, tag = "1")]
+    pub sub_page_id: i64,
+    /// `None` - the topic had no open sub page with anything in it, and the file is only there
+    /// for `unarchived`. Always present in files written before that could happen.
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub payload: Option<Vec<u8>>,
+    /// Closed sub pages a shutdown could not archive. They come back into memory with the tail,
+    /// and the GC archives them from there - a disk that was full at shutdown usually is not by
+    /// the next start.
+    #[prost(message, repeated, tag = "3")]
+    pub unarchived: Vec<UnarchivedSubPageModel>,
+}
+
+#[derive(Clone, prost::Message)]
+pub struct UnarchivedSubPageModel {
     #[prost(int64, tag = "1")]
     pub sub_page_id: i64,
     #[prost(bytes, tag = "2")]
@@ -59,23 +78,26 @@ pub async fn restore(app: &AppContext) -> Vec<RestoredSubPage> {
     let mut result = restore_legacy(app).await;
 
     for topic_key in crate::operations::scan_topic_folders(app.get_data_folder()).await {
-        if let Some(sub_page) = restore_topic(app, topic_key.to_ref()).await {
-            result.push(sub_page);
-        }
+        result.extend(restore_topic(app, topic_key.to_ref()).await);
     }
 
     result
 }
 
-async fn restore_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) -> Option<RestoredSubPage> {
+/// The open tail of one topic, if it has an `active` file - which is deleted once it is read - with
+/// whatever a shutdown could not archive before it.
+pub async fn restore_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) -> Vec<RestoredSubPage> {
     let path = storage_layout::get_local_path(
         app.get_data_folder(),
         storage_layout::get_active_relative_path(topic_key).as_str(),
     );
 
-    let file = FileStorage::open_if_exists(&path)
+    let Some(file) = FileStorage::open_if_exists(&path)
         .await
-        .expect("Can not open the active sub page file")?;
+        .expect("Can not open the active sub page file")
+    else {
+        return Vec::new();
+    };
 
     let content = file
         .read_all()
@@ -83,7 +105,7 @@ async fn restore_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) -> Option<R
         .expect("Can not read the active sub page file");
 
     if content.is_empty() {
-        return None;
+        return Vec::new();
     }
 
     let model: ActiveSubPageModel = match prost::Message::decode(content.as_slice()) {
@@ -93,35 +115,71 @@ async fn restore_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) -> Option<R
                 "Can not decode the active sub page of {}. Skipping it. Err: {:?}",
                 topic_key, err
             );
-            return None;
+            return Vec::new();
         }
     };
 
-    let sub_page = SubPageInner::from_compressed_payload(
-        SubPageId::new(model.sub_page_id),
-        model.payload.as_slice(),
-    );
+    let mut result = Vec::new();
+    let mut broken = false;
+
+    let sub_pages = model
+        .unarchived
+        .iter()
+        .map(|itm| (itm.sub_page_id, itm.payload.as_slice()))
+        .chain(
+            model
+                .payload
+                .as_deref()
+                .map(|payload| (model.sub_page_id, payload)),
+        );
 
-    let sub_page = match sub_page {
-        Ok(sub_page) => sub_page,
-        Err(err) => {
-            println!(
-                "Can not decompress the active sub page of {}. Skipping it. Err: {:?}",
-                topic_key, err
-            );
-            return None;
+    for (sub_page_id, payload) in sub_pages {
+        match SubPageInner::from_compressed_payload(SubPageId::new(sub_page_id), payload) {
+            Ok(sub_page) => result.push(RestoredSubPage {
+                topic_key: topic_key.to_owned_key(),
+                sub_page,
+            }),
+            Err(err) => {
+                broken = true;
+                println!(
+                    "Can not decompress sub page {} of {}. Skipping it. Err: {:?}",
+                    sub_page_id, topic_key, err
+                );
+            }
         }
-    };
+    }
+
+    // Left in place when a sub page did not decompress, so it can still be recovered by hand.
+    if broken {
+        return result;
+    }
 
     // Restored into memory - the file has served its purpose and must not be replayed again.
     delete_file_if_exists(&path)
         .await
         .expect("Can not delete the active sub page file");
 
-    Some(RestoredSubPage {
-        topic_key: topic_key.to_owned_key(),
-        sub_page,
-    })
+    result
+}
+
+/// The `active` file of a topic that is not in memory, left where it is - for copying it, not for
+/// restoring it. `None` when there is none or it does not decode.
+pub async fn read_active(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Option<ActiveSubPageModel> {
+    let path = storage_layout::get_local_path(
+        app.get_data_folder(),
+        storage_layout::get_active_relative_path(topic_key).as_str(),
+    );
+
+    let content = tokio::fs::read(&path).await.ok()?;
+
+    if content.is_empty() {
+        return None;
+    }
+
+    prost::Message::decode(content.as_slice()).ok()
 }
 
 async fn restore_legacy(app: &AppContext) -> Vec<RestoredSubPage> {
@@ -196,41 +254,108 @@ async fn restore_legacy(app: &AppContext) -> Vec<RestoredSubPage> {
     result
 }
 
-/// Writes the open tail of every topic, one file per topic.
-pub async fn write(app: &AppContext) {
-    for topic_data in app.topics_list.get_all().iter() {
-        let Some(sub_page) = topic_data.pages_list.get_active_sub_page().await else {
-            continue;
-        };
+/// The open tail of one topic into its `active` file - what a shutdown does for every topic, and
+/// `MoveTopic` for the one it takes out of memory. `unarchived` goes along with it: closed sub
+/// pages that could not be archived and would be lost otherwise - so the file is written for them
+/// even when the open tail is empty.
+pub async fn write_topic(app: &AppContext, topic_data: &TopicData, unarchived: &[Arc<SubPage>]) {
+    let mut model = ActiveSubPageModel {
+        sub_page_id: 0,
+        payload: None,
+        unarchived: Vec::with_capacity(unarchived.len()),
+    };
 
-        let Some(payload) = sub_page.to_compressed_payload().await else {
-            continue;
-        };
+    if let Some(sub_page) = topic_data.pages_list.get_active_sub_page().await {
+        if let Some(payload) = sub_page.to_compressed_payload().await {
+            model.sub_page_id = sub_page.get_id().get_value();
+            model.payload = Some(payload);
+        }
+    }
 
-        let model = ActiveSubPageModel {
-            sub_page_id: sub_page.get_id().get_value(),
-            payload,
-        };
+    for sub_page in unarchived {
+        if let Some(payload) = sub_page.to_compressed_payload().await {
+            model.unarchived.push(UnarchivedSubPageModel {
+                sub_page_id: sub_page.get_id().get_value(),
+                payload,
+            });
+        }
+    }
 
-        let mut content = Vec::new();
-        prost::Message::encode(&model, &mut content)
-            .expect("Can not serialize the active sub page");
+    if model.payload.is_none() && model.unarchived.is_empty() {
+        return;
+    }
 
-        let path = storage_layout::get_local_path(
-            app.get_data_folder(),
-            storage_layout::get_active_relative_path(topic_data.get_topic_key()).as_str(),
-        );
+    let mut content = Vec::new();
+    prost::Message::encode(&model, &mut content).expect("Can not serialize the active sub page");
 
-        let file = FileStorage::open_or_create(&path)
-            .await
-            .expect("Can not open the active sub page file");
+    let path = storage_layout::get_local_path(
+        app.get_data_folder(),
+        storage_layout::get_active_relative_path(topic_data.get_topic_key()).as_str(),
+    );
 
-        file.write_all(content.as_slice())
-            .await
-            .expect("Can not write the active sub page file");
+    let file = FileStorage::open_or_create(&path)
+        .await
+        .expect("Can not open the active sub page file");
+
+    file.write_all(content.as_slice())
+        .await
+        .expect("Can not write the active sub page file");
 
-        file.sync()
-            .await
-            .expect("Can not flush the active sub page file");
+    file.sync()
+        .await
+        .expect("Can not flush the active sub page file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActiveSubPageModel, UnarchivedSubPageModel};
+
+    /// How the file was written while the open tail was required.
+    #[derive(Clone, prost::Message)]
+    struct ActiveSubPageModelWithTail {
+        #[prost(int64, tag = "1")]
+        pub sub_page_id: i64,
+        #[prost(bytes, tag = "2")]
+        pub payload: Vec<u8>,
+    }
+
+    #[test]
+    fn a_file_written_with_a_tail_still_has_it() {
+        let mut content = Vec::new();
+        prost::Message::encode(
+            &ActiveSubPageModelWithTail {
+                sub_page_id: 12,
+                payload: vec![1, 2, 3],
+            },
+            &mut content,
+        )
+        .unwrap();
+
+        let model: ActiveSubPageModel = prost::Message::decode(content.as_slice()).unwrap();
+
+        assert_eq!(12, model.sub_page_id);
+        assert_eq!(Some(vec![1, 2, 3]), model.payload);
+    }
+
+    #[test]
+    fn unarchived_sub_pages_are_kept_without_a_tail() {
+        let mut content = Vec::new();
+        prost::Message::encode(
+            &ActiveSubPageModel {
+                sub_page_id: 0,
+                payload: None,
+                unarchived: vec![UnarchivedSubPageModel {
+                    sub_page_id: 11,
+                    payload: vec![4, 5],
+                }],
+            },
+            &mut content,
+        )
+        .unwrap();
+
+        let model: ActiveSubPageModel = prost::Message::decode(content.as_slice()).unwrap();
+
+        assert_eq!(None, model.payload);
+        assert_eq!(11, model.unarchived[0].sub_page_id);
     }
 }
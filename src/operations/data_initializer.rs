@@ -10,6 +10,9 @@ pub async fn init(app: Arc<AppContext>) {
 
     restore_pages(&app).await;
 
+    crate::operations::resume_topic_moves(&app);
+    crate::operations::resume_namespace_deletions(&app).await;
+
     my_logger::LOGGER.write_info(
         "Initialization".to_string(),
         format!("Application is initialized in {:?}", sw.duration()),
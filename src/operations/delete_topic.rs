@@ -1,57 +1,71 @@
-// TODO: Soft-delete topic flow.
-// See `TODO.md` for the design we want here:
-//   - mark topic with an expiration date (`gc_after`),
-//   - keep its data on disk until the timer triggers permanent removal.
-// The previous in-progress implementation is preserved below for reference.
-//
-// use rust_extensions::date_time::DateTimeAsMicroseconds;
-// use serde::*;
-//
-// use crate::app::AppContext;
-//
-// use super::OperationError;
-//
-// #[derive(Serialize, Deserialize, Debug)]
-// pub struct TopicSoftDeleteMetadataBlobModel {
-//     pub message_id: i64,
-//     pub delete_after: String,
-// }
-//
-// pub async fn delete_topic(
-//     app: &AppContext,
-//     topic_id: &str,
-//     delete_after: DateTimeAsMicroseconds,
-// ) -> Result<(), OperationError> {
-//     // Get current snapshot and find the topic
-//     let mut topics_snapshot = app.topics_snapshot.get().await;
-//
-//     let index = topics_snapshot
-//         .snapshot
-//         .data
-//         .iter()
-//         .position(|topic| topic.topic_id.as_str() == topic_id);
-//
-//     if index.is_none() {
-//         return Err(OperationError::TopicNotFound(topic_id.to_string()));
-//     }
-//
-//     let index = index.unwrap();
-//
-//     let message_id = topics_snapshot.snapshot.data[index].get_message_id();
-//
-//     // Remove topic from active snapshot data
-//     topics_snapshot.snapshot.data.remove(index);
-//     app.topics_snapshot
-//         .update(topics_snapshot.snapshot.data)
-//         .await;
-//
-//     // Drop in-memory topic data
-//     app.topics_list.delete(topic_id);
-//
-//     // Register soft-delete with GC deadline
-//     app.topics_snapshot
-//         .add_deleted_topic(topic_id, message_id, delete_after)
-//         .await;
-//
-//     Ok(())
-// }
+use std::time::Duration;
+
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{app::AppContext, topic_key::TopicKeyRef};
+
+use super::OperationError;
+
+/// Takes a topic out of service without touching its data: it leaves the snapshot, so the bus
+/// node is no longer given it, and every write to it is refused. Its folder and its cold objects
+/// stay where they are for `keep_for`, during which `restore_topic` brings it back as it was.
+/// After that the GC deletes them the way `HardDeleteTopic` does.
+///
+/// The record is part of the snapshot, so it survives a restart.
+pub async fn soft_delete_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    keep_for: Duration,
+) -> Result<(), OperationError> {
+    if app.namespace_deletions.is_deleting(topic_key.namespace) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            topic_key.namespace.to_string(),
+        ));
+    }
+
+    if app.topic_moves.is_moving(topic_key) {
+        return Err(OperationError::TopicIsMoving(format!(
+            "{} is being moved",
+            topic_key
+        )));
+    }
+
+    // Waits out the writes already checked, and holds back every later one until the record is
+    // there for it to see.
+    let _write_guard = app.topic_writes.write(topic_key).await;
+
+    if super::get_snapshot_message_id(app, topic_key)
+        .await
+        .is_none()
+    {
+        return Err(OperationError::TopicNotFound(topic_key.to_string()));
+    }
+
+    // Before the record: a topic that can not be put on disk stays as it was.
+    super::move_topic::unload_topic(app, topic_key)
+        .await
+        .map_err(OperationError::FileStorageError)?;
+
+    let now = DateTimeAsMicroseconds::now();
+    let gc_after = DateTimeAsMicroseconds::new(now.unix_microseconds + keep_for.as_micros() as i64);
+
+    if app
+        .topics_snapshot
+        .soft_delete_topic(topic_key, gc_after)
+        .await
+        .is_none()
+    {
+        return Err(OperationError::TopicNotFound(topic_key.to_string()));
+    }
+
+    my_logger::LOGGER.write_info(
+        "SoftDeleteTopic".to_string(),
+        format!(
+            "Topic is deleted, its data is kept until {}",
+            gc_after.to_rfc3339()
+        ),
+        my_logger::LogEventCtx::new().add("topicId", topic_key.to_string()),
+    );
+
+    Ok(())
+}
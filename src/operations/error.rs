@@ -1,6 +1,6 @@
 use zip::result::ZipError;
 
-use crate::message_pages::PageOperationError;
+use crate::{archive_storage::ArchiveStorageError, message_pages::PageOperationError};
 
 #[derive(Debug)]
 pub enum OperationError {
@@ -10,6 +10,35 @@ pub enum OperationError {
     ProtobufEncodeError(prost::EncodeError),
     ZipError(ZipError),
     FileStorageError(String),
+    ArchiveStorageError(ArchiveStorageError),
+    ColdStorageError(String),
+    /// A stored sub page that does not decompress.
+    CorruptedSubPage(String),
+    SnapshotRevisionNotFound(i64),
+    QueueNotFound(String),
+    /// A queue change that can not be applied as asked - the caller's mistake, not a failure.
+    InvalidQueueEdit(String),
+    /// A move whose target is the topic itself.
+    InvalidTopicMove(String),
+    /// A clone asked for an empty range or onto its own source.
+    InvalidTopicClone(String),
+    TopicAlreadyExists(String),
+    /// The topic takes part in a move that is not finished yet.
+    TopicIsMoving(String),
+    /// The topic is soft deleted and waits for `RestoreTopic` or the GC.
+    TopicIsDeleted(String),
+    NamespaceNotFound(String),
+    NamespaceIsBeingDeleted(String),
+    /// A bundle that is not one, or not whole.
+    InvalidBundle(String),
+    /// The stream a bundle is read from or written to broke off.
+    BundleStreamError(String),
+    /// A message export asked for a range it can not have.
+    InvalidMessagesExport(String),
+    /// A message import or export whose stream broke off.
+    MessagesStreamError(String),
+    /// A file to import messages from that is not one, or asks for what can not be done.
+    InvalidMessagesImport(String),
 }
 
 impl From<PageOperationError> for OperationError {
@@ -41,3 +70,9 @@ impl From<crate::file_storage::FileStorageError> for OperationError {
         Self::FileStorageError(format!("{}", src))
     }
 }
+
+impl From<ArchiveStorageError> for OperationError {
+    fn from(src: ArchiveStorageError) -> Self {
+        Self::ArchiveStorageError(src)
+    }
+}
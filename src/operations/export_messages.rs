@@ -0,0 +1,484 @@
+use my_service_bus::abstractions::MessageId;
+use my_service_bus::shared::{protobuf_models::MessageProtobufModel, sub_page::SubPageId};
+use rust_extensions::{base64::IntoBase64, date_time::DateTimeAsMicroseconds};
+use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    app::AppContext,
+    topic_key::{TopicKey, TopicKeyRef},
+};
+
+use super::OperationError;
+
+pub const CSV_HEADER: &str = "id,created,encoding,metadata,content\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessagesExportFormat {
+    /// One JSON object per line - see `NdJsonMessageModel`.
+    NdJson,
+    /// RFC 4180, with a header line: `id,created,encoding,metadata,content`. The metadata is a
+    /// JSON array in its field.
+    Csv,
+    /// Per message a little-endian `u32` length and then the message as the protobuf
+    /// `MessageProtobufModel` it is stored as - id, created, headers and content, nothing lost.
+    Binary,
+}
+
+impl MessagesExportFormat {
+    pub fn parse(src: &str) -> Option<Self> {
+        match src.to_lowercase().as_str() {
+            "ndjson" | "" => Some(Self::NdJson),
+            "csv" => Some(Self::Csv),
+            "binary" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            Self::NdJson => "application/x-ndjson",
+            Self::Csv => "text/csv",
+            Self::Binary => "application/octet-stream",
+        }
+    }
+}
+
+/// How the content goes into the text formats. A message that is not valid UTF-8 goes in as
+/// base64 either way; its `encoding` says which one it got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Base64,
+    Utf8,
+}
+
+impl ContentEncoding {
+    pub fn parse(src: &str) -> Option<Self> {
+        match src.to_lowercase().as_str() {
+            "base64" | "" => Some(Self::Base64),
+            "utf8" | "utf-8" => Some(Self::Utf8),
+            _ => None,
+        }
+    }
+}
+
+/// Where an export ends. A date bound is checked against `created`, so it stops at the first
+/// message stored after it.
+#[derive(Debug, Clone, Copy)]
+pub enum MessagesExportEnd {
+    /// Up to the topic's current message id.
+    Latest,
+    MessageId(MessageId),
+    Date(DateTimeAsMicroseconds),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MessagesExportStart {
+    MessageId(MessageId),
+    /// The first message the minute index has at or after it.
+    Date(DateTimeAsMicroseconds),
+}
+
+pub struct MessagesExportRequest {
+    pub from: MessagesExportStart,
+    pub to: MessagesExportEnd,
+    pub format: MessagesExportFormat,
+    pub content_encoding: ContentEncoding,
+}
+
+#[derive(Serialize)]
+struct NdJsonMessageModel<'s> {
+    id: i64,
+    created: String,
+    metadata: Vec<MetadataJsonModel<'s>>,
+    encoding: &'static str,
+    content: String,
+}
+
+/// An array rather than an object: a message may carry the same header twice.
+#[derive(Serialize)]
+struct MetadataJsonModel<'s> {
+    key: &'s str,
+    value: &'s str,
+}
+
+/// A request with its ends resolved to message ids. Taking it is everything an export can be
+/// refused for, so a caller that streams the result can still answer with an error before the
+/// first byte goes out.
+pub struct MessagesExport {
+    topic_key: TopicKey,
+    /// `None` when a date start finds no message at or after it - the export is then empty.
+    from_message_id: Option<MessageId>,
+    to_message_id: MessageId,
+    to_date: Option<DateTimeAsMicroseconds>,
+    format: MessagesExportFormat,
+    content_encoding: ContentEncoding,
+}
+
+impl MessagesExport {
+    pub fn get_format(&self) -> MessagesExportFormat {
+        self.format
+    }
+}
+
+pub async fn prepare_messages_export(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    request: &MessagesExportRequest,
+) -> Result<MessagesExport, OperationError> {
+    if !super::move_topic::topic_exists(app, topic_key).await {
+        return Err(OperationError::TopicNotFound(topic_key.to_string()));
+    }
+
+    let from_message_id = match request.from {
+        MessagesExportStart::MessageId(message_id) => Some(message_id),
+        MessagesExportStart::Date(from) => {
+            let topic_data = app.topics_list.get(topic_key);
+
+            super::find_first_indexed_message_id(app, topic_key, topic_data.as_deref(), from)
+                .await?
+        }
+    };
+
+    let (to_message_id, to_date) = match request.to {
+        MessagesExportEnd::MessageId(message_id) => (message_id, None),
+        MessagesExportEnd::Date(to_date) => {
+            (get_latest_message_id(app, topic_key).await?, Some(to_date))
+        }
+        MessagesExportEnd::Latest => (get_latest_message_id(app, topic_key).await?, None),
+    };
+
+    Ok(MessagesExport {
+        topic_key: topic_key.to_owned_key(),
+        from_message_id,
+        to_message_id,
+        to_date,
+        format: request.format,
+        content_encoding: request.content_encoding,
+    })
+}
+
+/// Writes a message range in one of `MessagesExportFormat`, sub page by sub page: each one is
+/// encoded into a buffer and written out before the next is read, so what the export holds at a
+/// time is one sub page and its encoding, whatever the size of the range.
+///
+/// Walks the range the way `send_messages_to_channel` does, including its limit on empty sub
+/// pages in a row - a range that runs into a hole nothing will fill stops there rather than
+/// reading the rest of it. Returns how many messages went out.
+pub async fn export_messages<W: AsyncWrite + Unpin>(
+    app: &AppContext,
+    export: &MessagesExport,
+    output: &mut W,
+) -> Result<usize, OperationError> {
+    let topic_key = export.topic_key.to_ref();
+
+    let Some(from_message_id) = export.from_message_id else {
+        return write_nothing(export, output).await;
+    };
+
+    let to_message_id = export.to_message_id;
+
+    if export.format == MessagesExportFormat::Csv {
+        write(output, CSV_HEADER.as_bytes()).await?;
+    }
+
+    let max_empty_sub_pages = app.settings.streamed_reads.max_empty_sub_pages;
+
+    let mut message_id = from_message_id.get_value();
+    let mut empty_sub_pages = 0;
+    let mut exported = 0;
+    let mut buffer = Vec::new();
+
+    while message_id <= to_message_id.get_value() {
+        if app.app_states.is_shutting_down() {
+            return Err(OperationError::MessagesStreamError(
+                "The service is shutting down".to_string(),
+            ));
+        }
+
+        let sub_page_id: SubPageId = MessageId::new(message_id).into();
+
+        let next_sub_page_first_id = sub_page_id
+            .get_first_message_id_of_next_sub_page()
+            .get_value();
+
+        let last_id_in_sub_page = to_message_id.get_value().min(next_sub_page_first_id - 1);
+
+        let sub_page = super::get_sub_page_to_read(app, topic_key, sub_page_id).await;
+        let sub_page_read_copy = sub_page.get_all_messages().await;
+
+        let mut past_the_end = false;
+        let mut found = false;
+
+        for id in message_id..=last_id_in_sub_page {
+            let Some(message) = sub_page_read_copy.get(id.into()) else {
+                continue;
+            };
+
+            found = true;
+
+            if let Some(to_date) = export.to_date {
+                if message.get_created().unix_microseconds > to_date.unix_microseconds {
+                    past_the_end = true;
+                    break;
+                }
+            }
+
+            encode_message(
+                message.as_ref(),
+                export.format,
+                export.content_encoding,
+                &mut buffer,
+            )?;
+
+            exported += 1;
+        }
+
+        write(output, buffer.as_slice()).await?;
+        buffer.clear();
+
+        if past_the_end {
+            break;
+        }
+
+        if found {
+            empty_sub_pages = 0;
+        } else {
+            empty_sub_pages += 1;
+
+            if max_empty_sub_pages > 0 && empty_sub_pages >= max_empty_sub_pages {
+                break;
+            }
+        }
+
+        message_id = next_sub_page_first_id;
+    }
+
+    output
+        .flush()
+        .await
+        .map_err(|err| OperationError::MessagesStreamError(format!("{}", err)))?;
+
+    Ok(exported)
+}
+
+/// An export that finds nothing to export is still a valid file of its format.
+async fn write_nothing<W: AsyncWrite + Unpin>(
+    export: &MessagesExport,
+    output: &mut W,
+) -> Result<usize, OperationError> {
+    if export.format == MessagesExportFormat::Csv {
+        write(output, CSV_HEADER.as_bytes()).await?;
+    }
+
+    output
+        .flush()
+        .await
+        .map_err(|err| OperationError::MessagesStreamError(format!("{}", err)))?;
+
+    Ok(0)
+}
+
+/// What the bus node last reported. A topic that is not in the snapshot has no end to default to.
+async fn get_latest_message_id(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<MessageId, OperationError> {
+    if let Some(message_id) = super::get_snapshot_message_id(app, topic_key).await {
+        return Ok(message_id);
+    }
+
+    Err(OperationError::InvalidMessagesExport(format!(
+        "Topic {} is not in the snapshot, so the export needs an end",
+        topic_key
+    )))
+}
+
+async fn write<W: AsyncWrite + Unpin>(
+    output: &mut W,
+    content: &[u8],
+) -> Result<(), OperationError> {
+    output
+        .write_all(content)
+        .await
+        .map_err(|err| OperationError::MessagesStreamError(format!("{}", err)))
+}
+
+pub fn encode_message(
+    message: &MessageProtobufModel,
+    format: MessagesExportFormat,
+    content_encoding: ContentEncoding,
+    output: &mut Vec<u8>,
+) -> Result<(), OperationError> {
+    match format {
+        MessagesExportFormat::NdJson => {
+            let (encoding, content) = encode_content(message.data.as_slice(), content_encoding);
+
+            let model = NdJsonMessageModel {
+                id: message.get_message_id().get_value(),
+                created: message.get_created().to_rfc3339(),
+                metadata: get_metadata(message),
+                encoding,
+                content,
+            };
+
+            serde_json::to_writer(&mut *output, &model).map_err(|err| {
+                OperationError::MessagesStreamError(format!("Can not encode a message: {}", err))
+            })?;
+
+            output.push(b'\n');
+        }
+        MessagesExportFormat::Csv => {
+            let (encoding, content) = encode_content(message.data.as_slice(), content_encoding);
+
+            let metadata = serde_json::to_string(&get_metadata(message)).map_err(|err| {
+                OperationError::MessagesStreamError(format!("Can not encode a message: {}", err))
+            })?;
+
+            output.extend_from_slice(message.get_message_id().get_value().to_string().as_bytes());
+            output.push(b',');
+            output.extend_from_slice(message.get_created().to_rfc3339().as_bytes());
+            output.push(b',');
+            output.extend_from_slice(encoding.as_bytes());
+            output.push(b',');
+            write_csv_field(metadata.as_str(), output);
+            output.push(b',');
+            write_csv_field(content.as_str(), output);
+            output.extend_from_slice(b"\r\n");
+        }
+        MessagesExportFormat::Binary => {
+            let len = prost::Message::encoded_len(message) as u32;
+            output.extend_from_slice(&len.to_le_bytes());
+            prost::Message::encode(message, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn encode_content(content: &[u8], content_encoding: ContentEncoding) -> (&'static str, String) {
+    if content_encoding == ContentEncoding::Utf8 {
+        if let Ok(content) = std::str::from_utf8(content) {
+            return ("utf8", content.to_string());
+        }
+    }
+
+    ("base64", content.to_vec().into_base64())
+}
+
+fn get_metadata(message: &MessageProtobufModel) -> Vec<MetadataJsonModel<'_>> {
+    message
+        .headers
+        .iter()
+        .map(|itm| MetadataJsonModel {
+            key: itm.key.as_str(),
+            value: itm.value.as_str(),
+        })
+        .collect()
+}
+
+/// Quoted only when it has to be.
+fn write_csv_field(value: &str, output: &mut Vec<u8>) {
+    if !value.contains([',', '"', '\r', '\n']) {
+        output.extend_from_slice(value.as_bytes());
+        return;
+    }
+
+    output.push(b'"');
+    output.extend_from_slice(value.replace('"', "\"\"").as_bytes());
+    output.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use my_service_bus::shared::protobuf_models::MessageMetaDataProtobufModel;
+
+    use super::*;
+
+    fn create_message(content: &[u8]) -> MessageProtobufModel {
+        MessageProtobufModel::new(
+            MessageId::new(5),
+            DateTimeAsMicroseconds::new(1_700_000_000_000_000),
+            content.to_vec(),
+            vec![MessageMetaDataProtobufModel {
+                key: "trace".to_string(),
+                value: "a,\"b\"".to_string(),
+            }],
+        )
+    }
+
+    #[test]
+    fn ndjson_falls_back_to_base64_for_binary_content() {
+        let mut output = Vec::new();
+
+        for content in [b"hello".as_slice(), &[0xff, 0xfe]] {
+            encode_message(
+                &create_message(content),
+                MessagesExportFormat::NdJson,
+                ContentEncoding::Utf8,
+                &mut output,
+            )
+            .unwrap();
+        }
+
+        let lines: Vec<serde_json::Value> = std::str::from_utf8(output.as_slice())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(2, lines.len());
+        assert_eq!("utf8", lines[0]["encoding"]);
+        assert_eq!("hello", lines[0]["content"]);
+        assert_eq!("base64", lines[1]["encoding"]);
+        assert_eq!("//4=", lines[1]["content"]);
+        assert_eq!("trace", lines[0]["metadata"][0]["key"]);
+    }
+
+    #[test]
+    fn csv_quotes_only_what_needs_it() {
+        let mut output = Vec::new();
+
+        encode_message(
+            &create_message(b"plain"),
+            MessagesExportFormat::Csv,
+            ContentEncoding::Utf8,
+            &mut output,
+        )
+        .unwrap();
+
+        let line = String::from_utf8(output).unwrap();
+
+        assert!(line.starts_with("5,2023-11-14T22:13:20"));
+        assert!(line.ends_with(
+            r#",utf8,"[{""key"":""trace"",""value"":""a,\""b\""""}]",plain"#.to_string() + "\r\n"
+        ));
+    }
+
+    #[test]
+    fn binary_is_length_prefixed_protobuf() {
+        let mut output = Vec::new();
+
+        for content in [b"first".as_slice(), b"second"] {
+            encode_message(
+                &create_message(content),
+                MessagesExportFormat::Binary,
+                ContentEncoding::Base64,
+                &mut output,
+            )
+            .unwrap();
+        }
+
+        let mut rest = output.as_slice();
+        let mut contents = Vec::new();
+
+        while !rest.is_empty() {
+            let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+            let message: MessageProtobufModel = prost::Message::decode(&rest[4..4 + len]).unwrap();
+            contents.push(message.data);
+            rest = &rest[4 + len..];
+        }
+
+        assert_eq!(vec![b"first".to_vec(), b"second".to_vec()], contents);
+    }
+}
@@ -0,0 +1,233 @@
+use my_logger::LogEventCtx;
+use my_service_bus::abstractions::MessageId;
+use my_service_bus::shared::sub_page::SubPageId;
+use serde::Serialize;
+
+use crate::{
+    app::AppContext,
+    message_pages::{SubPage, SubPageReadCopy},
+    topic_key::TopicKeyRef,
+};
+
+use super::OperationError;
+
+/// Lists and counts stop growing past this; `missing_messages` keeps counting.
+const MAX_REPORTED_RANGES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MessageIdRange {
+    pub from_id: i64,
+    pub to_id: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicGapReport {
+    pub namespace: String,
+    pub topic_id: String,
+    /// The snapshot's message id - the next one the bus node gives out. `None` for a topic that
+    /// is not in the snapshot; the check then ends at the highest id stored.
+    pub message_id: Option<i64>,
+    /// What was checked, both ends inclusive: from the lowest id stored up to the last one given
+    /// out. `None` - the topic stores nothing and has nothing to compare with.
+    pub checked: Option<MessageIdRange>,
+    /// Sub pages with no trace at all - neither in memory nor in an archive. Runs of them are
+    /// given as one range of sub page ids.
+    pub missing_sub_pages: Vec<MessageIdRange>,
+    /// Ids missing from sub pages that are there.
+    pub missing_ranges: Vec<MessageIdRange>,
+    /// Sub pages that are stored but do not decompress. Their ids count as missing.
+    pub corrupted_sub_pages: Vec<i64>,
+    pub missing_messages: i64,
+}
+
+/// Walks every sub page from the lowest id stored to the snapshot's message id and reports what
+/// is not there. A read returns nothing for a sub page that is missing or an id that is, which is
+/// right for a range nobody wrote and hides one that was lost - a tail that never got to
+/// `active`, an archive write that failed for good, a cold object that is gone.
+///
+/// Every sub page in the range is read - decompressed, from the cold tier where it is there - so
+/// this is for a topic after an incident, not for a timer. What lies below the lowest id stored
+/// is not reported - never written or gone as a whole, there is no telling which. The last few
+/// seconds' worth of ids can show up as a gap that fills in: the snapshot may run ahead of
+/// messages still on their way.
+///
+/// Updates the `topic_missing_messages` gauge with the count.
+pub async fn find_gaps(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<TopicGapReport, OperationError> {
+    let inventory = super::get_topic_inventory(app, topic_key).await?;
+
+    let message_id = inventory.message_id.map(|itm| itm.get_value());
+
+    let mut report = TopicGapReport {
+        namespace: topic_key.namespace.to_string(),
+        topic_id: topic_key.topic_id.to_string(),
+        message_id,
+        checked: None,
+        missing_sub_pages: vec![],
+        missing_ranges: vec![],
+        corrupted_sub_pages: vec![],
+        missing_messages: 0,
+    };
+
+    let Some(from_id) = inventory.min_stored_message_id.map(|itm| itm.get_value()) else {
+        app.metrics_keeper.update_missing_messages(topic_key, 0);
+        return Ok(report);
+    };
+
+    let to_id = match message_id {
+        Some(message_id) => message_id - 1,
+        None => inventory
+            .max_stored_message_id
+            .map(|itm| itm.get_value())
+            .unwrap_or(from_id),
+    };
+
+    report.checked = Some(MessageIdRange { from_id, to_id });
+
+    let from_sub_page: SubPageId = MessageId::new(from_id).into();
+    let to_sub_page: SubPageId = MessageId::new(to_id.max(from_id)).into();
+
+    for sub_page_id in from_sub_page.get_value()..=to_sub_page.get_value() {
+        let sub_page_id = SubPageId::new(sub_page_id);
+
+        let first_id = sub_page_id.get_first_message_id().get_value().max(from_id);
+        let last_id = sub_page_id.get_last_message_id().get_value().min(to_id);
+
+        if first_id > last_id {
+            continue;
+        }
+
+        let messages = match read_sub_page(app, topic_key, sub_page_id).await {
+            Ok(Some(messages)) => messages,
+            Ok(None) => {
+                report.add_missing_sub_page(sub_page_id);
+                report.missing_messages += last_id - first_id + 1;
+                continue;
+            }
+            Err(err) => {
+                my_logger::LOGGER.write_warning(
+                    "find_gaps",
+                    format!("{:?}", err),
+                    LogEventCtx::new().add("topic", topic_key.to_string()),
+                );
+
+                if report.corrupted_sub_pages.len() < MAX_REPORTED_RANGES {
+                    report.corrupted_sub_pages.push(sub_page_id.get_value());
+                }
+
+                report.missing_messages += last_id - first_id + 1;
+                continue;
+            }
+        };
+
+        for id in first_id..=last_id {
+            if messages.get(MessageId::new(id)).is_none() {
+                report.add_missing_id(id);
+            }
+        }
+    }
+
+    app.metrics_keeper
+        .update_missing_messages(topic_key, report.missing_messages);
+
+    Ok(report)
+}
+
+/// One topic, or every topic of the namespace when `topic_id` is `None` - one after another, so
+/// a namespace check reads one sub page at a time.
+pub async fn find_namespace_gaps(
+    app: &AppContext,
+    namespace: &str,
+    topic_id: Option<&str>,
+) -> Result<Vec<TopicGapReport>, OperationError> {
+    let topics: Vec<String> = match topic_id {
+        Some(topic_id) => vec![topic_id.to_string()],
+        None => {
+            if !super::namespaces::namespace_exists(app, namespace).await {
+                return Err(OperationError::NamespaceNotFound(namespace.to_string()));
+            }
+
+            super::get_namespace_topics(app, namespace)
+                .await
+                .into_iter()
+                .collect()
+        }
+    };
+
+    let mut result = Vec::with_capacity(topics.len());
+
+    for topic_id in topics {
+        result.push(find_gaps(app, TopicKeyRef::new(namespace, topic_id.as_str())).await?);
+    }
+
+    Ok(result)
+}
+
+impl TopicGapReport {
+    fn add_missing_sub_page(&mut self, sub_page_id: SubPageId) {
+        add_to_ranges(&mut self.missing_sub_pages, sub_page_id.get_value());
+    }
+
+    fn add_missing_id(&mut self, message_id: i64) {
+        self.missing_messages += 1;
+        add_to_ranges(&mut self.missing_ranges, message_id);
+    }
+}
+
+/// Extends the last range when `value` follows it, starts a new one otherwise.
+fn add_to_ranges(ranges: &mut Vec<MessageIdRange>, value: i64) {
+    if let Some(last) = ranges.last_mut() {
+        if last.to_id + 1 == value {
+            last.to_id = value;
+            return;
+        }
+    }
+
+    if ranges.len() < MAX_REPORTED_RANGES {
+        ranges.push(MessageIdRange {
+            from_id: value,
+            to_id: value,
+        });
+    }
+}
+
+/// Memory first - a sub page being filled there is newer than anything archived.
+pub(super) async fn read_sub_page(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_page_id: SubPageId,
+) -> Result<Option<SubPageReadCopy>, OperationError> {
+    if let Some(topic_data) = app.topics_list.get(topic_key) {
+        if let Some(sub_page) = topic_data.pages_list.get(sub_page_id).await {
+            return Ok(Some(sub_page.get_all_messages().await));
+        }
+    }
+
+    let Some(sub_page) = super::read_stored_sub_page(app, topic_key, sub_page_id).await? else {
+        return Ok(None);
+    };
+
+    let sub_page = SubPage::restore_from_archive(sub_page);
+
+    Ok(Some(sub_page.get_all_messages().await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_ids_make_one_range() {
+        let mut ranges = Vec::new();
+
+        for value in [3, 4, 5, 9, 11, 12] {
+            add_to_ranges(&mut ranges, value);
+        }
+
+        let ranges: Vec<(i64, i64)> = ranges.iter().map(|itm| (itm.from_id, itm.to_id)).collect();
+
+        assert_eq!(vec![(3, 5), (9, 9), (11, 12)], ranges);
+    }
+}
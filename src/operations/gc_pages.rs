@@ -1,12 +1,66 @@
 use std::sync::Arc;
 
+use my_logger::LogEventCtx;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
 use crate::{app::AppContext, topic_data::TopicData};
 
 use super::OperationError;
 
+/// Archives the closed sub pages of the topic, oldest first, each one leaving memory only once it
+/// is written. A write that fails stops the pass for this topic and puts the next one off -
+/// doubling each time it fails again - while the sub page keeps serving reads from memory. The
+/// topic is degraded until a write goes through; the others are not held up.
 pub async fn gc_pages(app: &AppContext, topic_data: Arc<TopicData>) -> Result<(), OperationError> {
-    while let Some(page_to_gc) = topic_data.pages_list.gc().await {
-        crate::operations::archive_io::save_sub_page(app, &topic_data, &page_to_gc).await;
+    if !topic_data
+        .metrics
+        .is_archive_write_due(DateTimeAsMicroseconds::now())
+    {
+        return Ok(());
+    }
+
+    while let Some(page_to_gc) = topic_data.pages_list.get_sub_page_to_gc().await {
+        let result =
+            crate::operations::archive_io::save_sub_page(app, &topic_data, &page_to_gc).await;
+
+        if let Err(err) = result {
+            let err = format!("{:?}", err);
+
+            app.metrics_keeper
+                .archive_write_failed(topic_data.namespace.as_str());
+
+            let delay = topic_data
+                .metrics
+                .archive_write_failed(err.clone(), DateTimeAsMicroseconds::now());
+
+            my_logger::LOGGER.write_error(
+                "ArchiveWrite".to_string(),
+                format!(
+                    "Can not archive sub page {}. Next attempt in {:?}. Err: {}",
+                    page_to_gc.get_id().get_value(),
+                    delay,
+                    err
+                ),
+                LogEventCtx::new().add("topicId", topic_data.get_topic_key().to_string()),
+            );
+
+            return Ok(());
+        }
+
+        topic_data.pages_list.remove(page_to_gc.get_id()).await;
+
+        let failures = topic_data.metrics.archive_write_succeeded();
+
+        if failures > 0 {
+            my_logger::LOGGER.write_info(
+                "ArchiveWrite".to_string(),
+                format!(
+                    "Archive writes go through again after {} failures",
+                    failures
+                ),
+                LogEventCtx::new().add("topicId", topic_data.get_topic_key().to_string()),
+            );
+        }
     }
 
     Ok(())
@@ -1,13 +1,15 @@
 use std::sync::Arc;
 
+use my_service_bus::abstractions::MessageId;
 use my_service_bus::shared::protobuf_models::MessageProtobufModel;
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
 use crate::{
-    app::AppContext,
+    app::{storage_layout, AppContext},
     index_by_minute::{MinuteWithinYear, YearlyIndexByMinute},
     topic_data::TopicData,
     topic_key::TopicKeyRef,
+    typing::Year,
 };
 
 use super::OperationError;
@@ -24,29 +26,115 @@ pub async fn get_messages_from_date(
 
     let topic_data = super::topics::get_topic(app, topic_key).await?;
 
-    let now = DateTimeAsMicroseconds::now();
+    let yearly_index = get_yearly_index(app, topic_data.as_ref(), year).await;
+
+    let Some(yearly_index) = yearly_index else {
+        return Ok(vec![]);
+    };
+
+    let result =
+        read_from_yearly_index(app, topic_data.as_ref(), &yearly_index, minute, max_amount).await;
+
+    return result;
+}
+
+/// The first message stored at or after `from`, found through the minute index. A "since"
+/// query rarely lands on a minute that saw traffic, so this walks forward - through the rest of
+/// that year and on into the following ones, up to the current year.
+pub async fn find_first_message_id_from_date(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    from: DateTimeAsMicroseconds,
+) -> Result<Option<MessageId>, OperationError> {
+    let topic_data = super::topics::get_topic(app, topic_key).await?;
+
+    find_first_indexed_message_id(app, topic_key, Some(topic_data.as_ref()), from).await
+}
+
+/// The same walk for a topic that does not have to be loaded. Without its `TopicData` the year
+/// indexes are read straight from storage and not registered - nothing is queued on them for a
+/// topic that has not received a message since the start.
+pub async fn find_first_indexed_message_id(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    topic_data: Option<&TopicData>,
+    from: DateTimeAsMicroseconds,
+) -> Result<Option<MessageId>, OperationError> {
+    let (mut minute, year) = app.index_by_minute_utils.get_minute_within_the_year(from);
+
+    let (_, current_year) = app
+        .index_by_minute_utils
+        .get_minute_within_the_year(DateTimeAsMicroseconds::now());
+
+    for year in year.get_value()..=current_year.get_value() {
+        let yearly_index = match topic_data {
+            Some(topic_data) => get_yearly_index(app, topic_data, year.into()).await,
+            None => app.try_open_index_by_minute(topic_key, year.into()).await,
+        };
 
-    let mut yearly_index = topic_data.yearly_index_by_minute.get(year, Some(now)).await;
+        if let Some(yearly_index) = yearly_index {
+            if let Some((_, message_id)) = yearly_index.find_first_message_id_from(minute).await? {
+                return Ok(Some(message_id));
+            }
+        }
 
-    if yearly_index.is_none() {
-        yearly_index = app.try_open_index_by_minute(topic_key, year).await;
+        minute = MinuteWithinYear::new(0);
     }
 
-    if yearly_index.is_none() {
-        return Ok(vec![]);
+    Ok(None)
+}
+
+/// When `message_id` was published, to the minute, from the year indexes on the local disk - so a
+/// topic that is not loaded is not loaded for it. Walks back from the current year to the one
+/// whose index has a minute the message is not older than. A year index that is only in the cold
+/// tier is not fetched: `None` then, as for a message no local index has.
+pub async fn find_indexed_created(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    message_id: MessageId,
+) -> Result<Option<DateTimeAsMicroseconds>, OperationError> {
+    let (_, current_year) = app
+        .index_by_minute_utils
+        .get_minute_within_the_year(DateTimeAsMicroseconds::now());
+
+    for year in (storage_layout::OLDEST_POSSIBLE_YEAR..=current_year.get_value()).rev() {
+        let path = app.get_year_index_path(topic_key, year.into());
+
+        let Some(yearly_index) = YearlyIndexByMinute::load_if_exists(&path).await else {
+            continue;
+        };
+
+        if let Some(minute) = yearly_index.find_minute_of_message_id(message_id).await? {
+            return Ok(app
+                .index_by_minute_utils
+                .get_minute_start(year.into(), minute));
+        }
     }
 
-    let yearly_index = yearly_index.unwrap();
+    Ok(None)
+}
+
+pub(super) async fn get_yearly_index(
+    app: &AppContext,
+    topic_data: &TopicData,
+    year: Year,
+) -> Option<Arc<YearlyIndexByMinute>> {
+    let now = DateTimeAsMicroseconds::now();
+
+    if let Some(yearly_index) = topic_data.yearly_index_by_minute.get(year, Some(now)).await {
+        return Some(yearly_index);
+    }
+
+    let yearly_index = app
+        .try_open_index_by_minute(topic_data.get_topic_key(), year)
+        .await?;
 
     topic_data
         .yearly_index_by_minute
         .add(year, yearly_index.clone())
         .await;
 
-    let result =
-        read_from_yearly_index(app, topic_data.as_ref(), &yearly_index, minute, max_amount).await;
-
-    return result;
+    Some(yearly_index)
 }
 
 async fn read_from_yearly_index(
@@ -1,21 +1,38 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use my_service_bus::shared::sub_page::SubPageId;
 
 use crate::{app::AppContext, message_pages::SubPage, topic_data::TopicData};
 
+use super::archive_io::ReadTier;
+
 pub async fn get_page_to_read(
     app: &AppContext,
     topic_data: &TopicData,
     sub_page_id: SubPageId,
 ) -> Arc<SubPage> {
+    let started = Instant::now();
+
+    // A restore times itself, by the tier it read from; only a first-time hit is a memory read.
+    let mut restored = false;
+
     loop {
         let page = topic_data.pages_list.get(sub_page_id).await;
 
         if let Some(page) = page {
+            if !restored {
+                app.metrics_keeper.sub_page_read(
+                    topic_data.get_topic_key().namespace,
+                    ReadTier::Memory.as_label(),
+                    started.elapsed(),
+                );
+            }
+
             return page;
         };
 
+        restored = true;
+
         let sub_page =
             crate::operations::archive_io::restore_sub_page(app, topic_data, sub_page_id).await;
 
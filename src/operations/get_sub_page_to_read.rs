@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use my_logger::LogEventCtx;
 use my_service_bus::shared::sub_page::SubPageId;
@@ -9,6 +9,8 @@ use crate::{
     topic_key::TopicKeyRef,
 };
 
+use super::archive_io::ReadTier;
+
 pub async fn get_sub_page_to_read(
     app: &AppContext,
     topic_key: TopicKeyRef<'_>,
@@ -30,7 +32,14 @@ async fn read(
 ) -> Option<Arc<SubPage>> {
     let topic = app.topics_list.get(topic_key)?;
 
+    let started = Instant::now();
+
     if let Some(sub_page) = topic.pages_list.get(sub_page_id).await {
+        app.metrics_keeper.sub_page_read(
+            topic_key.namespace,
+            ReadTier::Memory.as_label(),
+            started.elapsed(),
+        );
         return Some(sub_page);
     }
 
@@ -42,13 +51,41 @@ async fn read(
     let archive_storage = app
         .archive_storage_list
         .try_get_or_open(archive_file_no, topic_key, app)
-        .await?;
+        .await;
+
+    let Some(archive_storage) = archive_storage else {
+        app.metrics_keeper.sub_page_read(
+            topic_key.namespace,
+            ReadTier::Missing.as_label(),
+            started.elapsed(),
+        );
+        return None;
+    };
+
+    let tier = ReadTier::of_archive(archive_storage.as_ref());
 
     let payload = archive_storage.read_sub_page_payload(sub_page_id).await;
 
     match payload {
         Ok(payload) => {
-            let sub_page = SubPageInner::from_compressed_payload(sub_page_id, payload?.as_slice());
+            let Some(payload) = payload else {
+                app.metrics_keeper.sub_page_read(
+                    topic_key.namespace,
+                    ReadTier::Missing.as_label(),
+                    started.elapsed(),
+                );
+                return None;
+            };
+
+            let decompress_started = Instant::now();
+
+            let sub_page = SubPageInner::from_compressed_payload(sub_page_id, payload.as_slice());
+
+            app.metrics_keeper.sub_page_decompressed(
+                topic_key.namespace,
+                tier.as_label(),
+                decompress_started.elapsed(),
+            );
 
             if let Err(err) = &sub_page {
                 my_logger::LOGGER.write_warning(
@@ -61,6 +98,13 @@ async fn read(
             let sub_page = sub_page.unwrap();
 
             let sub_page = Arc::new(SubPage::restore_from_archive(sub_page));
+
+            app.metrics_keeper.sub_page_read(
+                topic_key.namespace,
+                tier.as_label(),
+                started.elapsed(),
+            );
+
             Some(sub_page)
         }
         Err(err) => {
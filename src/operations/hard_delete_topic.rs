@@ -2,7 +2,7 @@ use std::{sync::Arc, time::Duration};
 
 use chrono::Datelike;
 use my_logger::LogEventCtx;
-use my_service_bus::shared::sub_page::SubPageId;
+use my_service_bus::{abstractions::AsMessageId, shared::sub_page::SubPageId};
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
 use crate::{
@@ -13,11 +13,6 @@ use crate::{
     typing::Year,
 };
 
-/// The cold tier can not be listed, so the years a topic spans have to be guessed. Deleting is a
-/// background job with no deadline, so a generous range costs nothing: a few dozen requests that
-/// mostly answer "not there", which is not an error.
-const OLDEST_POSSIBLE_YEAR: u32 = 2000;
-
 const DELETE_ATTEMPTS: usize = 3;
 
 /// Deletes a topic within one namespace only: the same topic name in any other namespace is a
@@ -25,7 +20,7 @@ const DELETE_ATTEMPTS: usize = 3;
 ///
 /// Returns as soon as the topic stops being served - dropping it from the in-memory list is the
 /// only part that has to be immediate. Wiping the data can take a while (a topic folder is
-/// gigabytes, and the cold tier is deleted key by key), so it runs as a background job at
+/// gigabytes, and the cold tier is deleted object by object), so it runs as a background job at
 /// whatever pace it manages. Failures are logged, not surfaced: there is no caller left to tell.
 pub fn hard_delete_topic(app: &Arc<AppContext>, topic_key: TopicKeyRef<'_>) {
     app.topics_list.remove(topic_key);
@@ -35,9 +30,81 @@ pub fn hard_delete_topic(app: &Arc<AppContext>, topic_key: TopicKeyRef<'_>) {
 }
 
 async fn delete_topic_data(app: Arc<AppContext>, topic_key: TopicKey) {
-    let topic_key_ref = topic_key.to_ref();
+    let highest_archive_file_no =
+        get_highest_archive_file_no(app.as_ref(), topic_key.to_ref()).await;
+
+    wipe_topic(app.as_ref(), topic_key.to_ref(), highest_archive_file_no).await;
+
+    // A soft-deleted topic deleted for good right away - nothing is left for its record.
+    app.topics_snapshot
+        .remove_deleted_topic(topic_key.to_ref())
+        .await;
+
+    println!("Topic {} is deleted", topic_key);
+}
+
+/// Deletes the data of every soft-deleted topic whose time is up. The record only goes once
+/// everything of the topic is gone: a delete that failed is tried again on the next tick, rather
+/// than leaving data behind with nothing that points at it.
+pub async fn gc_expired_deleted_topics(app: &AppContext) {
+    for deleted in app.topics_snapshot.get_deleted_topics().await {
+        let topic_key = deleted.get_topic_key();
+
+        if !is_expired(app, topic_key).await {
+            continue;
+        }
+
+        // Exclusive against a restore, which takes the same lock: a topic is restored either
+        // whole or not at all. Checked again under it - it may have been restored meanwhile.
+        let _write_guard = app.topic_writes.write(topic_key).await;
+
+        if !is_expired(app, topic_key).await {
+            continue;
+        }
+
+        let highest_archive_file_no = get_highest_archive_file_no(app, topic_key).await;
+
+        let errors = wipe_topic(app, topic_key, highest_archive_file_no).await;
 
-    let highest_archive_file_no = get_highest_archive_file_no(app.as_ref(), topic_key_ref).await;
+        if errors > 0 {
+            write_error(
+                topic_key,
+                format!(
+                    "{} files of the soft-deleted topic are left, trying again on the next tick",
+                    errors
+                ),
+            );
+            continue;
+        }
+
+        app.topics_snapshot.remove_deleted_topic(topic_key).await;
+
+        my_logger::LOGGER.write_info(
+            "DeletedTopicsGc",
+            "The soft-deleted topic is gone".to_string(),
+            LogEventCtx::new().add("topic", topic_key.to_string()),
+        );
+    }
+}
+
+async fn is_expired(app: &AppContext, topic_key: TopicKeyRef<'_>) -> bool {
+    let now = DateTimeAsMicroseconds::now();
+
+    app.topics_snapshot
+        .get_deleted_topics()
+        .await
+        .iter()
+        .any(|itm| itm.get_topic_key() == topic_key && itm.gc_after <= now.unix_microseconds)
+}
+
+/// Everything of a topic that is no longer served: its folder, its cold objects and its
+/// retention. Returns how many of them could not be deleted - each one is logged.
+pub(super) async fn wipe_topic(
+    app: &AppContext,
+    topic_key_ref: TopicKeyRef<'_>,
+    highest_archive_file_no: Option<ArchiveFileNo>,
+) -> usize {
+    let mut errors = 0;
 
     let folder = storage_layout::get_topic_folder(app.get_data_folder(), topic_key_ref);
 
@@ -48,6 +115,7 @@ async fn delete_topic_data(app: Arc<AppContext>, topic_key: TopicKey) {
         let _indexes = app.index_locks.write(topic_key_ref).await;
 
         if let Err(err) = delete_folder_if_exists(folder.as_path()).await {
+            errors += 1;
             write_error(
                 topic_key_ref,
                 format!("Can not delete {:?}. Err: {}", folder, err),
@@ -58,68 +126,132 @@ async fn delete_topic_data(app: Arc<AppContext>, topic_key: TopicKey) {
     app.archive_locks.forget(topic_key_ref);
     app.index_locks.forget(topic_key_ref);
 
-    delete_from_cold_storage(app.as_ref(), topic_key_ref, highest_archive_file_no).await;
+    errors += delete_from_cold_storage(app, topic_key_ref, highest_archive_file_no).await;
+
+    // Lives next to the snapshot rather than in the topic folder, so it does not go with it. A
+    // topic re-created under the same name starts without a retention.
+    if let Err(err) = app.topics_retention.set(topic_key_ref, None).await {
+        errors += 1;
+        write_error(
+            topic_key_ref,
+            format!("Can not drop the retention. Err: {}", err),
+        );
+    }
 
-    println!("Topic {} is deleted", topic_key_ref);
+    errors
 }
 
 /// The highest archive file the topic can possibly have, derived from the message id the snapshot
-/// last recorded for it.
-async fn get_highest_archive_file_no(
+/// last recorded for it - in its record, for a soft-deleted one.
+pub(super) async fn get_highest_archive_file_no(
     app: &AppContext,
     topic_key: TopicKeyRef<'_>,
 ) -> Option<ArchiveFileNo> {
     let snapshot = app.topics_snapshot.get().await;
 
-    let topic = snapshot
+    let message_id = match snapshot
         .snapshot
         .data
         .iter()
-        .find(|itm| itm.get_topic_key() == topic_key)?;
+        .find(|itm| itm.get_topic_key() == topic_key)
+    {
+        Some(topic) => topic.get_message_id(),
+        None => snapshot
+            .snapshot
+            .deleted_topics
+            .iter()
+            .find(|itm| itm.get_topic_key() == topic_key)?
+            .message_id
+            .as_message_id(),
+    };
 
-    let sub_page_id: SubPageId = topic.get_message_id().into();
+    let sub_page_id: SubPageId = message_id.into();
 
     Some(sub_page_id.into())
 }
 
+/// Exactly what the cold tier lists for the topic. A key that may not list the bucket falls back
+/// to deleting every key the topic could have.
 async fn delete_from_cold_storage(
     app: &AppContext,
     topic_key: TopicKeyRef<'_>,
     highest_archive_file_no: Option<ArchiveFileNo>,
-) {
-    if app.get_cold_storage().is_none() {
-        return;
+) -> usize {
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return 0;
+    };
+
+    let files = match cold_storage.list_topic_files(topic_key).await {
+        Ok(files) => files,
+        Err(err) => {
+            write_error(
+                topic_key,
+                format!(
+                    "Can not list the cold storage, deleting every key the topic could have. Err: {}",
+                    err
+                ),
+            );
+
+            return delete_possible_keys(app, topic_key, highest_archive_file_no).await;
+        }
+    };
+
+    let mut errors = 0;
+
+    for file_name in files.keys() {
+        if !delete_key(app, topic_key, file_name.as_str()).await {
+            errors += 1;
+        }
     }
 
+    errors
+}
+
+async fn delete_possible_keys(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    highest_archive_file_no: Option<ArchiveFileNo>,
+) -> usize {
+    let mut errors = 0;
+
     // Archive numbering follows from the topic's message id, so the range is exact.
     if let Some(highest) = highest_archive_file_no {
         for file_no in 0..=highest.get_value() {
             let file_name = storage_layout::get_archive_file_name(ArchiveFileNo::new(file_no));
-            delete_key(app, topic_key, file_name.as_str()).await;
+            if !delete_key(app, topic_key, file_name.as_str()).await {
+                errors += 1;
+            }
         }
     }
 
     let current_year = DateTimeAsMicroseconds::now().to_chrono_utc().year() as u32;
 
-    for year in OLDEST_POSSIBLE_YEAR..=current_year + 1 {
+    // Deleting is a background job with no deadline, so the generous range costs nothing.
+    for year in storage_layout::OLDEST_POSSIBLE_YEAR..=current_year + 1 {
         let file_name = storage_layout::get_year_index_file_name(Year::new(year));
-        delete_key(app, topic_key, file_name.as_str()).await;
+        if !delete_key(app, topic_key, file_name.as_str()).await {
+            errors += 1;
+        }
     }
 
-    delete_key(app, topic_key, storage_layout::ACTIVE_FILE_NAME).await;
+    if !delete_key(app, topic_key, storage_layout::ACTIVE_FILE_NAME).await {
+        errors += 1;
+    }
+
+    errors
 }
 
 /// A missing key is not an error - `ColdStorage::delete` already treats a 404 as success. Only a
 /// real failure is retried, and only a handful of times: an orphaned object is worse than a slow
-/// delete, but not worth blocking the job forever.
-async fn delete_key(app: &AppContext, topic_key: TopicKeyRef<'_>, file_name: &str) {
-    let Some(cold_storage) = app.get_cold_storage() else {
-        return;
+/// delete, but not worth blocking the job forever. `false` - the key is left orphaned.
+async fn delete_key(app: &AppContext, topic_key: TopicKeyRef<'_>, file_name: &str) -> bool {
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return true;
     };
 
     for attempt_no in 1..=DELETE_ATTEMPTS {
         match cold_storage.delete(topic_key, file_name).await {
-            Ok(_) => return,
+            Ok(_) => return true,
             Err(err) => {
                 if attempt_no == DELETE_ATTEMPTS {
                     write_error(
@@ -129,13 +261,15 @@ async fn delete_key(app: &AppContext, topic_key: TopicKeyRef<'_>, file_name: &st
                             topic_key, file_name, DELETE_ATTEMPTS, err
                         ),
                     );
-                    return;
+                    return false;
                 }
 
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }
     }
+
+    false
 }
 
 fn write_error(topic_key: TopicKeyRef<'_>, message: String) {
@@ -0,0 +1,610 @@
+use base64::Engine;
+use my_logger::LogEventCtx;
+use my_service_bus::abstractions::MessageId;
+use my_service_bus::shared::{
+    protobuf_models::{MessageMetaDataProtobufModel, MessageProtobufModel},
+    sub_page::SubPageId,
+};
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+use crate::{
+    app::AppContext,
+    message_pages::{SubPage, SubPageInner, SubPageReadCopy},
+    topic_key::{Namespace, TopicKeyRef},
+    topics_snapshot::protobuf_model::TopicSnapshotProtobufModel,
+};
+
+use super::OperationError;
+
+/// Messages are handed to `new_messages` in batches of at most this many, so an import holds one
+/// batch at a time whatever the size of the file.
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+/// A line or a binary record longer than this is taken for a broken file rather than a message.
+const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+/// The report lists this many colliding ids; the count goes on past it.
+const MAX_REPORTED_COLLISIONS: usize = 1000;
+
+/// The formats `export_messages` writes that carry everything a message needs. CSV is not read
+/// back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessagesImportFormat {
+    NdJson,
+    Binary,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MessagesImportRequest {
+    pub format: MessagesImportFormat,
+    /// Give the messages ids from the topic's current message id on, in the order they come,
+    /// instead of the ids in the file.
+    pub renumber_ids: bool,
+    /// Read the whole file and report, but write nothing.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MessagesImportReport {
+    pub read: usize,
+    /// Written - or, on a dry run, that would have been.
+    pub imported: usize,
+    pub collisions: usize,
+    /// The first `MAX_REPORTED_COLLISIONS` of them.
+    pub collided_ids: Vec<MessageId>,
+    pub first_message_id: Option<MessageId>,
+    pub last_message_id: Option<MessageId>,
+}
+
+/// Writes the messages of an export file into a topic, through `new_messages` - the path
+/// `SaveMessages` takes, minute index included. The topic does not have to exist; one that is not
+/// in the snapshot is added to it.
+///
+/// An id that is already stored is a collision: it is skipped and reported, and everything else
+/// is imported. A sub page is immutable once archived, so a message missing from an archived one
+/// can not be written at all - the import stops there with `InvalidMessagesImport`, rather than
+/// reporting it as imported or skipping it as if it were there. What came before is written; a
+/// dry run stops at the same message without writing anything. So a backup can fill in sub pages
+/// that were lost as a whole, but not single messages missing from an archived one.
+///
+/// The topic's message id in the snapshot is moved past the last id written. The bus node keeps
+/// its own and sends it back with every `SaveQueueSnapshot`: stop it before importing into a
+/// topic it serves, the same as for the queue changes.
+pub async fn import_messages<R: AsyncRead + Unpin>(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    request: MessagesImportRequest,
+    input: R,
+) -> Result<MessagesImportReport, OperationError> {
+    if app.namespace_deletions.is_deleting(topic_key.namespace) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            topic_key.namespace.to_string(),
+        ));
+    }
+
+    // For the whole import: a move waits until it is done rather than copying half of it.
+    let _write_guard = app.topic_writes.read(topic_key).await;
+
+    if app.topic_moves.is_moving(topic_key) {
+        return Err(OperationError::TopicIsMoving(format!(
+            "{} is being moved",
+            topic_key
+        )));
+    }
+
+    // Its data waits for a restore as it was deleted.
+    if app.topics_snapshot.is_deleted(topic_key) {
+        return Err(OperationError::TopicIsDeleted(topic_key.to_string()));
+    }
+
+    let snapshot_message_id = super::get_snapshot_message_id(app, topic_key).await;
+
+    let mut next_renumbered_id = match snapshot_message_id {
+        Some(message_id) => message_id.get_value(),
+        None if request.renumber_ids && super::move_topic::topic_exists(app, topic_key).await => {
+            return Err(OperationError::InvalidMessagesImport(format!(
+                "Topic {} has data but is not in the snapshot, so there is no message id to renumber from",
+                topic_key
+            )));
+        }
+        None => 0,
+    };
+
+    let mut reader = MessagesReader::new(input, request.format);
+    let mut report = MessagesImportReport::default();
+    let mut batch = ImportBatch::new();
+
+    while let Some(message) = reader.next().await? {
+        report.read += 1;
+
+        let message_id = if request.renumber_ids {
+            let message_id = next_renumbered_id;
+            next_renumbered_id += 1;
+            message_id
+        } else {
+            match message.message_id {
+                Some(message_id) if message_id >= 0 => message_id,
+                _ => {
+                    return Err(OperationError::InvalidMessagesImport(format!(
+                        "Message {} has no id. Import with renumbering instead",
+                        report.read
+                    )));
+                }
+            }
+        };
+
+        let message = MessageProtobufModel::new(
+            MessageId::new(message_id),
+            message.created,
+            message.data,
+            message.headers,
+        );
+
+        let sub_page_id: SubPageId = message.get_message_id().into();
+
+        if batch.is_full_or_other(sub_page_id) {
+            batch.flush(app, topic_key, request, &mut report).await?;
+        }
+
+        batch.push(sub_page_id, message);
+    }
+
+    batch.flush(app, topic_key, request, &mut report).await?;
+
+    if !request.dry_run {
+        if let Some(last_message_id) = report.last_message_id {
+            update_snapshot(app, topic_key, last_message_id).await;
+        }
+    }
+
+    my_logger::LOGGER.write_info(
+        "import_messages",
+        format!(
+            "Read {}, imported {}, collisions {}{}",
+            report.read,
+            report.imported,
+            report.collisions,
+            if request.dry_run { " (dry run)" } else { "" }
+        ),
+        LogEventCtx::new().add("topic", topic_key.to_string()),
+    );
+
+    Ok(report)
+}
+
+/// Messages of one sub page waiting to be written.
+struct ImportBatch {
+    sub_page_id: Option<SubPageId>,
+    messages: Vec<MessageProtobufModel>,
+}
+
+impl ImportBatch {
+    fn new() -> Self {
+        Self {
+            sub_page_id: None,
+            messages: Vec::new(),
+        }
+    }
+
+    fn is_full_or_other(&self, sub_page_id: SubPageId) -> bool {
+        match self.sub_page_id {
+            Some(current) => current != sub_page_id || self.messages.len() >= IMPORT_BATCH_SIZE,
+            None => false,
+        }
+    }
+
+    fn push(&mut self, sub_page_id: SubPageId, message: MessageProtobufModel) {
+        self.sub_page_id = Some(sub_page_id);
+        self.messages.push(message);
+    }
+
+    async fn flush(
+        &mut self,
+        app: &AppContext,
+        topic_key: TopicKeyRef<'_>,
+        request: MessagesImportRequest,
+        report: &mut MessagesImportReport,
+    ) -> Result<(), OperationError> {
+        let Some(sub_page_id) = self.sub_page_id.take() else {
+            return Ok(());
+        };
+
+        let messages = std::mem::take(&mut self.messages);
+
+        let stored = get_stored_sub_page(app, topic_key, sub_page_id).await?;
+
+        let mut to_import = Vec::with_capacity(messages.len());
+
+        for message in messages {
+            let message_id = message.get_message_id();
+
+            let collides = match &stored {
+                StoredSubPage::Archived(messages) => {
+                    if messages.get(message_id).is_none() {
+                        return Err(OperationError::InvalidMessagesImport(format!(
+                            "Message {} belongs to sub page {}, which is archived without it. An archived sub page is not rewritten, so the import stops here; {} messages were imported before it",
+                            message_id.get_value(),
+                            sub_page_id.get_value(),
+                            report.imported
+                        )));
+                    }
+
+                    true
+                }
+                StoredSubPage::InMemory(messages) => messages.get(message_id).is_some(),
+                StoredSubPage::Nothing => false,
+            };
+
+            if collides {
+                report.collisions += 1;
+
+                if report.collided_ids.len() < MAX_REPORTED_COLLISIONS {
+                    report.collided_ids.push(message_id);
+                }
+
+                continue;
+            }
+
+            if report.first_message_id.is_none() {
+                report.first_message_id = Some(message_id);
+            }
+
+            if report.last_message_id.map(|itm| itm.get_value()) < Some(message_id.get_value()) {
+                report.last_message_id = Some(message_id);
+            }
+
+            to_import.push(message);
+        }
+
+        report.imported += to_import.len();
+
+        if !request.dry_run && !to_import.is_empty() {
+            super::new_messages(app, topic_key, to_import.into_iter()).await;
+        }
+
+        Ok(())
+    }
+}
+
+enum StoredSubPage {
+    /// Its slot in the archive is taken - nothing more can go in. With what is in it, to tell a
+    /// message that is there from one that can not be added.
+    Archived(SubPageReadCopy),
+    InMemory(SubPageReadCopy),
+    Nothing,
+}
+
+async fn get_stored_sub_page(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_page_id: SubPageId,
+) -> Result<StoredSubPage, OperationError> {
+    if let Some(topic_data) = app.topics_list.get(topic_key) {
+        if let Some(sub_page) = topic_data.pages_list.get(sub_page_id).await {
+            match sub_page.as_ref() {
+                SubPage::Active(..) => {
+                    return Ok(StoredSubPage::InMemory(sub_page.get_all_messages().await));
+                }
+                SubPage::FromArchive(_) => {
+                    return Ok(StoredSubPage::Archived(sub_page.get_all_messages().await));
+                }
+                // A read found nothing stored and remembered it. Writes do not go into that
+                // entry, so it is dropped, and the archive asked again below.
+                SubPage::Missing(_) => {
+                    topic_data.pages_list.remove(sub_page_id).await;
+                }
+            }
+        }
+    }
+
+    let Some(payload) = super::read_stored_payload(app, topic_key, sub_page_id).await? else {
+        return Ok(StoredSubPage::Nothing);
+    };
+
+    let sub_page =
+        SubPageInner::from_compressed_payload(sub_page_id, payload.as_slice()).map_err(|err| {
+            OperationError::CorruptedSubPage(format!(
+                "Sub page {} of {}: {:?}",
+                sub_page_id.get_value(),
+                topic_key,
+                err
+            ))
+        })?;
+
+    Ok(StoredSubPage::Archived(SubPageReadCopy::new(
+        sub_page_id,
+        sub_page.get_all_messages(),
+    )))
+}
+
+/// Moves the snapshot's message id past what was written, or adds the topic to the snapshot if
+/// it is not there - without it the sub pages would never be archived.
+async fn update_snapshot(app: &AppContext, topic_key: TopicKeyRef<'_>, last_message_id: MessageId) {
+    let next_message_id = last_message_id.get_value() + 1;
+
+    let updated = app
+        .topics_snapshot
+        .update_topic(topic_key, |topic| {
+            if topic.message_id >= next_message_id {
+                return ((), false);
+            }
+
+            topic.message_id = next_message_id;
+            ((), true)
+        })
+        .await;
+
+    if updated.is_some() {
+        return;
+    }
+
+    let mut topic = TopicSnapshotProtobufModel::new(
+        &Namespace::default_namespace(),
+        String::new(),
+        MessageId::new(next_message_id),
+        vec![],
+        None,
+        0,
+    );
+    topic.set_topic_key(topic_key);
+
+    app.topics_snapshot.insert_topic(topic).await;
+}
+
+/// A message as read from the file; the id may be left out when the import renumbers.
+struct ImportedMessage {
+    message_id: Option<i64>,
+    created: DateTimeAsMicroseconds,
+    headers: Vec<MessageMetaDataProtobufModel>,
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct NdJsonMessageInputModel {
+    #[serde(default)]
+    id: Option<i64>,
+    created: String,
+    #[serde(default)]
+    metadata: Vec<MetadataInputModel>,
+    #[serde(default)]
+    encoding: Option<String>,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct MetadataInputModel {
+    key: String,
+    value: String,
+}
+
+struct MessagesReader<R> {
+    input: BufReader<R>,
+    format: MessagesImportFormat,
+    line_no: usize,
+    line: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> MessagesReader<R> {
+    fn new(input: R, format: MessagesImportFormat) -> Self {
+        Self {
+            input: BufReader::new(input),
+            format,
+            line_no: 0,
+            line: Vec::new(),
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<ImportedMessage>, OperationError> {
+        match self.format {
+            MessagesImportFormat::NdJson => self.next_ndjson().await,
+            MessagesImportFormat::Binary => self.next_binary().await,
+        }
+    }
+
+    async fn next_ndjson(&mut self) -> Result<Option<ImportedMessage>, OperationError> {
+        loop {
+            self.line.clear();
+            self.line_no += 1;
+
+            let read = self
+                .input
+                .read_until(b'\n', &mut self.line)
+                .await
+                .map_err(|err| OperationError::MessagesStreamError(format!("{}", err)))?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            if self.line.len() > MAX_RECORD_SIZE {
+                return Err(self.invalid("the line is too long".to_string()));
+            }
+
+            if self.line.iter().all(|itm| itm.is_ascii_whitespace()) {
+                continue;
+            }
+
+            let model: NdJsonMessageInputModel = serde_json::from_slice(self.line.as_slice())
+                .map_err(|err| self.invalid(format!("{}", err)))?;
+
+            return self.decode_ndjson(model).map(Some);
+        }
+    }
+
+    fn decode_ndjson(
+        &self,
+        model: NdJsonMessageInputModel,
+    ) -> Result<ImportedMessage, OperationError> {
+        let created = chrono::DateTime::parse_from_rfc3339(model.created.as_str())
+            .map_err(|err| self.invalid(format!("created: {}", err)))?;
+
+        let data = match model.encoding.as_deref() {
+            None | Some("base64") => base64::engine::general_purpose::STANDARD
+                .decode(model.content.as_str())
+                .map_err(|err| self.invalid(format!("content: {}", err)))?,
+            Some("utf8") => model.content.into_bytes(),
+            Some(other) => return Err(self.invalid(format!("unknown encoding {}", other))),
+        };
+
+        Ok(ImportedMessage {
+            message_id: model.id,
+            created: DateTimeAsMicroseconds::new(created.timestamp_micros()),
+            headers: model
+                .metadata
+                .into_iter()
+                .map(|itm| MessageMetaDataProtobufModel {
+                    key: itm.key,
+                    value: itm.value,
+                })
+                .collect(),
+            data,
+        })
+    }
+
+    async fn next_binary(&mut self) -> Result<Option<ImportedMessage>, OperationError> {
+        self.line_no += 1;
+
+        // The file may only end at a record boundary - anywhere else it was cut short.
+        let buffered = self
+            .input
+            .fill_buf()
+            .await
+            .map_err(|err| OperationError::MessagesStreamError(format!("{}", err)))?;
+
+        if buffered.is_empty() {
+            return Ok(None);
+        }
+
+        let mut len = [0u8; 4];
+
+        self.input
+            .read_exact(&mut len)
+            .await
+            .map_err(|err| self.invalid(format!("{}", err)))?;
+
+        let len = u32::from_le_bytes(len) as usize;
+
+        if len > MAX_RECORD_SIZE {
+            return Err(self.invalid(format!("a record of {} bytes", len)));
+        }
+
+        self.line.resize(len, 0);
+
+        self.input
+            .read_exact(self.line.as_mut_slice())
+            .await
+            .map_err(|err| self.invalid(format!("{}", err)))?;
+
+        let message: MessageProtobufModel = prost::Message::decode(self.line.as_slice())
+            .map_err(|err| self.invalid(format!("{}", err)))?;
+
+        Ok(Some(ImportedMessage {
+            message_id: Some(message.get_message_id().get_value()),
+            created: message.get_created(),
+            headers: message.headers,
+            data: message.data,
+        }))
+    }
+
+    fn invalid(&self, message: String) -> OperationError {
+        OperationError::InvalidMessagesImport(format!("Record {}: {}", self.line_no, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{encode_message, ContentEncoding, MessagesExportFormat};
+
+    fn create_message(message_id: i64, content: &[u8]) -> MessageProtobufModel {
+        MessageProtobufModel::new(
+            MessageId::new(message_id),
+            DateTimeAsMicroseconds::new(1_700_000_000_123_456),
+            content.to_vec(),
+            vec![MessageMetaDataProtobufModel {
+                key: "trace".to_string(),
+                value: "1".to_string(),
+            }],
+        )
+    }
+
+    async fn read_all(
+        content: &[u8],
+        format: MessagesImportFormat,
+    ) -> Result<Vec<ImportedMessage>, OperationError> {
+        let mut reader = MessagesReader::new(content, format);
+        let mut result = Vec::new();
+
+        while let Some(message) = reader.next().await? {
+            result.push(message);
+        }
+
+        Ok(result)
+    }
+
+    #[tokio::test]
+    async fn reads_back_what_the_export_writes() {
+        for (export_format, import_format) in [
+            (MessagesExportFormat::NdJson, MessagesImportFormat::NdJson),
+            (MessagesExportFormat::Binary, MessagesImportFormat::Binary),
+        ] {
+            let mut file = Vec::new();
+
+            for (message_id, content) in [(7, b"text".as_slice()), (9, &[0xff, 0x00])] {
+                encode_message(
+                    &create_message(message_id, content),
+                    export_format,
+                    ContentEncoding::Utf8,
+                    &mut file,
+                )
+                .unwrap();
+            }
+
+            let messages = read_all(file.as_slice(), import_format).await.unwrap();
+
+            assert_eq!(2, messages.len());
+            assert_eq!(Some(7), messages[0].message_id);
+            assert_eq!(b"text".to_vec(), messages[0].data);
+            assert_eq!(vec![0xff, 0x00], messages[1].data);
+            assert_eq!(1_700_000_000_123_456, messages[1].created.unix_microseconds);
+            assert_eq!("trace", messages[1].headers[0].key);
+        }
+    }
+
+    #[tokio::test]
+    async fn ndjson_ids_may_be_left_out_and_blank_lines_are_skipped() {
+        let file =
+            "{\"created\":\"2024-01-01T00:00:00Z\",\"encoding\":\"utf8\",\"content\":\"a\"}\n\n";
+
+        let messages = read_all(file.as_bytes(), MessagesImportFormat::NdJson)
+            .await
+            .unwrap();
+
+        assert_eq!(1, messages.len());
+        assert_eq!(None, messages[0].message_id);
+    }
+
+    #[tokio::test]
+    async fn a_binary_file_cut_short_is_refused() {
+        let mut file = Vec::new();
+
+        encode_message(
+            &create_message(1, b"payload"),
+            MessagesExportFormat::Binary,
+            ContentEncoding::Base64,
+            &mut file,
+        )
+        .unwrap();
+
+        file.truncate(file.len() - 2);
+
+        let result = read_all(file.as_slice(), MessagesImportFormat::Binary).await;
+
+        assert!(matches!(
+            result,
+            Err(OperationError::InvalidMessagesImport(_))
+        ));
+    }
+}
@@ -91,6 +91,16 @@ pub async fn migrate_legacy_layout(data_folder: &str) {
     let _ = tokio::fs::remove_file(in_progress_path.as_path()).await;
 }
 
+/// Whether the data folder is in the current layout, with nothing of the legacy one left to bring
+/// over. The offline commands do not migrate - they are meant to leave the folder as they found
+/// it - so they need this to be true.
+pub fn is_layout_current(data_folder: &str) -> bool {
+    let root = PathBuf::from(data_folder);
+
+    root.join(LAYOUT_MARKER_FILE_NAME).is_file()
+        && !storage_layout::get_legacy_topics_snapshot_file(data_folder).exists()
+}
+
 /// The legacy `topicsdata` holds every namespace at once; it becomes one YAML file per namespace.
 /// Topics written before namespaces existed carry no namespace field and land in `default`.
 ///
@@ -2,8 +2,8 @@ mod archive_io;
 pub mod compressed_page_compiler;
 pub mod current_sub_pages_io;
 pub mod data_initializer;
-// TODO: re-enable once soft-delete + GC flow is reimplemented (see TODO.md)
-// mod delete_topic;
+mod delete_topic;
+pub use delete_topic::*;
 mod error;
 mod gc_pages;
 
@@ -12,6 +12,24 @@ mod get_messages_from_date;
 mod get_sub_page_to_read;
 mod hard_delete_topic;
 pub use hard_delete_topic::*;
+mod move_topic;
+pub use move_topic::*;
+mod clone_topic;
+pub use clone_topic::*;
+mod bundles;
+pub use bundles::*;
+mod export_messages;
+pub use export_messages::*;
+mod import_messages;
+pub use import_messages::*;
+mod find_gaps;
+pub use find_gaps::*;
+mod scrub;
+pub use scrub::*;
+mod reconcile_cold_storage;
+pub use reconcile_cold_storage::*;
+mod namespaces;
+pub use namespaces::*;
 mod send_messages_to_channel;
 
 mod get_page_to_read;
@@ -24,14 +42,20 @@ mod migrate_legacy_topics;
 pub use migrate_legacy_topics::*;
 mod scan_topic_folders;
 pub use scan_topic_folders::*;
+mod topic_inventory;
+pub use topic_inventory::*;
+mod rebuild_index_by_minute;
+pub use rebuild_index_by_minute::*;
+mod snapshot_revisions;
+pub use snapshot_revisions::*;
+mod queue_positions;
+pub use queue_positions::*;
 
 pub mod before_shut_down;
 mod new_messages;
 mod topics;
-// pub use delete_topic::*;
 pub use error::*;
 pub use gc_pages::*;
-// pub use hard_delete_topic::*;
 
 pub use get_message_by_id::*;
 pub use get_messages_from_date::*;
@@ -42,7 +66,5 @@ pub use get_topic_data_to_write::*;
 pub use init_new_topic::*;
 pub use new_messages::*;
 pub use send_messages_to_channel::*;
-
-// TODO: re-enable with soft-delete + GC (see TODO.md)
-// mod restore_topic;
-// pub use restore_topic::*;
+mod restore_topic;
+pub use restore_topic::*;
@@ -0,0 +1,596 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::Datelike;
+use my_logger::LogEventCtx;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    app::{storage_layout, AppContext},
+    archive_storage::{ArchiveFileNo, ArchiveStorage},
+    cold_storage::{ColdStorage, RangeReadKind},
+    file_storage::delete_file_if_exists,
+    topic_key::TopicKeyRef,
+    topic_moves::{TopicMove, TopicMoveStep},
+    typing::Year,
+};
+
+use super::{hard_delete_topic::get_highest_archive_file_no, OperationError};
+
+/// A cold object is copied through a local file, this much per ranged read.
+const COPY_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A step that failed is tried again after this.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Attempts at one step before the move is left failed. A step that fails this often - a bucket
+/// that refuses us, a folder that is in the way - needs a person, and trying on every minute until
+/// then only buries the first error in the log. Both names stay blocked, and the next start tries
+/// again from the same step.
+const STEP_ATTEMPTS: usize = 30;
+
+const TEMP_FILE_SUFFIX: &str = ".moving";
+
+/// Gives a topic a new name - another topic id, another namespace or both. Everything it has goes
+/// along: the folder, the cold objects, the snapshot entry and the retention.
+///
+/// Only the checks and the journal entry happen before this returns; the data is moved by a
+/// background job. From the moment the entry is written both names refuse writes, and the entry
+/// stays until the last step is done - so a move interrupted by a crash or a restart is finished
+/// on the next start rather than left half here and half there.
+pub async fn move_topic(
+    app: &Arc<AppContext>,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+) -> Result<(), OperationError> {
+    if from == to {
+        return Err(OperationError::InvalidTopicMove(format!(
+            "Topic {} is already there",
+            from
+        )));
+    }
+
+    if !topic_exists(app.as_ref(), from).await {
+        return Err(OperationError::TopicNotFound(from.to_string()));
+    }
+
+    // The record stays under the old name, and the restore would find nothing there.
+    if app.topics_snapshot.is_deleted(from) {
+        return Err(OperationError::TopicIsDeleted(from.to_string()));
+    }
+
+    if topic_exists(app.as_ref(), to).await {
+        return Err(OperationError::TopicAlreadyExists(to.to_string()));
+    }
+
+    // The cold files would have to come back to the local disk, and the move copies objects from
+    // bucket to bucket only.
+    if app.get_cold_storage(from.namespace).is_some()
+        && app.get_cold_storage(to.namespace).is_none()
+    {
+        return Err(OperationError::InvalidTopicMove(format!(
+            "Namespace '{}' has a cold tier and '{}' does not",
+            from.namespace, to.namespace
+        )));
+    }
+
+    for topic_key in [from, to] {
+        if app.namespace_deletions.is_deleting(topic_key.namespace) {
+            return Err(OperationError::NamespaceIsBeingDeleted(
+                topic_key.namespace.to_string(),
+            ));
+        }
+    }
+
+    let started = app
+        .topic_moves
+        .start(from, to)
+        .await
+        .map_err(OperationError::FileStorageError)?;
+
+    if !started {
+        return Err(OperationError::TopicIsMoving(format!(
+            "{} or {} is already being moved",
+            from, to
+        )));
+    }
+
+    let topic_move = app
+        .topic_moves
+        .get_all()
+        .into_iter()
+        .find(|itm| itm.get_from() == from)
+        .unwrap();
+
+    tokio::spawn(run_move(app.clone(), topic_move));
+
+    Ok(())
+}
+
+/// Picks up the moves a previous run did not finish, each from the step it had recorded - a
+/// failed one too: a restart is how it is retried once whatever stopped it is sorted out.
+pub fn resume_topic_moves(app: &Arc<AppContext>) {
+    for topic_move in app.topic_moves.get_all() {
+        let failed = match topic_move.failed.as_ref() {
+            Some(err) => format!(", where it failed before: {}", err),
+            None => String::new(),
+        };
+
+        my_logger::LOGGER.write_info(
+            "MoveTopic".to_string(),
+            format!(
+                "Resuming the move to {} from step {:?}{}",
+                topic_move.get_to(),
+                topic_move.step,
+                failed
+            ),
+            LogEventCtx::new().add("topicId", topic_move.get_from().to_string()),
+        );
+
+        tokio::spawn(run_move(app.clone(), topic_move));
+    }
+}
+
+/// A topic is there as soon as any trace of it is: loaded, in the snapshot or with a folder.
+pub(super) async fn topic_exists(app: &AppContext, topic_key: TopicKeyRef<'_>) -> bool {
+    if app.topics_list.get(topic_key).is_some() {
+        return true;
+    }
+
+    let snapshot = app.topics_snapshot.get().await;
+
+    if snapshot
+        .snapshot
+        .data
+        .iter()
+        .any(|itm| itm.get_topic_key() == topic_key)
+    {
+        return true;
+    }
+
+    let folder = storage_layout::get_topic_folder(app.get_data_folder(), topic_key);
+
+    tokio::fs::try_exists(folder).await.unwrap_or(false)
+}
+
+async fn run_move(app: Arc<AppContext>, topic_move: TopicMove) {
+    let from = topic_move.get_from();
+    let to = topic_move.get_to();
+
+    // A write checked before the journal entry was there may still be on its way into the topic.
+    // Past this, every one of them is in and every later one sees the entry.
+    for topic_key in [from, to] {
+        drop(app.topic_writes.write(topic_key).await);
+    }
+
+    let mut step = topic_move.step;
+    let mut attempt_no = 0;
+
+    loop {
+        attempt_no += 1;
+
+        match execute_step(app.as_ref(), from, to, step).await {
+            Ok(Some(next_step)) => {
+                step = next_step;
+                attempt_no = 0;
+            }
+            Ok(None) => {
+                my_logger::LOGGER.write_info(
+                    "MoveTopic".to_string(),
+                    format!("Topic is moved to {}", to),
+                    LogEventCtx::new().add("topicId", from.to_string()),
+                );
+                return;
+            }
+            Err(err) if attempt_no >= STEP_ATTEMPTS => {
+                my_logger::LOGGER.write_error(
+                    "MoveTopic".to_string(),
+                    format!(
+                        "Can not move the topic to {} at step {:?} after {} attempts. The move is left failed, both names stay blocked until a restart retries it. Err: {}",
+                        to, step, attempt_no, err
+                    ),
+                    LogEventCtx::new().add("topicId", from.to_string()),
+                );
+
+                if let Err(save_err) = app.topic_moves.set_failed(from, err).await {
+                    my_logger::LOGGER.write_error(
+                        "MoveTopic".to_string(),
+                        format!("Can not record the failed move. Err: {}", save_err),
+                        LogEventCtx::new().add("topicId", from.to_string()),
+                    );
+                }
+
+                return;
+            }
+            Err(err) => {
+                my_logger::LOGGER.write_error(
+                    "MoveTopic".to_string(),
+                    format!(
+                        "Can not move the topic to {} at step {:?} (attempt {} of {}). Next attempt in {:?}. Err: {}",
+                        to, step, attempt_no, STEP_ATTEMPTS, RETRY_DELAY, err
+                    ),
+                    LogEventCtx::new().add("topicId", from.to_string()),
+                );
+
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Does one step and records it. `None` - the move is done and out of the journal.
+async fn execute_step(
+    app: &AppContext,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+    step: TopicMoveStep,
+) -> Result<Option<TopicMoveStep>, String> {
+    match step {
+        TopicMoveStep::Started => {
+            unload_topic(app, from).await?;
+            move_folder(app, from, to).await?;
+            app.topic_moves
+                .set_step(from, TopicMoveStep::FolderMoved)
+                .await?;
+            Ok(Some(TopicMoveStep::FolderMoved))
+        }
+        TopicMoveStep::FolderMoved => {
+            move_cold_files(app, from, to).await?;
+            app.topic_moves
+                .set_step(from, TopicMoveStep::ColdMoved)
+                .await?;
+            Ok(Some(TopicMoveStep::ColdMoved))
+        }
+        TopicMoveStep::ColdMoved => {
+            rename_metadata(app, from, to).await?;
+            load_topic(app, to).await;
+            app.topic_moves.finished(from).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Everything the topic holds in memory goes to disk the way a shutdown would put it there -
+/// closed sub pages archived, the open one to `active`, the minute indexes flushed - so the folder
+/// is complete before it is renamed, or set aside by a soft delete. No write can arrive meanwhile:
+/// the journal entry, or the deleted record, refuses them.
+pub(super) async fn unload_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(), String> {
+    let Some(topic_data) = app.topics_list.get(topic_key) else {
+        return Ok(());
+    };
+
+    topic_data
+        .yearly_index_by_minute
+        .save_before_shutdown()
+        .await;
+
+    while let Some(sub_page) = topic_data.pages_list.get_sub_page_to_gc().await {
+        super::archive_io::save_sub_page(app, &topic_data, &sub_page)
+            .await
+            .map_err(|err| {
+                format!(
+                    "Can not archive sub page {}: {:?}",
+                    sub_page.get_id().get_value(),
+                    err
+                )
+            })?;
+
+        topic_data.pages_list.remove(sub_page.get_id()).await;
+    }
+
+    super::current_sub_pages_io::write_topic(app, &topic_data, &[]).await;
+
+    app.topics_list.remove(topic_key);
+    app.archive_storage_list.forget_topic(topic_key);
+
+    Ok(())
+}
+
+/// A rename on the same file system - the data folder is one - so the folder is either under the
+/// old name or under the new one, never half copied. Redone after a crash, it finds the folder
+/// already moved and does nothing.
+async fn move_folder(
+    app: &AppContext,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+) -> Result<(), String> {
+    let from_folder = storage_layout::get_topic_folder(app.get_data_folder(), from);
+    let to_folder = storage_layout::get_topic_folder(app.get_data_folder(), to);
+
+    {
+        let _archives = app.archive_locks.write(from).await;
+        let _indexes = app.index_locks.write(from).await;
+
+        if path_exists(&from_folder).await? {
+            if path_exists(&to_folder).await? {
+                return Err(format!(
+                    "Both {:?} and {:?} exist - one of them has to be sorted out by hand",
+                    from_folder, to_folder
+                ));
+            }
+
+            if let Some(parent) = to_folder.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|err| format!("Can not create {:?}: {}", parent, err))?;
+            }
+
+            tokio::fs::rename(&from_folder, &to_folder)
+                .await
+                .map_err(|err| {
+                    format!(
+                        "Can not rename {:?} to {:?}: {}",
+                        from_folder, to_folder, err
+                    )
+                })?;
+        }
+    }
+
+    app.archive_locks.forget(from);
+    app.index_locks.forget(from);
+
+    Ok(())
+}
+
+/// S3 has no rename, and the client has no server side copy, so each object is copied through
+/// the new local folder: the local file itself when the topic still keeps one, otherwise a
+/// temporary download. An object is only deleted under the old name once it is under the new one.
+/// The objects are the ones the cold tier lists under the old name, as `hard_delete_topic` takes
+/// them - the snapshot's message id is not trusted to know them all.
+async fn move_cold_files(
+    app: &AppContext,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+) -> Result<(), String> {
+    let Some(cold_storage) = app.get_cold_storage(from.namespace) else {
+        return Ok(());
+    };
+
+    // A move recorded before the settings changed under it.
+    if app.get_cold_storage(to.namespace).is_none() {
+        return Err(format!(
+            "Namespace '{}' has no cold tier for the files of {}",
+            to.namespace, from
+        ));
+    }
+
+    let to_folder = storage_layout::get_topic_folder(app.get_data_folder(), to);
+
+    tokio::fs::create_dir_all(&to_folder)
+        .await
+        .map_err(|err| format!("Can not create {:?}: {}", to_folder, err))?;
+
+    let files = match cold_storage.list_topic_files(from).await {
+        Ok(files) => files.into_keys().collect(),
+        Err(err) => {
+            my_logger::LOGGER.write_warning(
+                "MoveTopic".to_string(),
+                format!(
+                    "Can not list the cold storage, probing every key the topic could have. Err: {}",
+                    err
+                ),
+                LogEventCtx::new().add("topicId", from.to_string()),
+            );
+
+            get_possible_file_names(app, from, to).await
+        }
+    };
+
+    for file_name in files {
+        let archive_file_no = storage_layout::parse_archive_file_name(file_name.as_str());
+
+        move_cold_file(
+            cold_storage,
+            from,
+            to,
+            to_folder.as_path(),
+            file_name.as_str(),
+            archive_file_no,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// The keys `hard_delete_topic` falls back to - exact for archives, generous for years. Until the
+/// last step the snapshot still carries the old name, unless the bus node has since sent one with
+/// the new name.
+async fn get_possible_file_names(
+    app: &AppContext,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+) -> Vec<String> {
+    let mut files = Vec::new();
+
+    let highest = match get_highest_archive_file_no(app, from).await {
+        Some(highest) => Some(highest),
+        None => get_highest_archive_file_no(app, to).await,
+    };
+
+    if let Some(highest) = highest {
+        for file_no in 0..=highest.get_value() {
+            files.push(storage_layout::get_archive_file_name(ArchiveFileNo::new(
+                file_no,
+            )));
+        }
+    }
+
+    let current_year = DateTimeAsMicroseconds::now().to_chrono_utc().year() as u32;
+
+    for year in storage_layout::OLDEST_POSSIBLE_YEAR..=current_year + 1 {
+        files.push(storage_layout::get_year_index_file_name(Year::new(year)));
+    }
+
+    files.push(storage_layout::ACTIVE_FILE_NAME.to_string());
+
+    files
+}
+
+async fn move_cold_file(
+    cold_storage: &Arc<ColdStorage>,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+    to_folder: &Path,
+    file_name: &str,
+    archive_file_no: Option<ArchiveFileNo>,
+) -> Result<(), String> {
+    if !cold_storage.exists(from, file_name).await? {
+        return Ok(());
+    }
+
+    if !cold_storage.exists(to, file_name).await? {
+        let local_path = to_folder.join(file_name);
+
+        if path_exists(&local_path).await? {
+            cold_storage
+                .upload_file(to, file_name, local_path.as_path())
+                .await?;
+        } else {
+            let temp_path = to_folder.join(format!("{}{}", file_name, TEMP_FILE_SUFFIX));
+
+            let result =
+                match download_to_file(cold_storage, from, file_name, archive_file_no, &temp_path)
+                    .await
+                {
+                    Ok(()) => {
+                        cold_storage
+                            .upload_file(to, file_name, temp_path.as_path())
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+
+            let _ = delete_file_if_exists(&temp_path).await;
+
+            result?;
+        }
+    }
+
+    cold_storage.delete(from, file_name).await
+}
+
+/// An archive is read in ranges - it can be far bigger than is sensible to hold in memory, and
+/// its TOC says where it ends. A year index is of a fixed, modest size and comes in one piece.
+async fn download_to_file(
+    cold_storage: &Arc<ColdStorage>,
+    topic_key: TopicKeyRef<'_>,
+    file_name: &str,
+    archive_file_no: Option<ArchiveFileNo>,
+    path: &PathBuf,
+) -> Result<(), String> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|err| format!("Can not create {:?}: {}", path, err))?;
+
+    match archive_file_no {
+        Some(archive_file_no) => {
+            let archive = ArchiveStorage::open_cold(
+                archive_file_no,
+                cold_storage.clone(),
+                topic_key.to_owned_key(),
+                file_name.to_string(),
+            );
+
+            let size = archive
+                .get_content_size()
+                .await
+                .map_err(|err| format!("{:?}", err))?;
+
+            let mut position = 0;
+
+            while position < size {
+                let end = (position + COPY_CHUNK_SIZE).min(size);
+
+                let content = cold_storage
+                    .download_range(topic_key, file_name, position, end - 1, RangeReadKind::Copy)
+                    .await?;
+
+                file.write_all(content.as_slice())
+                    .await
+                    .map_err(|err| format!("Can not write {:?}: {}", path, err))?;
+
+                position = end;
+            }
+        }
+        None => {
+            let Some(content) = cold_storage.download(topic_key, file_name).await? else {
+                return Err(format!("{}/{} is gone", topic_key, file_name));
+            };
+
+            file.write_all(content.as_slice())
+                .await
+                .map_err(|err| format!("Can not write {:?}: {}", path, err))?;
+        }
+    }
+
+    file.sync_all()
+        .await
+        .map_err(|err| format!("Can not sync {:?}: {}", path, err))
+}
+
+async fn rename_metadata(
+    app: &AppContext,
+    from: TopicKeyRef<'_>,
+    to: TopicKeyRef<'_>,
+) -> Result<(), String> {
+    let snapshot = app.topics_snapshot.get().await;
+
+    let has_to = snapshot
+        .snapshot
+        .data
+        .iter()
+        .any(|itm| itm.get_topic_key() == to);
+
+    if !has_to {
+        app.topics_snapshot
+            .update_topic(from, |topic| {
+                topic.set_topic_key(to);
+                ((), true)
+            })
+            .await;
+    }
+
+    // Set under the new name before it is dropped under the old one, so a crash in between
+    // leaves one retention too many rather than none.
+    if let Some(retention) = app.topics_retention.get(from) {
+        app.topics_retention
+            .set(to, Some(retention.get_max_age()))
+            .await?;
+        app.topics_retention.set(from, None).await?;
+    }
+
+    Ok(())
+}
+
+/// The open sub page went to `active` with the folder; loading it back is what a start would do.
+pub(super) async fn load_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) {
+    if app.topics_list.get(topic_key).is_some() {
+        return;
+    }
+
+    let restored = super::current_sub_pages_io::restore_topic(app, topic_key).await;
+
+    if restored.is_empty() {
+        return;
+    }
+
+    let topic_data = app.topics_list.init_topic_data(topic_key);
+
+    for restored in restored {
+        topic_data.pages_list.insert(restored.sub_page).await;
+    }
+}
+
+async fn path_exists(path: &Path) -> Result<bool, String> {
+    tokio::fs::try_exists(path)
+        .await
+        .map_err(|err| format!("Can not check {:?}: {}", path, err))
+}
@@ -0,0 +1,299 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use my_logger::LogEventCtx;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{
+    app::{storage_layout, AppContext},
+    archive_storage::ArchiveFileNo,
+    file_storage::delete_folder_if_exists,
+    topic_key::TopicKeyRef,
+    topics_snapshot::{
+        file_storage::read_to_string_if_exists, yaml_model::TopicsAndQueuesSnapshotYamlModel,
+    },
+};
+
+use super::OperationError;
+
+/// The namespace-level files, as they would be written now.
+pub struct NamespaceExport {
+    pub snapshot_yaml: String,
+    /// `None` - no topic of the namespace has a retention.
+    pub retention_yaml: Option<String>,
+}
+
+/// Every topic of the namespace that has any trace - in the snapshot, loaded, or a folder.
+/// Sorted.
+pub async fn get_namespace_topics(app: &AppContext, namespace: &str) -> BTreeSet<String> {
+    let mut result = BTreeSet::new();
+
+    let snapshot = app.topics_snapshot.get().await;
+
+    for topic in snapshot.snapshot.data.iter() {
+        if topic.get_namespace() == namespace {
+            result.insert(topic.topic_id.clone());
+        }
+    }
+
+    for topic_data in app.topics_list.get_all().iter() {
+        if topic_data.namespace == namespace {
+            result.insert(topic_data.topic_id.clone());
+        }
+    }
+
+    for topic_key in super::scan_topic_folders(app.get_data_folder()).await {
+        if topic_key.namespace == namespace {
+            result.insert(topic_key.topic_id);
+        }
+    }
+
+    result
+}
+
+/// A namespace is there while it has a folder or a topic anywhere.
+pub(super) async fn namespace_exists(app: &AppContext, namespace: &str) -> bool {
+    let folder = storage_layout::get_local_path(app.get_data_folder(), namespace);
+
+    if tokio::fs::try_exists(folder).await.unwrap_or(false) {
+        return true;
+    }
+
+    !get_namespace_topics(app, namespace).await.is_empty()
+}
+
+/// The snapshot comes from memory rather than from the file - the file can be a save behind.
+pub async fn export_namespace(
+    app: &AppContext,
+    namespace: &str,
+) -> Result<NamespaceExport, OperationError> {
+    if !namespace_exists(app, namespace).await {
+        return Err(OperationError::NamespaceNotFound(namespace.to_string()));
+    }
+
+    let snapshot = app.topics_snapshot.get().await;
+
+    let model = TopicsAndQueuesSnapshotYamlModel::from_domain(
+        namespace,
+        snapshot.snapshot.data.as_slice(),
+        snapshot.snapshot.deleted_topics.as_slice(),
+    );
+
+    let snapshot_yaml = serde_yaml::to_string(&model).map_err(|err| {
+        OperationError::FileStorageError(format!(
+            "Can not serialize the snapshot of '{}': {}",
+            namespace, err
+        ))
+    })?;
+
+    let retention_path =
+        storage_layout::get_namespace_retention_file(app.get_data_folder(), namespace);
+
+    Ok(NamespaceExport {
+        snapshot_yaml,
+        retention_yaml: read_to_string_if_exists(&retention_path).await,
+    })
+}
+
+/// Retires a whole namespace: its snapshot records, every topic the way `hard_delete_topic` removes
+/// one, its snapshot revisions in the cold tier, and the namespace folder with everything left in
+/// it.
+///
+/// The checks happen before this returns; the rest is a background job whose progress is kept in
+/// `app.namespace_deletions`. From the start the namespace refuses writes. The bus node has to be
+/// told as well - a `SaveQueueSnapshot` with the namespace's topics in it after the deletion would
+/// bring them back as empty topics.
+pub async fn delete_namespace(
+    app: &Arc<AppContext>,
+    namespace: &str,
+) -> Result<(), OperationError> {
+    if !namespace_exists(app.as_ref(), namespace).await {
+        return Err(OperationError::NamespaceNotFound(namespace.to_string()));
+    }
+
+    let topics = get_namespace_topics(app.as_ref(), namespace).await;
+
+    for topic_id in topics.iter() {
+        let topic_key = TopicKeyRef::new(namespace, topic_id.as_str());
+
+        if app.topic_moves.is_moving(topic_key) {
+            return Err(OperationError::TopicIsMoving(format!(
+                "{} is being moved",
+                topic_key
+            )));
+        }
+    }
+
+    let started = app
+        .namespace_deletions
+        .start(namespace, topics.len(), DateTimeAsMicroseconds::now())
+        .await
+        .map_err(OperationError::FileStorageError)?;
+
+    if !started {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            namespace.to_string(),
+        ));
+    }
+
+    spawn_delete(app, namespace, topics).await;
+
+    Ok(())
+}
+
+/// Picks up the deletions a previous run did not finish. They refuse writes from the start - the
+/// record is loaded with the context - and wipe whatever of the namespace is still there.
+pub async fn resume_namespace_deletions(app: &Arc<AppContext>) {
+    for namespace in app.namespace_deletions.get_interrupted() {
+        let topics = get_namespace_topics(app.as_ref(), namespace.as_str()).await;
+
+        app.namespace_deletions
+            .resumed(namespace.as_str(), topics.len());
+
+        my_logger::LOGGER.write_info(
+            "delete_namespace".to_string(),
+            format!("Resuming the deletion, {} topics left", topics.len()),
+            LogEventCtx::new().add("namespace", namespace.to_string()),
+        );
+
+        spawn_delete(app, namespace.as_str(), topics).await;
+    }
+}
+
+async fn spawn_delete(app: &Arc<AppContext>, namespace: &str, topics: BTreeSet<String>) {
+    let mut to_delete = Vec::with_capacity(topics.len());
+
+    for topic_id in topics {
+        let topic_key = TopicKeyRef::new(namespace, topic_id.as_str());
+        let highest_archive_file_no =
+            super::hard_delete_topic::get_highest_archive_file_no(app.as_ref(), topic_key).await;
+        to_delete.push((topic_id, highest_archive_file_no));
+    }
+
+    // Out of the snapshot first: the snapshots the bus node sends meanwhile leave the namespace
+    // out, and the guard would see every one of them as a node that lost a namespace.
+    app.topics_snapshot
+        .replace_namespace(namespace, vec![])
+        .await;
+
+    tokio::spawn(run_delete(app.clone(), namespace.to_string(), to_delete));
+}
+
+async fn run_delete(
+    app: Arc<AppContext>,
+    namespace: String,
+    topics: Vec<(String, Option<ArchiveFileNo>)>,
+) {
+    my_logger::LOGGER.write_info(
+        "delete_namespace".to_string(),
+        format!("Deleting {} topics", topics.len()),
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+
+    for (topic_id, highest_archive_file_no) in topics {
+        let topic_key = TopicKeyRef::new(namespace.as_str(), topic_id.as_str());
+
+        app.namespace_deletions
+            .topic_started(namespace.as_str(), topic_id.as_str());
+
+        app.topics_list.remove(topic_key);
+        app.archive_storage_list.forget_topic(topic_key);
+
+        let errors =
+            super::hard_delete_topic::wipe_topic(app.as_ref(), topic_key, highest_archive_file_no)
+                .await;
+
+        app.namespace_deletions
+            .topic_deleted(namespace.as_str(), errors);
+    }
+
+    let mut errors = delete_cold_revisions(app.as_ref(), namespace.as_str()).await;
+
+    // The snapshot saver writes a file for every namespace that still has a folder - with the
+    // folder gone, there is nothing left to bring it back.
+    let folder = storage_layout::get_local_path(app.get_data_folder(), namespace.as_str());
+
+    if let Err(err) = delete_folder_if_exists(folder.as_path()).await {
+        errors += 1;
+        write_error(
+            namespace.as_str(),
+            format!("Can not delete {:?}. Err: {}", folder, err),
+        );
+    }
+
+    if let Err(err) = app
+        .namespace_deletions
+        .finished(namespace.as_str(), errors, DateTimeAsMicroseconds::now())
+        .await
+    {
+        write_error(
+            namespace.as_str(),
+            format!(
+                "Can not record the deletion as finished; the next start runs it again. Err: {}",
+                err
+            ),
+        );
+    }
+
+    let progress = app.namespace_deletions.get(namespace.as_str());
+
+    my_logger::LOGGER.write_info(
+        "delete_namespace".to_string(),
+        format!(
+            "Namespace is deleted. Files left orphaned: {}",
+            progress.map(|itm| itm.errors).unwrap_or(errors)
+        ),
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+}
+
+/// The revisions the history still keeps locally are the ones it may have uploaded; anything it
+/// pruned earlier is not known by name any more and stays in the bucket.
+async fn delete_cold_revisions(app: &AppContext, namespace: &str) -> usize {
+    let Some(cold_storage) = app.get_cold_storage(namespace) else {
+        return 0;
+    };
+
+    let folder = storage_layout::get_local_path(app.get_data_folder(), namespace);
+
+    let Ok(mut entries) = tokio::fs::read_dir(folder).await else {
+        return 0;
+    };
+
+    let mut errors = 0;
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name();
+
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+
+        if storage_layout::parse_snapshot_revision_file_name(file_name).is_none() {
+            continue;
+        }
+
+        if let Err(err) = cold_storage
+            .delete_namespace_file(namespace, file_name)
+            .await
+        {
+            errors += 1;
+            write_error(
+                namespace,
+                format!(
+                    "Can not delete {} from the cold storage. It stays orphaned. Err: {}",
+                    file_name, err
+                ),
+            );
+        }
+    }
+
+    errors
+}
+
+fn write_error(namespace: &str, message: String) {
+    my_logger::LOGGER.write_error(
+        "delete_namespace",
+        message,
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+}
@@ -0,0 +1,187 @@
+use my_service_bus::abstractions::MessageId;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{
+    app::AppContext,
+    topic_key::TopicKeyRef,
+    topics_snapshot::{QueueRangeProtobufModel, QueueSnapshotProtobufModel},
+};
+
+use super::OperationError;
+
+/// `Permanent`, `AutoDelete` and `PermanentWithSingleConnection` of `QueueTypePersistenceGrpcEnum`.
+const MAX_QUEUE_TYPE: i32 = 2;
+
+/// The queues of a topic as the snapshot has them, and the topic's next message id.
+///
+/// Every change below goes to the snapshot and is served with the next `GetQueueSnapshot`. The
+/// bus node only reads it on start, and until then it saves its own queues over the change with
+/// every `SaveQueueSnapshot` - so stop the node, change, start it.
+pub async fn get_queues(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(MessageId, Vec<QueueSnapshotProtobufModel>), OperationError> {
+    let snapshot = app.topics_snapshot.get().await;
+
+    let topic = snapshot
+        .snapshot
+        .data
+        .into_iter()
+        .find(|itm| itm.get_topic_key() == topic_key)
+        .ok_or_else(|| OperationError::TopicNotFound(topic_key.to_string()))?;
+
+    Ok((topic.get_message_id(), topic.queues))
+}
+
+/// Makes `message_id` the next message the queue delivers - back to rewind a consumer, forward to
+/// skip. Whatever was not delivered before it is dropped: the queue becomes a single range up to
+/// the topic's last message.
+pub async fn set_queue_position(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    queue_id: &str,
+    message_id: MessageId,
+) -> Result<QueueSnapshotProtobufModel, OperationError> {
+    if message_id.get_value() < 0 {
+        return Err(OperationError::InvalidQueueEdit(
+            "MessageId can not be negative".to_string(),
+        ));
+    }
+
+    edit_queue(app, topic_key, queue_id, |queue, topic_message_id| {
+        queue.ranges = vec![get_range_from(message_id, topic_message_id)];
+    })
+    .await
+}
+
+/// The same, from the first message stored at or after `from`, found through the minute index.
+/// Nothing stored since then - the queue is moved to the end of the topic.
+pub async fn set_queue_position_from_date(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    queue_id: &str,
+    from: DateTimeAsMicroseconds,
+) -> Result<QueueSnapshotProtobufModel, OperationError> {
+    let topic_data = app.topics_list.get(topic_key);
+
+    let message_id =
+        super::find_first_indexed_message_id(app, topic_key, topic_data.as_deref(), from).await?;
+
+    let message_id = match message_id {
+        Some(message_id) => message_id,
+        None => get_queues(app, topic_key).await?.0,
+    };
+
+    set_queue_position(app, topic_key, queue_id, message_id).await
+}
+
+pub async fn set_queue_type(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    queue_id: &str,
+    queue_type: i32,
+) -> Result<QueueSnapshotProtobufModel, OperationError> {
+    if !(0..=MAX_QUEUE_TYPE).contains(&queue_type) {
+        return Err(OperationError::InvalidQueueEdit(format!(
+            "Unknown queue type {}",
+            queue_type
+        )));
+    }
+
+    edit_queue(app, topic_key, queue_id, |queue, _| {
+        queue.queue_type = queue_type;
+    })
+    .await
+}
+
+/// A queue that still has subscribers is created again by the bus node on its next subscribe.
+pub async fn delete_queue(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    queue_id: &str,
+) -> Result<(), OperationError> {
+    let result = app
+        .topics_snapshot
+        .update_topic(topic_key, |topic| {
+            let queues_before = topic.queues.len();
+            topic.queues.retain(|itm| itm.queue_id != queue_id);
+            let deleted = topic.queues.len() < queues_before;
+            (deleted, deleted)
+        })
+        .await;
+
+    match result {
+        None => return Err(OperationError::TopicNotFound(topic_key.to_string())),
+        Some(false) => return Err(OperationError::QueueNotFound(queue_id.to_string())),
+        Some(true) => {}
+    }
+
+    app.topics_snapshot.flush_topics_snapshot_to_blob().await;
+
+    Ok(())
+}
+
+async fn edit_queue(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    queue_id: &str,
+    edit: impl FnOnce(&mut QueueSnapshotProtobufModel, MessageId),
+) -> Result<QueueSnapshotProtobufModel, OperationError> {
+    let result = app
+        .topics_snapshot
+        .update_topic(topic_key, |topic| {
+            let topic_message_id = topic.get_message_id();
+
+            match topic.queues.iter_mut().find(|itm| itm.queue_id == queue_id) {
+                Some(queue) => {
+                    edit(queue, topic_message_id);
+                    (Some(queue.clone()), true)
+                }
+                None => (None, false),
+            }
+        })
+        .await;
+
+    let queue = match result {
+        None => return Err(OperationError::TopicNotFound(topic_key.to_string())),
+        Some(None) => return Err(OperationError::QueueNotFound(queue_id.to_string())),
+        Some(Some(queue)) => queue,
+    };
+
+    app.topics_snapshot.flush_topics_snapshot_to_blob().await;
+
+    Ok(queue)
+}
+
+/// The topic's message id is the next one to be assigned, so the last message is one below it.
+/// A queue with nothing to deliver is the bus node's empty range: `from` one past `to`.
+fn get_range_from(message_id: MessageId, topic_message_id: MessageId) -> QueueRangeProtobufModel {
+    let to_id = topic_message_id.get_value() - 1;
+    let from_id = message_id.get_value().min(to_id + 1);
+
+    QueueRangeProtobufModel::new(from_id, to_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use my_service_bus::abstractions::AsMessageId;
+
+    use super::get_range_from;
+
+    #[test]
+    fn the_range_runs_to_the_last_message_of_the_topic() {
+        let range = get_range_from(40.as_message_id(), 100.as_message_id());
+        assert_eq!((40, 99), range_of(&range));
+
+        // Past the end - nothing to deliver
+        let range = get_range_from(150.as_message_id(), 100.as_message_id());
+        assert_eq!((100, 99), range_of(&range));
+    }
+
+    fn range_of(range: &crate::topics_snapshot::QueueRangeProtobufModel) -> (i64, i64) {
+        (
+            range.get_from_id().get_value(),
+            range.get_to_id().get_value(),
+        )
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use my_logger::LogEventCtx;
+use my_service_bus::{abstractions::MessageId, shared::protobuf_models::MessageProtobufModel};
+
+use crate::{
+    app::AppContext,
+    index_by_minute::{MinuteWithinYear, YearlyIndexByMinute},
+    topic_key::TopicKeyRef,
+    typing::Year,
+};
+
+use super::OperationError;
+
+/// year -> minute -> the lowest message id created within it
+pub(super) type MinutesOfYears = BTreeMap<u32, BTreeMap<MinuteWithinYear, MessageId>>;
+
+/// Rebuilds a topic's minute index from the messages themselves - for an index that was lost,
+/// never written (the write path did not register it for a while, see TODO.md) or is suspected
+/// to be wrong.
+///
+/// Every stored sub page is read, so this is as slow as reading the whole topic and runs as a
+/// background job; the outcome goes to the log. Each year index is replaced in one write, under
+/// the exclusive index lock. Messages arriving meanwhile keep being indexed as usual - the queue
+/// only ever fills a slot the rebuild left empty.
+pub fn rebuild_index_by_minute(app: &Arc<AppContext>, topic_key: TopicKeyRef<'_>) {
+    let app = app.clone();
+    let topic_key = topic_key.to_owned_key();
+
+    tokio::spawn(async move {
+        match rebuild(app.as_ref(), topic_key.to_ref()).await {
+            Ok(years) => {
+                my_logger::LOGGER.write_info(
+                    "rebuild_index_by_minute",
+                    format!("Rebuilt the minute index of {} years", years),
+                    LogEventCtx::new().add("topic", topic_key.to_string()),
+                );
+            }
+            Err(err) => {
+                my_logger::LOGGER.write_error(
+                    "rebuild_index_by_minute",
+                    format!("Can not rebuild the minute index. Err: {:?}", err),
+                    LogEventCtx::new().add("topic", topic_key.to_string()),
+                );
+            }
+        }
+    });
+}
+
+async fn rebuild(app: &AppContext, topic_key: TopicKeyRef<'_>) -> Result<usize, OperationError> {
+    let inventory = super::get_topic_inventory(app, topic_key).await?;
+
+    let mut minutes: MinutesOfYears = BTreeMap::new();
+
+    for archive in inventory.archives.iter() {
+        for sub_page_id in archive.stored_sub_pages.iter() {
+            let sub_page = super::read_stored_sub_page(app, topic_key, *sub_page_id).await?;
+
+            if let Some(sub_page) = sub_page {
+                for message in sub_page.messages.iter() {
+                    add_message(app, &mut minutes, message);
+                }
+            }
+        }
+    }
+
+    // Sub pages not archived yet exist in memory only.
+    if let Some(topic_data) = app.topics_list.get(topic_key) {
+        for sub_page in topic_data.pages_list.get_all().await {
+            for message in sub_page.get_all_messages().await.iter() {
+                add_message(app, &mut minutes, message);
+            }
+        }
+    }
+
+    // A year that has an index but not a single message any more is emptied rather than left
+    // pointing at nothing.
+    for year_index in inventory.year_indexes.iter() {
+        minutes.entry(year_index.year.get_value()).or_default();
+    }
+
+    let years = minutes.len();
+
+    write_minutes(app, topic_key, minutes).await;
+
+    Ok(years)
+}
+
+/// Each year index is replaced in one write, under the exclusive index lock.
+pub(super) async fn write_minutes(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    minutes: MinutesOfYears,
+) {
+    for (year, message_ids) in minutes {
+        let _guard = app.index_locks.write(topic_key).await;
+
+        let yearly_index = get_yearly_index(app, topic_key, Year::new(year)).await;
+        yearly_index.rewrite(&message_ids).await;
+    }
+}
+
+pub(super) fn add_message(
+    app: &AppContext,
+    minutes: &mut MinutesOfYears,
+    message: &MessageProtobufModel,
+) {
+    let (minute, year) = app
+        .index_by_minute_utils
+        .get_minute_within_the_year(message.get_created());
+
+    let message_id = message.get_message_id();
+
+    let slot = minutes
+        .entry(year.get_value())
+        .or_default()
+        .entry(minute)
+        .or_insert(message_id);
+
+    if message_id.get_value() < slot.get_value() {
+        *slot = message_id;
+    }
+}
+
+/// The registered instance when the topic is loaded, so its queue and the file stay one index.
+async fn get_yearly_index(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    year: Year,
+) -> Arc<YearlyIndexByMinute> {
+    let Some(topic_data) = app.topics_list.get(topic_key) else {
+        return Arc::new(app.open_or_create_index_by_minute(topic_key, year).await);
+    };
+
+    if let Some(yearly_index) = topic_data.yearly_index_by_minute.get(year, None).await {
+        return yearly_index;
+    }
+
+    let yearly_index = Arc::new(app.open_or_create_index_by_minute(topic_key, year).await);
+
+    topic_data
+        .yearly_index_by_minute
+        .add(year, yearly_index.clone())
+        .await;
+
+    yearly_index
+}
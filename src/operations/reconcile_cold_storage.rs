@@ -0,0 +1,496 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use my_logger::LogEventCtx;
+
+use crate::{
+    app::{storage_layout, AppContext},
+    cold_storage::ColdFile,
+    timers::cold_storage_uploader,
+    topic_key::TopicKeyRef,
+    topics_snapshot::file_storage::scan_namespaces,
+};
+
+#[derive(Debug)]
+pub enum ColdReconcileFinding {
+    /// Objects of a topic that is gone - not in the snapshot, not loaded, no folder. A hard
+    /// delete that ran out of attempts leaves these, and so did the probing of years and archive
+    /// numbers before listing. Repaired by deleting exactly what was listed.
+    OrphanedTopic {
+        topic_id: String,
+        files: usize,
+        bytes: u64,
+        repaired: bool,
+    },
+    /// A sealed local file the cold tier does not have. The uploader takes it on its next tick,
+    /// unless the cold tier keeps refusing it. Repaired by uploading the topic's sealed files
+    /// right away.
+    NotUploaded {
+        topic_id: String,
+        file_name: String,
+        size: u64,
+        repaired: bool,
+    },
+    /// A sealed local archive the cold tier already has, with the same size - an upload that got
+    /// through and a delete of the local copy that did not. Repaired by dropping the local copy.
+    /// A size that differs is left to the scrub.
+    UploadedStillLocal {
+        topic_id: String,
+        file_name: String,
+        size: u64,
+        repaired: bool,
+    },
+    /// What the cold usage records for a topic is not what was listed. Repaired by setting it to
+    /// the listing.
+    UsageOutOfDate {
+        topic_id: String,
+        recorded_bytes: u64,
+        listed_bytes: u64,
+        repaired: bool,
+    },
+    /// The namespace was not looked at - it is being deleted, or it could not be listed.
+    Skipped { reason: String },
+}
+
+impl ColdReconcileFinding {
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            ColdReconcileFinding::OrphanedTopic { .. } => "OrphanedTopic",
+            ColdReconcileFinding::NotUploaded { .. } => "NotUploaded",
+            ColdReconcileFinding::UploadedStillLocal { .. } => "UploadedStillLocal",
+            ColdReconcileFinding::UsageOutOfDate { .. } => "UsageOutOfDate",
+            ColdReconcileFinding::Skipped { .. } => "Skipped",
+        }
+    }
+
+    /// `None` for a finding about the whole namespace.
+    pub fn get_topic_id(&self) -> Option<&str> {
+        match self {
+            ColdReconcileFinding::OrphanedTopic { topic_id, .. } => Some(topic_id.as_str()),
+            ColdReconcileFinding::NotUploaded { topic_id, .. } => Some(topic_id.as_str()),
+            ColdReconcileFinding::UploadedStillLocal { topic_id, .. } => Some(topic_id.as_str()),
+            ColdReconcileFinding::UsageOutOfDate { topic_id, .. } => Some(topic_id.as_str()),
+            ColdReconcileFinding::Skipped { .. } => None,
+        }
+    }
+
+    pub fn get_description(&self) -> String {
+        match self {
+            ColdReconcileFinding::OrphanedTopic { files, bytes, .. } => format!(
+                "{} objects, {} bytes, of a topic that is not there any more",
+                files, bytes
+            ),
+            ColdReconcileFinding::NotUploaded {
+                file_name, size, ..
+            } => format!(
+                "{} is sealed, {} bytes, and not in the cold tier",
+                file_name, size
+            ),
+            ColdReconcileFinding::UploadedStillLocal {
+                file_name, size, ..
+            } => format!(
+                "{} is in the cold tier and still on the local disk, {} bytes",
+                file_name, size
+            ),
+            ColdReconcileFinding::UsageOutOfDate {
+                recorded_bytes,
+                listed_bytes,
+                ..
+            } => format!(
+                "The cold usage records {} bytes, the cold tier lists {}",
+                recorded_bytes, listed_bytes
+            ),
+            ColdReconcileFinding::Skipped { reason } => reason.clone(),
+        }
+    }
+
+    pub fn is_repaired(&self) -> bool {
+        match self {
+            ColdReconcileFinding::OrphanedTopic { repaired, .. } => *repaired,
+            ColdReconcileFinding::NotUploaded { repaired, .. } => *repaired,
+            ColdReconcileFinding::UploadedStillLocal { repaired, .. } => *repaired,
+            ColdReconcileFinding::UsageOutOfDate { repaired, .. } => *repaired,
+            ColdReconcileFinding::Skipped { .. } => false,
+        }
+    }
+}
+
+pub struct NamespaceReconcileReport {
+    pub namespace: String,
+    /// Objects the listing found, and their bytes.
+    pub listed_objects: usize,
+    pub listed_bytes: u64,
+    pub findings: Vec<ColdReconcileFinding>,
+}
+
+/// Lists the cold tier of `namespace`, or of every namespace known locally, and holds it against
+/// the snapshot and the local disk: objects of topics that are gone, sealed files that never went
+/// up, local copies of archives that did, and cold usage that drifted from what is there.
+///
+/// With `repair`, each of them is fixed with exact deletions and uploads - the listing says which
+/// keys there are, so nothing is probed.
+///
+/// A topic being moved is never an orphan: for the length of the move its objects are in both
+/// places on purpose. A namespace being deleted is skipped as a whole - its deletion is already
+/// removing everything the listing would find.
+///
+/// A listing is a request per thousand objects, and nothing is read or downloaded. Uploads done
+/// meanwhile by the timer can show up as `NotUploaded` that needed no repair, and a repaired cold
+/// usage can miss them until the next run.
+pub async fn reconcile_cold_storage(
+    app: &AppContext,
+    namespace: Option<&str>,
+    repair: bool,
+) -> Vec<NamespaceReconcileReport> {
+    if !app.has_cold_storage() {
+        return vec![];
+    }
+
+    let namespaces: BTreeSet<String> = match namespace {
+        Some(namespace) => BTreeSet::from([namespace.to_string()]),
+        None => get_namespaces(app).await,
+    };
+
+    let mut result = Vec::with_capacity(namespaces.len());
+
+    for namespace in namespaces {
+        let report = reconcile_namespace(app, namespace.as_str(), repair).await;
+
+        for finding in report.findings.iter() {
+            my_logger::LOGGER.write_warning(
+                "reconcile_cold_storage",
+                format!("{}: {}", finding.get_kind(), finding.get_description()),
+                LogEventCtx::new()
+                    .add("namespace", namespace.to_string())
+                    .add(
+                        "topic",
+                        finding.get_topic_id().unwrap_or_default().to_string(),
+                    ),
+            );
+        }
+
+        result.push(report);
+    }
+
+    result
+}
+
+/// Every namespace with a trace here - a folder, a topic in the snapshot, or cold usage.
+async fn get_namespaces(app: &AppContext) -> BTreeSet<String> {
+    let mut result = BTreeSet::new();
+
+    for namespace in scan_namespaces(app.get_data_folder()).await {
+        result.insert(namespace.as_str().to_string());
+    }
+
+    for topic_key in app.topics_snapshot.get_topics_list().await {
+        result.insert(topic_key.namespace);
+    }
+
+    for (topic_key, _) in app.cold_usage.get_all() {
+        result.insert(topic_key.namespace);
+    }
+
+    result
+}
+
+async fn reconcile_namespace(
+    app: &AppContext,
+    namespace: &str,
+    repair: bool,
+) -> NamespaceReconcileReport {
+    let mut report = NamespaceReconcileReport {
+        namespace: namespace.to_string(),
+        listed_objects: 0,
+        listed_bytes: 0,
+        findings: vec![],
+    };
+
+    if app.namespace_deletions.is_deleting(namespace) {
+        report.findings.push(ColdReconcileFinding::Skipped {
+            reason: "The namespace is being deleted".to_string(),
+        });
+        return report;
+    }
+
+    let Some(cold_storage) = app.get_cold_storage(namespace) else {
+        report.findings.push(ColdReconcileFinding::Skipped {
+            reason: "The namespace has no cold tier".to_string(),
+        });
+        return report;
+    };
+
+    let listed = match cold_storage.list_namespace_files(namespace).await {
+        Ok(listed) => listed,
+        Err(err) => {
+            report.findings.push(ColdReconcileFinding::Skipped {
+                reason: format!("Can not list the cold storage. Err: {}", err),
+            });
+            return report;
+        }
+    };
+
+    report.listed_objects = listed.len();
+    report.listed_bytes = listed.iter().map(|itm| itm.size).sum();
+
+    let listed = group_by_topic(listed);
+
+    // Taken after the listing: a topic created meanwhile is then known here, while its objects
+    // could not have been listed yet.
+    let topics = super::get_namespace_topics(app, namespace).await;
+
+    for (topic_id, files) in listed.iter() {
+        let topic_key = TopicKeyRef::new(namespace, topic_id.as_str());
+
+        if topics.contains(topic_id) || app.topic_moves.is_moving(topic_key) {
+            continue;
+        }
+
+        let repaired = if repair {
+            delete_files(app, topic_key, files).await
+        } else {
+            false
+        };
+
+        report.findings.push(ColdReconcileFinding::OrphanedTopic {
+            topic_id: topic_id.clone(),
+            files: files.len(),
+            bytes: files.values().sum(),
+            repaired,
+        });
+    }
+
+    for topic_id in topics.iter() {
+        let topic_key = TopicKeyRef::new(namespace, topic_id.as_str());
+
+        let files = listed.get(topic_id).cloned().unwrap_or_default();
+
+        reconcile_topic(app, topic_key, &files, repair, &mut report.findings).await;
+    }
+
+    reconcile_usage(
+        app,
+        namespace,
+        &topics,
+        &listed,
+        repair,
+        &mut report.findings,
+    )
+    .await;
+
+    report
+}
+
+async fn reconcile_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    listed: &BTreeMap<String, u64>,
+    repair: bool,
+    findings: &mut Vec<ColdReconcileFinding>,
+) {
+    let mut not_uploaded = Vec::new();
+
+    for sealed_file in cold_storage_uploader::get_sealed_files_of_topic(app, topic_key).await {
+        let file_name = sealed_file.file_name.as_str();
+
+        let Some(listed_size) = listed.get(file_name) else {
+            not_uploaded.push((sealed_file.file_name, sealed_file.size));
+            continue;
+        };
+
+        // A year index takes late writes, so its cold copy being there says nothing about the
+        // local one - the uploader sends it again.
+        let Some(archive_file_no) = sealed_file.archive_file_no else {
+            continue;
+        };
+
+        if *listed_size != sealed_file.size {
+            continue;
+        }
+
+        let repaired = if repair {
+            cold_storage_uploader::drop_uploaded_archive(app, topic_key, file_name, archive_file_no)
+                .await
+        } else {
+            false
+        };
+
+        findings.push(ColdReconcileFinding::UploadedStillLocal {
+            topic_id: topic_key.topic_id.to_string(),
+            file_name: sealed_file.file_name,
+            size: sealed_file.size,
+            repaired,
+        });
+    }
+
+    if not_uploaded.is_empty() {
+        return;
+    }
+
+    if repair {
+        cold_storage_uploader::upload_sealed_files_of_topic(app, topic_key).await;
+    }
+
+    for (file_name, size) in not_uploaded {
+        let repaired = repair && !is_local_file(app, topic_key, file_name.as_str()).await;
+
+        findings.push(ColdReconcileFinding::NotUploaded {
+            topic_id: topic_key.topic_id.to_string(),
+            file_name,
+            size,
+            repaired,
+        });
+    }
+}
+
+/// Topics with the cold usage out of step with the listing, orphans left out - a repaired one is
+/// forgotten by its deletes, one that is not stays as it was found.
+async fn reconcile_usage(
+    app: &AppContext,
+    namespace: &str,
+    topics: &BTreeSet<String>,
+    listed: &BTreeMap<String, BTreeMap<String, u64>>,
+    repair: bool,
+    findings: &mut Vec<ColdReconcileFinding>,
+) {
+    let mut recorded: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+
+    for (topic_key, _) in app.cold_usage.get_all() {
+        if topic_key.namespace == namespace {
+            let files = app.cold_usage.get_topic_files(topic_key.to_ref());
+            recorded.insert(topic_key.topic_id, files);
+        }
+    }
+
+    let topic_ids: BTreeSet<&String> = recorded.keys().chain(listed.keys()).collect();
+
+    for topic_id in topic_ids {
+        if !topics.contains(topic_id) && listed.contains_key(topic_id) {
+            continue;
+        }
+
+        let topic_key = TopicKeyRef::new(namespace, topic_id.as_str());
+
+        let recorded_files = recorded.get(topic_id).cloned().unwrap_or_default();
+        let listed_files = listed.get(topic_id).cloned().unwrap_or_default();
+
+        if recorded_files == listed_files {
+            continue;
+        }
+
+        let recorded_bytes: u64 = recorded_files.values().sum();
+        let listed_bytes: u64 = listed_files.values().sum();
+
+        let repaired = if repair {
+            match app
+                .cold_usage
+                .set_topic_files(topic_key, listed_files)
+                .await
+            {
+                Ok(_) => true,
+                Err(err) => {
+                    write_error(
+                        topic_key,
+                        format!("Can not update the cold usage. Err: {}", err),
+                    );
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        findings.push(ColdReconcileFinding::UsageOutOfDate {
+            topic_id: topic_id.clone(),
+            recorded_bytes,
+            listed_bytes,
+            repaired,
+        });
+    }
+}
+
+/// `true` - every one of them is gone.
+async fn delete_files(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    files: &BTreeMap<String, u64>,
+) -> bool {
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return false;
+    };
+
+    let mut result = true;
+
+    for file_name in files.keys() {
+        if let Err(err) = cold_storage.delete(topic_key, file_name.as_str()).await {
+            write_error(
+                topic_key,
+                format!(
+                    "Can not delete {} from the cold storage. Err: {}",
+                    file_name, err
+                ),
+            );
+            result = false;
+        }
+    }
+
+    result
+}
+
+async fn is_local_file(app: &AppContext, topic_key: TopicKeyRef<'_>, file_name: &str) -> bool {
+    let mut path = storage_layout::get_topic_folder(app.get_data_folder(), topic_key);
+    path.push(file_name);
+
+    tokio::fs::try_exists(path).await.unwrap_or(false)
+}
+
+/// topic_id -> file name -> size
+fn group_by_topic(listed: Vec<ColdFile>) -> BTreeMap<String, BTreeMap<String, u64>> {
+    let mut result: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+
+    for itm in listed {
+        result
+            .entry(itm.topic_id)
+            .or_default()
+            .insert(itm.file_name, itm.size);
+    }
+
+    result
+}
+
+fn write_error(topic_key: TopicKeyRef<'_>, message: String) {
+    my_logger::LOGGER.write_error(
+        "reconcile_cold_storage",
+        message,
+        LogEventCtx::new().add("topic", topic_key.to_string()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_files_are_grouped_by_topic() {
+        let file = |topic_id: &str, file_name: &str, size: u64| ColdFile {
+            topic_id: topic_id.to_string(),
+            file_name: file_name.to_string(),
+            size,
+        };
+
+        let grouped = group_by_topic(vec![
+            file("orders", "0.archive", 10),
+            file("payments", "2024.yearindex", 20),
+            file("orders", "1.archive", 30),
+        ]);
+
+        assert_eq!(2, grouped.len());
+        assert_eq!(
+            vec![("0.archive", 10), ("1.archive", 30)],
+            grouped["orders"]
+                .iter()
+                .map(|(file_name, size)| (file_name.as_str(), *size))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&20), grouped["payments"].get("2024.yearindex"));
+    }
+}
@@ -1,9 +1,36 @@
-// TODO: Restore soft-deleted topic. See `TODO.md`.
-// Should: clear the soft-delete entry from `deleted_topics` so the topic stops
-// being treated as scheduled for GC and remains available.
-//
-// use crate::{app::AppContext, topics_snapshot::DeletedTopicProtobufModel};
-//
-// pub async fn restore_topic(app: &AppContext, topic_id: &str) -> Option<DeletedTopicProtobufModel> {
-//     app.topics_snapshot.remove_deleted_topic(topic_id).await
-// }
+use crate::{app::AppContext, topic_key::TopicKeyRef};
+
+use super::OperationError;
+
+/// Undoes a soft delete the GC has not got to yet: the topic is back in the snapshot at the
+/// message id it was deleted at, and takes writes again. Its queues were not kept - the bus node
+/// subscribes them again.
+pub async fn restore_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<(), OperationError> {
+    if app.namespace_deletions.is_deleting(topic_key.namespace) {
+        return Err(OperationError::NamespaceIsBeingDeleted(
+            topic_key.namespace.to_string(),
+        ));
+    }
+
+    // The GC holds it while it deletes the data, so a topic is never restored half gone.
+    let _write_guard = app.topic_writes.write(topic_key).await;
+
+    match app.topics_snapshot.restore_deleted_topic(topic_key).await {
+        Ok(()) => {}
+        Err(false) => return Err(OperationError::TopicNotFound(topic_key.to_string())),
+        Err(true) => return Err(OperationError::TopicAlreadyExists(topic_key.to_string())),
+    }
+
+    super::move_topic::load_topic(app, topic_key).await;
+
+    my_logger::LOGGER.write_info(
+        "RestoreTopic".to_string(),
+        "Topic is restored".to_string(),
+        my_logger::LogEventCtx::new().add("topicId", topic_key.to_string()),
+    );
+
+    Ok(())
+}
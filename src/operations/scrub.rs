@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use ahash::AHashSet;
+use my_logger::LogEventCtx;
+use my_service_bus::shared::sub_page::SubPageId;
+
+use crate::{
+    app::{storage_layout, AppContext},
+    message_pages::SubPageReadCopy,
+    topic_key::{TopicKey, TopicKeyRef},
+};
+
+use super::{OperationError, StorageLocation, TopicInventory};
+
+/// Minutes of a year index listed by a finding; the count goes on past it.
+const MAX_REPORTED_MINUTES: usize = 100;
+
+#[derive(Debug)]
+pub enum ScrubFinding {
+    /// The snapshot's message id - the next one to give out - is at or below an id already
+    /// stored. A bus node starting from it gives those ids out a second time. Repaired by raising
+    /// it past the highest id stored.
+    SnapshotBehindStorage {
+        message_id: i64,
+        max_stored_message_id: i64,
+        repaired: bool,
+    },
+    /// Ids were given out that nothing stores - `find_gaps` tells which. The last few can be
+    /// messages still on their way.
+    SnapshotAheadOfStorage {
+        message_id: i64,
+        max_stored_message_id: i64,
+    },
+    /// Minutes of a year index that point at an id no sub page has. A read from a date lands on
+    /// nothing there. Repaired by rebuilding the topic's index, in the background.
+    DanglingIndexMinutes {
+        year: u32,
+        minutes: Vec<u32>,
+        count: usize,
+        repaired: bool,
+    },
+    /// An archive both on the local disk and in the cold tier, with another size in each - one
+    /// of the two is not the file the other is. Not repaired: which one is right takes a look.
+    ArchiveSizeMismatch {
+        archive_file_no: i64,
+        local_size: u64,
+        cold_size: u64,
+    },
+    /// A topic folder that the snapshot knows nothing about. Not repaired: a topic that got its
+    /// first messages after the last `SaveQueueSnapshot` looks the same, and the bus node drops
+    /// anything added to the snapshot behind its back anyway.
+    FolderWithoutSnapshot,
+    /// The same, with messages that have not been archived yet.
+    ActiveFileWithoutSnapshot,
+    /// The topic could not be checked - or not all of it.
+    CheckFailed { error: String },
+}
+
+impl ScrubFinding {
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            ScrubFinding::SnapshotBehindStorage { .. } => "SnapshotBehindStorage",
+            ScrubFinding::SnapshotAheadOfStorage { .. } => "SnapshotAheadOfStorage",
+            ScrubFinding::DanglingIndexMinutes { .. } => "DanglingIndexMinutes",
+            ScrubFinding::ArchiveSizeMismatch { .. } => "ArchiveSizeMismatch",
+            ScrubFinding::FolderWithoutSnapshot => "FolderWithoutSnapshot",
+            ScrubFinding::ActiveFileWithoutSnapshot => "ActiveFileWithoutSnapshot",
+            ScrubFinding::CheckFailed { .. } => "CheckFailed",
+        }
+    }
+
+    pub fn get_description(&self) -> String {
+        match self {
+            ScrubFinding::SnapshotBehindStorage {
+                message_id,
+                max_stored_message_id,
+                ..
+            } => format!(
+                "The snapshot gives out {} next, while {} is already stored",
+                message_id, max_stored_message_id
+            ),
+            ScrubFinding::SnapshotAheadOfStorage {
+                message_id,
+                max_stored_message_id,
+            } => format!(
+                "Ids {}..{} were given out and are not stored",
+                max_stored_message_id + 1,
+                message_id - 1
+            ),
+            ScrubFinding::DanglingIndexMinutes {
+                year,
+                minutes,
+                count,
+                ..
+            } => format!(
+                "{} minutes of the {} index point at ids that are not stored. First of them: {:?}",
+                count, year, minutes
+            ),
+            ScrubFinding::ArchiveSizeMismatch {
+                archive_file_no,
+                local_size,
+                cold_size,
+            } => format!(
+                "Archive {} is {} bytes locally and {} bytes in the cold tier",
+                archive_file_no, local_size, cold_size
+            ),
+            ScrubFinding::FolderWithoutSnapshot => {
+                "The topic has a folder and no snapshot entry".to_string()
+            }
+            ScrubFinding::ActiveFileWithoutSnapshot => {
+                "The topic has an active file and no snapshot entry".to_string()
+            }
+            ScrubFinding::CheckFailed { error } => error.clone(),
+        }
+    }
+
+    pub fn is_repaired(&self) -> bool {
+        match self {
+            ScrubFinding::SnapshotBehindStorage { repaired, .. } => *repaired,
+            ScrubFinding::DanglingIndexMinutes { repaired, .. } => *repaired,
+            _ => false,
+        }
+    }
+}
+
+pub struct TopicScrubReport {
+    pub topic_key: TopicKey,
+    pub findings: Vec<ScrubFinding>,
+}
+
+/// Cross-checks what each topic has in the snapshot, in its folder, in its year indexes and in
+/// the cold tier - every topic of `namespace`, or of every namespace. A topic counts when either
+/// the snapshot or the disk has it.
+///
+/// With `repair`, the cases that can not make anything worse are fixed on the way: a snapshot
+/// message id that is behind the stored ids is raised past them, and a year index with dangling
+/// minutes is rebuilt from the messages. Everything else is reported only.
+///
+/// A topic costs its inventory - a cold request per archive number and per year - plus a read of
+/// every sub page its year indexes point into. Run it by hand, not on a timer.
+pub async fn scrub(
+    app: &Arc<AppContext>,
+    namespace: Option<&str>,
+    repair: bool,
+) -> Vec<TopicScrubReport> {
+    let in_snapshot: AHashSet<TopicKey> = app
+        .topics_snapshot
+        .get_topics_list()
+        .await
+        .into_iter()
+        .filter(|itm| namespace.map_or(true, |namespace| itm.namespace == namespace))
+        .collect();
+
+    let mut topics: Vec<TopicKey> = in_snapshot.iter().cloned().collect();
+
+    for topic_key in super::scan_topic_folders(app.get_data_folder()).await {
+        if namespace.map_or(true, |namespace| topic_key.namespace == namespace)
+            && !in_snapshot.contains(&topic_key)
+        {
+            topics.push(topic_key);
+        }
+    }
+
+    topics.sort_by(|left, right| {
+        (left.namespace.as_str(), left.topic_id.as_str())
+            .cmp(&(right.namespace.as_str(), right.topic_id.as_str()))
+    });
+
+    let mut result = Vec::with_capacity(topics.len());
+
+    for topic_key in topics {
+        let findings = scrub_topic(
+            app,
+            topic_key.to_ref(),
+            in_snapshot.contains(&topic_key),
+            repair,
+        )
+        .await;
+
+        for finding in findings.iter() {
+            my_logger::LOGGER.write_warning(
+                "scrub",
+                format!("{}: {}", finding.get_kind(), finding.get_description()),
+                LogEventCtx::new().add("topic", topic_key.to_string()),
+            );
+        }
+
+        result.push(TopicScrubReport {
+            topic_key,
+            findings,
+        });
+    }
+
+    result
+}
+
+async fn scrub_topic(
+    app: &Arc<AppContext>,
+    topic_key: TopicKeyRef<'_>,
+    in_snapshot: bool,
+    repair: bool,
+) -> Vec<ScrubFinding> {
+    let mut findings = Vec::new();
+
+    if !in_snapshot {
+        findings.push(ScrubFinding::FolderWithoutSnapshot);
+
+        let active_file = storage_layout::get_local_path(
+            app.get_data_folder(),
+            storage_layout::get_active_relative_path(topic_key).as_str(),
+        );
+
+        if active_file.is_file() {
+            findings.push(ScrubFinding::ActiveFileWithoutSnapshot);
+        }
+    }
+
+    let inventory = match super::get_topic_inventory(app, topic_key).await {
+        Ok(inventory) => inventory,
+        Err(err) => {
+            findings.push(ScrubFinding::CheckFailed {
+                error: format!("{:?}", err),
+            });
+            return findings;
+        }
+    };
+
+    if let Some(finding) = check_message_id(app, topic_key, &inventory, repair).await {
+        findings.push(finding);
+    }
+
+    check_archive_sizes(app, topic_key, &inventory, &mut findings);
+
+    let first_dangling = findings.len();
+
+    if let Err(err) = check_year_indexes(app, topic_key, &inventory, &mut findings).await {
+        findings.push(ScrubFinding::CheckFailed {
+            error: format!("{:?}", err),
+        });
+    }
+
+    // One rebuild covers every year of the topic.
+    if repair && findings.len() > first_dangling {
+        let mut rebuild = false;
+
+        for finding in findings[first_dangling..].iter_mut() {
+            if let ScrubFinding::DanglingIndexMinutes { repaired, .. } = finding {
+                *repaired = true;
+                rebuild = true;
+            }
+        }
+
+        if rebuild {
+            super::rebuild_index_by_minute(app, topic_key);
+        }
+    }
+
+    findings
+}
+
+async fn check_message_id(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    inventory: &TopicInventory,
+    repair: bool,
+) -> Option<ScrubFinding> {
+    let message_id = inventory.message_id?.get_value();
+    let max_stored_message_id = inventory.max_stored_message_id?.get_value();
+
+    if message_id - 1 > max_stored_message_id {
+        return Some(ScrubFinding::SnapshotAheadOfStorage {
+            message_id,
+            max_stored_message_id,
+        });
+    }
+
+    if message_id > max_stored_message_id {
+        return None;
+    }
+
+    let mut repaired = false;
+
+    if repair {
+        let next_message_id = max_stored_message_id + 1;
+
+        // Raised only - the node may have moved it on meanwhile.
+        repaired = app
+            .topics_snapshot
+            .update_topic(topic_key, |topic| {
+                if topic.message_id >= next_message_id {
+                    return (false, false);
+                }
+
+                topic.message_id = next_message_id;
+                (true, true)
+            })
+            .await
+            .unwrap_or(false);
+    }
+
+    Some(ScrubFinding::SnapshotBehindStorage {
+        message_id,
+        max_stored_message_id,
+        repaired,
+    })
+}
+
+/// The cold side is the size the cold usage has - an archive it does not know is not compared.
+fn check_archive_sizes(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    inventory: &TopicInventory,
+    findings: &mut Vec<ScrubFinding>,
+) {
+    for archive in inventory.archives.iter() {
+        if archive.location != StorageLocation::LocalAndCold {
+            continue;
+        }
+
+        let file_name = storage_layout::get_archive_file_name(archive.archive_file_no);
+
+        let Some(cold_size) = app.cold_usage.get_file_size(topic_key, file_name.as_str()) else {
+            continue;
+        };
+
+        if cold_size != archive.size {
+            findings.push(ScrubFinding::ArchiveSizeMismatch {
+                archive_file_no: archive.archive_file_no.get_value(),
+                local_size: archive.size,
+                cold_size,
+            });
+        }
+    }
+}
+
+/// Only what the index files hold - minutes still queued are the newest, and point at messages
+/// still in memory. Ids below the lowest one stored went with the retention and are fine.
+async fn check_year_indexes(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    inventory: &TopicInventory,
+    findings: &mut Vec<ScrubFinding>,
+) -> Result<(), OperationError> {
+    let topic_data = app.topics_list.get(topic_key);
+
+    let min_stored_message_id = inventory.min_stored_message_id.map(|itm| itm.get_value());
+
+    // The ids of an index grow with the minutes, so the sub page read last is the one asked for
+    // next more often than not.
+    let mut last_read: Option<(SubPageId, Option<SubPageReadCopy>)> = None;
+
+    for year_index in inventory.year_indexes.iter() {
+        let yearly_index = match topic_data.as_deref() {
+            Some(topic_data) => super::get_yearly_index(app, topic_data, year_index.year).await,
+            None => {
+                app.try_open_index_by_minute(topic_key, year_index.year)
+                    .await
+            }
+        };
+
+        let Some(yearly_index) = yearly_index else {
+            continue;
+        };
+
+        let mut minutes = Vec::new();
+        let mut count = 0;
+
+        for (minute, message_id) in yearly_index.get_stored().await {
+            if let Some(min_stored_message_id) = min_stored_message_id {
+                if message_id.get_value() < min_stored_message_id {
+                    continue;
+                }
+            }
+
+            let sub_page_id: SubPageId = message_id.into();
+
+            let is_same = matches!(last_read.as_ref(), Some((id, _)) if *id == sub_page_id);
+
+            if !is_same {
+                let sub_page = super::find_gaps::read_sub_page(app, topic_key, sub_page_id).await?;
+                last_read = Some((sub_page_id, sub_page));
+            }
+
+            let is_stored = last_read
+                .as_ref()
+                .and_then(|(_, sub_page)| sub_page.as_ref())
+                .map(|sub_page| sub_page.get(message_id).is_some())
+                .unwrap_or(false);
+
+            if is_stored {
+                continue;
+            }
+
+            count += 1;
+
+            if minutes.len() < MAX_REPORTED_MINUTES {
+                minutes.push(minute.get_value());
+            }
+        }
+
+        if count > 0 {
+            findings.push(ScrubFinding::DanglingIndexMinutes {
+                year: year_index.year.get_value(),
+                minutes,
+                count,
+                repaired: false,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -6,40 +6,123 @@ use my_service_bus::shared::sub_page::SubPageId;
 
 use crate::{app::AppContext, persistence_grpc::MessageContentGrpcModel, topic_key::TopicKey};
 
+/// What a streamed read may cost before it is cut short.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamedReadLimits {
+    /// Taken from the `grpc-timeout` header. Once it passes the client has given up on the call
+    /// anyway, so there is nobody left to stream to.
+    pub deadline: Option<tokio::time::Instant>,
+    /// Sub pages in a row without a single message in the requested range. A range over a
+    /// long-gone or never-written part of a topic would otherwise walk every sub page of it -
+    /// each one a disk or cold-storage lookup. `0` switches the limit off.
+    pub max_empty_sub_pages: usize,
+}
+
+/// Why a streamed read stopped. Everything except `Completed` is counted as an aborted stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamedReadStop {
+    Completed,
+    ClientGone,
+    DeadlineExceeded,
+    EmptyRangeLimit,
+    ShuttingDown,
+}
+
+impl StreamedReadStop {
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            StreamedReadStop::Completed => "completed",
+            StreamedReadStop::ClientGone => "client_gone",
+            StreamedReadStop::DeadlineExceeded => "deadline_exceeded",
+            StreamedReadStop::EmptyRangeLimit => "empty_range_limit",
+            StreamedReadStop::ShuttingDown => "shutting_down",
+        }
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        *self != StreamedReadStop::Completed
+    }
+}
+
+/// Streams `from_message_id..=to_message_id` sub page by sub page. Runs detached from the gRPC
+/// call, so it has to notice on its own that the call is over: a failed send means the client
+/// has dropped the stream, and the deadline is checked between sub pages and around every send.
 pub async fn send_messages_to_channel(
     app: Arc<AppContext>,
     topic_key: TopicKey,
     from_message_id: MessageId,
     to_message_id: MessageId,
+    limits: StreamedReadLimits,
     producer: StreamedResponseProducer<MessageContentGrpcModel>,
-) {
+) -> StreamedReadStop {
     let topic_key = topic_key.to_ref();
 
-    let mut sub_page_read_copy = None;
+    let mut message_id = from_message_id.get_value();
+    let mut empty_sub_pages = 0;
+
+    while message_id <= to_message_id.get_value() {
+        if app.app_states.is_shutting_down() {
+            return StreamedReadStop::ShuttingDown;
+        }
+
+        if deadline_passed(limits.deadline) {
+            return StreamedReadStop::DeadlineExceeded;
+        }
 
-    for message_id in from_message_id.get_value()..to_message_id.get_value() + 1 {
-        let message_id: MessageId = message_id.into();
+        let sub_page_id: SubPageId = MessageId::new(message_id).into();
 
-        let sub_page_id: SubPageId = message_id.into();
+        let next_sub_page_first_id = sub_page_id
+            .get_first_message_id_of_next_sub_page()
+            .get_value();
 
-        if sub_page_read_copy.is_none() {
-            let sub_page =
-                crate::operations::get_sub_page_to_read(&app, topic_key, sub_page_id).await;
+        let last_id_in_sub_page = to_message_id.get_value().min(next_sub_page_first_id - 1);
 
-            sub_page_read_copy = Some(sub_page.get_all_messages().await);
-        }
+        let sub_page = crate::operations::get_sub_page_to_read(&app, topic_key, sub_page_id).await;
+        let sub_page_read_copy = sub_page.get_all_messages().await;
+
+        let mut sent = false;
+
+        for id in message_id..=last_id_in_sub_page {
+            let Some(message) = sub_page_read_copy.get(id.into()) else {
+                continue;
+            };
+
+            let send = producer.send(message.as_ref().into());
 
-        if sub_page_read_copy.as_ref().unwrap().sub_page_id.get_value() != sub_page_id.get_value() {
-            let sub_page =
-                crate::operations::get_sub_page_to_read(&app, topic_key, sub_page_id).await;
+            let result = match limits.deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, send).await {
+                    Ok(result) => result,
+                    Err(_) => return StreamedReadStop::DeadlineExceeded,
+                },
+                None => send.await,
+            };
 
-            sub_page_read_copy = Some(sub_page.get_all_messages().await);
+            if result.is_err() {
+                return StreamedReadStop::ClientGone;
+            }
+
+            sent = true;
         }
 
-        let message = sub_page_read_copy.as_ref().unwrap().get(message_id);
+        if sent {
+            empty_sub_pages = 0;
+        } else {
+            empty_sub_pages += 1;
 
-        if let Some(message) = message {
-            producer.send(message.as_ref().into()).await.unwrap();
+            if limits.max_empty_sub_pages > 0 && empty_sub_pages >= limits.max_empty_sub_pages {
+                return StreamedReadStop::EmptyRangeLimit;
+            }
         }
+
+        message_id = next_sub_page_first_id;
+    }
+
+    StreamedReadStop::Completed
+}
+
+fn deadline_passed(deadline: Option<tokio::time::Instant>) -> bool {
+    match deadline {
+        Some(deadline) => tokio::time::Instant::now() >= deadline,
+        None => false,
     }
 }
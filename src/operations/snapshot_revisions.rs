@@ -0,0 +1,105 @@
+use crate::{
+    app::AppContext,
+    topic_key::Namespace,
+    topics_snapshot::{
+        snapshot_diff::{diff_snapshots, SnapshotDiff},
+        snapshot_history::{SnapshotHistory, SnapshotRevision},
+        TopicSnapshotProtobufModel,
+    },
+};
+
+use super::OperationError;
+
+pub async fn list_snapshot_revisions(
+    app: &AppContext,
+    namespace: &Namespace,
+) -> Result<Vec<SnapshotRevision>, OperationError> {
+    let history = get_history(app)?;
+    Ok(history.list(namespace.as_str()).await)
+}
+
+/// `to` of `None` is the current state - what the bus node would get from `GetQueueSnapshot` now.
+pub async fn diff_snapshot_revisions(
+    app: &AppContext,
+    namespace: &Namespace,
+    from: i64,
+    to: Option<i64>,
+) -> Result<SnapshotDiff, OperationError> {
+    let from = read_revision(app, namespace, from).await?;
+
+    let to = match to {
+        Some(to) => read_revision(app, namespace, to).await?,
+        None => get_current(app, namespace).await,
+    };
+
+    Ok(diff_snapshots(from.as_slice(), to.as_slice()))
+}
+
+/// Makes a revision the current snapshot of its namespace; the other namespaces are untouched.
+/// Returns the id of the revision the replaced state was kept as.
+///
+/// The bus node keeps the queues in memory and pushes them with every `SaveQueueSnapshot`, so a
+/// restore done while it runs is overwritten within seconds. Stop the node, restore, start it -
+/// it reads the restored snapshot on start.
+pub async fn restore_snapshot_revision(
+    app: &AppContext,
+    namespace: &Namespace,
+    revision_id: i64,
+) -> Result<Option<i64>, OperationError> {
+    let history = get_history(app)?;
+
+    let topics = read_revision(app, namespace, revision_id).await?;
+
+    // What is on disk may be a few seconds behind memory; flushing first makes the kept copy the
+    // state that is actually being replaced.
+    app.topics_snapshot.flush_topics_snapshot_to_blob().await;
+
+    let kept_as = history
+        .take_current(namespace.as_str())
+        .await
+        .map_err(OperationError::FileStorageError)?;
+
+    app.topics_snapshot
+        .replace_namespace(namespace.as_str(), topics)
+        .await;
+
+    app.topics_snapshot.flush_topics_snapshot_to_blob().await;
+
+    Ok(kept_as)
+}
+
+fn get_history(app: &AppContext) -> Result<&SnapshotHistory, OperationError> {
+    app.topics_snapshot.storage.get_history().ok_or_else(|| {
+        OperationError::FileStorageError("The snapshot history is not set up".to_string())
+    })
+}
+
+async fn read_revision(
+    app: &AppContext,
+    namespace: &Namespace,
+    revision_id: i64,
+) -> Result<Vec<TopicSnapshotProtobufModel>, OperationError> {
+    let model = get_history(app)?
+        .read(namespace.as_str(), revision_id)
+        .await
+        .map_err(OperationError::FileStorageError)?;
+
+    let Some(model) = model else {
+        return Err(OperationError::SnapshotRevisionNotFound(revision_id));
+    };
+
+    let (topics, _) = model.into_domain(namespace);
+
+    Ok(topics)
+}
+
+async fn get_current(app: &AppContext, namespace: &Namespace) -> Vec<TopicSnapshotProtobufModel> {
+    let snapshot = app.topics_snapshot.get().await;
+
+    snapshot
+        .snapshot
+        .data
+        .into_iter()
+        .filter(|itm| itm.get_namespace() == namespace.as_str())
+        .collect()
+}
@@ -0,0 +1,523 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use chrono::Datelike;
+use my_service_bus::{abstractions::MessageId, shared::sub_page::SubPageId};
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{
+    app::{storage_layout, AppContext},
+    archive_storage::ArchiveFileNo,
+    message_pages::SubPageInner,
+    topic_data::TopicData,
+    topic_key::TopicKeyRef,
+    typing::Year,
+};
+
+use super::OperationError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageLocation {
+    Local,
+    Cold,
+    /// Uploaded, and the local copy is still there - either the upload has not reached its
+    /// delete step yet, or a cold year index was pulled back for a late write.
+    LocalAndCold,
+}
+
+impl StorageLocation {
+    fn new(local: bool, cold: bool) -> Option<Self> {
+        match (local, cold) {
+            (true, false) => Some(Self::Local),
+            (false, true) => Some(Self::Cold),
+            (true, true) => Some(Self::LocalAndCold),
+            (false, false) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageLocation::Local => "local",
+            StorageLocation::Cold => "cold",
+            StorageLocation::LocalAndCold => "local+cold",
+        }
+    }
+}
+
+pub struct ArchiveFileInventory {
+    pub archive_file_no: ArchiveFileNo,
+    pub location: StorageLocation,
+    /// The length of the file when it is on disk. In the cold tier, where its last sub page ends -
+    /// the same number for a sealed file, and the TOC is already fetched while a size would cost
+    /// another request.
+    pub size: u64,
+    /// The sub pages the TOC points at, in order. A slot left empty is a gap in the ids or a sub
+    /// page that has not been archived yet.
+    pub stored_sub_pages: Vec<SubPageId>,
+}
+
+impl ArchiveFileInventory {
+    pub fn get_first_message_id(&self) -> MessageId {
+        self.archive_file_no
+            .get_first_sub_page_id()
+            .get_first_message_id()
+    }
+
+    pub fn get_last_message_id(&self) -> MessageId {
+        self.archive_file_no
+            .get_last_sub_page_id()
+            .get_last_message_id()
+    }
+}
+
+pub struct YearIndexInventory {
+    pub year: Year,
+    pub location: StorageLocation,
+}
+
+pub struct TopicInventory {
+    /// What the bus node last reported for the topic. `None` for a topic that has files but is
+    /// not in the snapshot.
+    pub message_id: Option<MessageId>,
+    pub archives: Vec<ArchiveFileInventory>,
+    pub year_indexes: Vec<YearIndexInventory>,
+    /// The lowest and the highest id actually stored - archived or still in memory. Both `None`
+    /// for a topic that holds nothing.
+    pub min_stored_message_id: Option<MessageId>,
+    pub max_stored_message_id: Option<MessageId>,
+}
+
+/// Everything stored for one topic, wherever it sits.
+///
+/// The local side is a directory listing, and so is the cold side - one request per thousand
+/// files. A key that may not list the bucket gets the cold side probed key by key instead: every
+/// archive number up to the highest the topic's message id allows, and every year since
+/// `OLDEST_POSSIBLE_YEAR`. That is a request per key - fine for an operator looking at one topic,
+/// not something to run per topic on a timer.
+pub async fn get_topic_inventory(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<TopicInventory, OperationError> {
+    let message_id = get_snapshot_message_id(app, topic_key).await;
+    let topic_data = app.topics_list.get(topic_key);
+    let folder = storage_layout::get_topic_folder(app.get_data_folder(), topic_key);
+
+    if message_id.is_none() && topic_data.is_none() && !folder.is_dir() {
+        return Err(OperationError::TopicNotFound(topic_key.to_string()));
+    }
+
+    let local = scan_local_files(folder.as_path()).await;
+
+    let highest_archive_file_no = message_id
+        .map(|message_id| {
+            let sub_page_id: SubPageId = message_id.into();
+            let archive_file_no: ArchiveFileNo = sub_page_id.into();
+            archive_file_no.get_value()
+        })
+        .into_iter()
+        .chain(local.archives.keys().copied())
+        .max();
+
+    let (cold_archives, cold_years) =
+        get_cold_files(app, topic_key, highest_archive_file_no).await?;
+
+    let archive_numbers: BTreeSet<i64> = local
+        .archives
+        .keys()
+        .chain(cold_archives.iter())
+        .copied()
+        .collect();
+
+    let mut archives = Vec::with_capacity(archive_numbers.len());
+
+    for archive_file_no in archive_numbers {
+        let location = StorageLocation::new(
+            local.archives.contains_key(&archive_file_no),
+            cold_archives.contains(&archive_file_no),
+        )
+        .unwrap();
+
+        let archive = read_archive_inventory(
+            app,
+            topic_key,
+            ArchiveFileNo::new(archive_file_no),
+            location,
+            local.archives.get(&archive_file_no).copied(),
+        )
+        .await?;
+
+        if let Some(archive) = archive {
+            archives.push(archive);
+        }
+    }
+
+    let year_indexes = local
+        .years
+        .iter()
+        .chain(cold_years.iter())
+        .copied()
+        .collect::<BTreeSet<u32>>()
+        .into_iter()
+        .map(|year| YearIndexInventory {
+            year: Year::new(year),
+            location: StorageLocation::new(local.years.contains(&year), cold_years.contains(&year))
+                .unwrap(),
+        })
+        .collect();
+
+    let (min_stored_message_id, max_stored_message_id) =
+        get_stored_range(app, topic_key, topic_data.as_deref(), &archives).await?;
+
+    Ok(TopicInventory {
+        message_id,
+        archives,
+        year_indexes,
+        min_stored_message_id,
+        max_stored_message_id,
+    })
+}
+
+pub async fn get_snapshot_message_id(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Option<MessageId> {
+    let snapshot = app.topics_snapshot.get().await;
+
+    snapshot
+        .snapshot
+        .data
+        .iter()
+        .find(|itm| itm.get_topic_key() == topic_key)
+        .map(|itm| itm.get_message_id())
+}
+
+struct LocalFiles {
+    /// archive_file_no -> file size
+    archives: BTreeMap<i64, u64>,
+    years: BTreeSet<u32>,
+}
+
+async fn scan_local_files(folder: &Path) -> LocalFiles {
+    let mut result = LocalFiles {
+        archives: BTreeMap::new(),
+        years: BTreeSet::new(),
+    };
+
+    let Ok(mut entries) = tokio::fs::read_dir(folder).await else {
+        return result;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name();
+
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+
+        if let Some(archive_file_no) = storage_layout::parse_archive_file_name(file_name) {
+            result
+                .archives
+                .insert(archive_file_no.get_value(), metadata.len());
+            continue;
+        }
+
+        if let Some(year) = storage_layout::parse_year_index_file_name(file_name) {
+            result.years.insert(year.get_value());
+        }
+    }
+
+    result
+}
+
+async fn get_cold_files(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    highest_archive_file_no: Option<i64>,
+) -> Result<(BTreeSet<i64>, BTreeSet<u32>), OperationError> {
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return Ok((BTreeSet::new(), BTreeSet::new()));
+    };
+
+    match cold_storage.list_topic_files(topic_key).await {
+        Ok(files) => {
+            let archives = files
+                .keys()
+                .filter_map(|itm| storage_layout::parse_archive_file_name(itm))
+                .map(|itm| itm.get_value())
+                .collect();
+
+            let years = files
+                .keys()
+                .filter_map(|itm| storage_layout::parse_year_index_file_name(itm))
+                .map(|itm| itm.get_value())
+                .collect();
+
+            Ok((archives, years))
+        }
+        Err(err) => {
+            my_logger::LOGGER.write_warning(
+                "get_topic_inventory",
+                format!(
+                    "Can not list the cold storage, probing it key by key. Err: {}",
+                    err
+                ),
+                my_logger::LogEventCtx::new().add("topic", topic_key.to_string()),
+            );
+
+            let archives = probe_cold_archives(app, topic_key, highest_archive_file_no).await?;
+            let years = probe_cold_year_indexes(app, topic_key).await?;
+
+            Ok((archives, years))
+        }
+    }
+}
+
+async fn probe_cold_archives(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    highest_archive_file_no: Option<i64>,
+) -> Result<BTreeSet<i64>, OperationError> {
+    let mut result = BTreeSet::new();
+
+    let (Some(cold_storage), Some(highest)) = (
+        app.get_cold_storage(topic_key.namespace),
+        highest_archive_file_no,
+    ) else {
+        return Ok(result);
+    };
+
+    for archive_file_no in 0..=highest {
+        let file_name = storage_layout::get_archive_file_name(ArchiveFileNo::new(archive_file_no));
+
+        let exists = cold_storage
+            .exists(topic_key, file_name.as_str())
+            .await
+            .map_err(OperationError::ColdStorageError)?;
+
+        if exists {
+            result.insert(archive_file_no);
+        }
+    }
+
+    Ok(result)
+}
+
+async fn probe_cold_year_indexes(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Result<BTreeSet<u32>, OperationError> {
+    let mut result = BTreeSet::new();
+
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return Ok(result);
+    };
+
+    let current_year = DateTimeAsMicroseconds::now().to_chrono_utc().year() as u32;
+
+    for year in storage_layout::OLDEST_POSSIBLE_YEAR..=current_year {
+        let file_name = storage_layout::get_year_index_file_name(Year::new(year));
+
+        let exists = cold_storage
+            .exists(topic_key, file_name.as_str())
+            .await
+            .map_err(OperationError::ColdStorageError)?;
+
+        if exists {
+            result.insert(year);
+        }
+    }
+
+    Ok(result)
+}
+
+/// `None` when the file is gone by the time it is opened - the uploader dropped the local copy
+/// of a file the cold tier does not have under that name, or the topic was deleted meanwhile.
+async fn read_archive_inventory(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    archive_file_no: ArchiveFileNo,
+    location: StorageLocation,
+    local_size: Option<u64>,
+) -> Result<Option<ArchiveFileInventory>, OperationError> {
+    // See `archive_io::restore_sub_page` - the guard spans the open and the read.
+    let _guard = app.archive_locks.read(topic_key).await;
+
+    let Some(archive_storage) = app
+        .archive_storage_list
+        .try_get_or_open(archive_file_no, topic_key, app)
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let toc = archive_storage.read_toc().await?;
+
+    let first_sub_page_id = archive_file_no.get_first_sub_page_id().get_value();
+
+    let mut stored_sub_pages = Vec::new();
+    let mut end = 0;
+
+    for (no, position) in toc.iter().enumerate() {
+        if position.is_empty() {
+            continue;
+        }
+
+        stored_sub_pages.push(SubPageId::new(first_sub_page_id + no as i64));
+        end = end.max(position.offset + position.length as u64);
+    }
+
+    Ok(Some(ArchiveFileInventory {
+        archive_file_no,
+        location,
+        size: local_size.unwrap_or(end),
+        stored_sub_pages,
+    }))
+}
+
+/// The first stored sub page of the lowest archive and the last one of the highest give the
+/// archived range; whatever is in memory and not archived yet extends it. Only those two sub
+/// pages are read - the TOC already says which ones they are.
+async fn get_stored_range(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    topic_data: Option<&TopicData>,
+    archives: &[ArchiveFileInventory],
+) -> Result<(Option<MessageId>, Option<MessageId>), OperationError> {
+    let mut min: Option<i64> = None;
+    let mut max: Option<i64> = None;
+
+    let mut update = |message_id: Option<MessageId>| {
+        if let Some(message_id) = message_id {
+            let value = message_id.get_value();
+            min = Some(min.map_or(value, |min| min.min(value)));
+            max = Some(max.map_or(value, |max| max.max(value)));
+        }
+    };
+
+    let first_stored = archives
+        .iter()
+        .find_map(|itm| itm.stored_sub_pages.first().copied());
+
+    let last_stored = archives
+        .iter()
+        .rev()
+        .find_map(|itm| itm.stored_sub_pages.last().copied());
+
+    if let Some(sub_page_id) = first_stored {
+        if let Some(sub_page) = read_stored_sub_page(app, topic_key, sub_page_id).await? {
+            update(sub_page.messages.first().map(|itm| itm.get_message_id()));
+        }
+    }
+
+    if let Some(sub_page_id) = last_stored {
+        if let Some(sub_page) = read_stored_sub_page(app, topic_key, sub_page_id).await? {
+            update(sub_page.messages.last().map(|itm| itm.get_message_id()));
+        }
+    }
+
+    if let Some(topic_data) = topic_data {
+        for sub_page in topic_data.pages_list.get_all().await {
+            let messages = sub_page.get_all_messages().await;
+            update(messages.get_first_message_id());
+            update(messages.get_last_message_id());
+        }
+    }
+
+    Ok((min.map(MessageId::new), max.map(MessageId::new)))
+}
+
+pub async fn read_stored_sub_page(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_page_id: SubPageId,
+) -> Result<Option<SubPageInner>, OperationError> {
+    let Some(payload) = read_stored_payload(app, topic_key, sub_page_id).await? else {
+        return Ok(None);
+    };
+
+    let sub_page =
+        SubPageInner::from_compressed_payload(sub_page_id, payload.as_slice()).map_err(|err| {
+            OperationError::CorruptedSubPage(format!(
+                "{}, sub page {}: {:?}",
+                topic_key,
+                sub_page_id.get_value(),
+                err
+            ))
+        })?;
+
+    Ok(Some(sub_page))
+}
+
+/// The sub page as it is stored - compressed, wherever its archive sits.
+pub async fn read_stored_payload(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    sub_page_id: SubPageId,
+) -> Result<Option<Vec<u8>>, OperationError> {
+    let _guard = app.archive_locks.read(topic_key).await;
+
+    let Some(archive_storage) = app
+        .archive_storage_list
+        .try_get_or_open(sub_page_id.into(), topic_key, app)
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let payload = archive_storage.read_sub_page_payload(sub_page_id).await?;
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_follows_where_the_file_is() {
+        assert_eq!(
+            Some(StorageLocation::Local),
+            StorageLocation::new(true, false)
+        );
+        assert_eq!(
+            Some(StorageLocation::Cold),
+            StorageLocation::new(false, true)
+        );
+        assert_eq!(
+            Some(StorageLocation::LocalAndCold),
+            StorageLocation::new(true, true)
+        );
+        assert_eq!(None, StorageLocation::new(false, false));
+    }
+
+    #[tokio::test]
+    async fn local_listing_picks_archives_and_year_indexes_only() {
+        let mut folder = std::env::temp_dir();
+        folder.push("my-sb-persistence-inventory-local");
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+
+        std::fs::write(folder.join("0000000000000000001.archive"), [0u8; 7]).unwrap();
+        std::fs::write(folder.join(".2024.yearindex"), [0u8; 1]).unwrap();
+        std::fs::write(folder.join("active"), [0u8; 1]).unwrap();
+
+        let result = scan_local_files(folder.as_path()).await;
+
+        assert_eq!(Some(&7), result.archives.get(&1));
+        assert_eq!(1, result.archives.len());
+        assert!(result.years.contains(&2024));
+        assert_eq!(1, result.years.len());
+
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+}
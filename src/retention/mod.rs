@@ -0,0 +1,2 @@
+mod topics_retention;
+pub use topics_retention::*;
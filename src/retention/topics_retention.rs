@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::storage_layout,
+    topic_key::{TopicKey, TopicKeyRef},
+    topics_snapshot::file_storage::{read_to_string_if_exists, scan_namespaces},
+    utils::PersistedYaml,
+};
+
+/// How long one topic is meant to keep its data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopicRetention {
+    pub max_age_sec: u64,
+}
+
+impl TopicRetention {
+    pub fn get_max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_sec)
+    }
+}
+
+/// `{data_folder}/{namespace}/retention.yaml` - topic id to its retention. The namespace is the
+/// path, as for the topics snapshot next to it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct RetentionYamlModel {
+    #[serde(default)]
+    topics: BTreeMap<String, TopicRetention>,
+}
+
+/// namespace -> topic_id -> retention
+type RetentionByNamespace = BTreeMap<String, BTreeMap<String, TopicRetention>>;
+
+/// The retention an operator set on topics. A topic without an entry keeps everything, which is
+/// what every topic did before retention existed.
+///
+/// Set at runtime over the admin API rather than in the settings: it is a per-topic decision, and
+/// the settings file is the same for every node. Only kept for now - it goes along with a topic
+/// that is moved and away with one that is deleted, but nothing expires data by it yet.
+pub struct TopicsRetention {
+    data_folder: String,
+    data: PersistedYaml<RetentionByNamespace>,
+}
+
+impl TopicsRetention {
+    pub async fn load(data_folder: String) -> Self {
+        let mut data = BTreeMap::new();
+
+        for namespace in scan_namespaces(data_folder.as_str()).await {
+            let path = storage_layout::get_namespace_retention_file(
+                data_folder.as_str(),
+                namespace.as_str(),
+            );
+
+            let Some(content) = read_to_string_if_exists(&path).await else {
+                continue;
+            };
+
+            let model: RetentionYamlModel = match serde_yaml::from_str(&content) {
+                Ok(model) => model,
+                Err(err) => panic!("Can not parse {:?}: {}", path, err),
+            };
+
+            if !model.topics.is_empty() {
+                data.insert(namespace.as_str().to_string(), model.topics);
+            }
+        }
+
+        Self {
+            data_folder,
+            data: PersistedYaml::new(data),
+        }
+    }
+
+    pub fn get(&self, topic_key: TopicKeyRef<'_>) -> Option<TopicRetention> {
+        self.data.read(|data| {
+            data.get(topic_key.namespace)?
+                .get(topic_key.topic_id)
+                .cloned()
+        })
+    }
+
+    pub fn get_all(&self) -> Vec<(TopicKey, TopicRetention)> {
+        self.data.read(|data| {
+            let mut result = Vec::new();
+
+            for (namespace, topics) in data.iter() {
+                for (topic_id, retention) in topics.iter() {
+                    let topic_key = TopicKey {
+                        namespace: namespace.clone(),
+                        topic_id: topic_id.clone(),
+                    };
+
+                    result.push((topic_key, retention.clone()));
+                }
+            }
+
+            result
+        })
+    }
+
+    /// `None` drops the retention - the topic keeps everything from now on.
+    pub async fn set(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        max_age: Option<Duration>,
+    ) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(topic_key.namespace), |data| {
+                match max_age {
+                    Some(max_age) => {
+                        data.entry(topic_key.namespace.to_string())
+                            .or_default()
+                            .insert(
+                                topic_key.topic_id.to_string(),
+                                TopicRetention {
+                                    max_age_sec: max_age.as_secs(),
+                                },
+                            );
+                    }
+                    None => {
+                        if let Some(topics) = data.get_mut(topic_key.namespace) {
+                            topics.remove(topic_key.topic_id);
+                        }
+                    }
+                }
+
+                Some(to_yaml_model(data, topic_key.namespace))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    fn get_path(&self, namespace: &str) -> PathBuf {
+        storage_layout::get_namespace_retention_file(self.data_folder.as_str(), namespace)
+    }
+}
+
+fn to_yaml_model(data: &RetentionByNamespace, namespace: &str) -> RetentionYamlModel {
+    RetentionYamlModel {
+        topics: data.get(namespace).cloned().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::topic_key::TopicKeyRef;
+
+    use super::TopicsRetention;
+
+    #[tokio::test]
+    async fn survives_a_restart_and_a_removal() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-retention");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("alpha")).unwrap();
+
+        let data_folder = root.to_str().unwrap().to_string();
+
+        let orders = TopicKeyRef::new("alpha", "orders");
+        let payments = TopicKeyRef::new("alpha", "payments");
+
+        {
+            let retention = TopicsRetention::load(data_folder.clone()).await;
+
+            retention
+                .set(orders, Some(Duration::from_secs(3600)))
+                .await
+                .unwrap();
+            retention
+                .set(payments, Some(Duration::from_secs(60)))
+                .await
+                .unwrap();
+            retention.set(payments, None).await.unwrap();
+        }
+
+        let retention = TopicsRetention::load(data_folder).await;
+
+        let loaded = retention.get(orders).unwrap();
+        assert_eq!(3600, loaded.max_age_sec);
+
+        assert!(retention.get(payments).is_none());
+        assert_eq!(1, retention.get_all().len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use tokio::{fs::File, io::AsyncReadExt};
 
+use crate::topic_key::Namespace;
+
 /// How the cold tier lays its objects out. Exactly one of the two has to be given.
 #[derive(Debug, Clone)]
 pub enum S3BucketMode {
@@ -48,6 +52,22 @@ pub struct S3ConnectionSettings {
 
 impl S3ConnectionSettings {
     pub fn parse(conn_string: &str) -> Self {
+        Self::parse_with(conn_string, "s3_conn_string", None)
+    }
+
+    /// A namespace's entry of `s3_namespaces`: the same keys, each one left out taken from `base`
+    /// - the `s3_conn_string`. A namespace in another region of the same account gives only
+    /// `Endpoint` and `Region`; one in an account of its own gives the keys as well. Without an
+    /// `s3_conn_string` there is nothing to take them from, and the entry has to give every one.
+    ///
+    /// `Bucket` or `BucketPrefix` replaces the layout for the namespace alone and is read the same
+    /// way: `Bucket=x` keeps the namespace as the first key segment inside `x`.
+    pub fn parse_override(conn_string: &str, namespace: &str, base: Option<&Self>) -> Self {
+        let setting = format!("s3_namespaces.{}", namespace);
+        Self::parse_with(conn_string, setting.as_str(), base)
+    }
+
+    fn parse_with(conn_string: &str, setting: &str, base: Option<&Self>) -> Self {
         let mut endpoint = None;
         let mut region = None;
         let mut access_key = None;
@@ -65,7 +85,7 @@ impl S3ConnectionSettings {
 
             // Only the first `=` separates - a base64 secret key carries its own.
             let Some(separator) = pair.find('=') else {
-                panic!("Invalid s3_conn_string: '{}' is not Key=Value", pair);
+                panic!("Invalid {}: '{}' is not Key=Value", setting, pair);
             };
 
             let key = pair[..separator].trim();
@@ -78,10 +98,10 @@ impl S3ConnectionSettings {
                 "SecretKey" => secret_key = Some(value),
                 "Bucket" => bucket = Some(value),
                 "BucketPrefix" => bucket_prefix = Some(value),
-                "Debug" => debug = Some(parse_bool(value.as_str(), "Debug")),
+                "Debug" => debug = Some(parse_bool(value.as_str(), "Debug", setting)),
                 _ => panic!(
-                    "Invalid s3_conn_string: unknown key '{}'. Expected Endpoint, Region, AccessKey, SecretKey, one of Bucket or BucketPrefix, and optionally Debug",
-                    key
+                    "Invalid {}: unknown key '{}'. Expected Endpoint, Region, AccessKey, SecretKey, one of Bucket or BucketPrefix, and optionally Debug",
+                    setting, key
                 ),
             }
         }
@@ -92,45 +112,77 @@ impl S3ConnectionSettings {
         // Deliberately an error rather than a default: the two lay the objects out differently, so
         // guessing would put the data somewhere the operator did not mean, and switching later
         // means moving every object.
-        let bucket_mode = match (bucket, bucket_prefix) {
-            (Some(bucket), None) => S3BucketMode::Shared(bucket),
-            (None, Some(prefix)) => S3BucketMode::PerNamespace(prefix),
-            (Some(_), Some(_)) => panic!(
-                "Invalid s3_conn_string: Bucket and BucketPrefix are two different layouts - give one, not both. Bucket=x puts everything in one bucket under /x/{{namespace}}/{{topic}}/, BucketPrefix=x gives each namespace its own bucket x-{{namespace}}"
+        let bucket_mode = match (bucket, bucket_prefix, base) {
+            (Some(bucket), None, _) => S3BucketMode::Shared(bucket),
+            (None, Some(prefix), _) => S3BucketMode::PerNamespace(prefix),
+            (Some(_), Some(_), _) => panic!(
+                "Invalid {}: Bucket and BucketPrefix are two different layouts - give one, not both. Bucket=x puts everything in one bucket under /x/{{namespace}}/{{topic}}/, BucketPrefix=x gives each namespace its own bucket x-{{namespace}}",
+                setting
             ),
-            (None, None) => panic!(
-                "Invalid s3_conn_string: one of Bucket or BucketPrefix is required. Bucket=x puts everything in one bucket under /x/{{namespace}}/{{topic}}/, BucketPrefix=x gives each namespace its own bucket x-{{namespace}}"
+            (None, None, Some(base)) => base.bucket_mode.clone(),
+            (None, None, None) => panic!(
+                "Invalid {}: one of Bucket or BucketPrefix is required. Bucket=x puts everything in one bucket under /x/{{namespace}}/{{topic}}/, BucketPrefix=x gives each namespace its own bucket x-{{namespace}}",
+                setting
             ),
         };
 
+        let endpoint = required(
+            inherit(endpoint, base.map(|itm| &itm.endpoint)),
+            "Endpoint",
+            setting,
+        );
+
+        // Here rather than at the first listing, which would have it fail on every request.
+        if let Err(err) = crate::cold_storage::parse_endpoint(endpoint.as_str()) {
+            panic!("Invalid {}: {}", setting, err);
+        }
+
         Self {
-            endpoint: required(endpoint, "Endpoint"),
-            region: required(region, "Region"),
-            access_key: required(access_key, "AccessKey"),
-            secret_key: required(secret_key, "SecretKey"),
+            endpoint,
+            region: required(
+                inherit(region, base.map(|itm| &itm.region)),
+                "Region",
+                setting,
+            ),
+            access_key: required(
+                inherit(access_key, base.map(|itm| &itm.access_key)),
+                "AccessKey",
+                setting,
+            ),
+            secret_key: required(
+                inherit(secret_key, base.map(|itm| &itm.secret_key)),
+                "SecretKey",
+                setting,
+            ),
             bucket_mode,
-            debug: debug.unwrap_or(false),
+            debug: debug.unwrap_or_else(|| base.map(|base| base.debug).unwrap_or(false)),
         }
     }
 }
 
 /// Spelled out rather than `== "1"`: the setting is typed by hand into a deployment config, and a
 /// `Debug=true` that silently means "off" is worse than a refusal to start.
-fn parse_bool(value: &str, key: &str) -> bool {
+fn parse_bool(value: &str, key: &str, setting: &str) -> bool {
     match value.to_ascii_lowercase().as_str() {
         "1" | "true" | "yes" | "on" => true,
         "0" | "false" | "no" | "off" => false,
         _ => panic!(
-            "Invalid s3_conn_string: '{}' expects 1/0, true/false, yes/no or on/off - got '{}'",
-            key, value
+            "Invalid {}: '{}' expects 1/0, true/false, yes/no or on/off - got '{}'",
+            setting, key, value
         ),
     }
 }
 
-fn required(value: Option<String>, key: &str) -> String {
+fn inherit(value: Option<String>, base: Option<&String>) -> Option<String> {
+    value
+        .filter(|itm| !itm.is_empty())
+        .or_else(|| base.cloned())
+}
+
+fn required(value: Option<String>, key: &str, setting: &str) -> String {
     match value {
         Some(value) if !value.is_empty() => value,
-        _ => panic!("Invalid s3_conn_string: '{}' is missing", key),
+        _ => panic!("Invalid {}: '{}' is missing", setting, key),
     }
 }
 
@@ -147,6 +199,44 @@ pub struct SettingsModel {
 
     pub s3_conn_string: Option<String>,
 
+    /// Namespace to a connection string of its own, for a tenant whose data has to stay in its
+    /// own region or account. Each key left out is taken from `s3_conn_string`, which stays the
+    /// one for every other namespace - without it, every other namespace stays local. The whole
+    /// section is optional.
+    #[serde(default)]
+    pub s3_namespaces: BTreeMap<String, String>,
+
+    /// Limits of the streamed reads - `GetPage`, `GetSubPage` and `GetHistoryByDate`. The whole
+    /// section is optional.
+    #[serde(default)]
+    pub streamed_reads: StreamedReadsSettingsModel,
+
+    /// Past revisions of every namespace's `topics-and-queue.yaml`. The whole section is optional.
+    #[serde(default)]
+    pub snapshot_history: SnapshotHistorySettingsModel,
+
+    /// When a `SaveQueueSnapshot` is refused instead of replacing the known topics and queues.
+    /// The whole section is optional.
+    #[serde(default)]
+    pub snapshot_guard: SnapshotGuardSettingsModel,
+
+    /// How little free space on the data volume is too little. The whole section is optional.
+    #[serde(default)]
+    pub disk_watchdog: DiskWatchdogSettingsModel,
+
+    /// The lock that keeps a second instance off the same `data` folder. The whole section is
+    /// optional.
+    #[serde(default)]
+    pub instance_lock: InstanceLockSettingsModel,
+
+    /// Bearer tokens for the gRPC and the HTTP endpoints. Leave it out and both stay open to
+    /// anyone who can reach the ports - which is how every deployment ran before this existed.
+    pub auth: Option<AuthSettingsModel>,
+
+    /// TLS for the gRPC (7124) and the HTTP (7123) listeners. Leave it out and both stay
+    /// plaintext. The unix socket is always plaintext - it never leaves the host.
+    pub tls: Option<TlsSettingsModel>,
+
     /// The three folders the service used before everything moved under one root. Set the section
     /// only for the first start after upgrading; delete it once the migration has finished.
     ///
@@ -167,6 +257,188 @@ pub struct LegacyFoldersSettingsModel {
     pub archive: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthSettingsModel {
+    pub tokens: Vec<AuthTokenSettingsModel>,
+}
+
+/// One caller. The `name` is what shows up in the logs - the token itself never does.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthTokenSettingsModel {
+    pub name: String,
+    pub token: String,
+    /// Any of `read`, `write` and `admin`. `admin` grants the other two as well; `write` does not
+    /// grant `read` - the bus node needs both and says so.
+    pub scopes: Vec<AuthScope>,
+    /// The namespaces the token may touch. Left out - every namespace.
+    pub namespaces: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl AuthScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthScope::Read => "read",
+            AuthScope::Write => "write",
+            AuthScope::Admin => "admin",
+        }
+    }
+}
+
+/// PEM files. They are watched rather than read once: cert-manager and friends rotate them in
+/// place, and a new file is picked up for the next connection without a restart.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TlsSettingsModel {
+    /// The certificate chain, leaf first.
+    pub cert_path: String,
+    pub key_path: String,
+    /// Turns on mutual TLS for gRPC. A client certificate is verified against this CA whenever
+    /// one is presented, and is required for the calls that change data - `SaveMessages`,
+    /// `SaveQueueSnapshot`, `HardDeleteTopic` - so only the bus nodes can make them.
+    pub grpc_client_ca_path: Option<String>,
+    /// The loopback port of the plain HTTP server behind the TLS front. Left out, a free one is
+    /// picked at every start, so two instances on one host do not clash.
+    pub http_backend_port: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StreamedReadsSettingsModel {
+    /// How many sub pages in a row may come back without a single message before a stream stops.
+    /// A range past the end of a topic - or over a hole nothing will ever fill - would otherwise
+    /// be walked to the very last id the client asked for. `0` switches the limit off.
+    #[serde(default = "default_max_empty_sub_pages")]
+    pub max_empty_sub_pages: usize,
+}
+
+impl Default for StreamedReadsSettingsModel {
+    fn default() -> Self {
+        Self {
+            max_empty_sub_pages: default_max_empty_sub_pages(),
+        }
+    }
+}
+
+fn default_max_empty_sub_pages() -> usize {
+    100
+}
+
+/// `SaveQueueSnapshot` replaces the namespace snapshot in place, so one bad save from a bus node
+/// would otherwise overwrite the only copy. A revision is a copy of the file as it was written.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotHistorySettingsModel {
+    /// How many revisions each namespace keeps; the oldest go first. `0` switches the history off.
+    #[serde(default = "default_snapshot_history_keep")]
+    pub keep: usize,
+
+    /// The snapshot is saved every few seconds while queues move, so keeping every save would
+    /// cover minutes. A revision is taken at most this often instead.
+    #[serde(default = "default_snapshot_history_interval_sec")]
+    pub interval_sec: u64,
+
+    /// Also send each revision to the cold tier, next to the namespace's topics. No-op without an
+    /// `s3_conn_string`. Pruned there to `keep` as well, by a listing after each upload.
+    #[serde(default)]
+    pub upload_to_cold: bool,
+}
+
+impl Default for SnapshotHistorySettingsModel {
+    fn default() -> Self {
+        Self {
+            keep: default_snapshot_history_keep(),
+            interval_sec: default_snapshot_history_interval_sec(),
+            upload_to_cold: false,
+        }
+    }
+}
+
+fn default_snapshot_history_keep() -> usize {
+    288
+}
+
+fn default_snapshot_history_interval_sec() -> u64 {
+    300
+}
+
+/// A bus node that restarted with empty state saves a snapshot where every message id starts over
+/// and the queues are gone. A message id going back is always refused; topics and queues
+/// disappearing only in bulk, since deleting one is normal.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotGuardSettingsModel {
+    /// The share of the known topics - and separately of the known queues - that may disappear in
+    /// one save. `1.0` switches the check off.
+    #[serde(default = "default_max_disappeared_share")]
+    pub max_disappeared_share: f64,
+
+    /// Fewer disappearing at once are never refused, whatever their share: in a deployment of two
+    /// topics deleting one is half of them.
+    #[serde(default = "default_min_disappeared")]
+    pub min_disappeared: usize,
+}
+
+impl Default for SnapshotGuardSettingsModel {
+    fn default() -> Self {
+        Self {
+            max_disappeared_share: default_max_disappeared_share(),
+            min_disappeared: default_min_disappeared(),
+        }
+    }
+}
+
+fn default_max_disappeared_share() -> f64 {
+    0.5
+}
+
+fn default_min_disappeared() -> usize {
+    3
+}
+
+/// A full data volume used to be a panic in the archiver and the whole service down with it.
+/// Thresholds are a share of the volume, so the same settings fit a small disk and a large one.
+///
+/// Both are off unless set. Which share is too little depends on the volume - on a large one, or
+/// one shared with other data, a share picked here could refuse `SaveMessages` right after an
+/// upgrade on a disk that has room for days.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DiskWatchdogSettingsModel {
+    /// Below it, sealed files go to the cold tier right away, and local copies of archives the
+    /// cold tier already has are dropped, oldest first. `0` switches it off.
+    #[serde(default)]
+    pub soft_min_free_percent: f64,
+
+    /// Below it, `SaveMessages` answers `ResourceExhausted` instead of accepting what it has no
+    /// room to archive. `0` switches it off.
+    #[serde(default)]
+    pub hard_min_free_percent: f64,
+}
+
+/// A second instance on the same `data` folder refuses to start. During a handover the new one
+/// can be told to wait for the old one to let go instead.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceLockSettingsModel {
+    /// How long to wait for the lock before giving up. `0` gives up right away.
+    #[serde(default = "default_instance_lock_wait_sec")]
+    pub wait_sec: u64,
+}
+
+impl Default for InstanceLockSettingsModel {
+    fn default() -> Self {
+        Self {
+            wait_sec: default_instance_lock_wait_sec(),
+        }
+    }
+}
+
+fn default_instance_lock_wait_sec() -> u64 {
+    0
+}
+
 impl SettingsModel {
     pub fn get_s3_connection(&self) -> Option<S3ConnectionSettings> {
         let conn_string = self.s3_conn_string.as_ref()?;
@@ -178,6 +450,36 @@ impl SettingsModel {
         Some(S3ConnectionSettings::parse(conn_string))
     }
 
+    /// The `s3_namespaces` entries, each completed from `s3_conn_string` when there is one. An
+    /// entry stands on its own without it: a tenant can need a cold tier while nothing else does.
+    pub fn get_s3_namespace_connections(&self) -> Vec<(String, S3ConnectionSettings)> {
+        if self.s3_namespaces.is_empty() {
+            return vec![];
+        }
+
+        let base = self.get_s3_connection();
+
+        self.s3_namespaces
+            .iter()
+            .map(|(namespace, conn_string)| {
+                if let Err(err) = Namespace::parse(Some(namespace.as_str())) {
+                    panic!(
+                        "Invalid s3_namespaces: '{}' is not a namespace name: {}",
+                        namespace, err
+                    );
+                }
+
+                let settings = S3ConnectionSettings::parse_override(
+                    conn_string,
+                    namespace.as_str(),
+                    base.as_ref(),
+                );
+
+                (namespace.clone(), settings)
+            })
+            .collect()
+    }
+
     pub async fn read() -> Self {
         let filename = my_service_bus::shared::settings::get_settings_filename_path(
             ".myservicebus-persistence",
@@ -283,6 +585,14 @@ mod tests {
         S3ConnectionSettings::parse("Endpoint=https://s3;AccessKey=a;SecretKey=b;Bucket=c");
     }
 
+    #[test]
+    #[should_panic(expected = "Invalid S3 endpoint")]
+    fn an_endpoint_that_is_not_a_url_is_loud() {
+        S3ConnectionSettings::parse(
+            "Endpoint=s3.eu-central-1;Region=eu;AccessKey=a;SecretKey=b;Bucket=c",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "unknown key")]
     fn a_typo_is_loud() {
@@ -291,6 +601,75 @@ mod tests {
         );
     }
 
+    /// What a namespace leaves out comes from `s3_conn_string`, the layout included.
+    #[test]
+    fn a_namespace_override_takes_the_rest_from_the_global_one() {
+        let base = S3ConnectionSettings::parse(
+            "Endpoint=https://s3;Region=eu;AccessKey=a;SecretKey=b;Bucket=sb-data;Debug=1",
+        );
+
+        let parsed = S3ConnectionSettings::parse_override(
+            "Endpoint=https://s3.eu-north-1;Region=eu-north-1",
+            "tenant",
+            Some(&base),
+        );
+
+        assert_eq!("https://s3.eu-north-1", parsed.endpoint);
+        assert_eq!("eu-north-1", parsed.region);
+        assert_eq!("a", parsed.access_key);
+        assert_eq!("b", parsed.secret_key);
+        assert!(parsed.debug);
+        assert!(matches!(parsed.bucket_mode, S3BucketMode::Shared(bucket) if bucket == "sb-data"));
+
+        let parsed = S3ConnectionSettings::parse_override(
+            "AccessKey=c;SecretKey=d;BucketPrefix=tenant;Debug=0",
+            "tenant",
+            Some(&base),
+        );
+
+        assert_eq!("https://s3", parsed.endpoint);
+        assert_eq!("c", parsed.access_key);
+        assert!(!parsed.debug);
+        assert!(
+            matches!(parsed.bucket_mode, S3BucketMode::PerNamespace(prefix) if prefix == "tenant")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid s3_namespaces.tenant: unknown key")]
+    fn a_namespace_override_names_itself_when_it_is_wrong() {
+        let base = S3ConnectionSettings::parse(
+            "Endpoint=https://s3;Region=eu;AccessKey=a;SecretKey=b;Bucket=sb-data",
+        );
+
+        S3ConnectionSettings::parse_override("Regoin=eu-north-1", "tenant", Some(&base));
+    }
+
+    /// Without an `s3_conn_string` an entry is a whole connection string of its own.
+    #[test]
+    fn a_namespace_override_stands_on_its_own_without_the_global_one() {
+        let parsed = S3ConnectionSettings::parse_override(
+            "Endpoint=https://s3.eu-north-1;Region=eu-north-1;AccessKey=a;SecretKey=b;BucketPrefix=tenant",
+            "tenant",
+            None,
+        );
+
+        assert_eq!("https://s3.eu-north-1", parsed.endpoint);
+        assert!(
+            matches!(parsed.bucket_mode, S3BucketMode::PerNamespace(prefix) if prefix == "tenant")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid s3_namespaces.tenant: 'AccessKey' is missing")]
+    fn a_namespace_override_without_the_global_one_gives_every_key() {
+        S3ConnectionSettings::parse_override(
+            "Endpoint=https://s3.eu-north-1;Region=eu-north-1;Bucket=sb-data",
+            "tenant",
+            None,
+        );
+    }
+
     /// Off unless the connection string says otherwise - tracing every request is not something to
     /// end up with by accident.
     #[test]
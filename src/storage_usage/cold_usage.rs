@@ -0,0 +1,385 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use my_logger::LogEventCtx;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::storage_layout,
+    cold_storage::ColdStorage,
+    topic_key::{TopicKey, TopicKeyRef},
+    topics_snapshot::file_storage::{read_to_string_if_exists, scan_namespaces},
+    utils::PersistedYaml,
+};
+
+/// `{data_folder}/{namespace}/cold-usage.yaml` - topic id to its uploaded files and their sizes.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct ColdUsageYamlModel {
+    #[serde(default)]
+    topics: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+/// namespace -> topic_id -> file name -> size
+type ColdUsageByNamespace = BTreeMap<String, BTreeMap<String, BTreeMap<String, u64>>>;
+
+/// What each topic has in the cold tier, kept from the uploads and deletes themselves: listing a
+/// namespace is a request per thousand objects, too many for every metrics tick.
+///
+/// A namespace without its file - a data folder from before this existed, or one that lost it -
+/// is filled in once from a listing at start, see [`Self::rebuild_missing`]. After that, a
+/// delete that failed once the object was gone is set right only by a cold reconciliation with
+/// `repair`.
+pub struct ColdUsage {
+    data_folder: String,
+    data: PersistedYaml<ColdUsageByNamespace>,
+}
+
+impl ColdUsage {
+    pub async fn load(data_folder: String) -> Self {
+        let mut data = BTreeMap::new();
+
+        for namespace in scan_namespaces(data_folder.as_str()).await {
+            let path = storage_layout::get_namespace_cold_usage_file(
+                data_folder.as_str(),
+                namespace.as_str(),
+            );
+
+            let Some(content) = read_to_string_if_exists(&path).await else {
+                continue;
+            };
+
+            // Unlike the retention, a broken file loses nothing that matters: it is accounting,
+            // and it fills up again from the next uploads.
+            let model: ColdUsageYamlModel = match serde_yaml::from_str(&content) {
+                Ok(model) => model,
+                Err(err) => {
+                    println!("Can not parse {:?}, starting it over: {}", path, err);
+                    continue;
+                }
+            };
+
+            if !model.topics.is_empty() {
+                data.insert(namespace.as_str().to_string(), model.topics);
+            }
+        }
+
+        Self {
+            data_folder,
+            data: PersistedYaml::new(data),
+        }
+    }
+
+    /// Lists the cold tier of every namespace that has one but no `cold-usage.yaml`, and writes
+    /// what it found - even nothing, so the next start does not list it again. Runs at start,
+    /// before the uploader: an upload recorded during the listing would be overwritten by it.
+    ///
+    /// A namespace whose listing fails stays empty and is tried again at the next start.
+    pub async fn rebuild_missing(&self, cold_storage: &ColdStorage) {
+        for namespace in scan_namespaces(self.data_folder.as_str()).await {
+            let namespace = namespace.as_str();
+            let path = self.get_path(namespace);
+
+            if !cold_storage.covers(namespace) || path.exists() {
+                continue;
+            }
+
+            let listed = match cold_storage.list_namespace_files(namespace).await {
+                Ok(listed) => listed,
+                Err(err) => {
+                    my_logger::LOGGER.write_warning(
+                        "ColdUsage".to_string(),
+                        format!("Can not list the cold storage to count it. Err: {}", err),
+                        LogEventCtx::new().add("namespace", namespace.to_string()),
+                    );
+                    continue;
+                }
+            };
+
+            let files = listed.len();
+
+            let mut topics: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+
+            for itm in listed {
+                topics
+                    .entry(itm.topic_id)
+                    .or_default()
+                    .insert(itm.file_name, itm.size);
+            }
+
+            let result = self
+                .data
+                .modify(path, |data| {
+                    if topics.is_empty() {
+                        data.remove(namespace);
+                    } else {
+                        data.insert(namespace.to_string(), topics);
+                    }
+
+                    Some(to_yaml_model(data, namespace))
+                })
+                .await;
+
+            match result {
+                Ok(_) => my_logger::LOGGER.write_info(
+                    "ColdUsage".to_string(),
+                    format!("Counted {} files from a listing of the cold storage", files),
+                    LogEventCtx::new().add("namespace", namespace.to_string()),
+                ),
+                Err(err) => my_logger::LOGGER.write_error(
+                    "ColdUsage".to_string(),
+                    format!("Can not write the cold usage. Err: {}", err),
+                    LogEventCtx::new().add("namespace", namespace.to_string()),
+                ),
+            }
+        }
+    }
+
+    /// Bytes in the cold tier per topic.
+    pub fn get_all(&self) -> Vec<(TopicKey, u64)> {
+        self.data.read(|data| {
+            let mut result = Vec::new();
+
+            for (namespace, topics) in data.iter() {
+                for (topic_id, files) in topics.iter() {
+                    let topic_key = TopicKey {
+                        namespace: namespace.clone(),
+                        topic_id: topic_id.clone(),
+                    };
+
+                    result.push((topic_key, files.values().sum()));
+                }
+            }
+
+            result
+        })
+    }
+
+    /// The size the file had when it was uploaded, or when a listing found it. `None` for a file
+    /// this does not know about - not in the cold tier, or in a namespace whose listing failed.
+    pub fn get_file_size(&self, topic_key: TopicKeyRef<'_>, file_name: &str) -> Option<u64> {
+        self.data.read(|data| {
+            data.get(topic_key.namespace)?
+                .get(topic_key.topic_id)?
+                .get(file_name)
+                .copied()
+        })
+    }
+
+    pub async fn uploaded(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        file_name: &str,
+        size: u64,
+    ) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(topic_key.namespace), |data| {
+                data.entry(topic_key.namespace.to_string())
+                    .or_default()
+                    .entry(topic_key.topic_id.to_string())
+                    .or_default()
+                    .insert(file_name.to_string(), size);
+
+                Some(to_yaml_model(data, topic_key.namespace))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn deleted(&self, topic_key: TopicKeyRef<'_>, file_name: &str) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(topic_key.namespace), |data| {
+                let topics = data.get_mut(topic_key.namespace)?;
+                let files = topics.get_mut(topic_key.topic_id)?;
+
+                files.remove(file_name)?;
+
+                if files.is_empty() {
+                    topics.remove(topic_key.topic_id);
+                }
+
+                Some(to_yaml_model(data, topic_key.namespace))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces what is known of the topic with `files` - what a listing of the cold tier found.
+    /// An empty `files` forgets the topic.
+    pub async fn set_topic_files(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        files: BTreeMap<String, u64>,
+    ) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(topic_key.namespace), |data| {
+                let topics = data.entry(topic_key.namespace.to_string()).or_default();
+
+                if files.is_empty() {
+                    topics.remove(topic_key.topic_id);
+                } else {
+                    topics.insert(topic_key.topic_id.to_string(), files);
+                }
+
+                Some(to_yaml_model(data, topic_key.namespace))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Every file known for the topic with its size.
+    pub fn get_topic_files(&self, topic_key: TopicKeyRef<'_>) -> BTreeMap<String, u64> {
+        self.data.read(|data| {
+            data.get(topic_key.namespace)
+                .and_then(|topics| topics.get(topic_key.topic_id))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    fn get_path(&self, namespace: &str) -> PathBuf {
+        storage_layout::get_namespace_cold_usage_file(self.data_folder.as_str(), namespace)
+    }
+}
+
+fn to_yaml_model(data: &ColdUsageByNamespace, namespace: &str) -> ColdUsageYamlModel {
+    ColdUsageYamlModel {
+        topics: data.get(namespace).cloned().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topic_key::TopicKeyRef;
+
+    use super::ColdUsage;
+
+    #[tokio::test]
+    async fn survives_a_restart_and_forgets_deleted_files() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-cold-usage");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("alpha")).unwrap();
+
+        let data_folder = root.to_str().unwrap().to_string();
+
+        let orders = TopicKeyRef::new("alpha", "orders");
+
+        {
+            let usage = ColdUsage::load(data_folder.clone()).await;
+
+            usage.uploaded(orders, "a.archive", 100).await.unwrap();
+            usage.uploaded(orders, "b.archive", 50).await.unwrap();
+            // Uploaded again - replaced, not added
+            usage.uploaded(orders, "b.archive", 60).await.unwrap();
+            usage.deleted(orders, "a.archive").await.unwrap();
+        }
+
+        let usage = ColdUsage::load(data_folder).await;
+
+        let all = usage.get_all();
+        assert_eq!(1, all.len());
+        assert_eq!(60, all[0].1);
+
+        usage.deleted(orders, "b.archive").await.unwrap();
+        assert!(usage.get_all().is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn a_namespace_without_its_file_is_counted_from_a_listing() {
+        use crate::cold_storage::{fake_s3::FakeS3, ColdStorage};
+        use crate::settings::{S3BucketMode, S3ConnectionSettings};
+
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-cold-usage-rebuild");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("alpha")).unwrap();
+        std::fs::create_dir_all(root.join("beta")).unwrap();
+
+        let data_folder = root.to_str().unwrap().to_string();
+
+        let fake = FakeS3::start().await;
+        let cold_storage = ColdStorage::new(&S3ConnectionSettings {
+            endpoint: fake.endpoint.clone(),
+            region: "eu-central-1".to_string(),
+            access_key: "AKIATEST".to_string(),
+            secret_key: "secret".to_string(),
+            bucket_mode: S3BucketMode::PerNamespace("sb".to_string()),
+            debug: false,
+        });
+
+        for namespace in ["alpha", "beta"] {
+            let bucket = cold_storage.get_bucket(namespace);
+            fake.create_bucket(bucket.as_str());
+
+            for (key, size) in [
+                ("orders/0000000000000000000.archive", 3),
+                ("orders/2024.yearindex", 5),
+                ("payments/0000000000000000000.archive", 6),
+            ] {
+                fake.put_object(format!("/{}/{}", bucket, key).as_str(), vec![0u8; size]);
+            }
+        }
+
+        // `beta` has its file already - that is what counts, not the listing
+        {
+            let usage = ColdUsage::load(data_folder.clone()).await;
+            usage
+                .uploaded(TopicKeyRef::new("beta", "orders"), "a.archive", 1)
+                .await
+                .unwrap();
+        }
+
+        let usage = ColdUsage::load(data_folder.clone()).await;
+        usage.rebuild_missing(&cold_storage).await;
+
+        let mut all: Vec<(String, String, u64)> = usage
+            .get_all()
+            .into_iter()
+            .map(|(key, bytes)| (key.namespace, key.topic_id, bytes))
+            .collect();
+        all.sort();
+
+        assert_eq!(
+            vec![
+                ("alpha".to_string(), "orders".to_string(), 8),
+                ("alpha".to_string(), "payments".to_string(), 6),
+                ("beta".to_string(), "orders".to_string(), 1),
+            ],
+            all
+        );
+
+        // Written, so the next start reads it instead of listing again
+        fake.put_object(
+            format!(
+                "/{}/orders/0000000000000000001.archive",
+                cold_storage.get_bucket("alpha")
+            )
+            .as_str(),
+            vec![0u8; 4],
+        );
+
+        let usage = ColdUsage::load(data_folder).await;
+        usage.rebuild_missing(&cold_storage).await;
+
+        assert_eq!(
+            Some(3),
+            usage.get_file_size(
+                TopicKeyRef::new("alpha", "orders"),
+                "0000000000000000000.archive"
+            )
+        );
+        assert_eq!(
+            None,
+            usage.get_file_size(
+                TopicKeyRef::new("alpha", "orders"),
+                "0000000000000000001.archive"
+            )
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
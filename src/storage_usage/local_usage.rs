@@ -0,0 +1,95 @@
+use crate::{app::storage_layout, topic_key::TopicKeyRef};
+
+/// What one topic occupies on the local disk, by the kind of file.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct LocalUsage {
+    pub archive_bytes: u64,
+    pub year_index_bytes: u64,
+    pub active_bytes: u64,
+}
+
+impl LocalUsage {
+    pub fn get_total(&self) -> u64 {
+        self.archive_bytes + self.year_index_bytes + self.active_bytes
+    }
+}
+
+/// Sizes of the files in `{data_folder}/{namespace}/{topic}/`. Anything that is not an archive, a
+/// year index or the `active` file - a temp file of an atomic write, something an operator left
+/// there - is not the topic's data and is not counted.
+///
+/// The size is the file's length, not the blocks it holds: a year index is allocated whole and
+/// mostly empty, and `du` on a filesystem with sparse files reports less than this.
+pub async fn read_local_usage(data_folder: &str, topic_key: TopicKeyRef<'_>) -> LocalUsage {
+    let mut result = LocalUsage::default();
+
+    let folder = storage_layout::get_topic_folder(data_folder, topic_key);
+
+    let Ok(mut entries) = tokio::fs::read_dir(folder.as_path()).await else {
+        return result;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let file_name = entry.file_name();
+
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+
+        if file_name == storage_layout::ACTIVE_FILE_NAME {
+            result.active_bytes += metadata.len();
+        } else if storage_layout::parse_archive_file_name(file_name).is_some() {
+            result.archive_bytes += metadata.len();
+        } else if storage_layout::parse_year_index_file_name(file_name).is_some() {
+            result.year_index_bytes += metadata.len();
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topic_key::TopicKeyRef;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sums_each_kind_and_skips_what_is_not_topic_data() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-local-usage");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let topic_folder = root.join("alpha").join("orders");
+        std::fs::create_dir_all(&topic_folder).unwrap();
+
+        std::fs::write(topic_folder.join("0000000000000001.archive"), [0u8; 10]).unwrap();
+        std::fs::write(topic_folder.join("0000000000000002.archive"), [0u8; 20]).unwrap();
+        std::fs::write(topic_folder.join(".2024.yearindex"), [0u8; 5]).unwrap();
+        std::fs::write(topic_folder.join("active"), [0u8; 3]).unwrap();
+        std::fs::write(topic_folder.join("stray-file"), [0u8; 100]).unwrap();
+
+        let usage =
+            read_local_usage(root.to_str().unwrap(), TopicKeyRef::new("alpha", "orders")).await;
+
+        assert_eq!(
+            LocalUsage {
+                archive_bytes: 30,
+                year_index_bytes: 5,
+                active_bytes: 3,
+            },
+            usage
+        );
+        assert_eq!(38, usage.get_total());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,6 @@
+mod cold_usage;
+pub use cold_usage::*;
+mod local_usage;
+pub use local_usage::*;
+mod storage_usage;
+pub use storage_usage::*;
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::topic_key::TopicKey;
+
+use super::LocalUsage;
+
+/// What one topic occupies, locally and in the cold tier.
+#[derive(Clone)]
+pub struct TopicStorageUsage {
+    pub topic_key: TopicKey,
+    pub local: LocalUsage,
+    pub cold_bytes: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct NamespaceStorageUsage {
+    pub namespace: String,
+    pub topics: usize,
+    pub local: LocalUsage,
+    pub cold_bytes: u64,
+}
+
+/// The last accounting pass, for the status API. Walking every topic folder is not something to
+/// do per request - the numbers are as of `calculated`, refreshed by its timer.
+pub struct StorageUsage {
+    topics: Mutex<Vec<TopicStorageUsage>>,
+    calculated: Mutex<Option<DateTimeAsMicroseconds>>,
+}
+
+impl StorageUsage {
+    pub fn new() -> Self {
+        Self {
+            topics: Mutex::new(Vec::new()),
+            calculated: Mutex::new(None),
+        }
+    }
+
+    pub fn set(&self, topics: Vec<TopicStorageUsage>, calculated: DateTimeAsMicroseconds) {
+        *self.topics.lock() = topics;
+        *self.calculated.lock() = Some(calculated);
+    }
+
+    /// `None` until the first pass is done.
+    pub fn get_calculated(&self) -> Option<DateTimeAsMicroseconds> {
+        *self.calculated.lock()
+    }
+
+    pub fn get_topics(&self) -> Vec<TopicStorageUsage> {
+        self.topics.lock().clone()
+    }
+
+    pub fn get_namespaces(&self) -> Vec<NamespaceStorageUsage> {
+        sum_by_namespace(self.topics.lock().as_slice())
+    }
+}
+
+/// Sorted by namespace.
+pub fn sum_by_namespace(topics: &[TopicStorageUsage]) -> Vec<NamespaceStorageUsage> {
+    let mut result: BTreeMap<&str, NamespaceStorageUsage> = BTreeMap::new();
+
+    for topic in topics {
+        let namespace = result
+            .entry(topic.topic_key.namespace.as_str())
+            .or_insert_with(|| NamespaceStorageUsage {
+                namespace: topic.topic_key.namespace.clone(),
+                ..Default::default()
+            });
+
+        namespace.topics += 1;
+        namespace.local.archive_bytes += topic.local.archive_bytes;
+        namespace.local.year_index_bytes += topic.local.year_index_bytes;
+        namespace.local.active_bytes += topic.local.active_bytes;
+        namespace.cold_bytes += topic.cold_bytes;
+    }
+
+    result.into_values().collect()
+}
@@ -1,10 +1,10 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc, time::SystemTime};
 
 use my_logger::LogEventCtx;
 use rust_extensions::{MyTimerTick, RepeatTimerIteration};
 
 use crate::{
-    app::{storage_layout, AppContext, StorageLocks},
+    app::{storage_layout, AppContext, StorageLocks, UploadBacklog},
     archive_storage::ArchiveFileNo,
     file_storage::delete_file_if_exists,
     topic_key::{TopicKey, TopicKeyRef},
@@ -21,6 +21,10 @@ use crate::{
 /// Nothing is persisted and nothing needs to be. "The highest number on disk is the current one"
 /// stays true by itself: a rollover turns the previous current into a sealed file that the next
 /// tick picks up, and after a restart the same listing yields the same answer.
+///
+/// What is sealed and still local is the upload backlog, published per namespace twice a tick: as
+/// found, and as left once the tick is through. A backlog that only grows, or an oldest file that
+/// only ages, is a cold tier that is not taking uploads - and the disk filling up behind it.
 pub struct ColdStorageUploaderTimer {
     app: Arc<AppContext>,
 }
@@ -34,14 +38,28 @@ impl ColdStorageUploaderTimer {
 #[async_trait::async_trait]
 impl MyTimerTick for ColdStorageUploaderTimer {
     async fn tick(&self) -> RepeatTimerIteration {
-        if self.app.get_cold_storage().is_none() {
+        if !self.app.has_cold_storage() {
             return RepeatTimerIteration::WithInterval;
         }
 
-        for topic_folder in get_topic_folders(self.app.get_data_folder()).await {
-            upload_sealed_files(self.app.as_ref(), &topic_folder).await;
+        let mut pending = Vec::new();
+
+        for topic_folder in get_topic_folders(self.app.as_ref()).await {
+            let sealed_files = find_sealed_files(&topic_folder).await;
+            pending.push((topic_folder, sealed_files));
         }
 
+        publish_backlog(self.app.as_ref(), pending.as_slice());
+
+        let mut left = Vec::new();
+
+        for (topic_folder, sealed_files) in pending {
+            let sealed_files = upload_files(self.app.as_ref(), &topic_folder, sealed_files).await;
+            left.push((topic_folder, sealed_files));
+        }
+
+        publish_backlog(self.app.as_ref(), left.as_slice());
+
         RepeatTimerIteration::WithInterval
     }
 }
@@ -57,23 +75,165 @@ impl TopicFolder {
     }
 }
 
-async fn get_topic_folders(data_folder: &str) -> Vec<TopicFolder> {
+impl TopicFolder {
+    fn new(data_folder: &str, topic_key: TopicKey) -> Self {
+        let path = storage_layout::get_topic_folder(data_folder, topic_key.to_ref());
+        Self { topic_key, path }
+    }
+}
+
+/// Only the topics of namespaces with a cold tier - the rest have nowhere to go, and are no
+/// backlog either.
+async fn get_topic_folders(app: &AppContext) -> Vec<TopicFolder> {
+    let data_folder = app.get_data_folder();
+
     crate::operations::scan_topic_folders(data_folder)
         .await
         .into_iter()
-        .map(|topic_key| {
-            let path = storage_layout::get_topic_folder(data_folder, topic_key.to_ref());
-            TopicFolder { topic_key, path }
-        })
+        .filter(|topic_key| app.get_cold_storage(topic_key.namespace.as_str()).is_some())
+        .map(|topic_key| TopicFolder::new(data_folder, topic_key))
         .collect()
 }
 
-async fn upload_sealed_files(app: &AppContext, topic_folder: &TopicFolder) {
+/// The same sweep the timer does, for one topic and right now - the admin `ForceUpload`. Returns
+/// how many files went up. The file being written stays local whatever the caller wants: the cold
+/// copy of an archive is frozen, and the next sub page would have nowhere to go.
+pub async fn upload_sealed_files_of_topic(app: &AppContext, topic_key: TopicKeyRef<'_>) -> usize {
+    let topic_folder = TopicFolder::new(app.get_data_folder(), topic_key.to_owned_key());
+
+    let sealed_files = find_sealed_files(&topic_folder).await;
+    let found = sealed_files.len();
+
+    found - upload_files(app, &topic_folder, sealed_files).await.len()
+}
+
+/// What the disk watchdog runs when the data volume is low: first the local copies of sealed
+/// archives the cold tier already has, oldest first - dropping them costs no upload, so it is
+/// the quickest room there is; then the sweep of the timer, right away instead of on its next
+/// tick. Returns how many files left the disk.
+///
+/// Sealed archives are the only ones touched: the cold copy of a sealed archive is the same
+/// file, while a year index brought back from the cold tier may have taken a late write since.
+pub async fn offload_for_space(app: &AppContext) -> usize {
+    let topic_folders = get_topic_folders(app).await;
+
+    let mut archives = Vec::new();
+
+    for (index, topic_folder) in topic_folders.iter().enumerate() {
+        for sealed_file in find_sealed_files(topic_folder).await {
+            if sealed_file.archive_file_no.is_some() {
+                archives.push((index, sealed_file));
+            }
+        }
+    }
+
+    archives.sort_by_key(|(_, sealed_file)| sealed_file.modified);
+
+    let mut dropped = 0;
+
+    for (index, sealed_file) in archives {
+        let topic_folder = &topic_folders[index];
+        let file_name = sealed_file.file_name.as_str();
+
+        let Some(cold_storage) = app.get_cold_storage(topic_folder.topic_key.namespace.as_str())
+        else {
+            continue;
+        };
+
+        // The size, not just the key: an upload cut short, or a file sealed again after it went
+        // up, leaves an object the local copy is not. Such a file goes up again in the sweep below.
+        match cold_storage
+            .get_size(topic_folder.get_topic_key(), file_name)
+            .await
+        {
+            Ok(Some(size)) if size == sealed_file.size => {}
+            Ok(Some(size)) => {
+                write_error(
+                    format!("{}/{}", topic_folder.topic_key, file_name).as_str(),
+                    format!(
+                        "The cold copy has {} bytes and the local one {}. Keeping the local one",
+                        size, sealed_file.size
+                    ),
+                );
+                continue;
+            }
+            Ok(None) => continue,
+            Err(err) => {
+                write_error(
+                    format!("{}/{}", topic_folder.topic_key, file_name).as_str(),
+                    format!("Can not check the cold storage. Err: {}", err),
+                );
+                continue;
+            }
+        }
+
+        let removed = drop_local_copy(
+            app,
+            topic_folder,
+            file_name,
+            &app.archive_locks,
+            sealed_file.archive_file_no,
+        )
+        .await;
+
+        if removed {
+            dropped += 1;
+        }
+    }
+
+    for topic_folder in topic_folders.iter() {
+        let sealed_files = find_sealed_files(topic_folder).await;
+        let found = sealed_files.len();
+
+        dropped += found - upload_files(app, topic_folder, sealed_files).await.len();
+    }
+
+    dropped
+}
+
+pub struct SealedFile {
+    pub file_name: String,
+    /// `None` for a year index.
+    pub archive_file_no: Option<ArchiveFileNo>,
+    pub size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What the timer would upload for the topic on its next tick.
+pub async fn get_sealed_files_of_topic(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+) -> Vec<SealedFile> {
+    let topic_folder = TopicFolder::new(app.get_data_folder(), topic_key.to_owned_key());
+    find_sealed_files(&topic_folder).await
+}
+
+/// Deletes the local copy of a sealed archive the caller has found in the cold tier, the same way
+/// an upload ends. `false` - it is still there, and the error is logged.
+pub async fn drop_uploaded_archive(
+    app: &AppContext,
+    topic_key: TopicKeyRef<'_>,
+    file_name: &str,
+    archive_file_no: ArchiveFileNo,
+) -> bool {
+    let topic_folder = TopicFolder::new(app.get_data_folder(), topic_key.to_owned_key());
+
+    drop_local_copy(
+        app,
+        &topic_folder,
+        file_name,
+        &app.archive_locks,
+        Some(archive_file_no),
+    )
+    .await
+}
+
+async fn find_sealed_files(topic_folder: &TopicFolder) -> Vec<SealedFile> {
     let mut archives: Vec<(i64, String)> = Vec::new();
     let mut year_indexes: Vec<(u32, String)> = Vec::new();
 
     let Ok(mut entries) = tokio::fs::read_dir(topic_folder.path.as_path()).await else {
-        return;
+        return Vec::new();
     };
 
     while let Ok(Some(entry)) = entries.next_entry().await {
@@ -97,27 +257,100 @@ async fn upload_sealed_files(app: &AppContext, topic_folder: &TopicFolder) {
         }
     }
 
-    for (archive_file_no, file_name) in take_all_but_the_highest(archives) {
-        upload_and_drop(
-            app,
-            topic_folder,
-            file_name.as_str(),
-            &app.archive_locks,
-            Some(ArchiveFileNo::new(archive_file_no)),
-        )
-        .await;
+    let archives = take_all_but_the_highest(archives)
+        .into_iter()
+        .map(|(archive_file_no, file_name)| (file_name, Some(ArchiveFileNo::new(archive_file_no))));
+
+    let year_indexes = take_all_but_the_highest(year_indexes)
+        .into_iter()
+        .map(|(_, file_name)| (file_name, None));
+
+    let mut result = Vec::new();
+
+    for (file_name, archive_file_no) in archives.chain(year_indexes) {
+        let mut path = topic_folder.path.clone();
+        path.push(file_name.as_str());
+
+        let (size, modified) = match tokio::fs::metadata(path.as_path()).await {
+            Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+            Err(_) => (0, None),
+        };
+
+        result.push(SealedFile {
+            file_name,
+            archive_file_no,
+            size,
+            modified,
+        });
     }
 
-    for (_, file_name) in take_all_but_the_highest(year_indexes) {
-        upload_and_drop(
+    result
+}
+
+/// Returns what stayed behind.
+async fn upload_files(
+    app: &AppContext,
+    topic_folder: &TopicFolder,
+    sealed_files: Vec<SealedFile>,
+) -> Vec<SealedFile> {
+    let mut left = Vec::new();
+
+    for sealed_file in sealed_files {
+        let locks = if sealed_file.archive_file_no.is_some() {
+            &app.archive_locks
+        } else {
+            &app.index_locks
+        };
+
+        let moved = upload_and_drop(
             app,
             topic_folder,
-            file_name.as_str(),
-            &app.index_locks,
-            None,
+            sealed_file.file_name.as_str(),
+            locks,
+            sealed_file.archive_file_no,
         )
         .await;
+
+        if !moved {
+            left.push(sealed_file);
+        }
     }
+
+    left
+}
+
+/// Every namespace with a topic folder gets a value, so one that has caught up reads `0`.
+fn publish_backlog(app: &AppContext, pending: &[(TopicFolder, Vec<SealedFile>)]) {
+    let now = SystemTime::now();
+
+    let mut by_namespace: BTreeMap<String, UploadBacklog> = BTreeMap::new();
+
+    for (topic_folder, sealed_files) in pending {
+        let backlog = by_namespace
+            .entry(topic_folder.topic_key.namespace.clone())
+            .or_default();
+
+        for sealed_file in sealed_files {
+            backlog.files += 1;
+            backlog.bytes += sealed_file.size;
+
+            let Some(age) = sealed_file
+                .modified
+                .and_then(|modified| now.duration_since(modified).ok())
+            else {
+                continue;
+            };
+
+            backlog.oldest_age = Some(match backlog.oldest_age {
+                Some(oldest_age) => oldest_age.max(age),
+                None => age,
+            });
+        }
+    }
+
+    app.metrics_keeper
+        .get_cold_storage_metrics()
+        .update_upload_backlog(by_namespace.into_iter().collect());
 }
 
 /// The highest-numbered file is the live one; everything below it is sealed.
@@ -150,12 +383,13 @@ async fn upload_and_drop(
     file_name: &str,
     locks: &StorageLocks,
     archive_file_no: Option<ArchiveFileNo>,
-) {
-    let Some(cold_storage) = app.get_cold_storage() else {
-        return;
+) -> bool {
+    let topic_key = topic_folder.get_topic_key();
+
+    let Some(cold_storage) = app.get_cold_storage(topic_key.namespace) else {
+        return false;
     };
 
-    let topic_key = topic_folder.get_topic_key();
     // The namespace is the bucket, so the key is what is left of the path.
 
     let mut path = topic_folder.path.clone();
@@ -167,7 +401,7 @@ async fn upload_and_drop(
         let _guard = locks.read(topic_key).await;
 
         if !path.is_file() {
-            return;
+            return false;
         }
 
         if let Err(err) = cold_storage
@@ -178,19 +412,44 @@ async fn upload_and_drop(
                 format!("{}/{}", topic_key, file_name).as_str(),
                 format!("Can not upload. Err: {}", err),
             );
-            return;
+            return false;
         }
     }
 
     // Phase 2 - exclusive: nothing is mid-read, so the local copy can go.
+    if !drop_local_copy(app, topic_folder, file_name, locks, archive_file_no).await {
+        return false;
+    }
+
+    println!("Moved {}/{} to the cold storage", topic_key, file_name);
+
+    true
+}
+
+/// Deletes the local copy of a file the cold tier has, under the write lock.
+async fn drop_local_copy(
+    app: &AppContext,
+    topic_folder: &TopicFolder,
+    file_name: &str,
+    locks: &StorageLocks,
+    archive_file_no: Option<ArchiveFileNo>,
+) -> bool {
+    let topic_key = topic_folder.get_topic_key();
+
+    let mut path = topic_folder.path.clone();
+    path.push(file_name);
+
     let _guard = locks.write(topic_key).await;
 
     if let Err(err) = delete_file_if_exists(&path).await {
         write_error(
             format!("{}/{}", topic_key, file_name).as_str(),
-            format!("Uploaded, but can not delete the local copy. Err: {}", err),
+            format!(
+                "In the cold storage, but can not delete the local copy. Err: {}",
+                err
+            ),
         );
-        return;
+        return false;
     }
 
     // The cached handle points at a file that is gone - the next read reopens it against the cold
@@ -200,7 +459,7 @@ async fn upload_and_drop(
             .forget_archive(topic_key, archive_file_no);
     }
 
-    println!("Moved {}/{} to the cold storage", topic_key, file_name);
+    true
 }
 
 fn write_error(key: &str, message: String) {
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use rust_extensions::{date_time::DateTimeAsMicroseconds, MyTimerTick, RepeatTimerIteration};
+
+use crate::{
+    app::AppContext,
+    consumer_lag::{get_queue_backlog, QueueLag},
+    topic_key::TopicKey,
+};
+
+/// Turns the queue snapshot into backlog gauges. On the snapshot saver's 3 s: the bus node saves
+/// the snapshot about that often, so a tighter loop would see the same ranges.
+pub struct ConsumerLagUpdaterTimer {
+    app: Arc<AppContext>,
+}
+
+impl ConsumerLagUpdaterTimer {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl MyTimerTick for ConsumerLagUpdaterTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        let snapshot = self.app.topics_snapshot.get().await;
+
+        let mut result = Vec::new();
+
+        for topic in snapshot.snapshot.data.iter() {
+            let topic_key = topic.get_topic_key().to_owned_key();
+
+            for queue in topic.queues.iter() {
+                let (unconsumed, oldest_unconsumed) = get_queue_backlog(queue);
+
+                let oldest_unconsumed_created = match oldest_unconsumed {
+                    Some(message_id) => {
+                        get_created(self.app.as_ref(), &topic_key, &queue.queue_id, message_id)
+                            .await
+                    }
+                    None => None,
+                };
+
+                result.push(QueueLag {
+                    topic_key: topic_key.clone(),
+                    queue_id: queue.queue_id.clone(),
+                    unconsumed,
+                    oldest_unconsumed,
+                    oldest_unconsumed_created,
+                });
+            }
+        }
+
+        self.app
+            .metrics_keeper
+            .update_consumer_lag(result.as_slice(), DateTimeAsMicroseconds::now());
+
+        self.app.consumer_lag.set(result);
+
+        RepeatTimerIteration::WithInterval
+    }
+}
+
+/// A loaded topic has the message read. Any other is looked up in its year indexes instead: the
+/// bus node loads the topics it serves, and reading a message of one it does not would load it
+/// for nothing - while a queue of such a topic is the one most likely to be stuck.
+async fn get_created(
+    app: &AppContext,
+    topic_key: &TopicKey,
+    queue_id: &str,
+    message_id: my_service_bus::abstractions::MessageId,
+) -> Option<DateTimeAsMicroseconds> {
+    if let Some(created) = app
+        .consumer_lag
+        .get_created(topic_key, queue_id, message_id)
+    {
+        return created;
+    }
+
+    let created = if app.topics_list.get(topic_key.to_ref()).is_some() {
+        let message = crate::operations::get_message_by_id(app, topic_key.to_ref(), message_id)
+            .await
+            .ok()??;
+
+        Some(message.get_created())
+    } else {
+        // A failed read is tried again on the next tick; a message no index has is not.
+        crate::operations::find_indexed_created(app, topic_key.to_ref(), message_id)
+            .await
+            .ok()?
+    };
+
+    app.consumer_lag
+        .set_created(topic_key.clone(), queue_id.to_string(), message_id, created);
+
+    created
+}
@@ -1,24 +1,25 @@
-// TODO: timer that drives `gc_expired_deleted_topics`. See `TODO.md`.
-//
-// use std::sync::Arc;
-//
-// use rust_extensions::MyTimerTick;
-//
-// use crate::app::AppContext;
-//
-// pub struct DeletedTopicsGcTimer {
-//     app: Arc<AppContext>,
-// }
-//
-// impl DeletedTopicsGcTimer {
-//     pub fn new(app: Arc<AppContext>) -> Self {
-//         Self { app }
-//     }
-// }
-//
-// #[async_trait::async_trait]
-// impl MyTimerTick for DeletedTopicsGcTimer {
-//     async fn tick(&self) {
-//         crate::operations::gc_expired_deleted_topics(self.app.as_ref()).await;
-//     }
-// }
+use std::sync::Arc;
+
+use rust_extensions::{MyTimerTick, RepeatTimerIteration};
+
+use crate::app::AppContext;
+
+/// Deletes the data of the soft-deleted topics whose time is up - see
+/// `operations::gc_expired_deleted_topics`.
+pub struct DeletedTopicsGcTimer {
+    app: Arc<AppContext>,
+}
+
+impl DeletedTopicsGcTimer {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl MyTimerTick for DeletedTopicsGcTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        crate::operations::gc_expired_deleted_topics(self.app.as_ref()).await;
+        RepeatTimerIteration::WithInterval
+    }
+}
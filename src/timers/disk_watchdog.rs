@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use my_logger::LogEventCtx;
+use rust_extensions::{MyTimerTick, RepeatTimerIteration};
+
+use crate::{
+    app::AppContext,
+    disk_space::{read_disk_space, DiskSpaceLevel},
+};
+
+/// Looks at the free space of the data volume every 3 s - a full volume used to be a panic in the
+/// archiver, and by the time a minute-long check noticed, a busy topic could have filled it.
+///
+/// While it is low, room is made in the background: one offload at a time, started again on the
+/// next tick if the space is still low once it finished. While it is critical, `SaveMessages` is
+/// refused - what is already in memory can still be archived into the room that is left.
+pub struct DiskWatchdogTimer {
+    app: Arc<AppContext>,
+}
+
+impl DiskWatchdogTimer {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl MyTimerTick for DiskWatchdogTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        let Some((free_bytes, total_bytes)) = read_disk_space(self.app.get_data_folder()) else {
+            return RepeatTimerIteration::WithInterval;
+        };
+
+        let level = DiskSpaceLevel::get(free_bytes, total_bytes, &self.app.settings.disk_watchdog);
+
+        let previous = self.app.disk_space.set(free_bytes, total_bytes, level);
+
+        self.app
+            .metrics_keeper
+            .update_disk_space(free_bytes, total_bytes, level.as_value());
+
+        if previous != level {
+            write_level_change(previous, level, free_bytes, total_bytes);
+        }
+
+        if level != DiskSpaceLevel::Ok {
+            start_offload(&self.app);
+        }
+
+        RepeatTimerIteration::WithInterval
+    }
+}
+
+fn start_offload(app: &Arc<AppContext>) {
+    if !app.has_cold_storage() {
+        return;
+    }
+
+    if !app.disk_space.try_start_offload() {
+        return;
+    }
+
+    let app = app.clone();
+
+    tokio::spawn(async move {
+        let removed = crate::timers::cold_storage_uploader::offload_for_space(app.as_ref()).await;
+
+        app.disk_space.offload_finished();
+
+        if removed > 0 {
+            my_logger::LOGGER.write_warning(
+                "DiskWatchdog",
+                format!(
+                    "Low on disk space: {} files moved off the local disk",
+                    removed
+                ),
+                LogEventCtx::new(),
+            );
+        }
+    });
+}
+
+fn write_level_change(
+    previous: DiskSpaceLevel,
+    level: DiskSpaceLevel,
+    free_bytes: u64,
+    total_bytes: u64,
+) {
+    let message = format!(
+        "Disk space of the data volume went from {} to {}: {} of {} bytes free",
+        previous.as_label(),
+        level.as_label(),
+        free_bytes,
+        total_bytes
+    );
+
+    let ctx = LogEventCtx::new().add("level", level.as_label());
+
+    let process = "DiskWatchdog".to_string();
+
+    match level {
+        DiskSpaceLevel::Ok => my_logger::LOGGER.write_info(process, message, ctx),
+        DiskSpaceLevel::Low => my_logger::LOGGER.write_warning(process, message, ctx),
+        DiskSpaceLevel::Critical => my_logger::LOGGER.write_error(process, message, ctx),
+    }
+}
@@ -37,6 +37,7 @@ impl MyTimerTick for MetricsUpdater {
                         PrometheusMetricsToUpdate {
                             not_persisted_size: queue_size.amount,
                             content_size: queue_size.size,
+                            archive_write_failures: topic_data.metrics.get_archive_write_failures(),
                         },
                     );
                 }
@@ -46,6 +47,7 @@ impl MyTimerTick for MetricsUpdater {
                         PrometheusMetricsToUpdate {
                             not_persisted_size: 0,
                             content_size: 0,
+                            archive_write_failures: 0,
                         },
                     );
                 }
@@ -1,8 +1,10 @@
 pub mod cold_storage_uploader;
+pub mod consumer_lag_updater;
+pub mod deleted_topics_gc;
+pub mod disk_watchdog;
 pub mod metrics_updater;
 pub mod pages_gc;
-
-// TODO: re-enable with soft-delete + GC (see TODO.md)
-// pub mod deleted_topics_gc;
 pub mod save_min_index;
+pub mod storage_usage;
+pub mod tls_reload;
 pub mod topics_snapshot_saver;
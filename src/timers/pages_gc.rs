@@ -1,8 +1,8 @@
 use std::sync::Arc;
 
-use crate::{
-    app::AppContext, operations::OperationError, topics_snapshot::TopicSnapshotProtobufModel,
-};
+use my_logger::LogEventCtx;
+
+use crate::{app::AppContext, topics_snapshot::TopicSnapshotProtobufModel};
 
 use rust_extensions::{MyTimerTick, RepeatTimerIteration};
 
@@ -21,18 +21,13 @@ impl MyTimerTick for PagesGcTimer {
     async fn tick(&self) -> RepeatTimerIteration {
         let topics_snapshot = self.app.topics_snapshot.get().await;
 
-        gc_pages(self.app.clone(), &topics_snapshot.snapshot.data)
-            .await
-            .unwrap();
+        gc_pages(self.app.clone(), &topics_snapshot.snapshot.data).await;
 
         RepeatTimerIteration::WithInterval
     }
 }
 
-async fn gc_pages(
-    app: Arc<AppContext>,
-    topics: &Vec<TopicSnapshotProtobufModel>,
-) -> Result<(), OperationError> {
+async fn gc_pages(app: Arc<AppContext>, topics: &Vec<TopicSnapshotProtobufModel>) {
     for topic_snapshot in topics {
         let topic_key = topic_snapshot.get_topic_key();
 
@@ -51,10 +46,16 @@ async fn gc_pages(
             }
         }
 
-        crate::operations::gc_pages(app.as_ref(), topic_data.clone()).await?;
+        // A failed archive write is retried by `gc_pages` itself; this is anything else going
+        // wrong, and it must not keep the rest of the topics from being archived.
+        if let Err(err) = crate::operations::gc_pages(app.as_ref(), topic_data.clone()).await {
+            my_logger::LOGGER.write_error(
+                "PagesGc".to_string(),
+                format!("Can not gc pages. Err: {:?}", err),
+                LogEventCtx::new().add("topicId", topic_key.to_string()),
+            );
+        }
 
         topic_data.yearly_index_by_minute.gc().await;
     }
-
-    Ok(())
 }
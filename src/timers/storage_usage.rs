@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use rust_extensions::{date_time::DateTimeAsMicroseconds, MyTimerTick, RepeatTimerIteration};
+
+use crate::{
+    app::AppContext,
+    operations::scan_topic_folders,
+    storage_usage::{read_local_usage, LocalUsage, TopicStorageUsage},
+};
+
+/// Accounts what every topic occupies: its folder on disk, plus what the cold usage has on record
+/// for it. Once a minute - it is a `stat` per file, and billing does not need it any fresher.
+///
+/// Driven off the disk and the cold usage rather than the snapshot: a deleted topic's folder can
+/// take a while to go, and it occupies the disk until it has.
+pub struct StorageUsageTimer {
+    app: Arc<AppContext>,
+}
+
+impl StorageUsageTimer {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl MyTimerTick for StorageUsageTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        let data_folder = self.app.get_data_folder();
+
+        // By (namespace, topic), so the view reads sorted.
+        let mut topics: BTreeMap<(String, String), TopicStorageUsage> = BTreeMap::new();
+
+        for topic_key in scan_topic_folders(data_folder).await {
+            let local = read_local_usage(data_folder, topic_key.to_ref()).await;
+
+            topics.insert(
+                (topic_key.namespace.clone(), topic_key.topic_id.clone()),
+                TopicStorageUsage {
+                    topic_key,
+                    local,
+                    cold_bytes: 0,
+                },
+            );
+        }
+
+        // A topic whose every file went to the cold tier may have no folder left.
+        for (topic_key, cold_bytes) in self.app.cold_usage.get_all() {
+            topics
+                .entry((topic_key.namespace.clone(), topic_key.topic_id.clone()))
+                .or_insert_with(|| TopicStorageUsage {
+                    topic_key,
+                    local: LocalUsage::default(),
+                    cold_bytes: 0,
+                })
+                .cold_bytes = cold_bytes;
+        }
+
+        let topics: Vec<TopicStorageUsage> = topics.into_values().collect();
+
+        self.app
+            .metrics_keeper
+            .update_storage_usage(topics.as_slice());
+
+        self.app
+            .storage_usage
+            .set(topics, DateTimeAsMicroseconds::now());
+
+        RepeatTimerIteration::WithInterval
+    }
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use rust_extensions::{MyTimerTick, RepeatTimerIteration};
+
+use crate::app::AppContext;
+
+/// Picks up rotated certificate files. Only the modification times are looked at on a tick; the
+/// files are read again only when one of them changed.
+pub struct TlsReloadTimer {
+    app: Arc<AppContext>,
+}
+
+impl TlsReloadTimer {
+    pub fn new(app: Arc<AppContext>) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl MyTimerTick for TlsReloadTimer {
+    async fn tick(&self) -> RepeatTimerIteration {
+        if let Some(tls) = self.app.tls.as_ref() {
+            tls.reload_if_changed();
+        }
+
+        RepeatTimerIteration::WithInterval
+    }
+}
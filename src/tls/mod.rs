@@ -0,0 +1,4 @@
+mod tls_certificates;
+pub use tls_certificates::*;
+mod tls_incoming;
+pub use tls_incoming::*;
@@ -0,0 +1,230 @@
+use std::{sync::Arc, time::SystemTime};
+
+use arc_swap::ArcSwap;
+use my_logger::LogEventCtx;
+use parking_lot::Mutex;
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+
+use crate::settings::TlsSettingsModel;
+
+const ALPN_H2: &[u8] = b"h2";
+const ALPN_HTTP_1_1: &[u8] = b"http/1.1";
+
+/// The server configs both listeners hand to every new connection. A rotated certificate is
+/// swapped in here and the next handshake uses it; connections already open keep the old one
+/// until they close, which is what a rotation wants anyway.
+pub struct TlsCertificates {
+    settings: TlsSettingsModel,
+    grpc: ArcSwap<ServerConfig>,
+    http: ArcSwap<ServerConfig>,
+    files_version: Mutex<Vec<Option<SystemTime>>>,
+}
+
+impl TlsCertificates {
+    /// Fails the start on anything wrong with the files: a node that was asked for TLS and
+    /// quietly came up without it would be a hole in the network policy.
+    pub fn load(settings: &TlsSettingsModel) -> Self {
+        let files_version = get_files_version(settings);
+
+        let (grpc, http) = match build_configs(settings) {
+            Ok(result) => result,
+            Err(err) => panic!("Invalid TLS settings. {}", err),
+        };
+
+        Self {
+            settings: settings.clone(),
+            grpc: ArcSwap::from_pointee(grpc),
+            http: ArcSwap::from_pointee(http),
+            files_version: Mutex::new(files_version),
+        }
+    }
+
+    pub fn get_grpc_config(&self) -> Arc<ServerConfig> {
+        self.grpc.load_full()
+    }
+
+    pub fn get_http_config(&self) -> Arc<ServerConfig> {
+        self.http.load_full()
+    }
+
+    pub fn has_grpc_client_ca(&self) -> bool {
+        self.settings.grpc_client_ca_path.is_some()
+    }
+
+    /// Rebuilds both configs when any of the files changed since the last load. A broken new set
+    /// (a key written before its certificate, say) leaves the previous one serving and is tried
+    /// again on the next tick.
+    pub fn reload_if_changed(&self) {
+        let files_version = get_files_version(&self.settings);
+
+        if *self.files_version.lock() == files_version {
+            return;
+        }
+
+        match build_configs(&self.settings) {
+            Ok((grpc, http)) => {
+                self.grpc.store(Arc::new(grpc));
+                self.http.store(Arc::new(http));
+                *self.files_version.lock() = files_version;
+
+                my_logger::LOGGER.write_info(
+                    "TlsCertificates",
+                    "TLS certificates are reloaded".to_string(),
+                    LogEventCtx::new().add("cert", self.settings.cert_path.clone()),
+                );
+            }
+            Err(err) => {
+                my_logger::LOGGER.write_error(
+                    "TlsCertificates",
+                    format!(
+                        "Can not reload TLS certificates. Keeping the previous ones. {}",
+                        err
+                    ),
+                    LogEventCtx::new().add("cert", self.settings.cert_path.clone()),
+                );
+            }
+        }
+    }
+}
+
+fn get_files_version(settings: &TlsSettingsModel) -> Vec<Option<SystemTime>> {
+    let mut result = vec![
+        get_modified(settings.cert_path.as_str()),
+        get_modified(settings.key_path.as_str()),
+    ];
+
+    if let Some(client_ca_path) = settings.grpc_client_ca_path.as_ref() {
+        result.push(get_modified(client_ca_path.as_str()));
+    }
+
+    result
+}
+
+fn get_modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+fn build_configs(settings: &TlsSettingsModel) -> Result<(ServerConfig, ServerConfig), String> {
+    let certs = read_certs(settings.cert_path.as_str())?;
+    let key = read_key(settings.key_path.as_str())?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let grpc_builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("{}", err))?;
+
+    let grpc_builder = match settings.grpc_client_ca_path.as_ref() {
+        Some(client_ca_path) => {
+            let mut roots = RootCertStore::empty();
+
+            for cert in read_certs(client_ca_path.as_str())? {
+                roots
+                    .add(cert)
+                    .map_err(|err| format!("Invalid client CA {}. {}", client_ca_path, err))?;
+            }
+
+            // A certificate is optional at the handshake and required per call: reads stay
+            // available to tools without one, writes are refused in the RPC itself.
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .allow_unauthenticated()
+                    .build()
+                    .map_err(|err| format!("Invalid client CA {}. {}", client_ca_path, err))?;
+
+            grpc_builder.with_client_cert_verifier(verifier)
+        }
+        None => grpc_builder.with_no_client_auth(),
+    };
+
+    let mut grpc = grpc_builder
+        .with_single_cert(certs.clone(), key.clone_key())
+        .map_err(|err| format!("Invalid certificate or key. {}", err))?;
+
+    grpc.alpn_protocols = vec![ALPN_H2.to_vec()];
+
+    let mut http = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("{}", err))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| format!("Invalid certificate or key. {}", err))?;
+
+    http.alpn_protocols = vec![ALPN_HTTP_1_1.to_vec()];
+
+    Ok((grpc, http))
+}
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let content = std::fs::read(path).map_err(|err| format!("Can not read {}. {}", path, err))?;
+
+    let certs: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut content.as_slice())
+        .collect::<Result<_, _>>()
+        .map_err(|err| format!("Invalid PEM in {}. {}", path, err))?;
+
+    if certs.is_empty() {
+        return Err(format!("No certificate found in {}", path));
+    }
+
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    let content = std::fs::read(path).map_err(|err| format!("Can not read {}. {}", path, err))?;
+
+    match rustls_pemfile::private_key(&mut content.as_slice()) {
+        Ok(Some(key)) => Ok(key),
+        Ok(None) => Err(format!("No private key found in {}", path)),
+        Err(err) => Err(format!("Invalid PEM in {}. {}", path, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::TlsSettingsModel;
+
+    use super::build_configs;
+
+    fn temp_path(name: &str) -> String {
+        let mut path = std::env::temp_dir();
+        path.push(format!("my-sb-persistence-tls-{}", name));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn files_without_pem_blocks_are_refused() {
+        let cert_path = temp_path("cert.pem");
+        let key_path = temp_path("key.pem");
+
+        std::fs::write(&cert_path, b"not a certificate").unwrap();
+        std::fs::write(&key_path, b"not a key").unwrap();
+
+        let result = build_configs(&TlsSettingsModel {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            grpc_client_ca_path: None,
+            http_backend_port: None,
+        });
+
+        assert!(result.unwrap_err().contains("No certificate found"));
+
+        let _ = std::fs::remove_file(&cert_path);
+        let _ = std::fs::remove_file(&key_path);
+    }
+
+    #[test]
+    fn missing_files_are_refused() {
+        let result = build_configs(&TlsSettingsModel {
+            cert_path: temp_path("missing-cert.pem"),
+            key_path: temp_path("missing-key.pem"),
+            grpc_client_ca_path: None,
+            http_backend_port: None,
+        });
+
+        assert!(result.unwrap_err().starts_with("Can not read"));
+    }
+}
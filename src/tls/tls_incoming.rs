@@ -0,0 +1,72 @@
+use std::{sync::Arc, time::Duration};
+
+use my_logger::LogEventCtx;
+use rustls::ServerConfig;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_stream::wrappers::ReceiverStream;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepted and already decrypted connections. Every handshake runs in its own task, so a client
+/// that opens a socket and never says hello holds up nobody but itself - and only until the
+/// timeout.
+///
+/// `get_config` is asked once per connection, which is how a reloaded certificate gets used
+/// without restarting the listener.
+pub fn tls_incoming(
+    process: &'static str,
+    listener: TcpListener,
+    get_config: impl Fn() -> Arc<ServerConfig> + Send + Sync + 'static,
+) -> ReceiverStream<Result<TlsStream<TcpStream>, std::io::Error>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(128);
+
+    tokio::spawn(async move {
+        loop {
+            let (tcp_stream, addr) = match listener.accept().await {
+                Ok(result) => result,
+                Err(err) => {
+                    // Out of file descriptors and the like - back off instead of spinning.
+                    my_logger::LOGGER.write_error(
+                        process,
+                        format!("Can not accept a connection. {}", err),
+                        LogEventCtx::new(),
+                    );
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+
+            if sender.is_closed() {
+                break;
+            }
+
+            let acceptor = TlsAcceptor::from(get_config());
+            let sender = sender.clone();
+
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp_stream)).await {
+                    Ok(Ok(tls_stream)) => {
+                        let _ = sender.send(Ok(tls_stream)).await;
+                    }
+                    Ok(Err(err)) => {
+                        my_logger::LOGGER.write_warning(
+                            process,
+                            format!("TLS handshake failed. {}", err),
+                            LogEventCtx::new().add("addr", addr.to_string()),
+                        );
+                    }
+                    Err(_) => {
+                        my_logger::LOGGER.write_warning(
+                            process,
+                            "TLS handshake timed out".to_string(),
+                            LogEventCtx::new().add("addr", addr.to_string()),
+                        );
+                    }
+                }
+            });
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
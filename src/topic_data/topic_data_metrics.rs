@@ -1,14 +1,24 @@
 use std::{
-    sync::atomic::{AtomicI64, AtomicU64},
+    sync::atomic::{AtomicI64, AtomicU32, AtomicU64},
     time::Duration,
 };
 
+use parking_lot::Mutex;
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
+/// The first retry of a failed archive write, doubled on each failure in a row up to the max.
+const ARCHIVE_RETRY_MIN_DELAY: Duration = Duration::from_secs(1);
+const ARCHIVE_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub struct TopicDataMetrics {
     last_saved_duration: AtomicU64,
     last_saved_moment: AtomicI64,
+    /// Archive writes failed in a row. Anything but `0` is a degraded topic.
+    archive_write_failures: AtomicU32,
+    /// Unix microseconds before which the next archive write is not attempted.
+    next_archive_write: AtomicI64,
+    last_archive_error: Mutex<Option<String>>,
 }
 
 impl TopicDataMetrics {
@@ -16,6 +26,9 @@ impl TopicDataMetrics {
         Self {
             last_saved_duration: AtomicU64::new(0),
             last_saved_moment: AtomicI64::new(DateTimeAsMicroseconds::now().unix_microseconds),
+            archive_write_failures: AtomicU32::new(0),
+            next_archive_write: AtomicI64::new(0),
+            last_archive_error: Mutex::new(None),
         }
     }
 
@@ -47,4 +60,94 @@ impl TopicDataMetrics {
             .load(std::sync::atomic::Ordering::SeqCst);
         return DateTimeAsMicroseconds::new(unix_microseconds);
     }
+
+    /// Records a failed archive write and puts the next attempt off. Returns the delay.
+    pub fn archive_write_failed(&self, err: String, now: DateTimeAsMicroseconds) -> Duration {
+        let failures = self
+            .archive_write_failures
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            + 1;
+
+        let delay = get_archive_retry_delay(failures);
+
+        self.next_archive_write.store(
+            now.unix_microseconds + delay.as_micros() as i64,
+            std::sync::atomic::Ordering::SeqCst,
+        );
+
+        *self.last_archive_error.lock() = Some(err);
+
+        delay
+    }
+
+    /// Returns how many writes had failed in a row before this one - `0` for a healthy topic.
+    pub fn archive_write_succeeded(&self) -> u32 {
+        let failures = self
+            .archive_write_failures
+            .swap(0, std::sync::atomic::Ordering::SeqCst);
+
+        if failures > 0 {
+            self.next_archive_write
+                .store(0, std::sync::atomic::Ordering::SeqCst);
+            *self.last_archive_error.lock() = None;
+        }
+
+        failures
+    }
+
+    pub fn is_archive_write_due(&self, now: DateTimeAsMicroseconds) -> bool {
+        now.unix_microseconds
+            >= self
+                .next_archive_write
+                .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub fn get_archive_write_failures(&self) -> u32 {
+        self.archive_write_failures
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub fn get_last_archive_error(&self) -> Option<String> {
+        self.last_archive_error.lock().clone()
+    }
+}
+
+fn get_archive_retry_delay(failures: u32) -> Duration {
+    let factor = 1u32 << failures.saturating_sub(1).min(16);
+    (ARCHIVE_RETRY_MIN_DELAY * factor).min(ARCHIVE_RETRY_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+    use super::TopicDataMetrics;
+
+    #[test]
+    fn failed_archive_writes_back_off_until_one_succeeds() {
+        let metrics = TopicDataMetrics::new();
+        let now = DateTimeAsMicroseconds::new(1_000_000_000);
+
+        assert!(metrics.is_archive_write_due(now));
+
+        let delays: Vec<Duration> = (0..8)
+            .map(|_| metrics.archive_write_failed("disk".to_string(), now))
+            .collect();
+
+        assert_eq!(Duration::from_secs(1), delays[0]);
+        assert_eq!(Duration::from_secs(2), delays[1]);
+        assert_eq!(Duration::from_secs(4), delays[2]);
+        assert_eq!(Duration::from_secs(60), delays[7]);
+
+        assert!(!metrics.is_archive_write_due(now));
+        assert_eq!(8, metrics.get_archive_write_failures());
+        assert_eq!(Some("disk".to_string()), metrics.get_last_archive_error());
+
+        assert_eq!(8, metrics.archive_write_succeeded());
+        assert!(metrics.is_archive_write_due(now));
+        assert_eq!(0, metrics.get_archive_write_failures());
+        assert_eq!(None, metrics.get_last_archive_error());
+    }
 }
@@ -137,23 +137,6 @@ impl TopicsDataList {
         self.inner
             .store(Arc::new(TopicsDataInner::from_data(new_data)));
     }
-
-    // TODO: soft-delete (`delete`) is being reworked. See `TODO.md`.
-    // pub fn delete(&self, topic_key: TopicKeyRef<'_>) -> Option<Arc<TopicData>> {
-    //     let _guard = self.write_lock.lock().unwrap();
-    //     let current = self.inner.load_full();
-    //
-    //     let mut new_data = copy_of(&current.data);
-    //     let removed = new_data.remove(topic_key.namespace, topic_key.topic_id)?;
-    //
-    //     let mut new_deleted = current.deleted.clone();
-    //     new_deleted.insert(topic_key.to_owned_key());
-    //
-    //     self.inner
-    //         .store(Arc::new(TopicsDataInner::from_parts(new_data, new_deleted)));
-    //
-    //     Some(removed)
-    // }
 }
 
 #[cfg(test)]
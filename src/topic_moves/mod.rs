@@ -0,0 +1,2 @@
+mod topic_moves;
+pub use topic_moves::*;
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::storage_layout, topic_key::TopicKeyRef,
+    topics_snapshot::file_storage::read_to_string_if_exists, utils::PersistedYaml,
+};
+
+/// How far a move got. Each step is only recorded once it is done, so a move picked up after a
+/// crash redoes at most the step it was in - and every step is written to be redone.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TopicMoveStep {
+    /// Recorded, nothing done yet - the topic may still be in memory and its folder in place.
+    Started,
+    /// The local folder carries the new name.
+    FolderMoved,
+    /// Nothing of the topic is left in the cold tier under the old name.
+    ColdMoved,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopicMove {
+    pub from_namespace: String,
+    pub from_topic_id: String,
+    pub to_namespace: String,
+    pub to_topic_id: String,
+    pub step: TopicMoveStep,
+    /// Why the move gave up on `step`. It stays in the journal, both names blocked, until a
+    /// restart tries that step again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed: Option<String>,
+}
+
+impl TopicMove {
+    pub fn get_from(&self) -> TopicKeyRef<'_> {
+        TopicKeyRef::new(self.from_namespace.as_str(), self.from_topic_id.as_str())
+    }
+
+    pub fn get_to(&self) -> TopicKeyRef<'_> {
+        TopicKeyRef::new(self.to_namespace.as_str(), self.to_topic_id.as_str())
+    }
+
+    fn involves(&self, topic_key: TopicKeyRef<'_>) -> bool {
+        self.get_from() == topic_key || self.get_to() == topic_key
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct TopicMovesYamlModel {
+    #[serde(default)]
+    moves: Vec<TopicMove>,
+}
+
+/// The moves in progress, kept in `{data_folder}/.topic-moves.yaml` - at the root, since a move can
+/// cross namespaces. A move stays listed until its last step is done, so one interrupted by a
+/// crash is picked up again on the next start, and until then both of its names are off limits
+/// to writers.
+pub struct TopicMoves {
+    data_folder: String,
+    data: PersistedYaml<Vec<TopicMove>>,
+}
+
+impl TopicMoves {
+    pub async fn load(data_folder: String) -> Self {
+        let path = storage_layout::get_topic_moves_file(data_folder.as_str());
+
+        let moves = match read_to_string_if_exists(&path).await {
+            // A move that is forgotten is a topic left half in one place and half in the other,
+            // so this is not stepped over.
+            Some(content) => match serde_yaml::from_str::<TopicMovesYamlModel>(&content) {
+                Ok(model) => model.moves,
+                Err(err) => panic!("Can not parse {:?}: {}", path, err),
+            },
+            None => Vec::new(),
+        };
+
+        Self {
+            data_folder,
+            data: PersistedYaml::new(moves),
+        }
+    }
+
+    pub fn get_all(&self) -> Vec<TopicMove> {
+        self.data.read(|data| data.clone())
+    }
+
+    /// Either name of a move in progress.
+    pub fn is_moving(&self, topic_key: TopicKeyRef<'_>) -> bool {
+        self.data
+            .read(|data| data.iter().any(|itm| itm.involves(topic_key)))
+    }
+
+    /// Records a new move. `Ok(false)` when either name already takes part in one.
+    pub async fn start(&self, from: TopicKeyRef<'_>, to: TopicKeyRef<'_>) -> Result<bool, String> {
+        self.data
+            .modify(self.get_path(), |data| {
+                if data
+                    .iter()
+                    .any(|itm| itm.involves(from) || itm.involves(to))
+                {
+                    return None;
+                }
+
+                data.push(TopicMove {
+                    from_namespace: from.namespace.to_string(),
+                    from_topic_id: from.topic_id.to_string(),
+                    to_namespace: to.namespace.to_string(),
+                    to_topic_id: to.topic_id.to_string(),
+                    step: TopicMoveStep::Started,
+                    failed: None,
+                });
+
+                Some(to_yaml_model(data))
+            })
+            .await
+    }
+
+    pub async fn set_step(&self, from: TopicKeyRef<'_>, step: TopicMoveStep) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(), |data| {
+                let item = data.iter_mut().find(|itm| itm.get_from() == from)?;
+
+                item.step = step;
+                item.failed = None;
+
+                Some(to_yaml_model(data))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_failed(&self, from: TopicKeyRef<'_>, err: String) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(), |data| {
+                let item = data.iter_mut().find(|itm| itm.get_from() == from)?;
+
+                item.failed = Some(err);
+
+                Some(to_yaml_model(data))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn finished(&self, from: TopicKeyRef<'_>) -> Result<(), String> {
+        self.data
+            .modify(self.get_path(), |data| {
+                data.retain(|itm| itm.get_from() != from);
+
+                Some(to_yaml_model(data))
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    fn get_path(&self) -> PathBuf {
+        storage_layout::get_topic_moves_file(self.data_folder.as_str())
+    }
+}
+
+fn to_yaml_model(data: &[TopicMove]) -> TopicMovesYamlModel {
+    TopicMovesYamlModel {
+        moves: data.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topic_key::TopicKeyRef;
+
+    use super::{TopicMoveStep, TopicMoves};
+
+    #[tokio::test]
+    async fn a_move_survives_a_restart_until_it_is_finished() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-topic-moves");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let data_folder = root.to_str().unwrap().to_string();
+
+        let from = TopicKeyRef::new("default", "orders");
+        let to = TopicKeyRef::new("billing", "orders");
+
+        let moves = TopicMoves::load(data_folder.clone()).await;
+        assert!(moves.start(from, to).await.unwrap());
+
+        // Neither name can take part in a second move
+        assert!(!moves
+            .start(to, TopicKeyRef::new("alpha", "orders"))
+            .await
+            .unwrap());
+
+        moves
+            .set_step(from, TopicMoveStep::FolderMoved)
+            .await
+            .unwrap();
+
+        let reloaded = TopicMoves::load(data_folder.clone()).await;
+        assert!(reloaded.is_moving(from));
+        assert!(reloaded.is_moving(to));
+        assert!(!reloaded.is_moving(TopicKeyRef::new("billing", "payments")));
+        assert_eq!(TopicMoveStep::FolderMoved, reloaded.get_all()[0].step);
+
+        reloaded
+            .set_failed(from, "bucket refused".to_string())
+            .await
+            .unwrap();
+
+        let reloaded = TopicMoves::load(data_folder.clone()).await;
+        assert!(reloaded.is_moving(from));
+        assert_eq!(
+            Some("bucket refused"),
+            reloaded.get_all()[0].failed.as_deref()
+        );
+
+        reloaded.finished(from).await.unwrap();
+
+        let reloaded = TopicMoves::load(data_folder).await;
+        assert!(reloaded.get_all().is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,9 +1,30 @@
 use my_logger::LogEventCtx;
+use my_service_bus::abstractions::{AsMessageId, MessageId};
 use parking_lot::RwLock;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use crate::topic_key::TopicKey;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
-use super::{file_storage::TopicsSnapshotStorage, protobuf_model::*};
+use crate::{
+    cold_storage::ColdStorage,
+    settings::{SnapshotGuardSettingsModel, SnapshotHistorySettingsModel},
+    topic_key::{Namespace, TopicKey, TopicKeyRef},
+    topic_moves::TopicMove,
+};
+
+use super::{
+    file_storage::TopicsSnapshotStorage,
+    protobuf_model::*,
+    snapshot_guard::{check_snapshot, SnapshotRejected},
+    snapshot_history::SnapshotHistory,
+};
+
+/// A node whose snapshot is refused sends it again with every save, every second or so; the
+/// first refusal of a streak is logged, then every this many.
+const LOG_EVERY_REJECTED: usize = 100;
 
 #[derive(Clone)]
 pub struct TopicsSnapshotData {
@@ -32,6 +53,15 @@ impl TopicsSnapshotData {
         self.snapshot_id += 1;
     }
 
+    /// Swaps the topics of one namespace, leaving every other namespace as it is.
+    pub fn replace_namespace(&mut self, namespace: &str, topics: Vec<TopicSnapshotProtobufModel>) {
+        self.snapshot
+            .data
+            .retain(|itm| itm.get_namespace() != namespace);
+        self.snapshot.data.extend(topics);
+        self.snapshot_id += 1;
+    }
+
     pub fn update_snapshot_id(&mut self, saved_id: i64) {
         self.last_saved_snapshot_id = saved_id;
     }
@@ -42,11 +72,19 @@ impl TopicsSnapshotData {
 pub struct CurrentTopicsSnapshot {
     data: RwLock<TopicsSnapshotData>,
     pub storage: TopicsSnapshotStorage,
+    guard: SnapshotGuardSettingsModel,
+    rejected_in_a_row: AtomicUsize,
 }
 
 impl CurrentTopicsSnapshot {
-    pub async fn read_or_create(data_folder: String) -> Self {
-        let storage = TopicsSnapshotStorage::new(data_folder);
+    pub async fn read_or_create(
+        data_folder: String,
+        history_settings: SnapshotHistorySettingsModel,
+        guard: SnapshotGuardSettingsModel,
+        cold_storage: Option<Arc<ColdStorage>>,
+    ) -> Self {
+        let history = SnapshotHistory::new(data_folder.clone(), history_settings, cold_storage);
+        let storage = TopicsSnapshotStorage::new(data_folder).with_history(history);
         let loaded = storage.read().await;
 
         Self {
@@ -55,6 +93,8 @@ impl CurrentTopicsSnapshot {
                 loaded.deleted_topics,
             )),
             storage,
+            guard,
+            rejected_in_a_row: AtomicUsize::new(0),
         }
     }
 
@@ -73,9 +113,216 @@ impl CurrentTopicsSnapshot {
             .collect()
     }
 
-    pub async fn update(&self, snapshot: Vec<TopicSnapshotProtobufModel>) {
+    /// Replaces every namespace with what a bus node sent, unless it looks like the node lost its
+    /// state - see `check_snapshot`. `force` skips the check, for a reset that is meant. The topics
+    /// of the `moving` ones are taken care of by `keep_moving_topics`.
+    pub async fn update(
+        &self,
+        mut snapshot: Vec<TopicSnapshotProtobufModel>,
+        force: bool,
+        moving: &[TopicMove],
+    ) -> Result<(), SnapshotRejected> {
         let mut write_access = self.data.write();
+
+        // Under the same lock as the rename of the entry, so it is seen under one name or the
+        // other, never both or neither.
+        keep_moving_topics(write_access.snapshot.data.as_slice(), &mut snapshot, moving);
+
+        drop_deleted_topics(
+            write_access.snapshot.deleted_topics.as_slice(),
+            &mut snapshot,
+        );
+
+        if !force {
+            let result = check_snapshot(
+                &self.guard,
+                write_access.snapshot.data.as_slice(),
+                snapshot.as_slice(),
+            );
+
+            if let Err(err) = result {
+                let rejected_before = self.rejected_in_a_row.fetch_add(1, Ordering::SeqCst);
+
+                if rejected_before % LOG_EVERY_REJECTED == 0 {
+                    my_logger::LOGGER.write_error(
+                        "SaveQueueSnapshot".to_string(),
+                        format!(
+                            "Snapshot refused ({} in a row). {}",
+                            rejected_before + 1,
+                            err
+                        ),
+                        LogEventCtx::new().add("reason", err.as_label().to_string()),
+                    );
+                }
+
+                return Err(err);
+            }
+        }
+
+        self.rejected_in_a_row.store(0, Ordering::SeqCst);
+
         write_access.update(snapshot);
+
+        Ok(())
+    }
+
+    pub async fn replace_namespace(
+        &self,
+        namespace: &str,
+        topics: Vec<TopicSnapshotProtobufModel>,
+    ) {
+        let mut write_access = self.data.write();
+        write_access.replace_namespace(namespace, topics);
+    }
+
+    /// Adds a topic the bus node has not sent - `false` if it is already there.
+    pub async fn insert_topic(&self, topic: TopicSnapshotProtobufModel) -> bool {
+        let mut write_access = self.data.write();
+
+        if write_access
+            .snapshot
+            .data
+            .iter()
+            .any(|itm| itm.get_topic_key() == topic.get_topic_key())
+        {
+            return false;
+        }
+
+        write_access.snapshot.data.push(topic);
+        write_access.snapshot_id += 1;
+
+        true
+    }
+
+    /// Changes one topic in place. `None` - the topic is not in the snapshot. A change is only
+    /// counted when `edit` says so, so a refused edit does not cause a write.
+    pub async fn update_topic<TResult>(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        edit: impl FnOnce(&mut TopicSnapshotProtobufModel) -> (TResult, bool),
+    ) -> Option<TResult> {
+        let mut write_access = self.data.write();
+
+        let topic = write_access
+            .snapshot
+            .data
+            .iter_mut()
+            .find(|itm| itm.get_topic_key() == topic_key)?;
+
+        let (result, changed) = edit(topic);
+
+        if changed {
+            write_access.snapshot_id += 1;
+        }
+
+        Some(result)
+    }
+
+    pub fn is_deleted(&self, topic_key: TopicKeyRef<'_>) -> bool {
+        let read_access = self.data.read();
+        read_access
+            .snapshot
+            .deleted_topics
+            .iter()
+            .any(|itm| itm.get_topic_key() == topic_key)
+    }
+
+    pub async fn get_deleted_topics(&self) -> Vec<DeletedTopicProtobufModel> {
+        let read_access = self.data.read();
+        read_access.snapshot.deleted_topics.clone()
+    }
+
+    /// Takes the topic out of the snapshot and records it as deleted, in one step so it is never
+    /// seen as both or neither. `None` - the topic is not in the snapshot.
+    pub async fn soft_delete_topic(
+        &self,
+        topic_key: TopicKeyRef<'_>,
+        gc_after: DateTimeAsMicroseconds,
+    ) -> Option<MessageId> {
+        let mut write_access = self.data.write();
+
+        let index = write_access
+            .snapshot
+            .data
+            .iter()
+            .position(|itm| itm.get_topic_key() == topic_key)?;
+
+        let topic = write_access.snapshot.data.remove(index);
+        let message_id = topic.get_message_id();
+
+        write_access
+            .snapshot
+            .deleted_topics
+            .push(DeletedTopicProtobufModel::new(
+                topic_key,
+                message_id,
+                gc_after.unix_microseconds,
+            ));
+        write_access.snapshot_id += 1;
+
+        Some(message_id)
+    }
+
+    /// Puts a soft-deleted topic back with the message id it was deleted at. Its queues were not
+    /// kept: the bus node subscribes them again. `Err(false)` - there is no such record,
+    /// `Err(true)` - a topic of that name is in the snapshot again.
+    pub async fn restore_deleted_topic(&self, topic_key: TopicKeyRef<'_>) -> Result<(), bool> {
+        let mut write_access = self.data.write();
+
+        let Some(index) = write_access
+            .snapshot
+            .deleted_topics
+            .iter()
+            .position(|itm| itm.get_topic_key() == topic_key)
+        else {
+            return Err(false);
+        };
+
+        if write_access
+            .snapshot
+            .data
+            .iter()
+            .any(|itm| itm.get_topic_key() == topic_key)
+        {
+            return Err(true);
+        }
+
+        let deleted = write_access.snapshot.deleted_topics.remove(index);
+
+        let mut topic = TopicSnapshotProtobufModel::new(
+            &Namespace::default_namespace(),
+            String::new(),
+            deleted.message_id.as_message_id(),
+            vec![],
+            Some(true),
+            0,
+        );
+        topic.set_topic_key(topic_key);
+
+        write_access.snapshot.data.push(topic);
+        write_access.snapshot_id += 1;
+
+        Ok(())
+    }
+
+    /// Drops the record once the topic's data is gone - `false` if there was none.
+    pub async fn remove_deleted_topic(&self, topic_key: TopicKeyRef<'_>) -> bool {
+        let mut write_access = self.data.write();
+
+        let before = write_access.snapshot.deleted_topics.len();
+
+        write_access
+            .snapshot
+            .deleted_topics
+            .retain(|itm| itm.get_topic_key() != topic_key);
+
+        if write_access.snapshot.deleted_topics.len() == before {
+            return false;
+        }
+
+        write_access.snapshot_id += 1;
+
+        true
     }
 
     pub async fn update_snapshot_id_as_saved(&self, saved_id: i64) {
@@ -135,3 +382,140 @@ impl CurrentTopicsSnapshot {
         }
     }
 }
+
+/// A node that has not been told still sends a soft-deleted topic. Taking it would bring the
+/// topic back, empty, next to the record its data is kept under.
+fn drop_deleted_topics(
+    deleted_topics: &[DeletedTopicProtobufModel],
+    incoming: &mut Vec<TopicSnapshotProtobufModel>,
+) {
+    incoming.retain(|itm| {
+        !deleted_topics
+            .iter()
+            .any(|deleted| deleted.get_topic_key() == itm.get_topic_key())
+    });
+}
+
+/// The old name of a topic being moved is not the node's to set. Before the entry is renamed the
+/// positions under it are the ones the move carries over, and after it a node that has not caught
+/// up would bring the old name back next to the new one. So what is sent under the old name is
+/// left out, and the stored entry - under whichever name it has by now - is kept, unless the node
+/// already sends the new name.
+fn keep_moving_topics(
+    current: &[TopicSnapshotProtobufModel],
+    incoming: &mut Vec<TopicSnapshotProtobufModel>,
+    moving: &[TopicMove],
+) {
+    for topic_move in moving {
+        let from = topic_move.get_from();
+        let to = topic_move.get_to();
+
+        incoming.retain(|itm| itm.get_topic_key() != from);
+
+        if incoming.iter().any(|itm| itm.get_topic_key() == to) {
+            continue;
+        }
+
+        let stored = current
+            .iter()
+            .find(|itm| itm.get_topic_key() == from || itm.get_topic_key() == to);
+
+        if let Some(stored) = stored {
+            incoming.push(stored.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use my_service_bus::abstractions::AsMessageId;
+
+    use crate::{
+        topic_key::{Namespace, TopicKeyRef},
+        topic_moves::{TopicMove, TopicMoveStep},
+        topics_snapshot::{DeletedTopicProtobufModel, TopicSnapshotProtobufModel},
+    };
+
+    use super::{drop_deleted_topics, keep_moving_topics};
+
+    fn topic(namespace: &str, topic_id: &str, message_id: i64) -> TopicSnapshotProtobufModel {
+        TopicSnapshotProtobufModel::new(
+            &Namespace::parse(Some(namespace)).unwrap(),
+            topic_id.to_string(),
+            message_id.as_message_id(),
+            vec![],
+            Some(true),
+            0,
+        )
+    }
+
+    fn orders_to_billing() -> TopicMove {
+        TopicMove {
+            from_namespace: "default".to_string(),
+            from_topic_id: "orders".to_string(),
+            to_namespace: "billing".to_string(),
+            to_topic_id: "orders".to_string(),
+            step: TopicMoveStep::ColdMoved,
+            failed: None,
+        }
+    }
+
+    fn message_id_of(snapshot: &[TopicSnapshotProtobufModel], namespace: &str) -> Option<i64> {
+        snapshot
+            .iter()
+            .find(|itm| itm.get_topic_key() == TopicKeyRef::new(namespace, "orders"))
+            .map(|itm| itm.get_message_id().get_value())
+    }
+
+    #[test]
+    fn a_node_does_not_bring_the_old_name_back() {
+        // The entry is renamed already, the node still sends the old name
+        let current = vec![topic("billing", "orders", 100)];
+        let mut incoming = vec![topic("default", "orders", 120)];
+
+        keep_moving_topics(&current, &mut incoming, &[orders_to_billing()]);
+
+        assert_eq!(None, message_id_of(&incoming, "default"));
+        assert_eq!(Some(100), message_id_of(&incoming, "billing"));
+    }
+
+    #[test]
+    fn the_entry_is_kept_under_the_old_name_until_it_is_renamed() {
+        let current = vec![topic("default", "orders", 100)];
+        let mut incoming = vec![topic("default", "orders", 120)];
+
+        keep_moving_topics(&current, &mut incoming, &[orders_to_billing()]);
+
+        assert_eq!(Some(100), message_id_of(&incoming, "default"));
+        assert_eq!(None, message_id_of(&incoming, "billing"));
+    }
+
+    #[test]
+    fn a_node_sending_the_new_name_is_taken() {
+        let current = vec![topic("default", "orders", 100)];
+        let mut incoming = vec![topic("billing", "orders", 130)];
+
+        keep_moving_topics(&current, &mut incoming, &[orders_to_billing()]);
+
+        assert_eq!(None, message_id_of(&incoming, "default"));
+        assert_eq!(Some(130), message_id_of(&incoming, "billing"));
+    }
+
+    #[test]
+    fn a_node_does_not_bring_a_deleted_topic_back() {
+        let deleted = vec![DeletedTopicProtobufModel::new(
+            TopicKeyRef::new("default", "orders"),
+            100i64.as_message_id(),
+            0,
+        )];
+        let mut incoming = vec![
+            topic("default", "orders", 120),
+            topic("billing", "orders", 130),
+        ];
+
+        drop_deleted_topics(&deleted, &mut incoming);
+
+        assert_eq!(None, message_id_of(&incoming, "default"));
+        assert_eq!(Some(130), message_id_of(&incoming, "billing"));
+    }
+}
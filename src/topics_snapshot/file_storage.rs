@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use crate::{app::storage_layout, topic_key::Namespace};
 
-use super::{protobuf_model::*, yaml_model::TopicsAndQueuesSnapshotYamlModel};
+use super::{
+    protobuf_model::*, snapshot_history::SnapshotHistory,
+    yaml_model::TopicsAndQueuesSnapshotYamlModel,
+};
 
 pub struct LoadedTopicsSnapshot {
     pub topics: Vec<TopicSnapshotProtobufModel>,
@@ -17,11 +20,25 @@ pub struct LoadedTopicsSnapshot {
 /// one call and learns which namespaces exist. Splitting happens only on the way to disk.
 pub struct TopicsSnapshotStorage {
     data_folder: String,
+    /// `None` for the one-off writes of the migration - only the live snapshot keeps revisions.
+    history: Option<SnapshotHistory>,
 }
 
 impl TopicsSnapshotStorage {
     pub fn new(data_folder: String) -> Self {
-        Self { data_folder }
+        Self {
+            data_folder,
+            history: None,
+        }
+    }
+
+    pub fn with_history(mut self, history: SnapshotHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn get_history(&self) -> Option<&SnapshotHistory> {
+        self.history.as_ref()
     }
 
     pub async fn read(&self) -> LoadedTopicsSnapshot {
@@ -106,6 +123,12 @@ impl TopicsSnapshotStorage {
             );
 
             write_atomically(&path, content.as_bytes()).await?;
+
+            if let Some(history) = self.history.as_ref() {
+                history
+                    .on_written(namespace.as_str(), content.as_bytes())
+                    .await;
+            }
         }
 
         Ok(())
@@ -115,7 +138,7 @@ impl TopicsSnapshotStorage {
 /// Truncate-then-write would leave a half-written snapshot after a crash, and this file is the
 /// source of truth for which topics exist. Write next to it and rename - on POSIX the rename is
 /// atomic, so a reader sees either the old file or the new one.
-async fn write_atomically(path: &PathBuf, content: &[u8]) -> Result<(), String> {
+pub async fn write_atomically(path: &PathBuf, content: &[u8]) -> Result<(), String> {
     if let Some(folder) = path.parent() {
         tokio::fs::create_dir_all(folder)
             .await
@@ -136,7 +159,7 @@ async fn write_atomically(path: &PathBuf, content: &[u8]) -> Result<(), String>
     Ok(())
 }
 
-async fn read_to_string_if_exists(path: &PathBuf) -> Option<String> {
+pub async fn read_to_string_if_exists(path: &PathBuf) -> Option<String> {
     match tokio::fs::read_to_string(path).await {
         Ok(content) => Some(content),
         Err(err) => {
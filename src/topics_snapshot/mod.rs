@@ -2,5 +2,8 @@ pub mod current_snapshot;
 pub mod file_storage;
 #[allow(non_snake_case)]
 mod protobuf_model;
+pub mod snapshot_diff;
+pub mod snapshot_guard;
+pub mod snapshot_history;
 pub use protobuf_model::*;
 pub mod yaml_model;
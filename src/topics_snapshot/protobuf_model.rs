@@ -66,22 +66,15 @@ pub struct DeletedTopicProtobufModel {
     namespace: String,
 }
 
-// TODO: used by the soft-delete + GC flow when it is reimplemented (see TODO.md). The persisted
-// record is namespace-aware already, so GC can never delete a same-named topic in another
-// namespace.
-#[allow(dead_code)]
+/// A soft-deleted topic: out of the snapshot, its data kept until `gc_after`. Namespace-aware,
+/// so the GC can never delete a same-named topic in another namespace.
 impl DeletedTopicProtobufModel {
-    pub fn new(
-        namespace: &Namespace,
-        topic_id: String,
-        message_id: MessageId,
-        gc_after: i64,
-    ) -> Self {
+    pub fn new(topic_key: TopicKeyRef<'_>, message_id: MessageId, gc_after: i64) -> Self {
         Self {
-            topic_id,
+            topic_id: topic_key.topic_id.to_string(),
             message_id: message_id.get_value(),
             gc_after,
-            namespace: namespace_to_persist(namespace.as_str()),
+            namespace: namespace_to_persist(topic_key.namespace),
         }
     }
 
@@ -124,6 +117,13 @@ impl TopicSnapshotProtobufModel {
     pub fn get_topic_key(&self) -> TopicKeyRef<'_> {
         TopicKeyRef::new(self.get_namespace(), self.topic_id.as_str())
     }
+
+    /// For `MoveTopic` - the queues and the message id go with the topic to its new name - and
+    /// for `RestoreTopic`, which puts a topic back under the name it was deleted with.
+    pub fn set_topic_key(&mut self, topic_key: TopicKeyRef<'_>) {
+        self.topic_id = topic_key.topic_id.to_string();
+        self.namespace = namespace_to_persist(topic_key.namespace);
+    }
 }
 
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use super::{QueueSnapshotProtobufModel, TopicSnapshotProtobufModel};
+
+/// What changed in one namespace's snapshot between two points - two revisions, or a revision and
+/// the current state. Everything is keyed by id and sorted, so the same two inputs always read
+/// the same.
+pub struct SnapshotDiff {
+    pub topics_added: Vec<String>,
+    pub topics_removed: Vec<String>,
+    pub topics_changed: Vec<TopicSnapshotDiff>,
+}
+
+pub struct TopicSnapshotDiff {
+    pub topic_id: String,
+    pub message_id_from: i64,
+    pub message_id_to: i64,
+    pub queues_added: Vec<String>,
+    pub queues_removed: Vec<String>,
+    /// A different type or different ranges.
+    pub queues_changed: Vec<String>,
+}
+
+pub fn diff_snapshots(
+    from: &[TopicSnapshotProtobufModel],
+    to: &[TopicSnapshotProtobufModel],
+) -> SnapshotDiff {
+    let from: BTreeMap<&str, &TopicSnapshotProtobufModel> = from
+        .iter()
+        .map(|itm| (itm.topic_id.as_str(), itm))
+        .collect();
+    let to: BTreeMap<&str, &TopicSnapshotProtobufModel> =
+        to.iter().map(|itm| (itm.topic_id.as_str(), itm)).collect();
+
+    let mut result = SnapshotDiff {
+        topics_added: Vec::new(),
+        topics_removed: Vec::new(),
+        topics_changed: Vec::new(),
+    };
+
+    for (topic_id, from_topic) in from.iter() {
+        match to.get(topic_id) {
+            Some(to_topic) => {
+                if let Some(topic_diff) = diff_topic(from_topic, to_topic) {
+                    result.topics_changed.push(topic_diff);
+                }
+            }
+            None => result.topics_removed.push(topic_id.to_string()),
+        }
+    }
+
+    for topic_id in to.keys() {
+        if !from.contains_key(topic_id) {
+            result.topics_added.push(topic_id.to_string());
+        }
+    }
+
+    result
+}
+
+fn diff_topic(
+    from: &TopicSnapshotProtobufModel,
+    to: &TopicSnapshotProtobufModel,
+) -> Option<TopicSnapshotDiff> {
+    let from_queues: BTreeMap<&str, &QueueSnapshotProtobufModel> = from
+        .queues
+        .iter()
+        .map(|itm| (itm.queue_id.as_str(), itm))
+        .collect();
+    let to_queues: BTreeMap<&str, &QueueSnapshotProtobufModel> = to
+        .queues
+        .iter()
+        .map(|itm| (itm.queue_id.as_str(), itm))
+        .collect();
+
+    let mut result = TopicSnapshotDiff {
+        topic_id: from.topic_id.clone(),
+        message_id_from: from.get_message_id().get_value(),
+        message_id_to: to.get_message_id().get_value(),
+        queues_added: Vec::new(),
+        queues_removed: Vec::new(),
+        queues_changed: Vec::new(),
+    };
+
+    for (queue_id, from_queue) in from_queues.iter() {
+        match to_queues.get(queue_id) {
+            Some(to_queue) => {
+                if from_queue != to_queue {
+                    result.queues_changed.push(queue_id.to_string());
+                }
+            }
+            None => result.queues_removed.push(queue_id.to_string()),
+        }
+    }
+
+    for queue_id in to_queues.keys() {
+        if !from_queues.contains_key(queue_id) {
+            result.queues_added.push(queue_id.to_string());
+        }
+    }
+
+    let unchanged = result.message_id_from == result.message_id_to
+        && result.queues_added.is_empty()
+        && result.queues_removed.is_empty()
+        && result.queues_changed.is_empty();
+
+    if unchanged {
+        return None;
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use my_service_bus::abstractions::AsMessageId;
+
+    use crate::topic_key::Namespace;
+    use crate::topics_snapshot::{
+        QueueRangeProtobufModel, QueueSnapshotProtobufModel, TopicSnapshotProtobufModel,
+    };
+
+    use super::diff_snapshots;
+
+    fn topic(
+        topic_id: &str,
+        message_id: i64,
+        queues: Vec<QueueSnapshotProtobufModel>,
+    ) -> TopicSnapshotProtobufModel {
+        TopicSnapshotProtobufModel::new(
+            &Namespace::default_namespace(),
+            topic_id.to_string(),
+            message_id.as_message_id(),
+            queues,
+            Some(true),
+            0,
+        )
+    }
+
+    fn queue(queue_id: &str, from_id: i64, to_id: i64) -> QueueSnapshotProtobufModel {
+        QueueSnapshotProtobufModel {
+            queue_id: queue_id.to_string(),
+            ranges: vec![QueueRangeProtobufModel::new(from_id, to_id)],
+            queue_type: 0,
+        }
+    }
+
+    #[test]
+    fn reports_topics_and_queues_by_id() {
+        let from = vec![
+            topic(
+                "orders",
+                10,
+                vec![queue("billing", 0, 10), queue("audit", 0, 10)],
+            ),
+            topic("payments", 5, vec![]),
+            topic("quiet", 1, vec![queue("q", 0, 1)]),
+        ];
+
+        let to = vec![
+            topic(
+                "orders",
+                12,
+                vec![queue("billing", 11, 12), queue("reports", 0, 12)],
+            ),
+            topic("quiet", 1, vec![queue("q", 0, 1)]),
+            topic("refunds", 0, vec![]),
+        ];
+
+        let diff = diff_snapshots(&from, &to);
+
+        assert_eq!(vec!["refunds".to_string()], diff.topics_added);
+        assert_eq!(vec!["payments".to_string()], diff.topics_removed);
+
+        // `quiet` did not change and is not listed
+        assert_eq!(1, diff.topics_changed.len());
+
+        let orders = &diff.topics_changed[0];
+        assert_eq!("orders", orders.topic_id);
+        assert_eq!((10, 12), (orders.message_id_from, orders.message_id_to));
+        assert_eq!(vec!["reports".to_string()], orders.queues_added);
+        assert_eq!(vec!["audit".to_string()], orders.queues_removed);
+        assert_eq!(vec!["billing".to_string()], orders.queues_changed);
+
+        let same = diff_snapshots(&to, &to);
+        assert!(same.topics_added.is_empty());
+        assert!(same.topics_removed.is_empty());
+        assert!(same.topics_changed.is_empty());
+    }
+}
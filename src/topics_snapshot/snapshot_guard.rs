@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use crate::{settings::SnapshotGuardSettingsModel, topic_key::TopicKeyRef};
+
+use super::TopicSnapshotProtobufModel;
+
+/// How many regressed topics a rejection names; the rest are only counted.
+const TOPICS_TO_NAME: usize = 5;
+
+/// Why a `SaveQueueSnapshot` was not taken. Every variant is what a bus node that restarted with
+/// empty state sends: topics start over from zero, and the queues it has not heard of yet are
+/// simply not there.
+#[derive(Debug)]
+pub enum SnapshotRejected {
+    /// `(topic, known message id, sent message id)` for every topic that went back.
+    MessageIdWentBack(Vec<(String, i64, i64)>),
+    TopicsDisappeared {
+        disappeared: usize,
+        known: usize,
+    },
+    QueuesDisappeared {
+        disappeared: usize,
+        known: usize,
+    },
+}
+
+impl SnapshotRejected {
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            SnapshotRejected::MessageIdWentBack(_) => "message_id_went_back",
+            SnapshotRejected::TopicsDisappeared { .. } => "topics_disappeared",
+            SnapshotRejected::QueuesDisappeared { .. } => "queues_disappeared",
+        }
+    }
+}
+
+impl std::fmt::Display for SnapshotRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotRejected::MessageIdWentBack(topics) => {
+                write!(f, "MessageId went back in {} topic(s):", topics.len())?;
+
+                for (topic, known, sent) in topics.iter().take(TOPICS_TO_NAME) {
+                    write!(f, " {} {}->{};", topic, known, sent)?;
+                }
+
+                Ok(())
+            }
+            SnapshotRejected::TopicsDisappeared { disappeared, known } => {
+                write!(f, "{} of {} known topics disappeared", disappeared, known)
+            }
+            SnapshotRejected::QueuesDisappeared { disappeared, known } => {
+                write!(f, "{} of {} known queues disappeared", disappeared, known)
+            }
+        }
+    }
+}
+
+/// Compares what a bus node sent with what is known. A topic or a queue deleted on purpose
+/// disappears too, so disappearing is only suspicious in bulk: more than `max_disappeared_share`
+/// of them and at least `min_disappeared` at once.
+pub fn check_snapshot(
+    settings: &SnapshotGuardSettingsModel,
+    known: &[TopicSnapshotProtobufModel],
+    sent: &[TopicSnapshotProtobufModel],
+) -> Result<(), SnapshotRejected> {
+    let sent: HashMap<TopicKeyRef<'_>, &TopicSnapshotProtobufModel> =
+        sent.iter().map(|itm| (itm.get_topic_key(), itm)).collect();
+
+    let mut went_back = Vec::new();
+
+    let mut topics_disappeared = 0;
+    let mut queues_known = 0;
+    let mut queues_disappeared = 0;
+
+    for known_topic in known {
+        queues_known += known_topic.queues.len();
+
+        let Some(sent_topic) = sent.get(&known_topic.get_topic_key()) else {
+            topics_disappeared += 1;
+            queues_disappeared += known_topic.queues.len();
+            continue;
+        };
+
+        let known_message_id = known_topic.get_message_id().get_value();
+        let sent_message_id = sent_topic.get_message_id().get_value();
+
+        if sent_message_id < known_message_id {
+            went_back.push((
+                known_topic.get_topic_key().to_string(),
+                known_message_id,
+                sent_message_id,
+            ));
+        }
+
+        for queue in known_topic.queues.iter() {
+            if !sent_topic
+                .queues
+                .iter()
+                .any(|itm| itm.queue_id == queue.queue_id)
+            {
+                queues_disappeared += 1;
+            }
+        }
+    }
+
+    if !went_back.is_empty() {
+        return Err(SnapshotRejected::MessageIdWentBack(went_back));
+    }
+
+    if is_too_many(settings, topics_disappeared, known.len()) {
+        return Err(SnapshotRejected::TopicsDisappeared {
+            disappeared: topics_disappeared,
+            known: known.len(),
+        });
+    }
+
+    if is_too_many(settings, queues_disappeared, queues_known) {
+        return Err(SnapshotRejected::QueuesDisappeared {
+            disappeared: queues_disappeared,
+            known: queues_known,
+        });
+    }
+
+    Ok(())
+}
+
+fn is_too_many(settings: &SnapshotGuardSettingsModel, disappeared: usize, known: usize) -> bool {
+    if disappeared == 0 || disappeared < settings.min_disappeared {
+        return false;
+    }
+
+    disappeared as f64 > known as f64 * settings.max_disappeared_share
+}
+
+#[cfg(test)]
+mod tests {
+    use my_service_bus::abstractions::AsMessageId;
+
+    use crate::settings::SnapshotGuardSettingsModel;
+    use crate::topic_key::Namespace;
+    use crate::topics_snapshot::{
+        QueueRangeProtobufModel, QueueSnapshotProtobufModel, TopicSnapshotProtobufModel,
+    };
+
+    use super::{check_snapshot, SnapshotRejected};
+
+    fn topic(topic_id: &str, message_id: i64, queues: &[&str]) -> TopicSnapshotProtobufModel {
+        TopicSnapshotProtobufModel::new(
+            &Namespace::default_namespace(),
+            topic_id.to_string(),
+            message_id.as_message_id(),
+            queues
+                .iter()
+                .map(|queue_id| QueueSnapshotProtobufModel {
+                    queue_id: queue_id.to_string(),
+                    ranges: vec![QueueRangeProtobufModel::new(0, message_id)],
+                    queue_type: 0,
+                })
+                .collect(),
+            Some(true),
+            0,
+        )
+    }
+
+    fn settings() -> SnapshotGuardSettingsModel {
+        SnapshotGuardSettingsModel {
+            max_disappeared_share: 0.5,
+            min_disappeared: 3,
+        }
+    }
+
+    #[test]
+    fn a_message_id_going_back_is_rejected() {
+        let known = vec![topic("orders", 100, &["billing"])];
+
+        let result = check_snapshot(&settings(), &known, &[topic("orders", 101, &["billing"])]);
+        assert!(result.is_ok());
+
+        let result = check_snapshot(&settings(), &known, &[topic("orders", 0, &["billing"])]);
+        assert!(
+            matches!(result, Err(SnapshotRejected::MessageIdWentBack(topics)) if topics.len() == 1)
+        );
+    }
+
+    #[test]
+    fn only_a_mass_disappearance_is_rejected() {
+        let known: Vec<_> = (0..6)
+            .map(|no| topic(format!("topic-{}", no).as_str(), 10, &["q"]))
+            .collect();
+
+        // One topic deleted on purpose
+        let result = check_snapshot(&settings(), &known, &known[1..]);
+        assert!(result.is_ok());
+
+        // A node that came up empty
+        let result = check_snapshot(&settings(), &known, &[]);
+        assert!(matches!(
+            result,
+            Err(SnapshotRejected::TopicsDisappeared {
+                disappeared: 6,
+                known: 6
+            })
+        ));
+
+        // A small deployment is below `min_disappeared`
+        let result = check_snapshot(&settings(), &known[..2], &[]);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn queues_disappearing_from_kept_topics_count() {
+        let known = vec![
+            topic("orders", 10, &["a", "b", "c"]),
+            topic("payments", 10, &["d"]),
+        ];
+
+        let sent = vec![topic("orders", 10, &[]), topic("payments", 10, &["d"])];
+
+        let result = check_snapshot(&settings(), &known, &sent);
+        assert!(matches!(
+            result,
+            Err(SnapshotRejected::QueuesDisappeared {
+                disappeared: 3,
+                known: 4
+            })
+        ));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use my_logger::LogEventCtx;
+use parking_lot::Mutex;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+use crate::{
+    app::storage_layout, cold_storage::ColdStorage, settings::SnapshotHistorySettingsModel,
+};
+
+use super::{
+    file_storage::{read_to_string_if_exists, write_atomically},
+    yaml_model::TopicsAndQueuesSnapshotYamlModel,
+};
+
+pub struct SnapshotRevision {
+    /// When it was taken, in unix microseconds.
+    pub revision_id: i64,
+    pub size: u64,
+}
+
+impl SnapshotRevision {
+    pub fn get_created(&self) -> DateTimeAsMicroseconds {
+        DateTimeAsMicroseconds::new(self.revision_id)
+    }
+}
+
+/// Past revisions of each namespace's `topics-and-queue.yaml`, kept next to it as
+/// `.topics-and-queue.{revision_id}.yaml`.
+///
+/// A revision is a copy of what was written, taken at most every `interval_sec` per namespace:
+/// the snapshot is saved every few seconds while queues move, and keeping each save would push a
+/// good revision out within minutes of a bad one. Every namespace file is rewritten on every save,
+/// so one that did not change since its last revision gets no new one. A restore always takes one
+/// first, so a restore can itself be undone.
+pub struct SnapshotHistory {
+    data_folder: String,
+    settings: SnapshotHistorySettingsModel,
+    cold_storage: Option<Arc<ColdStorage>>,
+    /// namespace -> its latest revision. Filled from the disk on first touch.
+    last_taken: Mutex<HashMap<String, LastRevision>>,
+}
+
+#[derive(Clone, Copy)]
+struct LastRevision {
+    revision_id: i64,
+    /// `None` for a revision found on disk rather than taken by this process - the next one is
+    /// taken regardless of its content.
+    content_hash: Option<u64>,
+}
+
+impl SnapshotHistory {
+    pub fn new(
+        data_folder: String,
+        settings: SnapshotHistorySettingsModel,
+        cold_storage: Option<Arc<ColdStorage>>,
+    ) -> Self {
+        let cold_storage = if settings.upload_to_cold {
+            cold_storage
+        } else {
+            None
+        };
+
+        Self {
+            data_folder,
+            settings,
+            cold_storage,
+            last_taken: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Called with the content just written as the namespace snapshot. Never fails the save: a
+    /// revision that could not be kept is logged, and the current snapshot is what matters.
+    pub async fn on_written(&self, namespace: &str, content: &[u8]) {
+        if self.settings.keep == 0 {
+            return;
+        }
+
+        let now = DateTimeAsMicroseconds::now().unix_microseconds;
+
+        let last_taken = match self.get_last_taken(namespace) {
+            Some(last_taken) => Some(last_taken),
+            None => {
+                let last_taken = self.list(namespace).await.last().map(|itm| LastRevision {
+                    revision_id: itm.revision_id,
+                    content_hash: None,
+                });
+
+                if let Some(last_taken) = last_taken {
+                    self.last_taken
+                        .lock()
+                        .insert(namespace.to_string(), last_taken);
+                }
+
+                last_taken
+            }
+        };
+
+        if let Some(last_taken) = last_taken {
+            let interval = self.settings.interval_sec as i64 * 1_000_000;
+
+            if now - last_taken.revision_id < interval {
+                return;
+            }
+
+            if last_taken.content_hash == Some(get_hash(content)) {
+                return;
+            }
+        }
+
+        if let Err(err) = self.take(namespace, content).await {
+            write_error(namespace, err);
+        }
+    }
+
+    /// Keeps `content` as a new revision now, whatever the interval says. Returns its id.
+    pub async fn take(&self, namespace: &str, content: &[u8]) -> Result<i64, String> {
+        let mut revision_id = DateTimeAsMicroseconds::now().unix_microseconds;
+
+        // Two revisions within the same microsecond would share a name.
+        if let Some(last_taken) = self.get_last_taken(namespace) {
+            if revision_id <= last_taken.revision_id {
+                revision_id = last_taken.revision_id + 1;
+            }
+        }
+
+        let path = storage_layout::get_snapshot_revision_file(
+            self.data_folder.as_str(),
+            namespace,
+            revision_id,
+        );
+
+        write_atomically(&path, content).await?;
+
+        self.last_taken.lock().insert(
+            namespace.to_string(),
+            LastRevision {
+                revision_id,
+                content_hash: Some(get_hash(content)),
+            },
+        );
+
+        if let Some(cold_storage) = self
+            .cold_storage
+            .clone()
+            .filter(|cold_storage| cold_storage.covers(namespace))
+        {
+            // Not awaited: the saver timer is not going to wait for the cold tier.
+            let namespace = namespace.to_string();
+            let keep = self.settings.keep;
+            tokio::spawn(async move {
+                let file_name = storage_layout::get_snapshot_revision_file_name(revision_id);
+
+                let result = cold_storage
+                    .upload_namespace_file(namespace.as_str(), file_name.as_str(), path.as_path())
+                    .await;
+
+                if let Err(err) = result {
+                    write_error(
+                        namespace.as_str(),
+                        format!("Can not upload {}. Err: {}", file_name, err),
+                    );
+                }
+
+                prune_cold(cold_storage.as_ref(), namespace.as_str(), keep).await;
+            });
+        }
+
+        self.prune(namespace).await;
+
+        Ok(revision_id)
+    }
+
+    /// Keeps the current file of the namespace as a revision - what a restore does before it
+    /// replaces it. `None` when there is no current file.
+    pub async fn take_current(&self, namespace: &str) -> Result<Option<i64>, String> {
+        let path =
+            storage_layout::get_namespace_snapshot_file(self.data_folder.as_str(), namespace);
+
+        let Some(content) = read_to_string_if_exists(&path).await else {
+            return Ok(None);
+        };
+
+        let revision_id = self.take(namespace, content.as_bytes()).await?;
+
+        Ok(Some(revision_id))
+    }
+
+    /// Oldest first.
+    pub async fn list(&self, namespace: &str) -> Vec<SnapshotRevision> {
+        let mut result = Vec::new();
+
+        let mut folder = std::path::PathBuf::from(self.data_folder.as_str());
+        folder.push(namespace);
+
+        let Ok(mut entries) = tokio::fs::read_dir(folder.as_path()).await else {
+            return result;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Some(revision_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(storage_layout::parse_snapshot_revision_file_name)
+            else {
+                continue;
+            };
+
+            let size = match entry.metadata().await {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue,
+            };
+
+            result.push(SnapshotRevision { revision_id, size });
+        }
+
+        result.sort_by_key(|itm| itm.revision_id);
+
+        result
+    }
+
+    pub async fn read(
+        &self,
+        namespace: &str,
+        revision_id: i64,
+    ) -> Result<Option<TopicsAndQueuesSnapshotYamlModel>, String> {
+        let path = storage_layout::get_snapshot_revision_file(
+            self.data_folder.as_str(),
+            namespace,
+            revision_id,
+        );
+
+        let Some(content) = read_to_string_if_exists(&path).await else {
+            return Ok(None);
+        };
+
+        let model = serde_yaml::from_str(&content)
+            .map_err(|err| format!("Can not parse {:?}: {}", path, err))?;
+
+        Ok(Some(model))
+    }
+
+    fn get_last_taken(&self, namespace: &str) -> Option<LastRevision> {
+        self.last_taken.lock().get(namespace).copied()
+    }
+
+    async fn prune(&self, namespace: &str) {
+        let revisions = self.list(namespace).await;
+
+        if revisions.len() <= self.settings.keep {
+            return;
+        }
+
+        let to_delete = revisions.len() - self.settings.keep;
+
+        for revision in revisions.into_iter().take(to_delete) {
+            let path = storage_layout::get_snapshot_revision_file(
+                self.data_folder.as_str(),
+                namespace,
+                revision.revision_id,
+            );
+
+            if let Err(err) = tokio::fs::remove_file(path.as_path()).await {
+                write_error(
+                    namespace,
+                    format!("Can not delete {:?}. Err: {}", path, err),
+                );
+            }
+        }
+    }
+}
+
+/// Prunes the cold copies to `keep` the way [`SnapshotHistory::prune`] does the local ones. From a
+/// listing rather than from what was pruned locally: a delete that failed, or a revision pruned
+/// before the cold copies were, would otherwise stay in the bucket for good.
+async fn prune_cold(cold_storage: &ColdStorage, namespace: &str, keep: usize) {
+    let files = match cold_storage
+        .list_namespace_level_files(namespace, storage_layout::SNAPSHOT_REVISION_FILE_PREFIX)
+        .await
+    {
+        Ok(files) => files,
+        Err(err) => {
+            write_error(
+                namespace,
+                format!(
+                    "Can not list the revisions in the cold storage. Err: {}",
+                    err
+                ),
+            );
+            return;
+        }
+    };
+
+    let mut revision_ids: Vec<i64> = files
+        .keys()
+        .filter_map(|itm| storage_layout::parse_snapshot_revision_file_name(itm.as_str()))
+        .collect();
+
+    if revision_ids.len() <= keep {
+        return;
+    }
+
+    revision_ids.sort();
+
+    let to_delete = revision_ids.len() - keep;
+
+    for revision_id in revision_ids.into_iter().take(to_delete) {
+        let file_name = storage_layout::get_snapshot_revision_file_name(revision_id);
+
+        if let Err(err) = cold_storage
+            .delete_namespace_file(namespace, file_name.as_str())
+            .await
+        {
+            write_error(
+                namespace,
+                format!(
+                    "Can not delete {} from the cold storage. Err: {}",
+                    file_name, err
+                ),
+            );
+        }
+    }
+}
+
+fn get_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn write_error(namespace: &str, message: String) {
+    my_logger::LOGGER.write_error(
+        "SnapshotHistory",
+        message,
+        LogEventCtx::new().add("namespace", namespace.to_string()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::SnapshotHistorySettingsModel;
+
+    use super::SnapshotHistory;
+
+    fn temp_folder(name: &str) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("my-sb-persistence-snapshot-history-{}", name));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(path.join("alpha")).unwrap();
+        path
+    }
+
+    fn history(root: &std::path::Path, keep: usize, interval_sec: u64) -> SnapshotHistory {
+        SnapshotHistory::new(
+            root.to_str().unwrap().to_string(),
+            SnapshotHistorySettingsModel {
+                keep,
+                interval_sec,
+                upload_to_cold: false,
+            },
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn a_save_within_the_interval_takes_no_revision() {
+        let root = temp_folder("interval");
+        let history = history(&root, 10, 3600);
+
+        history.on_written("alpha", b"topics: []").await;
+        history.on_written("alpha", b"topics: []").await;
+
+        assert_eq!(1, history.list("alpha").await.len());
+
+        // Forced - the way a restore keeps what it replaces
+        history.take("alpha", b"topics: []").await.unwrap();
+        assert_eq!(2, history.list("alpha").await.len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn an_unchanged_namespace_takes_no_revision() {
+        let root = temp_folder("unchanged");
+        let history = history(&root, 10, 0);
+
+        history.on_written("alpha", b"topics: []").await;
+        history.on_written("alpha", b"topics: []").await;
+        assert_eq!(1, history.list("alpha").await.len());
+
+        history.on_written("alpha", b"topics: [] ").await;
+        assert_eq!(2, history.list("alpha").await.len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn the_oldest_revisions_are_pruned() {
+        let root = temp_folder("prune");
+        let history = history(&root, 2, 0);
+
+        let first = history.take("alpha", b"topics: []").await.unwrap();
+        let second = history.take("alpha", b"topics: []").await.unwrap();
+        let third = history.take("alpha", b"topics: []").await.unwrap();
+
+        let left: Vec<i64> = history
+            .list("alpha")
+            .await
+            .iter()
+            .map(|itm| itm.revision_id)
+            .collect();
+
+        assert_eq!(vec![second, third], left);
+        assert!(history.read("alpha", first).await.unwrap().is_none());
+        assert!(history.read("alpha", third).await.unwrap().is_some());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
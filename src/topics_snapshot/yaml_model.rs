@@ -18,7 +18,7 @@ pub struct TopicsAndQueuesSnapshotYamlModel {
     #[serde(default)]
     pub topics: Vec<TopicYamlModel>,
 
-    /// Kept for the soft-delete + GC flow that is currently disabled - see TODO.md.
+    /// Soft-deleted topics waiting for `RestoreTopic` or the GC.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub deleted_topics: Vec<DeletedTopicYamlModel>,
 }
@@ -109,7 +109,7 @@ impl TopicsAndQueuesSnapshotYamlModel {
 }
 
 impl TopicYamlModel {
-    fn from_domain(src: &TopicSnapshotProtobufModel) -> Self {
+    pub fn from_domain(src: &TopicSnapshotProtobufModel) -> Self {
         Self {
             topic_id: src.topic_id.clone(),
             message_id: src.get_message_id().get_value(),
@@ -119,7 +119,7 @@ impl TopicYamlModel {
         }
     }
 
-    fn into_domain(self, namespace: &Namespace) -> TopicSnapshotProtobufModel {
+    pub fn into_domain(self, namespace: &Namespace) -> TopicSnapshotProtobufModel {
         TopicSnapshotProtobufModel::new(
             namespace,
             self.topic_id,
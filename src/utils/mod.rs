@@ -1,2 +1,4 @@
 mod duration_utils;
 pub use duration_utils::*;
+mod persisted_yaml;
+pub use persisted_yaml::*;
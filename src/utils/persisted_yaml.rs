@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::topics_snapshot::file_storage::write_atomically;
+
+/// State that lives in memory and is written to a YAML file on every change - the runtime
+/// settings an operator or a background job makes, which the next start has to find again.
+///
+/// A change and the model it writes are taken under the same lock, and the writes are one at a
+/// time in the order of the changes: a change applied in memory and saved after a later one would
+/// put the older state on disk and keep it there until the next change.
+pub struct PersistedYaml<T> {
+    data: Mutex<T>,
+    save_lock: tokio::sync::Mutex<()>,
+}
+
+impl<T> PersistedYaml<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            data: Mutex::new(data),
+            save_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn read<TResult>(&self, read: impl FnOnce(&T) -> TResult) -> TResult {
+        read(&self.data.lock())
+    }
+
+    /// `modify` changes the state and returns what goes into the file at `path`, or `None` when
+    /// nothing changed and there is nothing to write. `Ok(false)` is that `None`.
+    pub async fn modify<TModel: Serialize>(
+        &self,
+        path: PathBuf,
+        modify: impl FnOnce(&mut T) -> Option<TModel>,
+    ) -> Result<bool, String> {
+        let _save_guard = self.save_lock.lock().await;
+
+        let Some(model) = modify(&mut self.data.lock()) else {
+            return Ok(false);
+        };
+
+        let content = serde_yaml::to_string(&model)
+            .map_err(|err| format!("Can not serialize {:?}: {}", path, err))?;
+
+        write_atomically(&path, content.as_bytes()).await?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::PersistedYaml;
+
+    #[tokio::test]
+    async fn writes_only_what_changed() {
+        let mut root = std::env::temp_dir();
+        root.push("my-sb-persistence-persisted-yaml");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let path = root.join("state.yaml");
+
+        let store = PersistedYaml::new(BTreeMap::<String, u64>::new());
+
+        let saved = store
+            .modify(path.clone(), |data| {
+                data.insert("orders".to_string(), 1);
+                Some(data.clone())
+            })
+            .await
+            .unwrap();
+        assert!(saved);
+
+        let saved = store
+            .modify(path.clone(), |data| {
+                data.insert("payments".to_string(), 2);
+                None::<BTreeMap<String, u64>>
+            })
+            .await
+            .unwrap();
+        assert!(!saved);
+
+        let on_disk: BTreeMap<String, u64> =
+            serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(1, on_disk.len());
+        assert_eq!(2, store.read(|data| data.len()));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}